@@ -4,6 +4,7 @@ pragma solidity ^0.8.13;
 import "./IBitcoinLightClient.sol";
 import {console2} from "forge-std/Test.sol";
 import "openzeppelin-contracts/contracts/token/ERC20/IERC20.sol";
+import "openzeppelin-contracts/contracts/token/ERC20/extensions/IERC20Metadata.sol";
 import "openzeppelin-contracts/contracts/utils/ReentrancyGuard.sol";
 import "bitcoin-spv/solidity/contracts/ValidateSPV.sol";
 import "bitcoin-spv/solidity/contracts/BTCUtils.sol";
@@ -24,6 +25,8 @@ contract P2PBTCLending is ReentrancyGuard {
     uint256 public constant LIQUIDATION_THRESHOLD = 125; // 125% liquidation threshold
     uint256 public constant LIQUIDATION_BONUS = 5; // 5% bonus for liquidators
     uint256 public constant SECONDS_PER_YEAR = 31536000;
+    uint256 public constant SATS_PER_BTC = 1e8;
+    uint256 public constant STABLE_CLAIM_PERIOD = 1 days; // Time for the borrower to claim lent stables
 
     struct BorrowRequest {
         uint256 amount;
@@ -59,6 +62,11 @@ contract P2PBTCLending is ReentrancyGuard {
         uint256 collateralAmount;
         uint256 stableLoanAmount;
         uint128 borrowerPreimage;
+        address lender;
+        bool repaid;
+        uint256 interestRate;
+        uint256 startTime; // Time of the lend until claimed, then start of the interest period
+        uint256 accruedInterest;
     }
 
     mapping(address => BorrowRequest) public borrowRequests;
@@ -73,6 +81,9 @@ contract P2PBTCLending is ReentrancyGuard {
     event LoanLiquidated(address indexed borrower, address indexed liquidator, uint256 amount);
     event StableLoanCreated(bytes32 indexed borrowerHash, uint256 collateralAmount, uint256 stableLoanAmount);
     event StableClaimed(bytes32 indexed borrowerHash, uint256 stableLoanAmount, uint128 borrowerPreimage, address indexed borrower);
+    event StableRepaid(bytes32 indexed borrowerHash, uint256 amountDue, address indexed lender);
+    event StableReclaimed(bytes32 indexed borrowerHash, uint256 stableLoanAmount, address indexed lender);
+    event StablePartiallyRepaid(bytes32 indexed borrowerHash, uint256 repaidAmount, uint256 stableLoanAmount, address indexed lender);
 
     constructor(
         address _stableToken,
@@ -184,27 +195,90 @@ contract P2PBTCLending is ReentrancyGuard {
         emit LoanLiquidated(borrower, msg.sender, collateralAmount);
     }
 
-    function lendStable(bytes32 borrowerHash, uint256 btcCollateralAmount) external nonReentrant {
+    function lendStable(bytes32 borrowerHash, uint256 btcCollateralAmount, uint256 stableLoanAmount, uint256 interestRate, uint256 collateralRatio) external nonReentrant {
         require(btcCollateralAmount > 0, " BTC Collateral amount must be greater than 0");
+        require(interestRate > 0 && interestRate <= 100, "Interest rate must be between 1 and 100");
+        require(collateralRatio > 100, "Collateral ratio must be greater than 100");
+        // Lend the agreed principal, as long as the collateral covers it at the agreed ratio.
+        require(stableLoanAmount > 0 && stableLoanAmount <= getStableLoanAmount(btcCollateralAmount, collateralRatio), "Stable loan amount must be between 0 and the collateral allowance");
         StableLoan memory stableLoan = stableLoans[borrowerHash];
         require(stableLoan.collateralAmount == 0, "Loan already exists for this borrower");
-        uint256 stableLoanAmount = getStableLoanAmount(btcCollateralAmount);
         require(stableToken.transferFrom(msg.sender, address(this), stableLoanAmount), "Stable token transfer failed");
         stableLoan.collateralAmount = btcCollateralAmount;
         stableLoan.stableLoanAmount = stableLoanAmount;
+        stableLoan.lender = msg.sender;
+        stableLoan.interestRate = interestRate;
+        stableLoan.startTime = block.timestamp;
         stableLoans[borrowerHash] = stableLoan;
         emit StableLoanCreated(borrowerHash, btcCollateralAmount, stableLoanAmount);
     }
 
+    function reclaimStable(bytes32 borrowerHash) external nonReentrant {
+        StableLoan memory stableLoan = stableLoans[borrowerHash];
+        require(stableLoan.collateralAmount > 0, "No stable loan exists for this borrower");
+        require(stableLoan.lender == msg.sender, "Only the lender can reclaim the stable loan");
+        require(stableLoan.borrowerPreimage == 0, "Stable loan already claimed");
+        require(block.timestamp >= stableLoan.startTime + STABLE_CLAIM_PERIOD, "Stable loan claim period has not ended");
+        // Remove the loan before paying back the lender, which also prevents
+        // the borrower from claiming it afterwards.
+        delete stableLoans[borrowerHash];
+        require(stableToken.transfer(msg.sender, stableLoan.stableLoanAmount), "Stable token transfer failed");
+        emit StableReclaimed(borrowerHash, stableLoan.stableLoanAmount, msg.sender);
+    }
+
     function claimStable(uint128 borrowerPreimage) external nonReentrant {
+        require(borrowerPreimage != 0, "Invalid borrower preimage");
         bytes32 borrowerHash = sha256(abi.encodePacked(borrowerPreimage));
         StableLoan memory stableLoan = stableLoans[borrowerHash];
         require(stableLoan.collateralAmount > 0, "No stable loan exists for this borrower");
+        require(stableLoan.borrowerPreimage == 0, "Stable loan already claimed");
         require(stableToken.transfer(msg.sender, stableLoan.stableLoanAmount), "Stable token transfer failed");
         stableLoans[borrowerHash].borrowerPreimage = borrowerPreimage;
+        // Interest accrues from the time the borrower received the stables.
+        stableLoans[borrowerHash].startTime = block.timestamp;
         emit StableClaimed(borrowerHash, stableLoan.stableLoanAmount, borrowerPreimage, msg.sender);
     }
 
+    function repayStable(bytes32 borrowerHash) external nonReentrant {
+        StableLoan memory stableLoan = stableLoans[borrowerHash];
+        require(stableLoan.borrowerPreimage != 0, "Stable loan has not been claimed");
+        require(!stableLoan.repaid, "Stable loan already repaid");
+        uint256 amountDue = getStableLoanAmountDue(stableLoan, block.timestamp);
+        require(stableToken.transferFrom(msg.sender, stableLoan.lender, amountDue), "Stable token transfer failed");
+        stableLoans[borrowerHash].repaid = true;
+        emit StableRepaid(borrowerHash, amountDue, stableLoan.lender);
+    }
+
+    function repayStablePartially(bytes32 borrowerHash, uint256 amount) external nonReentrant {
+        StableLoan memory stableLoan = stableLoans[borrowerHash];
+        require(stableLoan.borrowerPreimage != 0, "Stable loan has not been claimed");
+        require(!stableLoan.repaid, "Stable loan already repaid");
+        require(amount > 0 && amount < stableLoan.stableLoanAmount, "Partial repayment must be between 0 and the loan amount");
+        require(stableToken.transferFrom(msg.sender, stableLoan.lender, amount), "Stable token transfer failed");
+        // Close the interest period on the amount owed so far, the next one
+        // accrues on the remaining amount.
+        stableLoans[borrowerHash].accruedInterest = stableLoan.accruedInterest + calculateStableInterest(stableLoan, block.timestamp);
+        stableLoans[borrowerHash].startTime = block.timestamp;
+        stableLoans[borrowerHash].stableLoanAmount = stableLoan.stableLoanAmount - amount;
+        emit StablePartiallyRepaid(borrowerHash, amount, stableLoan.stableLoanAmount - amount, stableLoan.lender);
+    }
+
+    function getStableAmountDue(bytes32 borrowerHash, uint256 timestamp) external view returns (uint256) {
+        return getStableLoanAmountDue(stableLoans[borrowerHash], timestamp);
+    }
+
+    function getStableLoanAmountDue(StableLoan memory stableLoan, uint256 timestamp) public pure returns (uint256) {
+        return stableLoan.stableLoanAmount + stableLoan.accruedInterest + calculateStableInterest(stableLoan, timestamp);
+    }
+
+    function calculateStableInterest(StableLoan memory stableLoan, uint256 timestamp) public pure returns (uint256) {
+        if (stableLoan.borrowerPreimage == 0 || timestamp <= stableLoan.startTime) {
+            return 0;
+        }
+        uint256 timeElapsed = timestamp - stableLoan.startTime;
+        return (stableLoan.stableLoanAmount * stableLoan.interestRate * timeElapsed) / (SECONDS_PER_YEAR * 100);
+    }
+
     function setTestMode(bool _testMode) external {
         testMode = _testMode;
     }
@@ -220,15 +294,20 @@ contract P2PBTCLending is ReentrancyGuard {
         return requiredCollateral;
     }
 
-    function getStableLoanAmount(uint256 btcCollateralAmount) public view returns (uint256) {
+    function getStableCollateralValue(uint256 btcCollateralAmount) public view returns (uint256) {
         (, int256 btcPrice,,,) = btcPriceFeed.latestRoundData();
         (, int256 collateralPrice,,,) = collateralPriceFeed.latestRoundData();
-        
+
         require(btcPrice > 0 && collateralPrice > 0, "Invalid price data");
 
-        // Calculate stable loan amount based on collateral
-        uint256 stableLoanAmount = (btcCollateralAmount * uint256(collateralPrice) * 100) / (uint256(btcPrice) * COLLATERAL_RATIO);
-        return stableLoanAmount;
+        // The collateral is in satoshis and the value in the smallest unit of
+        // the stable token, both price feeds having the same decimals.
+        uint256 stableUnit = 10 ** IERC20Metadata(address(stableToken)).decimals();
+        return (btcCollateralAmount * uint256(btcPrice) * stableUnit) / (uint256(collateralPrice) * SATS_PER_BTC);
+    }
+
+    function getStableLoanAmount(uint256 btcCollateralAmount, uint256 collateralRatio) public view returns (uint256) {
+        return (getStableCollateralValue(btcCollateralAmount) * 100) / collateralRatio;
     }
 
     function getCurrentCollateralValue(uint256 collateralAmount) public view returns (uint256) {
@@ -2,8 +2,12 @@
 
 use crate::error::Error;
 
+use super::numerical_descriptor::NumericalDescriptor;
 use super::ContractDescriptor;
+use crate::payout_curve::{LoanPayoutCurveBuilder, PayoutFunction, RoundingIntervals};
+use crate::LendingDeployment;
 use bitcoin::Amount;
+use dlc_trie::OracleNumericInfo;
 use secp256k1_zkp::XOnlyPublicKey;
 #[cfg(feature = "use-serde")]
 use serde::{Deserialize, Serialize};
@@ -102,6 +106,30 @@ impl ContractInput {
     }
 }
 
+/// Represents the specifications of a loan contract, whose payout function is
+/// derived from the loan terms.
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "use-serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct LoanContractInputInfo {
+    /// Information about base and number of digits for each oracle.
+    pub oracle_numeric_infos: OracleNumericInfo,
+    /// Rounding intervals enabling reducing the precision of the payout values
+    /// which in turns reduces the number of required adaptor signatures.
+    pub rounding_intervals: RoundingIntervals,
+    /// The oracle information of the event on which the loan expires.
+    pub oracles: OracleInput,
+    /// The oracle information of intermediate events, for example daily
+    /// price events, on which the loan is liquidated if the attested price is
+    /// below the liquidation price. They must mature before the expiration
+    /// event.
+    #[cfg_attr(feature = "use-serde", serde(default))]
+    pub liquidation_oracles: Vec<OracleInput>,
+}
+
 /// Extends the contract input with additional information for the loan
 #[derive(Debug, Clone)]
 #[cfg_attr(
@@ -118,13 +146,44 @@ pub struct LoanContractInput {
     pub interest_rate: u64,
     /// Duration of the loan in seconds.
     pub duration: u64,
+    /// Delay in seconds after which the borrower can reclaim the escrow if the
+    /// loan is not initiated.
+    pub escrow_relative_locktime: u32,
+    /// Number of confirmations of the escrow transaction required before the
+    /// stable loan is lent.
+    pub escrow_confirmations: u32,
+    /// Time in seconds during which the offer can be accepted.
+    pub offer_validity: u64,
     /// The collateral from borrower.
     pub collateral: Amount,
+    /// The principal of the loan, in the unit of the price attested by the
+    /// oracles.
+    pub principal: u64,
+    /// The address of the ERC-20 token lent as the stable side of the loan.
+    #[cfg_attr(
+        feature = "use-serde",
+        serde(
+            serialize_with = "dlc_messages::serde_utils::serialize_evm_address",
+            deserialize_with = "dlc_messages::serde_utils::deserialize_evm_address"
+        )
+    )]
+    pub stable_token_address: [u8; 20],
+    /// The address of the lending contract holding the stable side of the loan.
+    #[cfg_attr(
+        feature = "use-serde",
+        serde(
+            serialize_with = "dlc_messages::serde_utils::serialize_evm_address",
+            deserialize_with = "dlc_messages::serde_utils::deserialize_evm_address"
+        )
+    )]
+    pub lending_contract_address: [u8; 20],
+    /// The id of the EVM chain on which the lending contract is deployed.
+    pub chain_id: u64,
     /// The fee rate used to construct the transactions.
     pub fee_rate: u64,
     /// The set of contract that make up the DLC (a single DLC can be based
     /// on multiple contracts).
-    pub contract_infos: Vec<ContractInputInfo>,
+    pub contract_infos: Vec<LoanContractInputInfo>,
 }
 impl LoanContractInput {
     /// Validate the loan contract input parameters
@@ -153,6 +212,32 @@ impl LoanContractInput {
             ));
         }
 
+        dlc::get_loan_escrow_relative_locktime(self.escrow_relative_locktime).map_err(|_| {
+            Error::InvalidParameters(format!(
+                "Escrow relative locktime must be between {} and {} seconds.",
+                dlc::MIN_LOAN_ESCROW_RELATIVE_LOCKTIME,
+                u16::MAX as u32 * 512
+            ))
+        })?;
+
+        if self.escrow_confirmations == 0 {
+            return Err(Error::InvalidParameters(
+                "Escrow confirmations must be greater than zero.".to_string(),
+            ));
+        }
+
+        if self.offer_validity == 0 {
+            return Err(Error::InvalidParameters(
+                "Offer validity must be greater than zero.".to_string(),
+            ));
+        }
+
+        if self.chain_id == 0 {
+            return Err(Error::InvalidParameters(
+                "Chain id must be greater than zero.".to_string(),
+            ));
+        }
+
         if self.contract_infos.is_empty() {
             return Err(Error::InvalidParameters(
                 "Need at least one contract info".to_string(),
@@ -161,11 +246,77 @@ impl LoanContractInput {
 
         for contract_info in &self.contract_infos {
             contract_info.oracles.validate()?;
+            contract_info.rounding_intervals.validate()?;
+            for oracles in &contract_info.liquidation_oracles {
+                oracles.validate()?;
+            }
         }
 
         dlc::util::validate_fee_rate(self.fee_rate)
             .map_err(|_| Error::InvalidParameters("Fee rate too high.".to_string()))
     }
+
+    /// Returns the lending contract deployment the loan is set up on.
+    pub fn get_lending_deployment(&self) -> LendingDeployment {
+        LendingDeployment {
+            chain_id: self.chain_id,
+            lending_contract_address: self.lending_contract_address,
+            stable_token_address: self.stable_token_address,
+        }
+    }
+
+    /// Returns the oracle information of the events of the loan, in the order
+    /// of the contract specifications.
+    pub fn get_oracle_inputs(&self) -> Vec<&OracleInput> {
+        self.contract_infos
+            .iter()
+            .map(|x| &x.oracles)
+            .chain(
+                self.contract_infos
+                    .iter()
+                    .flat_map(|x| x.liquidation_oracles.iter()),
+            )
+            .collect()
+    }
+
+    /// Returns the contract specifications of the loan, with payout functions
+    /// derived from the loan terms. The expiration events come first, followed
+    /// by the liquidation events whose payout functions only cover the
+    /// outcomes below the liquidation price.
+    pub fn get_contract_input_infos(&self) -> Result<Vec<ContractInputInfo>, Error> {
+        let mut expiration_infos = Vec::new();
+        let mut liquidation_infos = Vec::new();
+        for info in &self.contract_infos {
+            let builder = LoanPayoutCurveBuilder {
+                principal: self.principal,
+                interest_rate: self.interest_rate,
+                duration: self.duration,
+                liquidation_ratio: self.liquidation_ratio,
+                total_collateral: self.collateral,
+                oracle_numeric_info: info.oracle_numeric_infos.clone(),
+            };
+            let get_contract_input_info =
+                |payout_function: PayoutFunction, oracles: &OracleInput| ContractInputInfo {
+                    contract_descriptor: ContractDescriptor::Numerical(NumericalDescriptor {
+                        payout_function,
+                        rounding_intervals: info.rounding_intervals.clone(),
+                        difference_params: None,
+                        oracle_numeric_infos: info.oracle_numeric_infos.clone(),
+                    }),
+                    oracles: oracles.clone(),
+                };
+            expiration_infos.push(get_contract_input_info(builder.build()?, &info.oracles));
+            if !info.liquidation_oracles.is_empty() {
+                let payout_function = builder.build_liquidation()?;
+                for oracles in &info.liquidation_oracles {
+                    liquidation_infos
+                        .push(get_contract_input_info(payout_function.clone(), oracles));
+                }
+            }
+        }
+        expiration_infos.extend(liquidation_infos);
+        Ok(expiration_infos)
+    }
 }
 
 #[cfg(test)]
@@ -266,4 +417,61 @@ mod tests {
             .validate()
             .expect_err("the contract input to be invalid.");
     }
+
+    #[test]
+    fn loan_liquidation_events_come_after_expiration_events() {
+        let oracles = get_base_input().contract_infos[0].oracles.clone();
+        let get_oracles = |event_id: &str| OracleInput {
+            event_id: event_id.to_string(),
+            ..oracles.clone()
+        };
+        let input = LoanContractInput {
+            collateral_ratio: 150,
+            liquidation_ratio: 125,
+            interest_rate: 10,
+            duration: 86400 * 3,
+            escrow_relative_locktime: 8388608,
+            escrow_confirmations: 6,
+            offer_validity: 86400,
+            collateral: Amount::ONE_BTC,
+            principal: 40000,
+            stable_token_address: [0; 20],
+            lending_contract_address: [0; 20],
+            chain_id: 31337,
+            fee_rate: 2,
+            contract_infos: vec![LoanContractInputInfo {
+                oracle_numeric_infos: OracleNumericInfo {
+                    base: 2,
+                    nb_digits: vec![20],
+                },
+                rounding_intervals: RoundingIntervals {
+                    intervals: vec![crate::payout_curve::RoundingInterval {
+                        begin_interval: 0,
+                        rounding_mod: 1,
+                    }],
+                },
+                oracles: get_oracles("btcusd3"),
+                liquidation_oracles: vec![get_oracles("btcusd1"), get_oracles("btcusd2")],
+            }],
+        };
+        input.validate().expect("the loan input to be valid.");
+
+        let event_ids: Vec<_> = input
+            .get_oracle_inputs()
+            .iter()
+            .map(|x| x.event_id.as_str())
+            .collect();
+        assert_eq!(vec!["btcusd3", "btcusd1", "btcusd2"], event_ids);
+
+        let truncated: Vec<_> = input
+            .get_contract_input_infos()
+            .expect("to be able to get the contract infos")
+            .iter()
+            .map(|x| match &x.contract_descriptor {
+                ContractDescriptor::Numerical(n) => n.is_truncated(),
+                ContractDescriptor::Enum(_) => panic!("expected a numerical descriptor"),
+            })
+            .collect();
+        assert_eq!(vec![false, true, true], truncated);
+    }
 }
@@ -0,0 +1,260 @@
+//! #LoanNegotiation
+
+use secp256k1_zkp::PublicKey;
+
+use super::offered_contract::OfferedLoanContract;
+use crate::error::Error;
+use crate::ContractId;
+
+/// The maximum number of counter offers that can be made in a negotiation.
+pub const MAX_LOAN_NEGOTIATION_ROUNDS: usize = 3;
+
+/// The states of a loan negotiation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoanNegotiationState {
+    /// The lender offered terms that the borrower can accept or counter.
+    Offered,
+    /// The borrower countered the offer and awaits an answer from the lender.
+    Countered,
+    /// The lender declined the last counter offer.
+    Rejected,
+}
+
+impl std::fmt::Display for LoanNegotiationState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = match self {
+            LoanNegotiationState::Offered => "offered",
+            LoanNegotiationState::Countered => "countered",
+            LoanNegotiationState::Rejected => "rejected",
+        };
+        f.write_str(state)
+    }
+}
+
+/// Loan terms proposed by the borrower in place of those of an offer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoanTermsRevision {
+    /// The temporary id of the loan contract whose offer was countered.
+    pub countered_contract_id: ContractId,
+    /// Collateralization ratio in percent.
+    pub collateral_ratio: u64,
+    /// Interest rate in percent.
+    pub interest_rate: u64,
+    /// Duration of the loan in seconds.
+    pub duration: u64,
+}
+
+impl LoanTermsRevision {
+    /// Validate the revised terms against the countered offer.
+    pub fn validate(&self, countered: &OfferedLoanContract) -> Result<(), Error> {
+        if self.collateral_ratio <= countered.liquidation_ratio {
+            return Err(Error::InvalidParameters(
+                "Collateral ratio must be greater than liquidation ratio".to_string(),
+            ));
+        }
+        if self.interest_rate == 0 || self.interest_rate > 100 {
+            return Err(Error::InvalidParameters(
+                "Interest rate must be between 1 and 100.".to_string(),
+            ));
+        }
+        if self.duration == 0 {
+            return Err(Error::InvalidParameters(
+                "Loan duration must be greater than zero.".to_string(),
+            ));
+        }
+        if self.matches(countered) {
+            return Err(Error::InvalidParameters(
+                "Counter offer does not change the offered terms".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns whether the given loan offer has the revised terms.
+    pub fn matches(&self, offered_loan_contract: &OfferedLoanContract) -> bool {
+        offered_loan_contract.collateral_ratio == self.collateral_ratio
+            && offered_loan_contract.interest_rate == self.interest_rate
+            && offered_loan_contract.duration == self.duration
+    }
+}
+
+/// The negotiation of the terms of a loan through counter offers of the
+/// borrower and fresh offers of the lender.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoanNegotiation {
+    /// The id of the negotiation, which is the temporary id of its first offer.
+    pub id: [u8; 32],
+    /// The public key of the counter party.
+    pub counter_party: PublicKey,
+    /// Whether the local party is the lender.
+    pub is_lender: bool,
+    /// The terms proposed by the borrower, in order.
+    pub revisions: Vec<LoanTermsRevision>,
+    /// The state of the negotiation.
+    pub state: LoanNegotiationState,
+    /// The temporary id of the last loan contract offered in the negotiation.
+    pub contract_id: ContractId,
+}
+
+impl LoanNegotiation {
+    /// Creates the negotiation of the given loan offer.
+    pub fn new(offered_loan_contract: &OfferedLoanContract) -> Self {
+        let offered_contract = &offered_loan_contract.offered_contract;
+        LoanNegotiation {
+            id: offered_loan_contract.get_negotiation_id(),
+            counter_party: offered_contract.counter_party,
+            is_lender: offered_contract.is_offer_party,
+            revisions: Vec::new(),
+            state: LoanNegotiationState::Offered,
+            contract_id: offered_contract.id,
+        }
+    }
+
+    /// Returns the number of counter offers made so far.
+    pub fn get_round(&self) -> usize {
+        self.revisions.len()
+    }
+
+    /// Records a counter offer of the last offered terms.
+    pub fn add_revision(&mut self, revision: LoanTermsRevision) -> Result<(), Error> {
+        if self.state != LoanNegotiationState::Offered {
+            return Err(Error::InvalidState(format!(
+                "Cannot counter a loan negotiation that is {}",
+                self.state
+            )));
+        }
+        if revision.countered_contract_id != self.contract_id {
+            return Err(Error::InvalidParameters(
+                "Counter offer does not refer to the last offer of the negotiation".to_string(),
+            ));
+        }
+        if self.get_round() >= MAX_LOAN_NEGOTIATION_ROUNDS {
+            return Err(Error::InvalidState(format!(
+                "Loan negotiation reached the maximum of {} counter offers",
+                MAX_LOAN_NEGOTIATION_ROUNDS
+            )));
+        }
+
+        self.revisions.push(revision);
+        self.state = LoanNegotiationState::Countered;
+        Ok(())
+    }
+
+    /// Records a fresh offer made with the last revised terms.
+    pub fn add_offer(&mut self, offered_loan_contract: &OfferedLoanContract) -> Result<(), Error> {
+        if self.state != LoanNegotiationState::Countered {
+            return Err(Error::InvalidState(format!(
+                "Cannot offer terms for a loan negotiation that is {}",
+                self.state
+            )));
+        }
+        if !self
+            .revisions
+            .last()
+            .map_or(false, |r| r.matches(offered_loan_contract))
+        {
+            return Err(Error::InvalidParameters(
+                "Loan offer does not have the countered terms".to_string(),
+            ));
+        }
+
+        self.state = LoanNegotiationState::Offered;
+        self.contract_id = offered_loan_contract.offered_contract.id;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::offered_contract::OfferedContract;
+    use crate::LendingDeployment;
+
+    fn get_offered_loan_contract(id: [u8; 32]) -> OfferedLoanContract {
+        let offer_dlc =
+            serde_json::from_str(include_str!("../../test_inputs/offer_contract.json")).unwrap();
+        let counter_party: PublicKey =
+            "02e6642fd69bd211f93f7f1f36ca51a26a5290eb2dd1b0d8279a87bb0d480c8443"
+                .parse()
+                .unwrap();
+        let mut offered_contract =
+            OfferedContract::try_from_offer_dlc(&offer_dlc, counter_party, [0; 32]).unwrap();
+        offered_contract.id = id;
+        let lending_deployment = LendingDeployment {
+            chain_id: 31337,
+            lending_contract_address: [0; 20],
+            stable_token_address: [0; 20],
+        };
+        OfferedLoanContract::new(
+            offered_contract,
+            150,
+            125,
+            10,
+            86400,
+            40_000,
+            lending_deployment,
+            [0; 32],
+            86400,
+            6,
+            0,
+        )
+    }
+
+    fn get_revision(countered_contract_id: ContractId, interest_rate: u64) -> LoanTermsRevision {
+        LoanTermsRevision {
+            countered_contract_id,
+            collateral_ratio: 150,
+            interest_rate,
+            duration: 86400,
+        }
+    }
+
+    #[test]
+    fn loan_negotiation_test() {
+        let first_offer = get_offered_loan_contract([1; 32]);
+        let mut negotiation = LoanNegotiation::new(&first_offer);
+        assert_eq!([1; 32], negotiation.id);
+
+        get_revision([1; 32], 10)
+            .validate(&first_offer)
+            .expect_err("should not counter with the offered terms");
+        negotiation
+            .add_revision(get_revision([2; 32], 8))
+            .expect_err("should not counter another offer");
+        negotiation.add_revision(get_revision([1; 32], 8)).unwrap();
+        assert_eq!(LoanNegotiationState::Countered, negotiation.state);
+        negotiation
+            .add_revision(get_revision([1; 32], 7))
+            .expect_err("should not counter before the lender answers");
+
+        let mut second_offer = get_offered_loan_contract([2; 32]);
+        second_offer.negotiation_id = Some([1; 32]);
+        negotiation
+            .add_offer(&second_offer)
+            .expect_err("should not offer other terms than the countered ones");
+        second_offer.interest_rate = 8;
+        negotiation.add_offer(&second_offer).unwrap();
+        assert_eq!(LoanNegotiationState::Offered, negotiation.state);
+        assert_eq!([2; 32], negotiation.contract_id);
+        assert_eq!(negotiation.id, second_offer.get_negotiation_id());
+    }
+
+    #[test]
+    fn loan_negotiation_rounds_are_bounded_test() {
+        let mut negotiation = LoanNegotiation::new(&get_offered_loan_contract([1; 32]));
+        for round in 0..MAX_LOAN_NEGOTIATION_ROUNDS {
+            let countered_contract_id = negotiation.contract_id;
+            negotiation
+                .add_revision(get_revision(countered_contract_id, 8))
+                .unwrap();
+            let mut offer = get_offered_loan_contract([round as u8 + 2; 32]);
+            offer.interest_rate = 8;
+            negotiation.add_offer(&offer).unwrap();
+        }
+        assert_eq!(MAX_LOAN_NEGOTIATION_ROUNDS, negotiation.get_round());
+        let countered_contract_id = negotiation.contract_id;
+        negotiation
+            .add_revision(get_revision(countered_contract_id, 7))
+            .expect_err("should not exceed the maximum number of rounds");
+    }
+}
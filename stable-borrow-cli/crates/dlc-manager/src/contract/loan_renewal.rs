@@ -0,0 +1,159 @@
+//! #LoanRenewal
+
+use bitcoin::Amount;
+use secp256k1_zkp::PublicKey;
+
+use super::accepted_contract::AcceptedContract;
+use super::signed_contract::SignedContract;
+use super::LoanTerms;
+use crate::error::Error;
+use crate::ContractId;
+
+/// The states of the renewal of the collateral of a loan.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoanRenewalState {
+    /// The borrower proposed the renewal and awaits the lender signatures.
+    Offered,
+    /// Both parties signed the renewed contract and the lender awaits the
+    /// borrower signatures for the renewal transaction.
+    Accepted,
+    /// The renewal transaction was signed and awaits confirmation.
+    Confirmed,
+    /// The renewed contract replaced the previous one.
+    Completed,
+}
+
+impl std::fmt::Display for LoanRenewalState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = match self {
+            LoanRenewalState::Offered => "offered",
+            LoanRenewalState::Accepted => "accepted",
+            LoanRenewalState::Confirmed => "confirmed",
+            LoanRenewalState::Completed => "completed",
+        };
+        f.write_str(state)
+    }
+}
+
+/// The renewal of the collateral of an active loan, which re-spends the
+/// collateral output into a new one holding the additional collateral of the
+/// borrower, with CETs computed from the principal left after a partial
+/// repayment.
+#[derive(Clone)]
+pub struct LoanRenewal {
+    /// The id of the loan contract whose collateral is renewed.
+    pub contract_id: ContractId,
+    /// The public key of the counter party.
+    pub counter_party: PublicKey,
+    /// Whether the local party is the borrower who proposed the renewal.
+    pub is_borrower: bool,
+    /// The collateral added by the borrower.
+    pub additional_collateral: Amount,
+    /// The part of the principal repaid by the borrower.
+    pub repaid_principal: u64,
+    /// The stable amount owed on the lending contract once the partial
+    /// repayment is made.
+    pub stable_loan_amount: u128,
+    /// The state of the renewal.
+    pub state: LoanRenewalState,
+    /// The renewed contract as accepted by the borrower.
+    pub accepted_contract: AcceptedContract,
+    /// The renewed contract once signed by both parties.
+    pub signed_contract: Option<SignedContract>,
+}
+
+impl LoanRenewal {
+    /// Returns whether a new renewal of the loan can replace this one, which
+    /// is not the case once signatures for the renewal transaction were
+    /// exchanged.
+    pub fn can_be_replaced(&self) -> bool {
+        self.state != LoanRenewalState::Confirmed
+    }
+}
+
+/// Validate the parameters of the renewal of a loan with the given terms.
+pub fn validate_loan_renewal(
+    loan_terms: &LoanTerms,
+    additional_collateral: Amount,
+    repaid_principal: u64,
+) -> Result<(), Error> {
+    if additional_collateral == Amount::ZERO && repaid_principal == 0 {
+        return Err(Error::InvalidParameters(
+            "Loan renewal must add collateral or repay part of the principal.".to_string(),
+        ));
+    }
+    if repaid_principal >= loan_terms.principal {
+        return Err(Error::InvalidParameters(
+            "Repaid principal must be lower than the loan principal.".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the part of the stable amount lent that corresponds to the given
+/// part of the principal, rounded down.
+pub fn get_repaid_stable_amount(
+    stable_loan_amount: u128,
+    principal: u64,
+    repaid_principal: u64,
+) -> Result<u128, Error> {
+    if principal == 0 || repaid_principal >= principal {
+        return Err(Error::OutOfRange);
+    }
+    stable_loan_amount
+        .checked_mul(repaid_principal as u128)
+        .map(|x| x / principal as u128)
+        .ok_or(Error::OutOfRange)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LendingDeployment;
+
+    fn get_loan_terms(principal: u64) -> LoanTerms {
+        LoanTerms {
+            collateral_ratio: 150,
+            liquidation_ratio: 125,
+            interest_rate: 10,
+            duration: 86400,
+            principal,
+            lending_deployment: LendingDeployment {
+                chain_id: 31337,
+                lending_contract_address: [0; 20],
+                stable_token_address: [0; 20],
+            },
+            lender_hash: [0; 32],
+            escrow_relative_locktime: 86400,
+            escrow_confirmations: 6,
+            offer_expiry: 0,
+            escrow_txid: None,
+            borrower_hash: None,
+        }
+    }
+
+    #[test]
+    fn validate_loan_renewal_test() {
+        let loan_terms = get_loan_terms(40_000);
+        validate_loan_renewal(&loan_terms, Amount::ZERO, 0)
+            .expect_err("should not renew without changes");
+        validate_loan_renewal(&loan_terms, Amount::ZERO, 40_000)
+            .expect_err("should not repay the whole principal");
+        validate_loan_renewal(&loan_terms, Amount::from_sat(50_000), 0).unwrap();
+        validate_loan_renewal(&loan_terms, Amount::ZERO, 10_000).unwrap();
+    }
+
+    #[test]
+    fn repaid_stable_amount_test() {
+        assert_eq!(
+            125_000,
+            get_repaid_stable_amount(500_000, 40_000, 10_000).unwrap()
+        );
+        // Rounded down.
+        assert_eq!(1, get_repaid_stable_amount(5, 3, 1).unwrap());
+        assert_eq!(0, get_repaid_stable_amount(500_000, 40_000, 0).unwrap());
+        get_repaid_stable_amount(500_000, 40_000, 40_000)
+            .expect_err("should not repay the whole principal");
+        get_repaid_stable_amount(u128::MAX, 40_000, 10_000).expect_err("should overflow");
+    }
+}
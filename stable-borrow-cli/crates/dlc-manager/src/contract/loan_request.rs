@@ -0,0 +1,171 @@
+//! #LoanRequest
+
+use bitcoin::Amount;
+use dlc_messages::RequestLoanDlc;
+use secp256k1_zkp::PublicKey;
+
+use super::offered_contract::OfferedLoanContract;
+use crate::conversion_utils::PROTOCOL_VERSION;
+use crate::error::Error;
+use crate::ContractId;
+
+/// The states of a loan request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoanRequestState {
+    /// The request awaits an answer from the lender.
+    Pending,
+    /// The lender answered the request with a loan offer.
+    Offered,
+    /// The lender declined the request.
+    Rejected,
+}
+
+impl std::fmt::Display for LoanRequestState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = match self {
+            LoanRequestState::Pending => "pending",
+            LoanRequestState::Offered => "offered",
+            LoanRequestState::Rejected => "rejected",
+        };
+        f.write_str(state)
+    }
+}
+
+/// A loan asked by a borrower to a lender.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoanRequest {
+    /// The id of the request.
+    pub id: [u8; 32],
+    /// The public key of the counter party.
+    pub counter_party: PublicKey,
+    /// Whether the local party is the borrower who sent the request.
+    pub is_borrower: bool,
+    /// The collateral the borrower proposes to lock.
+    pub collateral: Amount,
+    /// The desired principal of the loan, in the unit of the price attested by
+    /// the oracles.
+    pub principal: u64,
+    /// The maximum interest rate in percent accepted by the borrower.
+    pub max_interest_rate: u64,
+    /// Duration of the loan in seconds.
+    pub duration: u64,
+    /// The state of the request.
+    pub state: LoanRequestState,
+    /// The temporary id of the loan contract offered in answer to the request.
+    pub contract_id: Option<ContractId>,
+    /// The id of the listing of the lender the request answers, if any.
+    pub listing_id: Option<[u8; 32]>,
+}
+
+impl LoanRequest {
+    /// Creates a new [`LoanRequest`] sent by the local party.
+    pub fn new(
+        id: [u8; 32],
+        counter_party: PublicKey,
+        collateral: Amount,
+        principal: u64,
+        max_interest_rate: u64,
+        duration: u64,
+    ) -> Self {
+        LoanRequest {
+            id,
+            counter_party,
+            is_borrower: true,
+            collateral,
+            principal,
+            max_interest_rate,
+            duration,
+            state: LoanRequestState::Pending,
+            contract_id: None,
+            listing_id: None,
+        }
+    }
+
+    /// Creates a [`LoanRequest`] from a [`RequestLoanDlc`] message received
+    /// from the given counter party.
+    pub fn from_request_loan_dlc(request_msg: &RequestLoanDlc, counter_party: PublicKey) -> Self {
+        LoanRequest {
+            is_borrower: false,
+            listing_id: request_msg.listing_id,
+            ..LoanRequest::new(
+                request_msg.request_id,
+                counter_party,
+                request_msg.collateral,
+                request_msg.principal,
+                request_msg.max_interest_rate,
+                request_msg.duration,
+            )
+        }
+    }
+
+    /// Validate the parameters of the request.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.collateral == Amount::ZERO {
+            return Err(Error::InvalidParameters(
+                "Loan collateral must be greater than zero.".to_string(),
+            ));
+        }
+        if self.principal == 0 {
+            return Err(Error::InvalidParameters(
+                "Loan principal must be greater than zero.".to_string(),
+            ));
+        }
+        if self.max_interest_rate == 0 || self.max_interest_rate > 100 {
+            return Err(Error::InvalidParameters(
+                "Interest rate must be between 1 and 100.".to_string(),
+            ));
+        }
+        if self.duration == 0 {
+            return Err(Error::InvalidParameters(
+                "Loan duration must be greater than zero.".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks that the given loan offer answers the request, that is that it
+    /// lends the requested principal against the requested collateral for the
+    /// requested duration at a rate not above the maximum one.
+    pub fn validate_offer(&self, offered_loan_contract: &OfferedLoanContract) -> Result<(), Error> {
+        let offered_contract = &offered_loan_contract.offered_contract;
+        let borrower_collateral = offered_contract
+            .total_collateral
+            .checked_sub(offered_contract.offer_params.collateral)
+            .ok_or(Error::OutOfRange)?;
+        if borrower_collateral != self.collateral {
+            return Err(Error::InvalidParameters(
+                "Loan offer collateral does not match the request.".to_string(),
+            ));
+        }
+        if offered_loan_contract.principal != self.principal {
+            return Err(Error::InvalidParameters(
+                "Loan offer principal does not match the request.".to_string(),
+            ));
+        }
+        if offered_loan_contract.interest_rate > self.max_interest_rate {
+            return Err(Error::InvalidParameters(
+                "Loan offer interest rate is above the requested maximum.".to_string(),
+            ));
+        }
+        if offered_loan_contract.duration != self.duration {
+            return Err(Error::InvalidParameters(
+                "Loan offer duration does not match the request.".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl From<&LoanRequest> for RequestLoanDlc {
+    fn from(loan_request: &LoanRequest) -> RequestLoanDlc {
+        RequestLoanDlc {
+            protocol_version: PROTOCOL_VERSION,
+            request_id: loan_request.id,
+            collateral: loan_request.collateral,
+            principal: loan_request.principal,
+            max_interest_rate: loan_request.max_interest_rate,
+            duration: loan_request.duration,
+            listing_id: loan_request.listing_id,
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! #LoanState
+
+use crate::error::Error;
+
+/// The states of the lifecycle of a loan.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoanState {
+    /// The loan was offered and the borrower escrow is awaited or confirming.
+    AwaitingEscrow,
+    /// The borrower escrow is confirmed.
+    EscrowConfirmed,
+    /// The lender locked the stable loan in the lending contract.
+    StableLent,
+    /// The borrower claimed the stable loan, revealing its preimage.
+    StableClaimed,
+    /// The borrower accepted the loan but the lender did not sign it in time.
+    /// The lender can still publish the collateral transaction, so the loan
+    /// cannot be aborted anymore.
+    Stalled,
+    /// The collateral transaction was broadcast.
+    CollateralLocked,
+    /// The collateral transaction is confirmed.
+    Active,
+    /// The loan was repaid and the collateral released to the borrower.
+    Repaid,
+    /// The collateral was liquidated.
+    Liquidated,
+    /// The loan reached its maturity and was settled through a CET or the
+    /// refund transaction.
+    Expired,
+    /// The loan was abandoned before the collateral was locked.
+    Aborted,
+}
+
+impl LoanState {
+    /// Returns the position of the state on the path to an active loan.
+    fn get_setup_index(&self) -> Option<usize> {
+        match self {
+            LoanState::AwaitingEscrow => Some(0),
+            LoanState::EscrowConfirmed => Some(1),
+            LoanState::StableLent => Some(2),
+            LoanState::StableClaimed | LoanState::Stalled => Some(3),
+            LoanState::CollateralLocked => Some(4),
+            LoanState::Active => Some(5),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the state is final.
+    pub fn is_final(&self) -> bool {
+        self.get_setup_index().is_none()
+    }
+
+    /// Returns whether a loan can move from this state to the given one. Setup
+    /// states can only move forward, possibly skipping the steps that a party
+    /// does not observe. Loans can be aborted until their collateral is locked,
+    /// unless they stalled, and can only be settled afterwards.
+    pub fn can_transition_to(&self, next: LoanState) -> bool {
+        match (self, next) {
+            (LoanState::StableClaimed, LoanState::Stalled) => return true,
+            (_, LoanState::Stalled) | (LoanState::Stalled, LoanState::Aborted) => return false,
+            _ => {}
+        }
+        match (self.get_setup_index(), next.get_setup_index()) {
+            (Some(cur), Some(next)) => cur < next,
+            (Some(cur), None) => match next {
+                LoanState::Aborted => cur < 4,
+                _ => cur >= 4,
+            },
+            (None, _) => false,
+        }
+    }
+
+    /// Returns the action expected from the lender or the borrower to move the
+    /// loan forward from this state.
+    pub fn get_next_action(&self, is_lender: bool) -> &'static str {
+        match (self, is_lender) {
+            (LoanState::AwaitingEscrow, false) => "wait for the escrow to confirm",
+            (LoanState::AwaitingEscrow, true) => {
+                "wait for the borrower escrow to be published and confirm"
+            }
+            (LoanState::EscrowConfirmed, false) => "wait for the lender to lend the stables",
+            (LoanState::EscrowConfirmed, true) => "lend the stables",
+            (LoanState::StableLent, false) => "claim the stables",
+            (LoanState::StableLent, true) => "wait for the borrower to claim the stables",
+            (LoanState::StableClaimed, false) => "accept the loan offer",
+            (LoanState::StableClaimed, true) => "wait for the borrower to accept the loan",
+            (LoanState::Stalled, _) => "wait for the lender to sign the loan",
+            (LoanState::CollateralLocked, _) => "wait for the collateral to confirm",
+            (LoanState::Active, false) => "repay the loan before its maturity",
+            (LoanState::Active, true) => "wait for the repayment or the loan maturity",
+            _ => "none",
+        }
+    }
+}
+
+impl std::fmt::Display for LoanState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = match self {
+            LoanState::AwaitingEscrow => "awaiting escrow",
+            LoanState::EscrowConfirmed => "escrow confirmed",
+            LoanState::StableLent => "stable lent",
+            LoanState::StableClaimed => "stable claimed",
+            LoanState::Stalled => "stalled",
+            LoanState::CollateralLocked => "collateral locked",
+            LoanState::Active => "active",
+            LoanState::Repaid => "repaid",
+            LoanState::Liquidated => "liquidated",
+            LoanState::Expired => "expired",
+            LoanState::Aborted => "aborted",
+        };
+        f.write_str(state)
+    }
+}
+
+/// A state entered by a loan at a given time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoanStateTransition {
+    /// The state that was entered.
+    pub state: LoanState,
+    /// The unix time at which the state was entered.
+    pub timestamp: u64,
+}
+
+/// The lifecycle of a loan, recording every state it went through.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoanLifecycle {
+    /// Whether the local party is the lender of the loan.
+    pub is_lender: bool,
+    /// The states entered by the loan, in order.
+    pub transitions: Vec<LoanStateTransition>,
+}
+
+impl LoanLifecycle {
+    /// Creates the lifecycle of a newly offered loan.
+    pub fn new(is_lender: bool, timestamp: u64) -> Self {
+        LoanLifecycle {
+            is_lender,
+            transitions: vec![LoanStateTransition {
+                state: LoanState::AwaitingEscrow,
+                timestamp,
+            }],
+        }
+    }
+
+    /// Returns the current state of the loan.
+    pub fn get_state(&self) -> LoanState {
+        self.transitions
+            .last()
+            .map(|t| t.state)
+            .unwrap_or(LoanState::AwaitingEscrow)
+    }
+
+    /// Returns the action expected from the local party to move the loan
+    /// forward.
+    pub fn get_next_action(&self) -> &'static str {
+        self.get_state().get_next_action(self.is_lender)
+    }
+
+    /// Returns the unix time at which the loan entered its current state.
+    pub fn get_state_timestamp(&self) -> u64 {
+        self.transitions.last().map(|t| t.timestamp).unwrap_or(0)
+    }
+
+    /// Returns the unix time at which the collateral of the loan was locked,
+    /// or None if it was not locked yet.
+    pub fn get_start_time(&self) -> Option<u64> {
+        self.transitions
+            .iter()
+            .find(|t| t.state == LoanState::CollateralLocked || t.state == LoanState::Active)
+            .map(|t| t.timestamp)
+    }
+
+    /// Moves the loan to the given state, returning false if it was already
+    /// in it and an error if the transition is not allowed.
+    pub fn transition(&mut self, state: LoanState, timestamp: u64) -> Result<bool, Error> {
+        let cur_state = self.get_state();
+        if cur_state == state {
+            return Ok(false);
+        }
+        if !cur_state.can_transition_to(state) {
+            return Err(Error::InvalidState(format!(
+                "Invalid loan state transition from {} to {}",
+                cur_state, state
+            )));
+        }
+
+        self.transitions
+            .push(LoanStateTransition { state, timestamp });
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loan_lifecycle_test() {
+        let mut lifecycle = LoanLifecycle::new(true, 1);
+        assert_eq!(LoanState::AwaitingEscrow, lifecycle.get_state());
+        assert_eq!(
+            "wait for the borrower escrow to be published and confirm",
+            lifecycle.get_next_action()
+        );
+
+        assert!(lifecycle.transition(LoanState::StableLent, 2).unwrap());
+        assert_eq!(None, lifecycle.get_start_time());
+        assert!(!lifecycle.transition(LoanState::StableLent, 3).unwrap());
+        lifecycle
+            .transition(LoanState::EscrowConfirmed, 4)
+            .expect_err("should not move backward");
+        lifecycle
+            .transition(LoanState::Repaid, 4)
+            .expect_err("should not settle a loan without collateral");
+        assert!(lifecycle
+            .transition(LoanState::CollateralLocked, 5)
+            .unwrap());
+        lifecycle
+            .transition(LoanState::Aborted, 6)
+            .expect_err("should not abort a loan with locked collateral");
+        assert!(lifecycle.transition(LoanState::Active, 7).unwrap());
+        assert!(lifecycle.transition(LoanState::Repaid, 8).unwrap());
+        lifecycle
+            .transition(LoanState::Expired, 9)
+            .expect_err("should not leave a final state");
+
+        assert_eq!(LoanState::Repaid, lifecycle.get_state());
+        assert_eq!(8, lifecycle.get_state_timestamp());
+        assert_eq!(Some(5), lifecycle.get_start_time());
+        assert_eq!(5, lifecycle.transitions.len());
+    }
+
+    #[test]
+    fn loan_can_be_aborted_before_collateral_lock_test() {
+        let mut lifecycle = LoanLifecycle::new(false, 1);
+        lifecycle.transition(LoanState::StableClaimed, 2).unwrap();
+        assert!(lifecycle.transition(LoanState::Aborted, 3).unwrap());
+        assert!(lifecycle.get_state().is_final());
+    }
+
+    #[test]
+    fn stalled_loan_cannot_be_aborted_test() {
+        let mut lifecycle = LoanLifecycle::new(false, 1);
+        lifecycle
+            .transition(LoanState::Stalled, 2)
+            .expect_err("should only stall once the stables are claimed");
+        lifecycle.transition(LoanState::StableClaimed, 2).unwrap();
+        assert!(lifecycle.transition(LoanState::Stalled, 3).unwrap());
+        assert!(!lifecycle.get_state().is_final());
+        lifecycle
+            .transition(LoanState::Aborted, 4)
+            .expect_err("should not abort a loan whose collateral may be locked");
+        lifecycle
+            .transition(LoanState::StableClaimed, 4)
+            .expect_err("should not move backward");
+        assert!(lifecycle
+            .transition(LoanState::CollateralLocked, 5)
+            .unwrap());
+        assert_eq!(Some(5), lifecycle.get_start_time());
+    }
+}
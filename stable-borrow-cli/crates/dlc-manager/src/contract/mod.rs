@@ -2,7 +2,8 @@
 
 use crate::error::Error;
 use crate::ContractId;
-use bitcoin::{SignedAmount, Transaction};
+use crate::LendingDeployment;
+use bitcoin::{SignedAmount, Transaction, Txid};
 use dlc_messages::{
     oracle_msgs::{EventDescriptor, OracleAnnouncement, OracleAttestation},
     AcceptDlc, SignDlc,
@@ -20,6 +21,10 @@ pub mod accepted_contract;
 pub mod contract_info;
 pub mod contract_input;
 pub mod enum_descriptor;
+pub mod loan_negotiation;
+pub mod loan_renewal;
+pub mod loan_request;
+pub mod loan_state;
 pub mod numerical_descriptor;
 pub mod offered_contract;
 pub mod ser;
@@ -53,6 +58,21 @@ pub enum Contract {
     FailedSign(FailedSignContract),
     /// A contract that was rejected by the party to whom it was offered.
     Rejected(offered_contract::OfferedContract),
+    /// A loan contract for which the lender preimage was revealed, but whose
+    /// collateral sweep is not yet confirmed.
+    PreRepaid(RepaidContract),
+    /// A loan contract that was repaid.
+    Repaid(RepaidContract),
+    /// An offered loan contract whose escrow was swept back to the borrower
+    /// after its relative locktime expired.
+    EscrowReclaimed(EscrowReclaimedContract),
+    /// An offered loan contract that failed when verifying the stable loan
+    /// recorded by the lender.
+    FailedLoan(FailedLoanContract),
+    /// A loan contract offer that was declined by the borrower.
+    RejectedLoan(offered_contract::OfferedLoanContract),
+    /// A loan contract offer that was withdrawn by the lender.
+    CancelledLoan(offered_contract::OfferedLoanContract),
 }
 
 impl std::fmt::Debug for Contract {
@@ -70,6 +90,12 @@ impl std::fmt::Debug for Contract {
             Contract::FailedAccept(_) => "failed accept",
             Contract::FailedSign(_) => "failed sign",
             Contract::Rejected(_) => "rejected",
+            Contract::PreRepaid(_) => "pre-repaid",
+            Contract::Repaid(_) => "repaid",
+            Contract::EscrowReclaimed(_) => "escrow reclaimed",
+            Contract::FailedLoan(_) => "failed loan",
+            Contract::RejectedLoan(_) => "rejected loan",
+            Contract::CancelledLoan(_) => "cancelled loan",
         };
         f.debug_struct("Contract").field("state", &state).finish()
     }
@@ -81,7 +107,10 @@ impl Contract {
     pub fn get_id(&self) -> ContractId {
         match self {
             Contract::Offered(o) | Contract::Rejected(o) => o.id,
-            Contract::OfferedLoan(o) | Contract::OfferedLoanEscrowConfirmed(o) => o.offered_contract.id,
+            Contract::OfferedLoan(o)
+            | Contract::OfferedLoanEscrowConfirmed(o)
+            | Contract::RejectedLoan(o)
+            | Contract::CancelledLoan(o) => o.offered_contract.id,
             Contract::Accepted(o) => o.get_contract_id(),
             Contract::Signed(o) | Contract::Confirmed(o) | Contract::Refunded(o) => {
                 o.accepted_contract.get_contract_id()
@@ -90,6 +119,11 @@ impl Contract {
             Contract::FailedSign(c) => c.accepted_contract.get_contract_id(),
             Contract::PreClosed(c) => c.signed_contract.accepted_contract.get_contract_id(),
             Contract::Closed(c) => c.contract_id,
+            Contract::PreRepaid(c) | Contract::Repaid(c) => {
+                c.signed_contract.accepted_contract.get_contract_id()
+            }
+            Contract::EscrowReclaimed(c) => c.offered_loan_contract.offered_contract.id,
+            Contract::FailedLoan(c) => c.offered_loan_contract.offered_contract.id,
         }
     }
 
@@ -97,7 +131,10 @@ impl Contract {
     pub fn get_temporary_id(&self) -> ContractId {
         match self {
             Contract::Offered(o) | Contract::Rejected(o) => o.id,
-            Contract::OfferedLoan(o) | Contract::OfferedLoanEscrowConfirmed(o) => o.offered_contract.id,
+            Contract::OfferedLoan(o)
+            | Contract::OfferedLoanEscrowConfirmed(o)
+            | Contract::RejectedLoan(o)
+            | Contract::CancelledLoan(o) => o.offered_contract.id,
             Contract::Accepted(o) => o.offered_contract.id,
             Contract::Signed(o) | Contract::Confirmed(o) | Contract::Refunded(o) => {
                 o.accepted_contract.offered_contract.id
@@ -106,6 +143,11 @@ impl Contract {
             Contract::FailedSign(c) => c.accepted_contract.offered_contract.id,
             Contract::PreClosed(c) => c.signed_contract.accepted_contract.offered_contract.id,
             Contract::Closed(c) => c.temporary_contract_id,
+            Contract::PreRepaid(c) | Contract::Repaid(c) => {
+                c.signed_contract.accepted_contract.offered_contract.id
+            }
+            Contract::EscrowReclaimed(c) => c.offered_loan_contract.offered_contract.id,
+            Contract::FailedLoan(c) => c.offered_loan_contract.offered_contract.id,
         }
     }
 
@@ -113,7 +155,10 @@ impl Contract {
     pub fn get_counter_party_id(&self) -> PublicKey {
         match self {
             Contract::Offered(o) | Contract::Rejected(o) => o.counter_party,
-            Contract::OfferedLoan(o) | Contract::OfferedLoanEscrowConfirmed(o) => o.offered_contract.counter_party,
+            Contract::OfferedLoan(o)
+            | Contract::OfferedLoanEscrowConfirmed(o)
+            | Contract::RejectedLoan(o)
+            | Contract::CancelledLoan(o) => o.offered_contract.counter_party,
             Contract::Accepted(a) => a.offered_contract.counter_party,
             Contract::Signed(s) | Contract::Confirmed(s) | Contract::Refunded(s) => {
                 s.accepted_contract.offered_contract.counter_party
@@ -127,6 +172,14 @@ impl Contract {
             Contract::Closed(c) => c.counter_party_id,
             Contract::FailedAccept(f) => f.offered_contract.counter_party,
             Contract::FailedSign(f) => f.accepted_contract.offered_contract.counter_party,
+            Contract::PreRepaid(c) | Contract::Repaid(c) => {
+                c.signed_contract
+                    .accepted_contract
+                    .offered_contract
+                    .counter_party
+            }
+            Contract::EscrowReclaimed(c) => c.offered_loan_contract.offered_contract.counter_party,
+            Contract::FailedLoan(c) => c.offered_loan_contract.offered_contract.counter_party,
         }
     }
 }
@@ -181,6 +234,73 @@ pub struct ClosedContract {
     pub pnl: SignedAmount,
 }
 
+/// Information about a loan contract that was repaid, releasing the collateral
+/// to the borrower.
+#[derive(Clone)]
+pub struct RepaidContract {
+    /// The signed contract that was repaid.
+    pub signed_contract: SignedContract,
+    /// The lender preimage that was revealed upon repayment.
+    pub lender_preimage: u128,
+    /// The transaction sweeping the collateral to the borrower. On the lender
+    /// side it is unsigned and only used to watch for the sweep.
+    pub collateral_sweep_tx: Option<Transaction>,
+}
+
+/// Information about an offered loan contract whose escrow was reclaimed by
+/// the borrower through the relative timelock branch.
+#[derive(Clone)]
+pub struct EscrowReclaimedContract {
+    /// The offered loan contract that was abandoned.
+    pub offered_loan_contract: offered_contract::OfferedLoanContract,
+    /// The signed transaction returning the escrow to the borrower.
+    pub escrow_reclaim_tx: Transaction,
+}
+
+/// Information about an offered loan contract for which the stable loan
+/// recorded by the lender did not match the offered terms.
+#[derive(Clone)]
+pub struct FailedLoanContract {
+    /// The offered loan contract that failed.
+    pub offered_loan_contract: offered_contract::OfferedLoanContract,
+    /// The error message that was generated.
+    pub error_message: String,
+}
+
+/// The terms of a loan along with the data gathered while setting it up,
+/// stored alongside the contract (under its temporary id) so that the loan
+/// context of a contract is available in every state. The preimages of the
+/// loan are not part of it: each party keeps its own in the loan preimage
+/// store, and the lender preimage ends up in the repaid contract.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoanTerms {
+    /// Collateralization ratio in percent.
+    pub collateral_ratio: u64,
+    /// Liquidation ratio in percent.
+    pub liquidation_ratio: u64,
+    /// Interest rate in percent.
+    pub interest_rate: u64,
+    /// Duration of the loan in seconds.
+    pub duration: u64,
+    /// The principal of the loan, in the unit of the price attested by the
+    /// oracles.
+    pub principal: u64,
+    /// The lending contract deployment holding the stable side of the loan.
+    pub lending_deployment: LendingDeployment,
+    /// Lender's hash value for the collateral transaction.
+    pub lender_hash: [u8; 32],
+    /// Delay in seconds after which the borrower can reclaim the escrow.
+    pub escrow_relative_locktime: u32,
+    /// Number of confirmations of the escrow required before lending.
+    pub escrow_confirmations: u32,
+    /// Unix timestamp after which the offer could no longer be accepted.
+    pub offer_expiry: u64,
+    /// The id of the escrow transaction, if known.
+    pub escrow_txid: Option<Txid>,
+    /// The hash of the borrower preimage, if known.
+    pub borrower_hash: Option<[u8; 32]>,
+}
+
 /// Information about the adaptor signatures and the CET for which they are
 /// valid.
 #[derive(Clone)]
@@ -67,6 +67,17 @@ impl NumericalDescriptor {
         self.payout_function.validate(max_value)
     }
 
+    /// Returns whether the payout function stops before the maximum outcome
+    /// that the oracles can attest, as for the liquidation events of a loan
+    /// which only have CETs below the liquidation price.
+    pub fn is_truncated(&self) -> bool {
+        let max_value = (self.oracle_numeric_infos.base as u64)
+            .checked_pow(self.oracle_numeric_infos.get_min_nb_digits() as u32)
+            .map(|x| x - 1)
+            .unwrap_or(u64::MAX);
+        self.payout_function.get_last_outcome() < max_value
+    }
+
     /// Returns the set of payouts for the descriptor generated from the payout
     /// function.
     pub fn get_payouts(&self, total_collateral: Amount) -> Result<Vec<Payout>, Error> {
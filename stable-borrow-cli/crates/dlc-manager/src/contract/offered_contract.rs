@@ -7,9 +7,11 @@ use crate::utils::get_new_serial_id;
 
 use super::contract_info::ContractInfo;
 use super::contract_input::ContractInput;
+use super::numerical_descriptor::NumericalDescriptor;
 use super::ContractDescriptor;
-use crate::{ContractId, KeysId};
-use bitcoin::Amount;
+use crate::payout_curve::LoanPayoutCurveBuilder;
+use crate::{ContractId, KeysId, LendingDeployment};
+use bitcoin::{Amount, Sequence};
 use dlc::PartyParams;
 use dlc_messages::oracle_msgs::OracleAnnouncement;
 use dlc_messages::{FundingInput, OfferDlc, OfferLoanDlc};
@@ -54,29 +56,37 @@ impl OfferedContract {
     /// Validate that the contract info covers all the possible outcomes that
     /// can be attested by the oracle(s).
     pub fn validate(&self) -> Result<(), crate::error::Error> {
-        dlc::util::validate_fee_rate(self.fee_rate_per_vb).map_err(|_| {
-            crate::error::Error::InvalidParameters("Fee rate is too high".to_string())
-        })?;
+        self.validate_fee_rate()?;
 
         for info in &self.contract_info {
             info.validate()?;
-            let payouts = match &info.contract_descriptor {
-                ContractDescriptor::Enum(e) => e.get_payouts(),
-                ContractDescriptor::Numerical(e) => e.get_payouts(self.total_collateral)?,
-            };
-            let valid = payouts
-                .iter()
-                .all(|p| p.accept + p.offer == self.total_collateral);
-            if !valid {
-                return Err(crate::error::Error::InvalidParameters(
-                    "Sum of payout doesn't equal total collateral".to_string(),
-                ));
-            }
+            self.validate_payouts(info)?;
         }
 
         Ok(())
     }
 
+    fn validate_fee_rate(&self) -> Result<(), crate::error::Error> {
+        dlc::util::validate_fee_rate(self.fee_rate_per_vb)
+            .map_err(|_| crate::error::Error::InvalidParameters("Fee rate is too high".to_string()))
+    }
+
+    fn validate_payouts(&self, info: &ContractInfo) -> Result<(), crate::error::Error> {
+        let payouts = match &info.contract_descriptor {
+            ContractDescriptor::Enum(e) => e.get_payouts(),
+            ContractDescriptor::Numerical(e) => e.get_payouts(self.total_collateral)?,
+        };
+        let valid = payouts
+            .iter()
+            .all(|p| p.accept + p.offer == self.total_collateral);
+        if !valid {
+            return Err(crate::error::Error::InvalidParameters(
+                "Sum of payout doesn't equal total collateral".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
     /// Creates a new [`OfferedContract`] from the given parameters.
     pub fn new(
         id: ContractId,
@@ -200,15 +210,67 @@ pub struct OfferedLoanContract {
     pub interest_rate: u64,
     /// Duration of the loan in seconds.
     pub duration: u64,
+    /// The principal of the loan, in the unit of the price attested by the
+    /// oracles.
+    pub principal: u64,
+    /// The lending contract deployment holding the stable side of the loan.
+    pub lending_deployment: LendingDeployment,
     /// Lender's hash value for the collateral transaction.
     pub lender_hash: [u8; 32],
+    /// Delay in seconds after which the borrower can reclaim the escrow.
+    pub escrow_relative_locktime: u32,
+    /// Number of confirmations of the escrow required before lending.
+    pub escrow_confirmations: u32,
+    /// Unix timestamp after which the offer can no longer be accepted.
+    pub offer_expiry: u64,
+    /// The id of the loan request answered by the offer, if any.
+    pub request_id: Option<[u8; 32]>,
+    /// The id of the negotiation the offer was re-issued for, if any.
+    pub negotiation_id: Option<[u8; 32]>,
 }
 
 impl OfferedLoanContract {
-    /// Validate that the contract info covers all the possible outcomes that
-    /// can be attested by the oracle(s).
+    /// Validate that the expiration contract infos cover all the possible
+    /// outcomes that can be attested by the oracle(s), and that the
+    /// liquidation ones only cover the outcomes below the liquidation price
+    /// and mature before the expiration of the loan.
     pub fn validate(&self) -> Result<(), crate::error::Error> {
-        self.offered_contract.validate()?;
+        let offered_contract = &self.offered_contract;
+        offered_contract.validate_fee_rate()?;
+
+        let mut expiration_maturity = None;
+        let mut liquidation_maturity = None;
+        for info in &offered_contract.contract_info {
+            let maturities = info
+                .oracle_announcements
+                .iter()
+                .map(|x| x.oracle_event.event_maturity_epoch);
+            match &info.contract_descriptor {
+                ContractDescriptor::Numerical(n) if n.is_truncated() => {
+                    self.validate_liquidation_contract_info(info, n)?;
+                    liquidation_maturity = maturities.chain(liquidation_maturity).max();
+                }
+                _ => {
+                    info.validate()?;
+                    expiration_maturity = maturities.chain(expiration_maturity).min();
+                }
+            }
+            offered_contract.validate_payouts(info)?;
+        }
+
+        match (expiration_maturity, liquidation_maturity) {
+            (None, _) => {
+                return Err(crate::error::Error::InvalidParameters(
+                    "Loan contract must have an expiration event".to_string(),
+                ))
+            }
+            (Some(expiration), Some(liquidation)) if liquidation >= expiration => {
+                return Err(crate::error::Error::InvalidParameters(
+                    "Liquidation events must mature before the expiration event".to_string(),
+                ))
+            }
+            _ => {}
+        }
 
         if self.collateral_ratio <= self.liquidation_ratio {
             return Err(crate::error::Error::InvalidParameters(
@@ -216,17 +278,65 @@ impl OfferedLoanContract {
             ));
         }
 
+        self.get_escrow_relative_locktime()?;
+
+        Ok(())
+    }
+
+    fn validate_liquidation_contract_info(
+        &self,
+        info: &ContractInfo,
+        descriptor: &NumericalDescriptor,
+    ) -> Result<(), crate::error::Error> {
+        if info.oracle_announcements.is_empty() {
+            return Err(crate::error::Error::InvalidState(
+                "ContractInfo doesn't contain any announcement.".to_string(),
+            ));
+        }
+        descriptor.rounding_intervals.validate()?;
+
+        let last_outcome = descriptor.payout_function.get_last_outcome();
+        descriptor.payout_function.validate(last_outcome)?;
+        let liquidation_outcome = LoanPayoutCurveBuilder {
+            principal: self.principal,
+            interest_rate: self.interest_rate,
+            duration: self.duration,
+            liquidation_ratio: self.liquidation_ratio,
+            total_collateral: self.offered_contract.total_collateral,
+            oracle_numeric_info: descriptor.oracle_numeric_infos.clone(),
+        }
+        .get_liquidation_outcome()?;
+        if last_outcome != liquidation_outcome {
+            return Err(crate::error::Error::InvalidParameters(
+                "Liquidation event must only cover outcomes below the liquidation price"
+                    .to_string(),
+            ));
+        }
+
         Ok(())
     }
 
-    /// Creates a new [`OfferedLoanContract`] from the given parameters.
+    /// Returns the relative locktime of the escrow output.
+    pub fn get_escrow_relative_locktime(&self) -> Result<Sequence, crate::error::Error> {
+        dlc::get_loan_escrow_relative_locktime(self.escrow_relative_locktime).map_err(|_| {
+            crate::error::Error::InvalidParameters("Invalid escrow relative locktime".to_string())
+        })
+    }
+
+    /// Creates a new [`OfferedLoanContract`] from the given parameters, not
+    /// bound to any loan request or negotiation.
     pub fn new(
         offered_contract: OfferedContract,
         collateral_ratio: u64,
         liquidation_ratio: u64,
         interest_rate: u64,
         duration: u64,
+        principal: u64,
+        lending_deployment: LendingDeployment,
         lender_hash: [u8; 32],
+        escrow_relative_locktime: u32,
+        escrow_confirmations: u32,
+        offer_expiry: u64,
     ) -> Self {
         OfferedLoanContract {
             offered_contract,
@@ -234,9 +344,42 @@ impl OfferedLoanContract {
             liquidation_ratio,
             interest_rate,
             duration,
+            principal,
+            lending_deployment,
             lender_hash,
+            escrow_relative_locktime,
+            escrow_confirmations,
+            offer_expiry,
+            request_id: None,
+            negotiation_id: None,
         }
     }
+
+    /// Returns the id of the negotiation of the loan terms, which is the
+    /// temporary id of the first offer of the negotiation.
+    pub fn get_negotiation_id(&self) -> [u8; 32] {
+        self.negotiation_id.unwrap_or(self.offered_contract.id)
+    }
+
+    /// Returns the terms of the loan, without any of the data gathered while
+    /// setting it up.
+    pub fn get_loan_terms(&self) -> super::LoanTerms {
+        super::LoanTerms {
+            collateral_ratio: self.collateral_ratio,
+            liquidation_ratio: self.liquidation_ratio,
+            interest_rate: self.interest_rate,
+            duration: self.duration,
+            principal: self.principal,
+            lending_deployment: self.lending_deployment,
+            lender_hash: self.lender_hash,
+            escrow_relative_locktime: self.escrow_relative_locktime,
+            escrow_confirmations: self.escrow_confirmations,
+            offer_expiry: self.offer_expiry,
+            escrow_txid: None,
+            borrower_hash: None,
+        }
+    }
+
     /// Convert an [`OfferLoanDlc`] message to an [`OfferedLoanContract`].
     pub fn try_from_offer_loan_dlc(
         offer_loan_dlc: &OfferLoanDlc,
@@ -255,7 +398,18 @@ impl OfferedLoanContract {
             liquidation_ratio: offer_loan_dlc.liquidation_ratio,
             interest_rate: offer_loan_dlc.interest_rate,
             duration: offer_loan_dlc.duration,
+            principal: offer_loan_dlc.principal,
+            lending_deployment: LendingDeployment {
+                chain_id: offer_loan_dlc.chain_id,
+                lending_contract_address: offer_loan_dlc.lending_contract_address,
+                stable_token_address: offer_loan_dlc.stable_token_address,
+            },
             lender_hash: offer_loan_dlc.lender_hash,
+            escrow_relative_locktime: offer_loan_dlc.escrow_relative_locktime,
+            escrow_confirmations: offer_loan_dlc.escrow_confirmations,
+            offer_expiry: offer_loan_dlc.offer_expiry,
+            request_id: offer_loan_dlc.request_id,
+            negotiation_id: offer_loan_dlc.negotiation_id,
         })
     }
 }
@@ -268,7 +422,16 @@ impl From<&OfferedLoanContract> for OfferLoanDlc {
             liquidation_ratio: loan_contract.liquidation_ratio,
             interest_rate: loan_contract.interest_rate,
             duration: loan_contract.duration,
+            principal: loan_contract.principal,
+            stable_token_address: loan_contract.lending_deployment.stable_token_address,
+            lending_contract_address: loan_contract.lending_deployment.lending_contract_address,
+            chain_id: loan_contract.lending_deployment.chain_id,
             lender_hash: loan_contract.lender_hash,
+            escrow_relative_locktime: loan_contract.escrow_relative_locktime,
+            escrow_confirmations: loan_contract.escrow_confirmations,
+            offer_expiry: loan_contract.offer_expiry,
+            request_id: loan_contract.request_id,
+            negotiation_id: loan_contract.negotiation_id,
         }
     }
 }
@@ -9,7 +9,8 @@ use crate::contract::offered_contract::OfferedContract;
 use crate::contract::signed_contract::SignedContract;
 use crate::contract::AdaptorInfo;
 use crate::contract::{
-    ClosedContract, ContractDescriptor, FailedAcceptContract, FailedSignContract, PreClosedContract,
+    ClosedContract, ContractDescriptor, EscrowReclaimedContract, FailedAcceptContract,
+    FailedLoanContract, FailedSignContract, LoanTerms, PreClosedContract, RepaidContract,
 };
 use crate::payout_curve::{
     HyperbolaPayoutCurvePiece, PayoutFunction, PayoutFunctionPiece, PayoutPoint,
@@ -29,6 +30,10 @@ use lightning::io::Read;
 use lightning::ln::msgs::DecodeError;
 use lightning::util::ser::{Readable, Writeable, Writer};
 
+use super::loan_negotiation::{LoanNegotiation, LoanNegotiationState, LoanTermsRevision};
+use super::loan_renewal::{LoanRenewal, LoanRenewalState};
+use super::loan_request::{LoanRequest, LoanRequestState};
+use super::loan_state::{LoanLifecycle, LoanState, LoanStateTransition};
 use super::offered_contract::OfferedLoanContract;
 
 /// Trait used to de/serialize an object to/from a vector of bytes.
@@ -108,7 +113,79 @@ impl_dlc_writeable!(OfferedLoanContract, {
     (liquidation_ratio, writeable),
     (interest_rate, writeable),
     (duration, writeable),
-    (lender_hash, writeable)
+    (principal, writeable),
+    (lending_deployment, writeable),
+    (lender_hash, writeable),
+    (escrow_relative_locktime, writeable),
+    (escrow_confirmations, writeable),
+    (offer_expiry, writeable),
+    (request_id, option),
+    (negotiation_id, option)
+});
+impl_dlc_writeable!(LoanTerms, {
+    (collateral_ratio, writeable),
+    (liquidation_ratio, writeable),
+    (interest_rate, writeable),
+    (duration, writeable),
+    (principal, writeable),
+    (lending_deployment, writeable),
+    (lender_hash, writeable),
+    (escrow_relative_locktime, writeable),
+    (escrow_confirmations, writeable),
+    (offer_expiry, writeable),
+    (escrow_txid, option),
+    (borrower_hash, option)
+});
+impl_dlc_writeable_enum!(LoanState,;;;
+    (0, AwaitingEscrow),
+    (1, EscrowConfirmed),
+    (2, StableLent),
+    (3, StableClaimed),
+    (4, CollateralLocked),
+    (5, Active),
+    (6, Repaid),
+    (7, Liquidated),
+    (8, Expired),
+    (9, Aborted),
+    (10, Stalled)
+);
+impl_dlc_writeable!(LoanStateTransition, {(state, writeable), (timestamp, writeable)});
+impl_dlc_writeable!(LoanLifecycle, { (is_lender, writeable), (transitions, vec) });
+impl_dlc_writeable_enum!(LoanRequestState,;;;
+    (0, Pending),
+    (1, Offered),
+    (2, Rejected)
+);
+impl_dlc_writeable!(LoanRequest, {
+    (id, writeable),
+    (counter_party, writeable),
+    (is_borrower, writeable),
+    (collateral, writeable),
+    (principal, writeable),
+    (max_interest_rate, writeable),
+    (duration, writeable),
+    (state, writeable),
+    (contract_id, option),
+    (listing_id, option)
+});
+impl_dlc_writeable_enum!(LoanNegotiationState,;;;
+    (0, Offered),
+    (1, Countered),
+    (2, Rejected)
+);
+impl_dlc_writeable!(LoanTermsRevision, {
+    (countered_contract_id, writeable),
+    (collateral_ratio, writeable),
+    (interest_rate, writeable),
+    (duration, writeable)
+});
+impl_dlc_writeable!(LoanNegotiation, {
+    (id, writeable),
+    (counter_party, writeable),
+    (is_lender, writeable),
+    (revisions, vec),
+    (state, writeable),
+    (contract_id, writeable)
 });
 impl_dlc_writeable_external!(RangeInfo, range_info, { (cet_index, usize), (adaptor_index, usize)});
 impl_dlc_writeable_enum!(AdaptorInfo,;; (0, Numerical, write_multi_oracle_trie, read_multi_oracle_trie), (1, NumericalWithDifference, write_multi_oracle_trie_with_diff, read_multi_oracle_trie_with_diff); (2, Enum));
@@ -135,6 +212,23 @@ impl_dlc_writeable!(SignedContract, {
     (funding_signatures, writeable),
     (channel_id, option)
 });
+impl_dlc_writeable_enum!(LoanRenewalState,;;;
+    (0, Offered),
+    (1, Accepted),
+    (2, Confirmed),
+    (3, Completed)
+);
+impl_dlc_writeable!(LoanRenewal, {
+    (contract_id, writeable),
+    (counter_party, writeable),
+    (is_borrower, writeable),
+    (additional_collateral, writeable),
+    (repaid_principal, writeable),
+    (stable_loan_amount, writeable),
+    (state, writeable),
+    (accepted_contract, writeable),
+    (signed_contract, option)
+});
 impl_dlc_writeable!(PreClosedContract, {
     (signed_contract, writeable),
     (attestations, {option_cb, write_vec, read_vec}),
@@ -148,6 +242,16 @@ impl_dlc_writeable!(ClosedContract, {
     (counter_party_id, writeable),
     (pnl, SignedAmount)
 });
+impl_dlc_writeable!(RepaidContract, {
+    (signed_contract, writeable),
+    (lender_preimage, writeable),
+    (collateral_sweep_tx, writeable)
+});
+impl_dlc_writeable!(EscrowReclaimedContract, {
+    (offered_loan_contract, writeable),
+    (escrow_reclaim_tx, writeable)
+});
+impl_dlc_writeable!(FailedLoanContract, {(offered_loan_contract, writeable), (error_message, string)});
 impl_dlc_writeable!(FailedAcceptContract, {(offered_contract, writeable), (accept_message, writeable), (error_message, string)});
 impl_dlc_writeable!(FailedSignContract, {(accepted_contract, writeable), (sign_message, writeable), (error_message, string)});
 
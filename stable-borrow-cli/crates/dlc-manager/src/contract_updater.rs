@@ -2,14 +2,15 @@
 
 use std::ops::Deref;
 
-use bitcoin::consensus::Encodable;
+use bitcoin::consensus::Decodable;
 use bitcoin::hashes::Hash;
-use bitcoin::hex::DisplayHex;
-use bitcoin::{Amount, EcdsaSighashType, ScriptBuf, Sequence, TxIn, Txid};
+use bitcoin::psbt::Psbt;
+use bitcoin::{Amount, EcdsaSighashType, OutPoint, ScriptBuf, TxIn, TxOut, Txid};
 use bitcoin::{Script, Transaction, Witness};
 use dlc::util::get_sig_for_tx_input;
 use dlc::{create_collateral_transaction, make_loan_escrow_after_codesep_script, make_loan_funding_redeemscript, DlcTransactions, PartyParams};
 use dlc_messages::{AcceptLoanDlc, FundingInput, OfferLoanDlc};
+use dlc_messages::{RenewLoanAcceptDlc, RenewLoanConfirmDlc, RenewLoanOfferDlc};
 use dlc_messages::{
     oracle_msgs::{OracleAnnouncement, OracleAttestation},
     AcceptDlc, FundingSignature, FundingSignatures, OfferDlc, SignDlc, WitnessElement,
@@ -22,6 +23,10 @@ use secp256k1_zkp::{
 use crate::contract::accepted_contract::AcceptedLoanContract;
 // use crate::contract::accepted_contract::AcceptedLoanContract;
 use crate::contract::contract_input::LoanContractInput;
+use crate::contract::loan_renewal::validate_loan_renewal;
+use crate::contract::numerical_descriptor::NumericalDescriptor;
+use crate::contract::{ContractDescriptor, LoanTerms};
+use crate::payout_curve::LoanPayoutCurveBuilder;
 use crate::contract::offered_contract::{OfferedLoanContract};
 use crate::{
     contract::{
@@ -31,7 +36,7 @@ use crate::{
     },
     conversion_utils::get_tx_input_infos,
     error::Error,
-    Blockchain, ChannelId, ContractSigner, ContractSignerProvider, Time, Wallet,
+    Blockchain, ChannelId, ContractSigner, ContractSignerProvider, StableLoan, Time, Wallet,
 };
 
 /// Creates an [`OfferedContract`] and [`OfferDlc`] message from the provided
@@ -85,7 +90,8 @@ where
 }
 
 /// Creates an [`OfferedLoanContract`] and [`OfferLoanDlc`] message from the provided
-/// contract and oracle information.
+/// contract and oracle information, answering the loan request or re-issued for
+/// the negotiation with the given ids if any.
 pub fn offer_loan_contract<W: Deref, B: Deref, T: Deref, X: ContractSigner, SP: Deref, C: Signing>(
     secp: &Secp256k1<C>,
     loan_contract_input: &LoanContractInput,
@@ -93,6 +99,8 @@ pub fn offer_loan_contract<W: Deref, B: Deref, T: Deref, X: ContractSigner, SP:
     refund_delay: u32,
     counter_party: &PublicKey,
     lender_preimage: u128,
+    request_id: Option<[u8; 32]>,
+    negotiation_id: Option<[u8; 32]>,
     wallet: &W,
     blockchain: &B,
     time: &T,
@@ -122,7 +130,7 @@ where
         offer_collateral: Amount::ZERO,
         accept_collateral: loan_contract_input.collateral,
         fee_rate: loan_contract_input.fee_rate,
-        contract_infos: loan_contract_input.contract_infos.clone(),
+        contract_infos: loan_contract_input.get_contract_input_infos()?,
     };
 
     let offered_contract = OfferedContract::new(
@@ -143,7 +151,14 @@ where
         liquidation_ratio: loan_contract_input.liquidation_ratio,
         interest_rate: loan_contract_input.interest_rate,
         duration: loan_contract_input.duration,
+        principal: loan_contract_input.principal,
+        lending_deployment: loan_contract_input.get_lending_deployment(),
         lender_hash: lender_hash,
+        escrow_relative_locktime: loan_contract_input.escrow_relative_locktime,
+        escrow_confirmations: loan_contract_input.escrow_confirmations,
+        offer_expiry: time.unix_time_now() + loan_contract_input.offer_validity,
+        request_id,
+        negotiation_id,
     };
 
     let offer_msg: OfferLoanDlc = (&offered_loan_contract).into();
@@ -168,7 +183,7 @@ where
     let total_collateral = offered_contract.total_collateral;
 
     let signer = signer_provider.derive_contract_signer(offered_contract.keys_id)?;
-    let (accept_params, funding_inputs) = crate::utils::get_party_params(
+    let (accept_params, funding_inputs) = crate::utils::get_borrower_party_params(
         secp,
         total_collateral - offered_contract.offer_params.collateral,
         offered_contract.fee_rate_per_vb,
@@ -307,7 +322,7 @@ pub(crate) fn accept_contract_internal(
 pub fn accept_loan_contract<W: Deref, X: ContractSigner, SP: Deref, B: Deref>(
     secp: &Secp256k1<All>,
     offered_loan_contract: &OfferedLoanContract,
-    escrow_txid: Txid,
+    escrow_outpoint: OutPoint,
     borrower_hash: [u8; 32],
     borrower_preimage_bytes: &[u8],
     wallet: &W,
@@ -323,33 +338,36 @@ where
     let total_collateral = offered_loan_contract.offered_contract.total_collateral;
     let signer = signer_provider.derive_contract_signer(offered_loan_contract.offered_contract.keys_id)?;
     let borrower_pubkey = signer.get_public_key(secp)?;
-    println!("Borrower pubkey: {}", borrower_pubkey);
     let lender_pubkey = offered_loan_contract.offered_contract.offer_params.fund_pubkey;
     
-    let locktime = Sequence::from_hex("0x64004000").unwrap(); // 51200 seconds
     let funding_script_pubkey =  // TODO: Rename function to make loan escrow redeem script
-        make_loan_funding_redeemscript(&borrower_pubkey, &lender_pubkey, locktime, &borrower_hash);
+        make_loan_funding_redeemscript(&borrower_pubkey, &lender_pubkey, offered_loan_contract.get_escrow_relative_locktime()?, &borrower_hash);
     let funding_script_pubkey_to_sign = 
         make_loan_escrow_after_codesep_script(&lender_pubkey, &borrower_hash);
 
     let escrow_input: TxIn = TxIn {
-        previous_output: bitcoin::OutPoint {
-            txid: escrow_txid,
-            vout: 0,
-        },
+        previous_output: escrow_outpoint,
         script_sig: Script::new().into(),
         sequence: bitcoin::Sequence(0xFFFFFFFF),
         witness: Witness::default(),
     };
 
-    let escrow_tx = blockchain.get_transaction(&escrow_txid).unwrap();
-    let escrow_output_amount = escrow_tx.output[0].value;
+    let escrow_tx = blockchain.get_transaction(&escrow_outpoint.txid)?;
+    let escrow_output_amount = escrow_tx
+        .output
+        .get(escrow_outpoint.vout as usize)
+        .ok_or_else(|| Error::InvalidState("Escrow output not found".to_string()))?
+        .value;
 
-    let mut writer = Vec::new();
-    let _ = escrow_tx.consensus_encode(&mut writer);
-    println!("raw escrow tx {}", writer.to_lower_hex_string());
-
-    let (mut collateral_tx, collateral_script_pubkey) = create_collateral_transaction(&borrower_pubkey, &lender_pubkey, escrow_output_amount, escrow_input, offered_loan_contract.lender_hash).unwrap();
+    let (mut collateral_tx, collateral_script_pubkey) = create_collateral_transaction(
+        &borrower_pubkey,
+        &lender_pubkey,
+        escrow_output_amount,
+        escrow_input,
+        &funding_script_pubkey,
+        offered_loan_contract.lender_hash,
+        offered_loan_contract.offered_contract.fee_rate_per_vb,
+    )?;
     let borrower_signature = get_sig_for_tx_input(
         secp,
         &collateral_tx,
@@ -357,8 +375,8 @@ where
         &funding_script_pubkey_to_sign,
         escrow_output_amount,
         EcdsaSighashType::All,
-        &signer.get_secret_key().unwrap())
-    .unwrap();
+        &signer.get_secret_key()?,
+    )?;
 
     collateral_tx.input[0].witness = Witness::from_slice(&[
         Vec::new(), // Placeholder for the other party's signature
@@ -399,7 +417,7 @@ where
         collateral_output_value,
         None,
         &dlc_transactions,
-        escrow_txid,
+        escrow_outpoint.txid,
         borrower_hash,
         collateral_tx_clone,
     )?;
@@ -538,15 +556,17 @@ pub fn accept_loan_contract_internal(
     Ok((accepted_contract, adaptor_sigs))
 }
 
-/// Creates and sends the escrow transaction from the borrower side (accepting party).
-pub fn send_escrow_transaction<W: Deref, X: ContractSigner, SP: Deref, B: Deref>(
+/// Creates and signs the escrow transaction from the borrower side (accepting
+/// party), returning it along with the outpoint of its escrow output. The
+/// transaction is not broadcast.
+pub fn create_escrow_transaction<W: Deref, X: ContractSigner, SP: Deref, B: Deref>(
     secp: &Secp256k1<All>,
     offered_loan_contract: &OfferedLoanContract,
     borrower_preimage: u128,
     wallet: &W,
     signer_provider: &SP,
     blockchain: &B,
-) -> Result<Txid, Box<dyn std::error::Error>>
+) -> Result<(Transaction, OutPoint), Error>
 where
     W::Target: Wallet,
     B::Target: Blockchain,
@@ -556,7 +576,7 @@ where
     let total_collateral = offered_contract.total_collateral;
 
     let signer = signer_provider.derive_contract_signer(offered_contract.keys_id)?;
-    let (accept_params, funding_inputs) = crate::utils::get_party_params(
+    let (accept_params, funding_inputs) = crate::utils::get_borrower_party_params(
         secp,
         total_collateral - offered_contract.offer_params.collateral,
         offered_contract.fee_rate_per_vb,
@@ -565,20 +585,244 @@ where
         blockchain,
     )?; 
 
-    let (escrow_tx, _script_buf) = dlc::create_escrow_transaction(
+    let (escrow_tx, escrow_redeemscript) = dlc::create_escrow_transaction(
         &offered_contract.offer_params,
         &accept_params,
         offered_contract.fee_rate_per_vb,
         offered_contract.refund_locktime,
-        offered_contract.fund_output_serial_id,
-        Amount::from_sat(300),
+        Amount::ZERO,
         borrower_preimage,
+        &offered_loan_contract.lender_hash,
+        offered_loan_contract.get_escrow_relative_locktime()?,
     )?;
 
+    let (escrow_vout, _) = get_escrow_output(&escrow_tx, &escrow_redeemscript)?;
     let signed_escrow_tx = wallet.sign_raw_transaction(&escrow_tx)?;
-    blockchain.send_transaction(&signed_escrow_tx)?;
-    let txid = escrow_tx.compute_txid();
-    return Ok(txid);
+    let escrow_outpoint = OutPoint {
+        txid: escrow_tx.compute_txid(),
+        vout: escrow_vout,
+    };
+    Ok((signed_escrow_tx, escrow_outpoint))
+}
+
+/// Returns the index and the output of the given escrow transaction that pays
+/// to the given escrow redeem script.
+pub fn get_escrow_output<'a>(
+    escrow_tx: &'a Transaction,
+    escrow_redeemscript: &Script,
+) -> Result<(u32, &'a TxOut), Error> {
+    let escrow_script_pubkey = escrow_redeemscript.to_p2wsh();
+    escrow_tx
+        .output
+        .iter()
+        .enumerate()
+        .find(|(_, o)| o.script_pubkey == escrow_script_pubkey)
+        .map(|(vout, o)| (vout as u32, o))
+        .ok_or_else(|| {
+            Error::InvalidParameters(
+                "Escrow transaction has no output paying to the loan escrow script".to_string(),
+            )
+        })
+}
+
+/// Verifies that an output of the given escrow transaction locks, in the
+/// escrow script of the loan, at least the borrower collateral and the fees of
+/// the collateral transaction spending it and of the CET or refund transaction
+/// spending the collateral. Returns the index of the escrow output.
+pub fn verify_escrow_transaction(
+    offered_loan_contract: &OfferedLoanContract,
+    escrow_tx: &Transaction,
+    borrower_pubkey: &PublicKey,
+    borrower_hash: &[u8; 32],
+) -> Result<u32, Error> {
+    let offered_contract = &offered_loan_contract.offered_contract;
+    let escrow_redeemscript = make_loan_funding_redeemscript(
+        borrower_pubkey,
+        &offered_contract.offer_params.fund_pubkey,
+        offered_loan_contract.get_escrow_relative_locktime()?,
+        borrower_hash,
+    );
+    let (escrow_vout, escrow_output) = get_escrow_output(escrow_tx, &escrow_redeemscript)?;
+
+    let collateral_fee = dlc::get_collateral_transaction_fee(
+        &escrow_redeemscript,
+        offered_contract.fee_rate_per_vb,
+    )?;
+    let collateral_redeemscript = dlc::make_collateral_redeemscript(
+        borrower_pubkey,
+        &offered_contract.offer_params.fund_pubkey,
+        &offered_loan_contract.lender_hash,
+    );
+    let cet_fee = dlc::get_loan_cet_fee(
+        &collateral_redeemscript,
+        &offered_contract.offer_params.payout_script_pubkey,
+        offered_contract.fee_rate_per_vb,
+    )?;
+    let borrower_collateral =
+        offered_contract.total_collateral - offered_contract.offer_params.collateral;
+    let required_amount = borrower_collateral + collateral_fee + cet_fee;
+    if escrow_output.value < required_amount {
+        return Err(Error::InvalidParameters(format!(
+            "Escrow output amount {} is lower than the required {}",
+            escrow_output.value, required_amount
+        )));
+    }
+
+    Ok(escrow_vout)
+}
+
+/// The number of witness items of the collateral transaction input: the lender
+/// signature, the borrower preimage, the borrower signature and the escrow
+/// redeem script.
+const COLLATERAL_TX_WITNESS_ITEMS: usize = 4;
+
+/// Verifies that the given collateral transaction spends the given escrow
+/// output of the loan into a single output locking the escrow value, minus the
+/// collateral transaction fee, in the collateral script of the loan. Returns
+/// the escrow script to sign the collateral transaction with along with the
+/// collateral redeem script.
+pub fn verify_collateral_transaction(
+    offered_loan_contract: &OfferedLoanContract,
+    collateral_tx: &Transaction,
+    escrow_outpoint: &OutPoint,
+    escrow_output: &TxOut,
+    borrower_pubkey: &PublicKey,
+    borrower_hash: &[u8; 32],
+) -> Result<(ScriptBuf, ScriptBuf), Error> {
+    let offered_contract = &offered_loan_contract.offered_contract;
+    let lender_pubkey = &offered_contract.offer_params.fund_pubkey;
+    if collateral_tx.input.len() != 1 || collateral_tx.input[0].previous_output != *escrow_outpoint
+    {
+        return Err(Error::InvalidParameters(
+            "Collateral transaction does not spend the loan escrow".to_string(),
+        ));
+    }
+    if collateral_tx.input[0].witness.len() != COLLATERAL_TX_WITNESS_ITEMS {
+        return Err(Error::InvalidParameters(format!(
+            "Collateral transaction witness has {} items instead of {}",
+            collateral_tx.input[0].witness.len(),
+            COLLATERAL_TX_WITNESS_ITEMS
+        )));
+    }
+
+    let escrow_redeemscript = make_loan_funding_redeemscript(
+        borrower_pubkey,
+        lender_pubkey,
+        offered_loan_contract.get_escrow_relative_locktime()?,
+        borrower_hash,
+    );
+    if escrow_output.script_pubkey != escrow_redeemscript.to_p2wsh() {
+        return Err(Error::InvalidParameters(
+            "Escrow output does not pay to the loan escrow script".to_string(),
+        ));
+    }
+
+    let collateral_redeemscript = dlc::make_collateral_redeemscript(
+        borrower_pubkey,
+        lender_pubkey,
+        &offered_loan_contract.lender_hash,
+    );
+    let collateral_fee = dlc::get_collateral_transaction_fee(
+        &escrow_redeemscript,
+        offered_contract.fee_rate_per_vb,
+    )?;
+    let collateral_value = escrow_output
+        .value
+        .checked_sub(collateral_fee)
+        .ok_or(Error::OutOfRange)?;
+    match collateral_tx.output.as_slice() {
+        [output]
+            if output.script_pubkey == collateral_redeemscript.to_p2wsh()
+                && output.value == collateral_value => {}
+        _ => {
+            return Err(Error::InvalidParameters(format!(
+                "Collateral transaction must have a single output of {} paying to the collateral script",
+                collateral_value
+            )))
+        }
+    }
+
+    Ok((
+        make_loan_escrow_after_codesep_script(lender_pubkey, borrower_hash),
+        collateral_redeemscript,
+    ))
+}
+
+/// Returns the amount of stable token units lent for the principal of the
+/// given offered loan contract, given the number of stable token units making
+/// up one unit of principal.
+pub fn get_stable_loan_amount(
+    offered_loan_contract: &OfferedLoanContract,
+    stable_unit: u128,
+) -> Result<u128, Error> {
+    (offered_loan_contract.principal as u128)
+        .checked_mul(stable_unit)
+        .ok_or(Error::OutOfRange)
+}
+
+/// Verifies that the principal of the given offered loan contract is covered
+/// by its collateral at its collateral ratio, given the current value of the
+/// collateral and the number of stable token units making up one unit of
+/// principal.
+pub fn verify_loan_principal(
+    offered_loan_contract: &OfferedLoanContract,
+    collateral_value: u128,
+    stable_unit: u128,
+) -> Result<(), Error> {
+    if offered_loan_contract.principal == 0 {
+        return Err(Error::InvalidParameters(
+            "Loan principal must be greater than zero".to_string(),
+        ));
+    }
+
+    // Bound the stable loan amount the way the lending contract does, so that
+    // it never refuses to lend an accepted principal.
+    let stable_loan_amount = get_stable_loan_amount(offered_loan_contract, stable_unit)?;
+    let required_collateral_value = stable_loan_amount
+        .checked_mul(offered_loan_contract.collateral_ratio as u128)
+        .ok_or(Error::OutOfRange)?;
+    if collateral_value.checked_mul(100).ok_or(Error::OutOfRange)? < required_collateral_value {
+        return Err(Error::InvalidParameters(format!(
+            "Collateral value {} does not cover the principal {} at a {}% collateral ratio",
+            collateral_value, stable_loan_amount, offered_loan_contract.collateral_ratio
+        )));
+    }
+
+    Ok(())
+}
+
+/// Verifies that the stable loan recorded by the lender matches the terms of
+/// the given offered loan contract, given the number of stable token units
+/// making up one unit of its principal.
+pub fn verify_stable_loan(
+    offered_loan_contract: &OfferedLoanContract,
+    stable_loan: &StableLoan,
+    stable_unit: u128,
+) -> Result<(), Error> {
+    let total_collateral = offered_loan_contract.offered_contract.total_collateral;
+    if stable_loan.collateral_amount != total_collateral {
+        return Err(Error::InvalidParameters(format!(
+            "Stable loan collateral amount {} does not match the offered {}",
+            stable_loan.collateral_amount, total_collateral
+        )));
+    }
+
+    let expected_loan_amount = get_stable_loan_amount(offered_loan_contract, stable_unit)?;
+    if stable_loan.stable_loan_amount != expected_loan_amount {
+        return Err(Error::InvalidParameters(format!(
+            "Stable loan amount {} does not match the expected {}",
+            stable_loan.stable_loan_amount, expected_loan_amount
+        )));
+    }
+
+    if stable_loan.interest_rate != offered_loan_contract.interest_rate {
+        return Err(Error::InvalidParameters(format!(
+            "Stable loan interest rate {} does not match the offered {}",
+            stable_loan.interest_rate, offered_loan_contract.interest_rate
+        )));
+    }
+
+    Ok(())
 }
 
 /// Verifies the information of the accepting party [`Accept` message](dlc_messages::AcceptDlc),
@@ -1092,6 +1336,436 @@ where
     Ok((signed_contract, fund_tx.clone()))
 }
 
+/// Returns the offered contract of a loan once its collateral is renewed, with
+/// payouts computed from the principal left after the given repayment and the
+/// collateral increased by the given amount.
+fn get_renewed_loan_offered_contract(
+    offered_contract: &OfferedContract,
+    loan_terms: &LoanTerms,
+    additional_collateral: Amount,
+    repaid_principal: u64,
+) -> Result<OfferedContract, Error> {
+    validate_loan_renewal(loan_terms, additional_collateral, repaid_principal)?;
+    let principal = loan_terms.principal - repaid_principal;
+    let total_collateral = offered_contract
+        .total_collateral
+        .checked_add(additional_collateral)
+        .ok_or(Error::OutOfRange)?;
+
+    let contract_info = offered_contract
+        .contract_info
+        .iter()
+        .map(|info| {
+            let descriptor = match &info.contract_descriptor {
+                ContractDescriptor::Numerical(n) => n,
+                ContractDescriptor::Enum(_) => {
+                    return Err(Error::InvalidState(
+                        "Cannot renew a loan contract with an enumeration descriptor".to_string(),
+                    ))
+                }
+            };
+            let builder = LoanPayoutCurveBuilder {
+                principal,
+                interest_rate: loan_terms.interest_rate,
+                duration: loan_terms.duration,
+                liquidation_ratio: loan_terms.liquidation_ratio,
+                total_collateral,
+                oracle_numeric_info: descriptor.oracle_numeric_infos.clone(),
+            };
+            let payout_function = if descriptor.is_truncated() {
+                builder.build_liquidation()?
+            } else {
+                builder.build()?
+            };
+            Ok(ContractInfo {
+                contract_descriptor: ContractDescriptor::Numerical(NumericalDescriptor {
+                    payout_function,
+                    rounding_intervals: descriptor.rounding_intervals.clone(),
+                    difference_params: descriptor.difference_params.clone(),
+                    oracle_numeric_infos: descriptor.oracle_numeric_infos.clone(),
+                }),
+                oracle_announcements: info.oracle_announcements.clone(),
+                threshold: info.threshold,
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    Ok(OfferedContract {
+        contract_info,
+        total_collateral,
+        ..offered_contract.clone()
+    })
+}
+
+/// Returns the borrower parameters of a renewed loan contract. The payout
+/// script and serial ids are kept so that both parties build the same
+/// transactions.
+fn get_renewed_loan_accept_params(
+    accept_params: &PartyParams,
+    additional_collateral: Amount,
+    funding_inputs: &[FundingInput],
+    change_script_pubkey: ScriptBuf,
+) -> Result<PartyParams, Error> {
+    let (inputs, input_amount) = get_tx_input_infos(funding_inputs)?;
+    Ok(PartyParams {
+        change_script_pubkey,
+        inputs,
+        input_amount,
+        collateral: accept_params
+            .collateral
+            .checked_add(additional_collateral)
+            .ok_or(Error::OutOfRange)?,
+        ..accept_params.clone()
+    })
+}
+
+/// Creates the transactions of a renewed loan contract, whose fund transaction
+/// spends the collateral output of the given contract.
+fn get_renewed_loan_dlc_transactions(
+    signed_contract: &SignedContract,
+    offered_contract: &OfferedContract,
+    accept_params: &PartyParams,
+    additional_collateral: Amount,
+) -> Result<DlcTransactions, Error> {
+    let dlc_transactions = &signed_contract.accepted_contract.dlc_transactions;
+    let renewal_tx = dlc::create_collateral_renewal_transaction(
+        dlc_transactions.get_fund_outpoint(),
+        dlc_transactions.get_fund_output().value,
+        &dlc_transactions.funding_script_pubkey,
+        additional_collateral,
+        &accept_params.inputs,
+        accept_params.input_amount,
+        accept_params.change_script_pubkey.clone(),
+        offered_contract.fee_rate_per_vb,
+    )?;
+
+    Ok(dlc::create_loan_expiration_dlc_transactions(
+        &offered_contract.offer_params,
+        accept_params,
+        &offered_contract.contract_info[0].get_payouts(offered_contract.total_collateral)?,
+        offered_contract.refund_locktime,
+        offered_contract.cet_locktime,
+        renewal_tx,
+        dlc_transactions.funding_script_pubkey.clone(),
+    )?)
+}
+
+/// Creates the renewed contract of a loan on the borrower side, adding the
+/// given collateral and reducing the principal by the given repaid amount, and
+/// returns it along with a [`RenewLoanOfferDlc`] message carrying the borrower
+/// signatures for it.
+pub fn renew_loan_contract<W: Deref, X: ContractSigner, SP: Deref, B: Deref>(
+    secp: &Secp256k1<All>,
+    signed_contract: &SignedContract,
+    loan_terms: &LoanTerms,
+    additional_collateral: Amount,
+    repaid_principal: u64,
+    wallet: &W,
+    signer_provider: &SP,
+    blockchain: &B,
+) -> Result<(AcceptedContract, RenewLoanOfferDlc), Error>
+where
+    W::Target: Wallet,
+    B::Target: Blockchain,
+    SP::Target: ContractSignerProvider<Signer = X>,
+{
+    let previous_contract = &signed_contract.accepted_contract;
+    let offered_contract = get_renewed_loan_offered_contract(
+        &previous_contract.offered_contract,
+        loan_terms,
+        additional_collateral,
+        repaid_principal,
+    )?;
+    let signer = signer_provider.derive_contract_signer(offered_contract.keys_id)?;
+
+    let (party_params, funding_inputs) = crate::utils::get_loan_renewal_party_params(
+        secp,
+        additional_collateral,
+        offered_contract.fee_rate_per_vb,
+        wallet,
+        &signer,
+        blockchain,
+    )?;
+    let accept_params = get_renewed_loan_accept_params(
+        &previous_contract.accept_params,
+        additional_collateral,
+        &funding_inputs,
+        party_params.change_script_pubkey,
+    )?;
+
+    let dlc_transactions = get_renewed_loan_dlc_transactions(
+        signed_contract,
+        &offered_contract,
+        &accept_params,
+        additional_collateral,
+    )?;
+
+    let (accepted_contract, adaptor_sigs) = accept_contract_internal(
+        secp,
+        &offered_contract,
+        &accept_params,
+        &funding_inputs,
+        &signer.get_secret_key()?,
+        dlc_transactions.get_fund_output().value,
+        None,
+        &dlc_transactions,
+    )?;
+
+    let offer_msg = RenewLoanOfferDlc {
+        protocol_version: crate::conversion_utils::PROTOCOL_VERSION,
+        contract_id: previous_contract.get_contract_id(),
+        additional_collateral,
+        repaid_principal,
+        funding_inputs,
+        change_spk: accept_params.change_script_pubkey,
+        cet_adaptor_signatures: adaptor_sigs.as_slice().into(),
+        refund_signature: accepted_contract.accept_refund_signature,
+    };
+
+    Ok((accepted_contract, offer_msg))
+}
+
+/// Verifies the renewal of a loan proposed by the borrower in a
+/// [`RenewLoanOfferDlc`] message, and returns the renewed contract signed by
+/// the lender along with a [`RenewLoanAcceptDlc`] message carrying the lender
+/// signatures.
+pub fn verify_renew_loan_offer_and_sign<W: Deref, X: ContractSigner, SP: Deref>(
+    secp: &Secp256k1<All>,
+    signed_contract: &SignedContract,
+    loan_terms: &LoanTerms,
+    offer_msg: &RenewLoanOfferDlc,
+    wallet: &W,
+    signer_provider: &SP,
+) -> Result<(SignedContract, RenewLoanAcceptDlc), Error>
+where
+    W::Target: Wallet,
+    SP::Target: ContractSignerProvider<Signer = X>,
+{
+    let previous_contract = &signed_contract.accepted_contract;
+    let offered_contract = get_renewed_loan_offered_contract(
+        &previous_contract.offered_contract,
+        loan_terms,
+        offer_msg.additional_collateral,
+        offer_msg.repaid_principal,
+    )?;
+    let accept_params = get_renewed_loan_accept_params(
+        &previous_contract.accept_params,
+        offer_msg.additional_collateral,
+        &offer_msg.funding_inputs,
+        offer_msg.change_spk.clone(),
+    )?;
+
+    let dlc_transactions = get_renewed_loan_dlc_transactions(
+        signed_contract,
+        &offered_contract,
+        &accept_params,
+        offer_msg.additional_collateral,
+    )?;
+
+    let cet_adaptor_signatures: Vec<_> = (&offer_msg.cet_adaptor_signatures).into();
+    let signer = signer_provider.derive_contract_signer(offered_contract.keys_id)?;
+    let (renewed_contract, adaptor_sigs) = verify_accepted_and_sign_contract_internal(
+        secp,
+        &offered_contract,
+        &accept_params,
+        &offer_msg.funding_inputs,
+        &offer_msg.refund_signature,
+        &cet_adaptor_signatures,
+        dlc_transactions.get_fund_output().value,
+        wallet,
+        &signer,
+        None,
+        None,
+        &dlc_transactions,
+        None,
+    )?;
+
+    let accept_msg = RenewLoanAcceptDlc {
+        protocol_version: crate::conversion_utils::PROTOCOL_VERSION,
+        contract_id: offer_msg.contract_id,
+        cet_adaptor_signatures: adaptor_sigs.as_slice().into(),
+        refund_signature: renewed_contract.offer_refund_signature,
+    };
+
+    Ok((renewed_contract, accept_msg))
+}
+
+/// Verifies the lender signatures from a [`RenewLoanAcceptDlc`] message for
+/// the renewed contract of a loan and returns it signed.
+pub fn verify_renew_loan_accept<W: Deref>(
+    secp: &Secp256k1<All>,
+    accepted_contract: &AcceptedContract,
+    accept_msg: &RenewLoanAcceptDlc,
+    wallet: &W,
+) -> Result<SignedContract, Error>
+where
+    W::Target: Wallet,
+{
+    let cet_adaptor_signatures: Vec<_> = (&accept_msg.cet_adaptor_signatures).into();
+    let (signed_contract, _) = verify_signed_contract_internal(
+        secp,
+        accepted_contract,
+        &accept_msg.refund_signature,
+        &cet_adaptor_signatures,
+        &FundingSignatures {
+            funding_signatures: Vec::new(),
+        },
+        accepted_contract.dlc_transactions.get_fund_output().value,
+        None,
+        None,
+        wallet,
+        None,
+    )?;
+
+    Ok(signed_contract)
+}
+
+/// Signs, on the borrower side, the spending of the current collateral and the
+/// borrower inputs of the collateral renewal transaction of a loan, and
+/// returns the signatures in a [`RenewLoanConfirmDlc`] message.
+pub fn sign_loan_renewal<W: Deref, X: ContractSigner, SP: Deref>(
+    secp: &Secp256k1<All>,
+    signed_contract: &SignedContract,
+    renewed_contract: &SignedContract,
+    wallet: &W,
+    signer_provider: &SP,
+) -> Result<RenewLoanConfirmDlc, Error>
+where
+    W::Target: Wallet,
+    SP::Target: ContractSignerProvider<Signer = X>,
+{
+    let dlc_transactions = &signed_contract.accepted_contract.dlc_transactions;
+    let renewed = &renewed_contract.accepted_contract;
+    let renewal_tx = &renewed.dlc_transactions.fund;
+
+    let signer = signer_provider.derive_contract_signer(renewed.offered_contract.keys_id)?;
+    let collateral_signature = dlc::util::get_raw_sig_for_tx_input(
+        secp,
+        renewal_tx,
+        0,
+        &dlc_transactions.funding_script_pubkey,
+        dlc_transactions.get_fund_output().value,
+        &signer.get_secret_key()?,
+    )?;
+
+    let mut renewal_psbt = Psbt::from_unsigned_tx(renewal_tx.clone())
+        .map_err(|_| Error::InvalidState("Tried to create PSBT from signed tx".to_string()))?;
+    let mut funding_inputs = renewed.funding_inputs.iter().collect::<Vec<_>>();
+    funding_inputs.sort_by_key(|x| x.input_serial_id);
+
+    let funding_signatures = funding_inputs
+        .into_iter()
+        .enumerate()
+        .map(|(i, funding_input)| {
+            // The current collateral is spent by the first input.
+            let input_index = i + 1;
+            let prev_tx = Transaction::consensus_decode(&mut funding_input.prev_tx.as_slice())
+                .map_err(|_| {
+                    Error::InvalidParameters(
+                        "Could not decode funding input previous tx parameter".to_string(),
+                    )
+                })?;
+            let vout = funding_input.prev_tx_vout;
+            let tx_out = prev_tx.output.get(vout as usize).ok_or_else(|| {
+                Error::InvalidParameters(format!("Previous tx output not found at index {}", vout))
+            })?;
+            renewal_psbt.inputs[input_index].witness_utxo = Some(tx_out.clone());
+            if !funding_input.redeem_script.is_empty() {
+                renewal_psbt.inputs[input_index].redeem_script =
+                    Some(funding_input.redeem_script.clone());
+            }
+
+            wallet.sign_psbt_input(&mut renewal_psbt, input_index)?;
+
+            let witness = renewal_psbt.inputs[input_index]
+                .final_script_witness
+                .clone()
+                .ok_or(Error::InvalidParameters(
+                    "No witness from signing psbt input".to_string(),
+                ))?;
+            Ok(FundingSignature {
+                witness_elements: witness
+                    .iter()
+                    .map(|z| WitnessElement {
+                        witness: z.to_vec(),
+                    })
+                    .collect(),
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    Ok(RenewLoanConfirmDlc {
+        protocol_version: crate::conversion_utils::PROTOCOL_VERSION,
+        contract_id: signed_contract.accepted_contract.get_contract_id(),
+        collateral_signature,
+        funding_signatures: FundingSignatures { funding_signatures },
+    })
+}
+
+/// Verifies the borrower signatures from a [`RenewLoanConfirmDlc`] message
+/// and returns the collateral renewal transaction of a loan fully signed.
+pub fn verify_renew_loan_confirm_and_sign<X: ContractSigner, SP: Deref>(
+    secp: &Secp256k1<All>,
+    signed_contract: &SignedContract,
+    renewed_contract: &SignedContract,
+    confirm_msg: &RenewLoanConfirmDlc,
+    signer_provider: &SP,
+) -> Result<Transaction, Error>
+where
+    SP::Target: ContractSignerProvider<Signer = X>,
+{
+    let dlc_transactions = &signed_contract.accepted_contract.dlc_transactions;
+    let renewed = &renewed_contract.accepted_contract;
+    let collateral_amount = dlc_transactions.get_fund_output().value;
+    let mut renewal_tx = renewed.dlc_transactions.fund.clone();
+
+    dlc::verify_tx_input_sig(
+        secp,
+        &confirm_msg.collateral_signature,
+        &renewal_tx,
+        0,
+        &dlc_transactions.funding_script_pubkey,
+        collateral_amount,
+        &renewed.accept_params.fund_pubkey,
+    )?;
+
+    let funding_signatures = &confirm_msg.funding_signatures.funding_signatures;
+    if funding_signatures.len() + 1 != renewal_tx.input.len() {
+        return Err(Error::InvalidParameters(format!(
+            "Expected {} funding signatures but got {}",
+            renewal_tx.input.len() - 1,
+            funding_signatures.len()
+        )));
+    }
+    for (input, funding_signature) in renewal_tx
+        .input
+        .iter_mut()
+        .skip(1)
+        .zip(funding_signatures.iter())
+    {
+        input.witness = Witness::from_slice(
+            &funding_signature
+                .witness_elements
+                .iter()
+                .map(|x| x.witness.clone())
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    let signer = signer_provider.derive_contract_signer(renewed.offered_contract.keys_id)?;
+    dlc::sign_collateral_renewal_input(
+        secp,
+        &mut renewal_tx,
+        0,
+        &confirm_msg.collateral_signature,
+        &signer.get_secret_key()?,
+        &dlc_transactions.funding_script_pubkey,
+        collateral_amount,
+    )?;
+
+    Ok(renewal_tx)
+}
+
 /// Signs and return the CET that can be used to close the given contract.
 pub fn get_signed_cet<C: Signing, S: Deref>(
     secp: &Secp256k1<C>,
@@ -1225,4 +1899,217 @@ mod tests {
         )
         .expect("Not to fail");
     }
+
+    fn get_offered_loan_contract() -> crate::contract::offered_contract::OfferedLoanContract {
+        let offer_dlc =
+            serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
+        let dummy_pubkey: PublicKey =
+            "02e6642fd69bd211f93f7f1f36ca51a26a5290eb2dd1b0d8279a87bb0d480c8443"
+                .parse()
+                .unwrap();
+        let offered_contract = crate::contract::offered_contract::OfferedContract::try_from_offer_dlc(
+            &offer_dlc,
+            dummy_pubkey,
+            [0; 32],
+        )
+        .unwrap();
+        crate::contract::offered_contract::OfferedLoanContract {
+            offered_contract,
+            collateral_ratio: 150,
+            liquidation_ratio: 125,
+            interest_rate: 5,
+            duration: 86400,
+            principal: 40_000,
+            lending_deployment: crate::LendingDeployment {
+                chain_id: 31337,
+                lending_contract_address: [0; 20],
+                stable_token_address: [0; 20],
+            },
+            lender_hash: [0; 32],
+            escrow_relative_locktime: 86400,
+            escrow_confirmations: 6,
+            offer_expiry: 0,
+            request_id: None,
+            negotiation_id: None,
+        }
+    }
+
+    #[test]
+    fn verify_loan_principal_test() {
+        let offered_loan_contract = get_offered_loan_contract();
+
+        // A principal of 40_000 with 25 token units per unit is 1_000_000
+        // units, requiring 1_500_000 units of collateral at 150%.
+        super::verify_loan_principal(&offered_loan_contract, 1_500_000, 25)
+            .expect("principal covered by the collateral to be valid");
+        super::verify_loan_principal(&offered_loan_contract, 1_499_999, 25)
+            .expect_err("principal above the collateral ratio to be invalid");
+        super::verify_loan_principal(
+            &crate::contract::offered_contract::OfferedLoanContract {
+                principal: 0,
+                ..offered_loan_contract.clone()
+            },
+            1_500_000,
+            25,
+        )
+        .expect_err("zero principal to be invalid");
+    }
+
+    #[test]
+    fn verify_stable_loan_test() {
+        let offered_loan_contract = get_offered_loan_contract();
+        let total_collateral = offered_loan_contract.offered_contract.total_collateral;
+        let stable_loan = |collateral_amount, stable_loan_amount| crate::StableLoan {
+            collateral_amount,
+            stable_loan_amount,
+            borrower_preimage: 0,
+            repaid: false,
+            interest_rate: 5,
+            start_time: 0,
+            accrued_interest: 0,
+        };
+
+        // A principal of 40_000 with 25 token units per unit is 1_000_000 units.
+        super::verify_stable_loan(
+            &offered_loan_contract,
+            &stable_loan(total_collateral, 1_000_000),
+            25,
+        )
+        .expect("stable loan matching the terms to be valid");
+        super::verify_stable_loan(
+            &offered_loan_contract,
+            &stable_loan(total_collateral, 999_999),
+            25,
+        )
+        .expect_err("stable loan lower than the principal to be invalid");
+        super::verify_stable_loan(
+            &offered_loan_contract,
+            &stable_loan(total_collateral, 1_000_001),
+            25,
+        )
+        .expect_err("stable loan higher than the principal to be invalid");
+        super::verify_stable_loan(
+            &crate::contract::offered_contract::OfferedLoanContract {
+                principal: 50_000,
+                ..offered_loan_contract.clone()
+            },
+            &stable_loan(total_collateral, 1_000_000),
+            25,
+        )
+        .expect_err("stable loan for another principal to be invalid");
+        super::verify_stable_loan(
+            &offered_loan_contract,
+            &stable_loan(total_collateral - Amount::from_sat(1), 1_000_000),
+            25,
+        )
+        .expect_err("stable loan with a different collateral to be invalid");
+        super::verify_stable_loan(
+            &offered_loan_contract,
+            &crate::StableLoan {
+                interest_rate: 4,
+                ..stable_loan(total_collateral, 1_000_000)
+            },
+            25,
+        )
+        .expect_err("stable loan with a different interest rate to be invalid");
+    }
+
+    #[test]
+    fn verify_collateral_transaction_test() {
+        use bitcoin::hashes::Hash;
+
+        let offered_loan_contract = get_offered_loan_contract();
+        let lender_pubkey = offered_loan_contract
+            .offered_contract
+            .offer_params
+            .fund_pubkey;
+        let borrower_pubkey: PublicKey =
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+                .parse()
+                .unwrap();
+        let borrower_hash = [2; 32];
+        let escrow_redeemscript = dlc::make_loan_funding_redeemscript(
+            &borrower_pubkey,
+            &lender_pubkey,
+            offered_loan_contract
+                .get_escrow_relative_locktime()
+                .unwrap(),
+            &borrower_hash,
+        );
+        let escrow_outpoint = bitcoin::OutPoint::new(bitcoin::Txid::from_byte_array([1; 32]), 1);
+        let escrow_output = bitcoin::TxOut {
+            value: Amount::from_sat(200_000),
+            script_pubkey: escrow_redeemscript.to_p2wsh(),
+        };
+        let (mut collateral_tx, collateral_redeemscript) = dlc::create_collateral_transaction(
+            &borrower_pubkey,
+            &lender_pubkey,
+            escrow_output.value,
+            bitcoin::TxIn {
+                previous_output: escrow_outpoint,
+                ..Default::default()
+            },
+            &escrow_redeemscript,
+            offered_loan_contract.lender_hash,
+            offered_loan_contract.offered_contract.fee_rate_per_vb,
+        )
+        .unwrap();
+        collateral_tx.input[0].witness = bitcoin::Witness::from_slice(&[
+            Vec::new(),
+            vec![1],
+            vec![2],
+            escrow_redeemscript.to_bytes(),
+        ]);
+        let verify = |collateral_tx: &bitcoin::Transaction, escrow_outpoint, borrower_hash| {
+            super::verify_collateral_transaction(
+                &offered_loan_contract,
+                collateral_tx,
+                escrow_outpoint,
+                &escrow_output,
+                &borrower_pubkey,
+                borrower_hash,
+            )
+        };
+
+        let (escrow_script, collateral_script) =
+            verify(&collateral_tx, &escrow_outpoint, &borrower_hash)
+                .expect("collateral transaction spending the escrow to be valid");
+        assert_eq!(
+            dlc::make_loan_escrow_after_codesep_script(&lender_pubkey, &borrower_hash),
+            escrow_script
+        );
+        assert_eq!(collateral_redeemscript, collateral_script);
+
+        verify(
+            &collateral_tx,
+            &bitcoin::OutPoint::new(escrow_outpoint.txid, 0),
+            &borrower_hash,
+        )
+        .expect_err("collateral transaction spending another output to be invalid");
+        verify(&collateral_tx, &escrow_outpoint, &[3; 32])
+            .expect_err("escrow output paying to another script to be invalid");
+
+        let mut missing_witness_tx = collateral_tx.clone();
+        missing_witness_tx.input[0].witness = bitcoin::Witness::default();
+        verify(&missing_witness_tx, &escrow_outpoint, &borrower_hash)
+            .expect_err("collateral transaction without witness to be invalid");
+
+        let mut lower_fee_tx = collateral_tx.clone();
+        lower_fee_tx.output[0].value += Amount::from_sat(1);
+        verify(&lower_fee_tx, &escrow_outpoint, &borrower_hash)
+            .expect_err("collateral output above the escrow value minus the fee to be invalid");
+
+        let mut other_script_tx = collateral_tx.clone();
+        other_script_tx.output[0].script_pubkey = escrow_output.script_pubkey.clone();
+        verify(&other_script_tx, &escrow_outpoint, &borrower_hash)
+            .expect_err("collateral output paying to another script to be invalid");
+
+        let mut extra_output_tx = collateral_tx.clone();
+        extra_output_tx.output.push(bitcoin::TxOut {
+            value: Amount::from_sat(1_000),
+            script_pubkey: escrow_output.script_pubkey.clone(),
+        });
+        verify(&extra_output_tx, &escrow_outpoint, &borrower_hash)
+            .expect_err("collateral transaction with several outputs to be invalid");
+    }
 }
@@ -23,6 +23,8 @@ pub enum Error {
     StorageError(String),
     /// The oracle component encountered an error.
     OracleError(String),
+    /// The lending backend encountered an error.
+    LendingError(String),
     /// An error occurred in the DLC library.
     DlcError(dlc::Error),
     /// An error occurred in the Secp library.
@@ -44,6 +46,7 @@ impl fmt::Display for Error {
             Error::StorageError(ref s) => write!(f, "Storage error {}", s),
             Error::DlcError(ref e) => write!(f, "Dlc error {}", e),
             Error::OracleError(ref s) => write!(f, "Oracle error {}", s),
+            Error::LendingError(ref s) => write!(f, "Lending error {}", s),
             Error::SecpError(_) => write!(f, "Secp error"),
             Error::OutOfRange => write!(f, "Out of range error"),
         }
@@ -99,6 +102,7 @@ impl std::error::Error for Error {
             Error::BlockchainError(_) => None,
             Error::StorageError(_) => None,
             Error::OracleError(_) => None,
+            Error::LendingError(_) => None,
             Error::DlcError(e) => Some(e),
             Error::SecpError(e) => Some(e),
             Error::OutOfRange => None,
@@ -19,6 +19,7 @@ extern crate dlc;
 extern crate dlc_messages;
 extern crate core;
 extern crate dlc_trie;
+extern crate futures;
 extern crate lightning;
 extern crate log;
 #[cfg(feature = "fuzztarget")]
@@ -32,6 +33,9 @@ pub mod contract;
 pub mod contract_updater;
 mod conversion_utils;
 pub mod error;
+pub mod loan_marketplace;
+pub mod loan_math;
+pub mod loan_monitor;
 pub mod manager;
 pub mod payout_curve;
 mod utils;
@@ -43,11 +47,19 @@ use channel::offered_channel::OfferedChannel;
 use channel::signed_channel::{SignedChannel, SignedChannelStateType};
 use channel::Channel;
 use contract::offered_contract::OfferedLoanContract;
+use contract::FailedLoanContract;
+use contract::loan_negotiation::LoanNegotiation;
+use contract::loan_renewal::LoanRenewal;
+use contract::loan_request::LoanRequest;
+use contract::loan_state::LoanLifecycle;
+use contract::LoanTerms;
 use contract::PreClosedContract;
+use contract::RepaidContract;
 use contract::{offered_contract::OfferedContract, signed_contract::SignedContract, Contract};
 use dlc_messages::oracle_msgs::{OracleAnnouncement, OracleAttestation};
-use dlc_messages::ser_impls::{read_address, write_address};
+use dlc_messages::ser_impls::{read_address, read_evm_address, write_address, write_evm_address};
 use error::Error;
+use hex::DisplayHex;
 use lightning::ln::msgs::DecodeError;
 use lightning::util::ser::{Readable, Writeable, Writer};
 use secp256k1_zkp::{PublicKey, SecretKey, Signing};
@@ -202,10 +214,59 @@ pub trait Storage {
     fn set_loan_preimage(&self, contract_id: &ContractId, preimage: u128) -> Result<(), Error>;
     /// Get the borrower's preimage for a loan contract.
     fn get_loan_preimage(&self, contract_id: &ContractId) -> Result<Option<u128>, Error>;
-    /// Sets the escrow transaction id for the contract with the given id.
-    fn set_escrow_txid(&self, contract_id: &ContractId, txid: Txid) -> Result<(), Error>;
+    /// Sets the outpoint of the escrow output for the contract with the given id.
+    fn set_escrow_outpoint(&self, contract_id: &ContractId, outpoint: OutPoint)
+        -> Result<(), Error>;
+    /// Returns the outpoint of the escrow output for the contract with the given id.
+    fn get_escrow_outpoint(&self, contract_id: &ContractId) -> Result<Option<OutPoint>, Error>;
     /// Returns the escrow transaction id for the contract with the given id.
-    fn get_escrow_txid(&self, contract_id: &ContractId) -> Result<Option<Txid>, Error>;
+    fn get_escrow_txid(&self, contract_id: &ContractId) -> Result<Option<Txid>, Error> {
+        Ok(self.get_escrow_outpoint(contract_id)?.map(|o| o.txid))
+    }
+    /// Set the borrower's hash for a loan contract.
+    fn set_loan_borrower_hash(
+        &self,
+        contract_id: &ContractId,
+        borrower_hash: &[u8; 32],
+    ) -> Result<(), Error>;
+    /// Get the borrower's hash for a loan contract.
+    fn get_loan_borrower_hash(&self, contract_id: &ContractId) -> Result<Option<[u8; 32]>, Error>;
+    /// Stores the loan terms of the contract with the given temporary id.
+    fn set_loan_terms(&self, contract_id: &ContractId, loan_terms: &LoanTerms)
+        -> Result<(), Error>;
+    /// Returns the loan terms of the contract with the given temporary id, if any.
+    fn get_loan_terms(&self, contract_id: &ContractId) -> Result<Option<LoanTerms>, Error>;
+    /// Stores the lifecycle of the loan with the given temporary contract id.
+    fn update_loan_lifecycle(
+        &self,
+        contract_id: &ContractId,
+        lifecycle: &LoanLifecycle,
+    ) -> Result<(), Error>;
+    /// Returns the lifecycle of the loan with the given temporary contract id, if any.
+    fn get_loan_lifecycle(&self, contract_id: &ContractId) -> Result<Option<LoanLifecycle>, Error>;
+    /// Returns the lifecycles of all the loans, along with their temporary
+    /// contract ids.
+    fn get_loan_lifecycles(&self) -> Result<Vec<(ContractId, LoanLifecycle)>, Error>;
+    /// Stores the given loan request, replacing any previous version of it.
+    fn upsert_loan_request(&self, loan_request: &LoanRequest) -> Result<(), Error>;
+    /// Returns the loan request with the given id, if any.
+    fn get_loan_request(&self, request_id: &[u8; 32]) -> Result<Option<LoanRequest>, Error>;
+    /// Returns all the loan requests.
+    fn get_loan_requests(&self) -> Result<Vec<LoanRequest>, Error>;
+    /// Stores the given loan negotiation, replacing any previous version of it.
+    fn upsert_loan_negotiation(&self, negotiation: &LoanNegotiation) -> Result<(), Error>;
+    /// Returns the loan negotiation with the given id, if any.
+    fn get_loan_negotiation(&self, negotiation_id: &[u8; 32])
+        -> Result<Option<LoanNegotiation>, Error>;
+    /// Returns all the loan negotiations.
+    fn get_loan_negotiations(&self) -> Result<Vec<LoanNegotiation>, Error>;
+    /// Stores the given loan renewal, replacing any previous renewal of the
+    /// same loan.
+    fn upsert_loan_renewal(&self, renewal: &LoanRenewal) -> Result<(), Error>;
+    /// Returns the renewal of the loan with the given contract id, if any.
+    fn get_loan_renewal(&self, contract_id: &ContractId) -> Result<Option<LoanRenewal>, Error>;
+    /// Returns all the loan renewals.
+    fn get_loan_renewals(&self) -> Result<Vec<LoanRenewal>, Error>;
     /// Delete the record for the contract with the given id.
     fn delete_contract(&self, id: &ContractId) -> Result<(), Error>;
     /// Update the given contract.
@@ -214,6 +275,12 @@ pub trait Storage {
     fn get_contract_offers(&self) -> Result<Vec<OfferedContract>, Error>;
     /// Returns the set of contracts in offered loan state.
     fn get_loan_contract_offers(&self) -> Result<Vec<OfferedLoanContract>, Error>;
+    /// Returns the set of loan contracts in offered state whose escrow was confirmed.
+    fn get_escrow_confirmed_loan_contracts(&self) -> Result<Vec<OfferedLoanContract>, Error>;
+    /// Returns the set of loan contracts that failed before the loan was taken.
+    fn get_failed_loan_contracts(&self) -> Result<Vec<FailedLoanContract>, Error>;
+    /// Returns the set of loan contracts whose offer was withdrawn by the lender.
+    fn get_cancelled_loan_contracts(&self) -> Result<Vec<OfferedLoanContract>, Error>;
     /// Returns the set of contracts in signed state.
     fn get_signed_contracts(&self) -> Result<Vec<SignedContract>, Error>;
     /// Returns the set of confirmed contracts.
@@ -221,6 +288,9 @@ pub trait Storage {
     /// Returns the set of contracts whos broadcasted cet has not been verified to be confirmed on
     /// blockchain
     fn get_preclosed_contracts(&self) -> Result<Vec<PreClosedContract>, Error>;
+    /// Returns the set of repaid loan contracts whose collateral sweep has not
+    /// been verified to be confirmed on blockchain.
+    fn get_prerepaid_contracts(&self) -> Result<Vec<RepaidContract>, Error>;
     /// Update the state of the channel and optionally its associated contract
     /// atomically.
     fn upsert_channel(&self, channel: Channel, contract: Option<Contract>) -> Result<(), Error>;
@@ -252,6 +322,146 @@ pub trait Oracle {
     fn get_attestation(&self, event_id: &str) -> Result<OracleAttestation, Error>;
 }
 
+/// PriceFeed trait provides the current price of a bitcoin, in the unit of the
+/// price attested by the oracles.
+pub trait PriceFeed {
+    /// Returns the current price of a bitcoin.
+    fn get_price(&self) -> Result<u64, Error>;
+}
+
+/// A stable loan as recorded by the lending contract for a borrower hash.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StableLoan {
+    /// The bitcoin collateral the loan was lent against, zero if there is no
+    /// loan for the borrower hash.
+    pub collateral_amount: Amount,
+    /// The amount of stable tokens lent, in the smallest unit of the token.
+    pub stable_loan_amount: u128,
+    /// The preimage revealed by the borrower when claiming the stable tokens,
+    /// zero until the loan is claimed.
+    pub borrower_preimage: u128,
+    /// Whether the stable tokens were repaid to the lender.
+    pub repaid: bool,
+    /// The yearly interest rate in percent charged on the stable loan.
+    pub interest_rate: u64,
+    /// The unix time at which the borrower claimed the stable tokens, from
+    /// which interest accrues, or at which they were lent until the loan is
+    /// claimed. Partial repayments restart the accrual from their own time.
+    pub start_time: u64,
+    /// The interest accrued before the last partial repayment, owed on top of
+    /// the interest accruing since `start_time`.
+    pub accrued_interest: u128,
+}
+
+/// The EVM deployment of the lending contract a loan is set up on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "use-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct LendingDeployment {
+    /// The id of the EVM chain on which the lending contract is deployed.
+    pub chain_id: u64,
+    /// The address of the lending contract.
+    #[cfg_attr(
+        feature = "use-serde",
+        serde(
+            serialize_with = "dlc_messages::serde_utils::serialize_evm_address",
+            deserialize_with = "dlc_messages::serde_utils::deserialize_evm_address"
+        )
+    )]
+    pub lending_contract_address: [u8; 20],
+    /// The address of the ERC-20 token lent by the lending contract.
+    #[cfg_attr(
+        feature = "use-serde",
+        serde(
+            serialize_with = "dlc_messages::serde_utils::serialize_evm_address",
+            deserialize_with = "dlc_messages::serde_utils::deserialize_evm_address"
+        )
+    )]
+    pub stable_token_address: [u8; 20],
+}
+
+impl LendingDeployment {
+    /// Checks that the deployment is the one of the local lending backend.
+    pub fn validate(&self, local: &LendingDeployment) -> Result<(), Error> {
+        if self.chain_id != local.chain_id {
+            return Err(Error::InvalidParameters(format!(
+                "Loan is on chain id {} while the lending backend is on chain id {}",
+                self.chain_id, local.chain_id
+            )));
+        }
+        if self.lending_contract_address != local.lending_contract_address {
+            return Err(Error::InvalidParameters(format!(
+                "Loan lending contract 0x{} does not match the lending backend contract 0x{}",
+                self.lending_contract_address.to_lower_hex_string(),
+                local.lending_contract_address.to_lower_hex_string()
+            )));
+        }
+        if self.stable_token_address != local.stable_token_address {
+            return Err(Error::InvalidParameters(format!(
+                "Loan stable token 0x{} does not match the lending backend token 0x{}",
+                self.stable_token_address.to_lower_hex_string(),
+                local.stable_token_address.to_lower_hex_string()
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl_dlc_writeable!(LendingDeployment, {
+    (chain_id, writeable),
+    (lending_contract_address, {cb_writeable, write_evm_address, read_evm_address}),
+    (stable_token_address, {cb_writeable, write_evm_address, read_evm_address})
+});
+
+/// LendingBackend trait provides access to the lending contract holding the
+/// stable side of loans.
+#[async_trait::async_trait]
+pub trait LendingBackend {
+    /// Returns the stable loan recorded for the given borrower hash.
+    async fn get_stable_loan(&self, borrower_hash: &[u8; 32]) -> Result<StableLoan, Error>;
+    /// Locks the given amount of stable token units for the given borrower
+    /// hash against the given amount of bitcoin collateral, at the given
+    /// yearly interest rate in percent. The lending contract refuses to lend
+    /// more than the value of the collateral allows at the given collateral
+    /// ratio in percent.
+    async fn lend_stable(
+        &self,
+        borrower_hash: &[u8; 32],
+        collateral: Amount,
+        stable_loan_amount: u128,
+        interest_rate: u64,
+        collateral_ratio: u64,
+    ) -> Result<(), Error>;
+    /// Claims the stable tokens of a loan by revealing the borrower preimage.
+    async fn claim_stable(&self, borrower_preimage: u128) -> Result<(), Error>;
+    /// Takes back the stable tokens lent for the given borrower hash, which
+    /// the lending contract only allows once the borrower did not claim them
+    /// within its claim period.
+    async fn reclaim_stable(&self, borrower_hash: &[u8; 32]) -> Result<(), Error>;
+    /// Repays the stable loan with the given borrower hash to its lender, along
+    /// with the interest accrued since it was claimed.
+    async fn repay_stable(&self, borrower_hash: &[u8; 32]) -> Result<(), Error>;
+    /// Repays the given amount of the stable loan with the given borrower hash
+    /// to its lender, lowering the amount owed.
+    async fn repay_stable_partially(&self, borrower_hash: &[u8; 32], amount: u128)
+        -> Result<(), Error>;
+    /// Returns the current value of the given amount of bitcoin collateral,
+    /// expressed in stable token units.
+    async fn get_collateral_value(&self, collateral: Amount) -> Result<u128, Error>;
+    /// Returns the number of stable token units making up one unit of the
+    /// price attested by the oracles, in which loan principals are expressed.
+    async fn get_stable_unit(&self) -> Result<u128, Error>;
+    /// Returns the latest price of a bitcoin reported by the BTC price feed
+    /// of the lending contract.
+    async fn get_btc_price(&self) -> Result<u64, Error>;
+    /// Returns the chain, lending contract and stable token the backend is
+    /// configured with.
+    async fn get_deployment(&self) -> Result<LendingDeployment, Error>;
+}
+
 /// Represents a UTXO.
 #[derive(Clone, Debug)]
 pub struct Utxo {
@@ -0,0 +1,636 @@
+//! #LoanMarketplace
+//!
+//! An order book of the loan listings published by lenders. Listings are
+//! signed by the lender node and relayed from peer to peer, so that borrowers
+//! can find lenders they are not connected to and answer a listing with a loan
+//! request.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use dlc_messages::{LoanListingDlc, LoanListingTerms};
+use secp256k1_zkp::{PublicKey, Secp256k1, SecretKey, Signing, Verification, XOnlyPublicKey};
+#[cfg(feature = "use-serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::contract::loan_request::LoanRequest;
+use crate::conversion_utils::PROTOCOL_VERSION;
+use crate::error::Error;
+
+/// The maximum number of listings kept in the order book, so that peers
+/// cannot exhaust the memory of the node.
+pub const MAX_LOAN_LISTINGS: usize = 1000;
+/// The maximum number of listings of a single lender kept in the order book,
+/// so that a lender cannot take all of its room.
+pub const MAX_LOAN_LISTINGS_PER_LENDER: usize = 10;
+/// The maximum time in seconds a listing can stay valid once added to the
+/// order book, so that listings are eventually removed.
+pub const MAX_LOAN_LISTING_VALIDITY: u64 = 86400 * 7;
+
+/// The terms of a listing to publish.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "use-serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct LoanListingInput {
+    /// The address at which the lender node accepts connections, as
+    /// `host:port`.
+    pub lender_address: String,
+    /// The minimum principal lent, in the unit of the price attested by the
+    /// oracle.
+    pub min_principal: u64,
+    /// The maximum principal lent, in the unit of the price attested by the
+    /// oracle.
+    pub max_principal: u64,
+    /// The interest rate in percent.
+    pub interest_rate: u64,
+    /// The collateral ratio in percent required from borrowers.
+    pub collateral_ratio: u64,
+    /// Duration of the loans in seconds.
+    pub duration: u64,
+    /// The public key of the oracle attesting the price the loans are
+    /// settled at.
+    pub oracle_public_key: XOnlyPublicKey,
+    /// The number of seconds the listing stays valid.
+    pub validity: u64,
+}
+
+/// A filter on the listings of the order book. Listings match the filter if
+/// they match each of its set fields.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LoanListingFilter {
+    /// A principal within the range lent.
+    pub principal: Option<u64>,
+    /// The maximum interest rate in percent.
+    pub max_interest_rate: Option<u64>,
+    /// The maximum collateral ratio in percent.
+    pub max_collateral_ratio: Option<u64>,
+    /// The maximum duration in seconds.
+    pub max_duration: Option<u64>,
+    /// The public key of the oracle.
+    pub oracle_public_key: Option<XOnlyPublicKey>,
+    /// The node id of the lender.
+    pub lender: Option<PublicKey>,
+}
+
+impl LoanListingFilter {
+    /// Returns whether the given listing matches the filter.
+    pub fn matches(&self, listing: &LoanListingDlc) -> bool {
+        let terms = &listing.terms;
+        let is_lent = |p: u64| terms.min_principal <= p && p <= terms.max_principal;
+        self.principal.map_or(true, is_lent)
+            && self
+                .max_interest_rate
+                .map_or(true, |r| terms.interest_rate <= r)
+            && self
+                .max_collateral_ratio
+                .map_or(true, |r| terms.collateral_ratio <= r)
+            && self.max_duration.map_or(true, |d| terms.duration <= d)
+            && self
+                .oracle_public_key
+                .map_or(true, |o| terms.oracle_public_key == o)
+            && self.lender.map_or(true, |l| terms.lender == l)
+    }
+}
+
+/// Creates a listing with the given terms and id, signed with the secret key
+/// of the lender node and valid from the given time.
+pub fn sign_loan_listing<C: Signing>(
+    secp: &Secp256k1<C>,
+    input: &LoanListingInput,
+    listing_id: [u8; 32],
+    node_secret_key: &SecretKey,
+    timestamp: u64,
+) -> Result<LoanListingDlc, Error> {
+    let terms = LoanListingTerms {
+        listing_id,
+        lender: PublicKey::from_secret_key(secp, node_secret_key),
+        lender_address: input.lender_address.clone(),
+        min_principal: input.min_principal,
+        max_principal: input.max_principal,
+        interest_rate: input.interest_rate,
+        collateral_ratio: input.collateral_ratio,
+        duration: input.duration,
+        oracle_public_key: input.oracle_public_key,
+        expiry: timestamp
+            .checked_add(input.validity)
+            .ok_or(Error::OutOfRange)?,
+    };
+
+    Ok(LoanListingDlc {
+        protocol_version: PROTOCOL_VERSION,
+        signature: secp.sign_ecdsa(&terms.get_signature_message(), node_secret_key),
+        terms,
+    })
+}
+
+/// Validate the terms of the given listing and its signature, and that it did
+/// not expire at the given time.
+pub fn validate_loan_listing<C: Verification>(
+    secp: &Secp256k1<C>,
+    listing: &LoanListingDlc,
+    timestamp: u64,
+) -> Result<(), Error> {
+    let terms = &listing.terms;
+    if terms.min_principal == 0 || terms.min_principal > terms.max_principal {
+        return Err(Error::InvalidParameters(
+            "Loan listing principal range is invalid.".to_string(),
+        ));
+    }
+    if terms.interest_rate == 0 || terms.interest_rate > 100 {
+        return Err(Error::InvalidParameters(
+            "Interest rate must be between 1 and 100.".to_string(),
+        ));
+    }
+    if terms.collateral_ratio <= 100 {
+        return Err(Error::InvalidParameters(
+            "Collateral ratio must be greater than 100.".to_string(),
+        ));
+    }
+    if terms.duration == 0 {
+        return Err(Error::InvalidParameters(
+            "Loan duration must be greater than zero.".to_string(),
+        ));
+    }
+    if terms.expiry <= timestamp {
+        return Err(Error::InvalidParameters(
+            "Loan listing has expired.".to_string(),
+        ));
+    }
+    listing.validate(secp)?;
+    Ok(())
+}
+
+/// Checks that the given loan request answers the given listing, that is that
+/// it asks for a principal in the range lent for the listed duration and
+/// accepts the listed interest rate.
+pub fn validate_listing_request(
+    listing: &LoanListingDlc,
+    loan_request: &LoanRequest,
+) -> Result<(), Error> {
+    let terms = &listing.terms;
+    if loan_request.listing_id != Some(terms.listing_id) {
+        return Err(Error::InvalidParameters(
+            "Loan request does not answer the listing.".to_string(),
+        ));
+    }
+    if loan_request.principal < terms.min_principal || loan_request.principal > terms.max_principal
+    {
+        return Err(Error::InvalidParameters(
+            "Loan request principal is not in the range of the listing.".to_string(),
+        ));
+    }
+    if loan_request.max_interest_rate < terms.interest_rate {
+        return Err(Error::InvalidParameters(
+            "Loan request maximum interest rate is below the listed rate.".to_string(),
+        ));
+    }
+    if loan_request.duration != terms.duration {
+        return Err(Error::InvalidParameters(
+            "Loan request duration does not match the listing.".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+struct LoanListingEntry {
+    listing: LoanListingDlc,
+    is_local: bool,
+}
+
+/// Keeps the valid listings published by the local node or received from
+/// peers, and the listings to relay to the other peers. Listings are kept per
+/// lender and id, as listing ids are only chosen by each lender.
+pub struct LoanOrderBook {
+    listings: Mutex<HashMap<(PublicKey, [u8; 32]), LoanListingEntry>>,
+    listings_to_relay: Mutex<Vec<(Option<PublicKey>, LoanListingDlc)>>,
+}
+
+impl Default for LoanOrderBook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LoanOrderBook {
+    /// Creates an empty order book.
+    pub fn new() -> Self {
+        LoanOrderBook {
+            listings: Mutex::new(HashMap::new()),
+            listings_to_relay: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Adds a validated listing received from the given peer, or published by
+    /// the local node if None, and queues it to be relayed. Listings already
+    /// in the order book are ignored, so that relaying eventually stops, and
+    /// false is returned. Expired listings are removed.
+    pub fn add_listing(
+        &self,
+        listing: LoanListingDlc,
+        from: Option<PublicKey>,
+        timestamp: u64,
+    ) -> Result<bool, Error> {
+        if listing.terms.expiry > timestamp.saturating_add(MAX_LOAN_LISTING_VALIDITY) {
+            return Err(Error::InvalidParameters(
+                "Loan listing expiry is too far in the future".to_string(),
+            ));
+        }
+        let lender = listing.terms.lender;
+        let key = (lender, listing.terms.listing_id);
+        let mut listings = self.listings.lock().unwrap();
+        listings.retain(|_, x| x.listing.terms.expiry > timestamp);
+        if listings.contains_key(&key) {
+            return Ok(false);
+        }
+        if listings.len() >= MAX_LOAN_LISTINGS {
+            return Err(Error::InvalidState("Loan order book is full".to_string()));
+        }
+        if listings.keys().filter(|(l, _)| *l == lender).count() >= MAX_LOAN_LISTINGS_PER_LENDER {
+            return Err(Error::InvalidState(
+                "Too many loan listings from the lender".to_string(),
+            ));
+        }
+
+        self.listings_to_relay
+            .lock()
+            .unwrap()
+            .push((from, listing.clone()));
+        listings.insert(
+            key,
+            LoanListingEntry {
+                listing,
+                is_local: from.is_none(),
+            },
+        );
+        Ok(true)
+    }
+
+    /// Returns the listing of the given lender with the given id if it did not
+    /// expire at the given time, along with whether it was published by the
+    /// local node.
+    pub fn get_listing(
+        &self,
+        lender: &PublicKey,
+        listing_id: &[u8; 32],
+        timestamp: u64,
+    ) -> Option<(LoanListingDlc, bool)> {
+        self.listings
+            .lock()
+            .unwrap()
+            .get(&(*lender, *listing_id))
+            .filter(|x| x.listing.terms.expiry > timestamp)
+            .map(|x| (x.listing.clone(), x.is_local))
+    }
+
+    /// Returns the listing published by the local node with the given id if
+    /// it did not expire at the given time.
+    pub fn get_local_listing(
+        &self,
+        listing_id: &[u8; 32],
+        timestamp: u64,
+    ) -> Option<LoanListingDlc> {
+        self.listings
+            .lock()
+            .unwrap()
+            .values()
+            .find(|x| {
+                x.is_local
+                    && x.listing.terms.listing_id == *listing_id
+                    && x.listing.terms.expiry > timestamp
+            })
+            .map(|x| x.listing.clone())
+    }
+
+    /// Returns the listings matching the given filter that did not expire at
+    /// the given time, from the lowest interest rate to the highest.
+    pub fn get_listings(&self, filter: &LoanListingFilter, timestamp: u64) -> Vec<LoanListingDlc> {
+        let mut listings: Vec<_> = self
+            .listings
+            .lock()
+            .unwrap()
+            .values()
+            .map(|x| &x.listing)
+            .filter(|x| x.terms.expiry > timestamp && filter.matches(x))
+            .cloned()
+            .collect();
+        listings.sort_by_key(|x| (x.terms.interest_rate, x.terms.listing_id));
+        listings
+    }
+
+    /// Returns the listings to relay along with the peer they were received
+    /// from, or None for the local ones, and empties the relay queue.
+    pub fn get_and_clear_listings_to_relay(&self) -> Vec<(Option<PublicKey>, LoanListingDlc)> {
+        let mut ret = Vec::new();
+        std::mem::swap(&mut *self.listings_to_relay.lock().unwrap(), &mut ret);
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::Amount;
+    use secp256k1_zkp::SECP256K1;
+
+    const NOW: u64 = 1_000_000;
+
+    struct Peer {
+        node_id: PublicKey,
+        order_book: LoanOrderBook,
+        connections: Vec<usize>,
+    }
+
+    fn get_peers(connections: &[&[usize]]) -> Vec<(SecretKey, Peer)> {
+        connections
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                let secret_key = SecretKey::from_slice(&[i as u8 + 1; 32]).unwrap();
+                let peer = Peer {
+                    node_id: PublicKey::from_secret_key(SECP256K1, &secret_key),
+                    order_book: LoanOrderBook::new(),
+                    connections: c.to_vec(),
+                };
+                (secret_key, peer)
+            })
+            .collect()
+    }
+
+    fn get_input(interest_rate: u64) -> LoanListingInput {
+        let oracle_secret_key = SecretKey::from_slice(&[42; 32]).unwrap();
+        LoanListingInput {
+            lender_address: "127.0.0.1:9000".to_string(),
+            min_principal: 10_000,
+            max_principal: 40_000,
+            interest_rate,
+            collateral_ratio: 150,
+            duration: 86_400,
+            oracle_public_key: oracle_secret_key.x_only_public_key(SECP256K1).0,
+            validity: 3600,
+        }
+    }
+
+    /// Delivers the listings queued by each peer to the peers it is connected
+    /// to, except the one it received them from, until no listing is left to
+    /// relay. Returns the number of messages sent.
+    fn relay(peers: &[(SecretKey, Peer)]) -> usize {
+        let mut nb_messages = 0;
+        loop {
+            let mut messages = Vec::new();
+            for (_, peer) in peers {
+                for (from, listing) in peer.order_book.get_and_clear_listings_to_relay() {
+                    for to in &peer.connections {
+                        if Some(peers[*to].1.node_id) != from {
+                            messages.push((peer.node_id, *to, listing.clone()));
+                        }
+                    }
+                }
+            }
+            if messages.is_empty() {
+                return nb_messages;
+            }
+            nb_messages += messages.len();
+            for (from, to, listing) in messages {
+                validate_loan_listing(SECP256K1, &listing, NOW).unwrap();
+                peers[to]
+                    .1
+                    .order_book
+                    .add_listing(listing, Some(from), NOW)
+                    .unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn listings_are_relayed_to_unconnected_peers_test() {
+        // Peers connected in a cycle: 0 - 1 - 2 - 3 - 0.
+        let peers = get_peers(&[&[1, 3], &[0, 2], &[1, 3], &[2, 0]]);
+        let (lender_key, lender) = &peers[0];
+        let listing =
+            sign_loan_listing(SECP256K1, &get_input(10), [1; 32], lender_key, NOW).unwrap();
+        validate_loan_listing(SECP256K1, &listing, NOW).unwrap();
+        assert!(lender
+            .order_book
+            .add_listing(listing.clone(), None, NOW)
+            .unwrap());
+
+        // The lender sends the listing to both its peers, and each other peer
+        // relays it once to its other peer, the last copy being ignored.
+        assert_eq!(5, relay(&peers));
+        let lender_id = lender.node_id;
+        for (_, peer) in &peers {
+            let (received, _) = peer
+                .order_book
+                .get_listing(&lender_id, &[1; 32], NOW)
+                .unwrap();
+            assert_eq!(listing, received);
+        }
+        assert!(
+            lender
+                .order_book
+                .get_listing(&lender_id, &[1; 32], NOW)
+                .unwrap()
+                .1
+        );
+        assert!(
+            !peers[2]
+                .1
+                .order_book
+                .get_listing(&lender_id, &[1; 32], NOW)
+                .unwrap()
+                .1
+        );
+    }
+
+    #[test]
+    fn borrower_filters_listings_and_requests_loan_test() {
+        let peers = get_peers(&[&[2], &[2], &[0, 1]]);
+        for (i, rate) in [(0, 12), (1, 8)] {
+            let (lender_key, lender) = &peers[i];
+            let listing =
+                sign_loan_listing(SECP256K1, &get_input(rate), [i as u8; 32], lender_key, NOW)
+                    .unwrap();
+            lender.order_book.add_listing(listing, None, NOW).unwrap();
+        }
+        relay(&peers);
+
+        let borrower = &peers[2].1.order_book;
+        let listings = borrower.get_listings(&LoanListingFilter::default(), NOW);
+        assert_eq!(
+            vec![8, 12],
+            listings
+                .iter()
+                .map(|x| x.terms.interest_rate)
+                .collect::<Vec<_>>()
+        );
+        let filter = LoanListingFilter {
+            principal: Some(20_000),
+            max_interest_rate: Some(10),
+            ..Default::default()
+        };
+        let listings = borrower.get_listings(&filter, NOW);
+        assert_eq!(1, listings.len());
+        assert_eq!(peers[1].1.node_id, listings[0].terms.lender);
+        let filter = LoanListingFilter {
+            principal: Some(50_000),
+            ..Default::default()
+        };
+        assert!(borrower.get_listings(&filter, NOW).is_empty());
+
+        let listing = &listings[0];
+        let loan_request = LoanRequest {
+            listing_id: Some(listing.terms.listing_id),
+            ..LoanRequest::new(
+                [7; 32],
+                listing.terms.lender,
+                Amount::from_sat(100_000),
+                20_000,
+                listing.terms.interest_rate,
+                listing.terms.duration,
+            )
+        };
+        validate_listing_request(listing, &loan_request).unwrap();
+        validate_listing_request(
+            listing,
+            &LoanRequest {
+                principal: 50_000,
+                ..loan_request.clone()
+            },
+        )
+        .expect_err("principal should be out of the listed range");
+        validate_listing_request(
+            listing,
+            &LoanRequest {
+                max_interest_rate: 5,
+                ..loan_request
+            },
+        )
+        .expect_err("rate should be below the listed one");
+    }
+
+    #[test]
+    fn invalid_listings_are_rejected_test() {
+        let secret_key = SecretKey::from_slice(&[1; 32]).unwrap();
+        let listing =
+            sign_loan_listing(SECP256K1, &get_input(10), [1; 32], &secret_key, NOW).unwrap();
+        validate_loan_listing(SECP256K1, &listing, NOW + 3600).expect_err("should be expired");
+
+        let mut altered = listing.clone();
+        altered.terms.max_principal = 1_000_000;
+        validate_loan_listing(SECP256K1, &altered, NOW).expect_err("should not be signed");
+
+        let input = LoanListingInput {
+            min_principal: 50_000,
+            ..get_input(10)
+        };
+        let listing = sign_loan_listing(SECP256K1, &input, [2; 32], &secret_key, NOW).unwrap();
+        validate_loan_listing(SECP256K1, &listing, NOW).expect_err("should have an empty range");
+    }
+
+    #[test]
+    fn expired_listings_are_removed_test() {
+        let secret_key = SecretKey::from_slice(&[1; 32]).unwrap();
+        let order_book = LoanOrderBook::new();
+        let listing =
+            sign_loan_listing(SECP256K1, &get_input(10), [1; 32], &secret_key, NOW).unwrap();
+        assert!(order_book.add_listing(listing.clone(), None, NOW).unwrap());
+        assert!(!order_book.add_listing(listing, None, NOW).unwrap());
+        assert_eq!(1, order_book.get_and_clear_listings_to_relay().len());
+
+        let lender = PublicKey::from_secret_key(SECP256K1, &secret_key);
+        assert!(order_book
+            .get_listing(&lender, &[1; 32], NOW + 3600)
+            .is_none());
+        let listing =
+            sign_loan_listing(SECP256K1, &get_input(10), [2; 32], &secret_key, NOW + 3600).unwrap();
+        order_book.add_listing(listing, None, NOW + 3600).unwrap();
+        assert_eq!(1, order_book.listings.lock().unwrap().len());
+    }
+
+    #[test]
+    fn listings_are_kept_per_lender_test() {
+        let first_key = SecretKey::from_slice(&[1; 32]).unwrap();
+        let second_key = SecretKey::from_slice(&[2; 32]).unwrap();
+        let order_book = LoanOrderBook::new();
+        for secret_key in [&first_key, &second_key] {
+            let listing =
+                sign_loan_listing(SECP256K1, &get_input(10), [1; 32], secret_key, NOW).unwrap();
+            assert!(order_book.add_listing(listing, None, NOW).unwrap());
+        }
+
+        // Another lender reusing the id of a listing does not replace it.
+        let first_lender = PublicKey::from_secret_key(SECP256K1, &first_key);
+        let (listing, _) = order_book
+            .get_listing(&first_lender, &[1; 32], NOW)
+            .unwrap();
+        assert_eq!(first_lender, listing.terms.lender);
+        assert_eq!(2, order_book.get_listings(&Default::default(), NOW).len());
+    }
+
+    #[test]
+    fn listings_per_lender_are_capped_test() {
+        let secret_key = SecretKey::from_slice(&[1; 32]).unwrap();
+        let order_book = LoanOrderBook::new();
+        for i in 0..MAX_LOAN_LISTINGS_PER_LENDER {
+            let listing =
+                sign_loan_listing(SECP256K1, &get_input(10), [i as u8; 32], &secret_key, NOW)
+                    .unwrap();
+            order_book.add_listing(listing, None, NOW).unwrap();
+        }
+        let listing =
+            sign_loan_listing(SECP256K1, &get_input(10), [0xff; 32], &secret_key, NOW).unwrap();
+        order_book
+            .add_listing(listing, None, NOW)
+            .expect_err("should not keep more listings of the lender");
+
+        let other_key = SecretKey::from_slice(&[2; 32]).unwrap();
+        let listing =
+            sign_loan_listing(SECP256K1, &get_input(10), [0xff; 32], &other_key, NOW).unwrap();
+        assert!(order_book.add_listing(listing, None, NOW).unwrap());
+    }
+
+    #[test]
+    fn listing_expiry_is_bounded_test() {
+        let secret_key = SecretKey::from_slice(&[1; 32]).unwrap();
+        let order_book = LoanOrderBook::new();
+        let input = LoanListingInput {
+            validity: MAX_LOAN_LISTING_VALIDITY + 1,
+            ..get_input(10)
+        };
+        let listing = sign_loan_listing(SECP256K1, &input, [1; 32], &secret_key, NOW).unwrap();
+        validate_loan_listing(SECP256K1, &listing, NOW).unwrap();
+        order_book
+            .add_listing(listing, None, NOW)
+            .expect_err("should not keep a listing valid for too long");
+
+        let input = LoanListingInput {
+            validity: MAX_LOAN_LISTING_VALIDITY,
+            ..get_input(10)
+        };
+        let listing = sign_loan_listing(SECP256K1, &input, [1; 32], &secret_key, NOW).unwrap();
+        assert!(order_book.add_listing(listing, None, NOW).unwrap());
+    }
+
+    #[test]
+    fn local_listing_is_found_by_id_test() {
+        let secret_key = SecretKey::from_slice(&[1; 32]).unwrap();
+        let order_book = LoanOrderBook::new();
+        let listing =
+            sign_loan_listing(SECP256K1, &get_input(10), [1; 32], &secret_key, NOW).unwrap();
+        order_book.add_listing(listing.clone(), None, NOW).unwrap();
+        let other_key = SecretKey::from_slice(&[2; 32]).unwrap();
+        let peer_listing =
+            sign_loan_listing(SECP256K1, &get_input(10), [2; 32], &other_key, NOW).unwrap();
+        let peer = peer_listing.terms.lender;
+        order_book
+            .add_listing(peer_listing, Some(peer), NOW)
+            .unwrap();
+
+        assert_eq!(Some(listing), order_book.get_local_listing(&[1; 32], NOW));
+        assert!(order_book.get_local_listing(&[2; 32], NOW).is_none());
+        assert!(order_book.get_local_listing(&[1; 32], NOW + 3600).is_none());
+    }
+}
@@ -0,0 +1,303 @@
+//! #LoanMath
+//!
+//! Computes what a borrower owes on a loan over time. Interest is simple
+//! interest over a 365 days year computed with integer arithmetic and rounded
+//! down, the same way as the `calculateInterest` function of the lending
+//! contract, so that both sides agree on the amounts owed.
+
+use crate::error::Error;
+use crate::StableLoan;
+
+/// The number of seconds in a year, used to compute the interest of loans.
+pub const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
+
+/// The number of basis points in a percent.
+pub const BPS_PER_PERCENT: u64 = 100;
+
+const BPS_PER_UNIT: u64 = 100 * BPS_PER_PERCENT;
+
+/// Converts a yearly interest rate in whole percents to basis points.
+pub fn percent_to_bps(interest_rate: u64) -> Result<u64, Error> {
+    interest_rate
+        .checked_mul(BPS_PER_PERCENT)
+        .ok_or(Error::OutOfRange)
+}
+
+/// Returns the interest accrued on the principal after the given number of
+/// seconds at the given yearly rate, rounded down. For rates in whole
+/// percents, this is exactly `principal * interestRate * timeElapsed /
+/// (SECONDS_PER_YEAR * 100)` as computed by the lending contract.
+pub fn calculate_interest(
+    principal: u128,
+    interest_rate_bps: u64,
+    elapsed: u64,
+) -> Result<u128, Error> {
+    let denominator = (SECONDS_PER_YEAR * BPS_PER_UNIT) as u128;
+    let numerator = principal
+        .checked_mul(interest_rate_bps as u128)
+        .ok_or(Error::OutOfRange)?;
+    // Splitting the numerator gives the same rounding as the full product
+    // while keeping intermediate values within 128 bits.
+    let quotient = numerator / denominator;
+    let remainder = numerator % denominator;
+    quotient
+        .checked_mul(elapsed as u128)
+        .and_then(|x| x.checked_add(remainder * elapsed as u128 / denominator))
+        .ok_or(Error::OutOfRange)
+}
+
+/// The amounts owed by a borrower at a given time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AmountDue {
+    /// The principal of the loan.
+    pub principal: u128,
+    /// The interest accrued on the principal.
+    pub accrued_interest: u128,
+    /// The principal plus the accrued interest.
+    pub total_due: u128,
+}
+
+/// The terms determining how the amount owed on a loan grows over time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LoanAccrual {
+    /// The principal of the loan.
+    pub principal: u128,
+    /// The yearly interest rate in basis points.
+    pub interest_rate_bps: u64,
+    /// The unix time from which interest accrues.
+    pub start_time: u64,
+    /// The duration of the loan in seconds.
+    pub duration: u64,
+}
+
+impl LoanAccrual {
+    /// Returns the unix time at which the loan matures.
+    pub fn get_maturity(&self) -> u64 {
+        self.start_time.saturating_add(self.duration)
+    }
+
+    /// Returns the number of seconds of interest owed at the given time. Loans
+    /// repaid early only pay interest for the time they were outstanding, and
+    /// interest stops accruing at maturity, where the DLC settles for the debt
+    /// owed over the whole duration. The lending contract does not cap its
+    /// interest, see [`get_stable_loan_amount_due`] for what it charges.
+    pub fn get_accrual_period(&self, timestamp: u64) -> u64 {
+        timestamp.saturating_sub(self.start_time).min(self.duration)
+    }
+
+    /// Returns the amounts owed at the given time.
+    pub fn get_amount_due(&self, timestamp: u64) -> Result<AmountDue, Error> {
+        let accrued_interest = calculate_interest(
+            self.principal,
+            self.interest_rate_bps,
+            self.get_accrual_period(timestamp),
+        )?;
+        let total_due = self
+            .principal
+            .checked_add(accrued_interest)
+            .ok_or(Error::OutOfRange)?;
+        Ok(AmountDue {
+            principal: self.principal,
+            accrued_interest,
+            total_due,
+        })
+    }
+
+    /// Returns the amounts owed at the maturity of the loan.
+    pub fn get_amount_due_at_maturity(&self) -> Result<AmountDue, Error> {
+        self.get_amount_due(self.get_maturity())
+    }
+}
+
+/// Returns the interest accrued on the given stable loan since the start of
+/// its current interest period, as computed by the `calculateStableInterest`
+/// function of the lending contract. Interest keeps accruing past the
+/// maturity of the loan until it is repaid.
+pub fn calculate_stable_interest(stable_loan: &StableLoan, timestamp: u64) -> Result<u128, Error> {
+    if stable_loan.borrower_preimage == 0 || timestamp <= stable_loan.start_time {
+        return Ok(0);
+    }
+    calculate_interest(
+        stable_loan.stable_loan_amount,
+        percent_to_bps(stable_loan.interest_rate)?,
+        timestamp - stable_loan.start_time,
+    )
+}
+
+/// Returns the amounts owed on the given stable loan at the given time, as
+/// computed by the `getStableLoanAmountDue` function of the lending contract:
+/// the amount left to repay, plus the interest accrued before the last partial
+/// repayment and since then.
+pub fn get_stable_loan_amount_due(
+    stable_loan: &StableLoan,
+    timestamp: u64,
+) -> Result<AmountDue, Error> {
+    let accrued_interest = stable_loan
+        .accrued_interest
+        .checked_add(calculate_stable_interest(stable_loan, timestamp)?)
+        .ok_or(Error::OutOfRange)?;
+    let total_due = stable_loan
+        .stable_loan_amount
+        .checked_add(accrued_interest)
+        .ok_or(Error::OutOfRange)?;
+    Ok(AmountDue {
+        principal: stable_loan.stable_loan_amount,
+        accrued_interest,
+        total_due,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::Amount;
+
+    // The formula used by the lending contract, with the rate in percent.
+    fn calculate_contract_interest(principal: u128, interest_rate: u64, elapsed: u64) -> u128 {
+        principal * interest_rate as u128 * elapsed as u128 / (SECONDS_PER_YEAR as u128 * 100)
+    }
+
+    #[test]
+    fn interest_matches_lending_contract_test() {
+        let principals = [1, 999, 40_000, 1_234_567_890_123, 40_000 * 10u128.pow(18)];
+        let elapsed = [
+            0,
+            1,
+            59,
+            86_399,
+            3_600 * 24 * 30 + 17,
+            SECONDS_PER_YEAR,
+            3 * SECONDS_PER_YEAR + 1,
+        ];
+        for principal in principals.iter() {
+            for interest_rate in [1, 7, 10, 33, 100].iter() {
+                for elapsed in elapsed.iter() {
+                    assert_eq!(
+                        calculate_contract_interest(*principal, *interest_rate, *elapsed),
+                        calculate_interest(
+                            *principal,
+                            percent_to_bps(*interest_rate).unwrap(),
+                            *elapsed
+                        )
+                        .unwrap()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn interest_with_bps_precision_test() {
+        // 2.5% of 40000 over half a year.
+        assert_eq!(
+            500,
+            calculate_interest(40_000, 250, SECONDS_PER_YEAR / 2).unwrap()
+        );
+        // Rounded down like the lending contract does.
+        assert_eq!(0, calculate_interest(100, 1, SECONDS_PER_YEAR).unwrap());
+    }
+
+    #[test]
+    fn interest_overflow_is_error_test() {
+        calculate_interest(u128::MAX, 2, 1).expect_err("should overflow");
+        calculate_interest(u128::MAX / 10_000, 10_000, u64::MAX).expect_err("should overflow");
+    }
+
+    #[test]
+    fn amount_due_is_prorated_test() {
+        let accrual = LoanAccrual {
+            principal: 40_000,
+            interest_rate_bps: 1_000,
+            start_time: 1_000,
+            duration: SECONDS_PER_YEAR,
+        };
+
+        let before_start = accrual.get_amount_due(0).unwrap();
+        assert_eq!(0, before_start.accrued_interest);
+        assert_eq!(40_000, before_start.total_due);
+
+        let early = accrual
+            .get_amount_due(1_000 + SECONDS_PER_YEAR / 4)
+            .unwrap();
+        assert_eq!(1_000, early.accrued_interest);
+        assert_eq!(41_000, early.total_due);
+
+        let at_maturity = accrual.get_amount_due_at_maturity().unwrap();
+        assert_eq!(
+            AmountDue {
+                principal: 40_000,
+                accrued_interest: 4_000,
+                total_due: 44_000,
+            },
+            at_maturity
+        );
+        assert_eq!(
+            at_maturity,
+            accrual
+                .get_amount_due(accrual.get_maturity() + SECONDS_PER_YEAR)
+                .unwrap()
+        );
+    }
+
+    fn get_stable_loan(start_time: u64) -> StableLoan {
+        StableLoan {
+            collateral_amount: Amount::ONE_BTC,
+            stable_loan_amount: 1_234_567,
+            borrower_preimage: 42,
+            repaid: false,
+            interest_rate: 10,
+            start_time,
+            accrued_interest: 25,
+        }
+    }
+
+    #[test]
+    fn stable_loan_amount_due_matches_lending_contract_test() {
+        let stable_loan = get_stable_loan(1_000);
+
+        // Pinned to the value of test_StableLoanAmountDueIsNotCappedAtMaturity
+        // in P2PBTCLendingStable.t.sol, two years past the start of the loan.
+        let amount_due =
+            get_stable_loan_amount_due(&stable_loan, 1_000 + 2 * SECONDS_PER_YEAR + 12_345)
+                .unwrap();
+        assert_eq!(
+            AmountDue {
+                principal: 1_234_567,
+                accrued_interest: 25 + 246_961,
+                total_due: 1_481_553,
+            },
+            amount_due
+        );
+
+        for elapsed in [0, 1, 86_399, SECONDS_PER_YEAR, 5 * SECONDS_PER_YEAR + 7].iter() {
+            assert_eq!(
+                stable_loan.stable_loan_amount
+                    + stable_loan.accrued_interest
+                    + calculate_contract_interest(
+                        stable_loan.stable_loan_amount,
+                        stable_loan.interest_rate,
+                        *elapsed
+                    ),
+                get_stable_loan_amount_due(&stable_loan, 1_000 + elapsed)
+                    .unwrap()
+                    .total_due
+            );
+        }
+    }
+
+    #[test]
+    fn stable_loan_interest_before_claim_or_start_is_zero_test() {
+        let stable_loan = get_stable_loan(1_000);
+
+        assert_eq!(0, calculate_stable_interest(&stable_loan, 999).unwrap());
+        assert_eq!(0, calculate_stable_interest(&stable_loan, 1_000).unwrap());
+        let unclaimed = StableLoan {
+            borrower_preimage: 0,
+            ..stable_loan
+        };
+        assert_eq!(
+            0,
+            calculate_stable_interest(&unclaimed, 1_000 + SECONDS_PER_YEAR).unwrap()
+        );
+    }
+}
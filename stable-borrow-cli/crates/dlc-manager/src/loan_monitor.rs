@@ -0,0 +1,379 @@
+//! #LoanMonitor
+//!
+//! Monitors how close active loans are to their liquidation. The collateral
+//! ratio of a loan is the value of its collateral at the current price over
+//! the amount owed on it, and a warning and then a margin call are emitted as
+//! it gets close to the liquidation ratio of the loan.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use bitcoin::Amount;
+use dlc_messages::oracle_msgs::OracleAttestation;
+
+use crate::error::Error;
+use crate::{ContractId, PriceFeed};
+
+const SATS_PER_BTC: u128 = 100_000_000;
+
+/// The source of the price used to compute the collateral ratio of loans.
+#[derive(Clone)]
+pub enum LoanPriceSource {
+    /// The latest price attested by the oracles of the events of each loan.
+    Oracle,
+    /// The BTC price feed of the lending contract.
+    LendingContract,
+    /// A price feed configured by the user.
+    Feed(Arc<dyn PriceFeed + Send + Sync>),
+}
+
+/// The configuration of the monitoring of loans. Thresholds are margins in
+/// percent above the liquidation ratio of each loan.
+#[derive(Clone)]
+pub struct LoanMonitorConfig {
+    /// The source of the price of a bitcoin.
+    pub price_source: LoanPriceSource,
+    /// The margin above the liquidation ratio under which a warning is emitted.
+    pub warning_margin: u64,
+    /// The margin above the liquidation ratio under which a margin call is
+    /// emitted.
+    pub margin_call_margin: u64,
+    /// The minimum time in seconds between two checks of the loans.
+    pub check_interval: u64,
+}
+
+impl Default for LoanMonitorConfig {
+    fn default() -> Self {
+        LoanMonitorConfig {
+            price_source: LoanPriceSource::Oracle,
+            warning_margin: 30,
+            margin_call_margin: 10,
+            check_interval: 60,
+        }
+    }
+}
+
+impl LoanMonitorConfig {
+    /// Checks that a margin call is emitted closer to the liquidation than a
+    /// warning.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.margin_call_margin >= self.warning_margin {
+            return Err(Error::InvalidParameters(
+                "Margin call margin must be lower than the warning margin.".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the risk level of a loan with the given collateral and
+    /// liquidation ratios.
+    pub fn get_risk_level(&self, collateral_ratio: u64, liquidation_ratio: u64) -> LoanRiskLevel {
+        if collateral_ratio < liquidation_ratio.saturating_add(self.margin_call_margin) {
+            LoanRiskLevel::MarginCall
+        } else if collateral_ratio < liquidation_ratio.saturating_add(self.warning_margin) {
+            LoanRiskLevel::Warning
+        } else {
+            LoanRiskLevel::Healthy
+        }
+    }
+}
+
+/// How close a loan is to its liquidation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoanRiskLevel {
+    /// The collateral ratio is above the warning threshold.
+    Healthy,
+    /// The collateral ratio is below the warning threshold.
+    Warning,
+    /// The collateral ratio is below the margin call threshold, and the
+    /// borrower should add collateral or repay part of the loan.
+    MarginCall,
+}
+
+impl std::fmt::Display for LoanRiskLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let level = match self {
+            LoanRiskLevel::Healthy => "healthy",
+            LoanRiskLevel::Warning => "warning",
+            LoanRiskLevel::MarginCall => "margin call",
+        };
+        f.write_str(level)
+    }
+}
+
+/// The collateral ratio of a loan at a given price.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoanRisk {
+    /// The id of the loan contract.
+    pub contract_id: ContractId,
+    /// Whether the local party is the lender of the loan.
+    pub is_lender: bool,
+    /// The price of a bitcoin the ratio was computed at.
+    pub price: u64,
+    /// The collateral ratio of the loan in percent.
+    pub collateral_ratio: u64,
+    /// The liquidation ratio of the loan in percent.
+    pub liquidation_ratio: u64,
+    /// The risk level of the loan.
+    pub level: LoanRiskLevel,
+    /// The unix time at which the ratio was computed.
+    pub timestamp: u64,
+}
+
+/// Emitted when the risk level of a loan changes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoanRiskEvent {
+    /// The risk of the loan that triggered the event.
+    pub risk: LoanRisk,
+    /// The risk level of the loan before the event.
+    pub previous_level: LoanRiskLevel,
+}
+
+/// Returns the collateral ratio in percent of a loan with the given collateral
+/// and debt at the given price, rounded down. The debt and the price must be
+/// expressed in the same unit.
+pub fn get_collateral_ratio(collateral: Amount, price: u64, debt: u128) -> Result<u64, Error> {
+    if debt == 0 {
+        return Err(Error::InvalidParameters(
+            "Loan debt must be greater than zero.".to_string(),
+        ));
+    }
+    let collateral_value = (collateral.to_sat() as u128)
+        .checked_mul(price as u128)
+        .and_then(|x| x.checked_mul(100))
+        .ok_or(Error::OutOfRange)?;
+    let debt_value = debt.checked_mul(SATS_PER_BTC).ok_or(Error::OutOfRange)?;
+    Ok(u64::try_from(collateral_value / debt_value).unwrap_or(u64::MAX))
+}
+
+/// Returns the value attested by the given attestation of a numerical event
+/// with the given base.
+pub fn get_attested_value(attestation: &OracleAttestation, base: u16) -> Result<u64, Error> {
+    let mut outcomes = attestation.outcomes.iter().peekable();
+    match outcomes.peek().map(|x| x.as_str()) {
+        Some("+") => {
+            outcomes.next();
+        }
+        Some("-") => {
+            return Err(Error::InvalidParameters(
+                "Attested price is negative.".to_string(),
+            ))
+        }
+        _ => {}
+    }
+    outcomes.try_fold(0u64, |value, outcome| {
+        let digit: u64 = outcome
+            .parse()
+            .map_err(|_| Error::InvalidParameters(format!("Invalid attested digit {}", outcome)))?;
+        if digit >= base as u64 {
+            return Err(Error::InvalidParameters(format!(
+                "Attested digit {} is not in base {}",
+                digit, base
+            )));
+        }
+        value
+            .checked_mul(base as u64)
+            .and_then(|x| x.checked_add(digit))
+            .ok_or(Error::OutOfRange)
+    })
+}
+
+/// Keeps the latest risk of the monitored loans and notifies the subscribers
+/// when their risk level changes.
+pub struct LoanMonitor {
+    config: LoanMonitorConfig,
+    last_check: Mutex<Option<u64>>,
+    risks: Mutex<HashMap<ContractId, LoanRisk>>,
+    subscribers: Mutex<Vec<Sender<LoanRiskEvent>>>,
+}
+
+impl LoanMonitor {
+    /// Creates a new monitor with the given configuration.
+    pub fn new(config: LoanMonitorConfig) -> Self {
+        LoanMonitor {
+            config,
+            last_check: Mutex::new(None),
+            risks: Mutex::new(HashMap::new()),
+            subscribers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns the configuration of the monitor.
+    pub fn get_config(&self) -> &LoanMonitorConfig {
+        &self.config
+    }
+
+    /// Sets the configuration of the monitor, so that the loans are checked
+    /// again at the next opportunity.
+    pub fn set_config(&mut self, config: LoanMonitorConfig) {
+        self.config = config;
+        *self.last_check.lock().unwrap() = None;
+    }
+
+    /// Returns a receiver of the events emitted when the risk level of a loan
+    /// changes.
+    pub fn subscribe(&self) -> Receiver<LoanRiskEvent> {
+        let (sender, receiver) = channel();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    /// Returns whether the loans should be checked at the given time, in which
+    /// case the time is recorded as the one of the last check.
+    pub fn start_check(&self, timestamp: u64) -> bool {
+        let mut last_check = self.last_check.lock().unwrap();
+        match *last_check {
+            Some(last) if timestamp < last.saturating_add(self.config.check_interval) => false,
+            _ => {
+                *last_check = Some(timestamp);
+                true
+            }
+        }
+    }
+
+    /// Records the given risks and forgets the loans that are no longer
+    /// monitored. Returns the events emitted for the loans whose risk level
+    /// changed, a loan starting out healthy.
+    pub fn update(&self, monitored: &[ContractId], risks: Vec<LoanRisk>) -> Vec<LoanRiskEvent> {
+        let mut cur_risks = self.risks.lock().unwrap();
+        cur_risks.retain(|id, _| monitored.contains(id));
+
+        let mut events = Vec::new();
+        for risk in risks {
+            let previous_level = cur_risks
+                .get(&risk.contract_id)
+                .map(|x| x.level)
+                .unwrap_or(LoanRiskLevel::Healthy);
+            if previous_level != risk.level {
+                events.push(LoanRiskEvent {
+                    risk: risk.clone(),
+                    previous_level,
+                });
+            }
+            cur_risks.insert(risk.contract_id, risk);
+        }
+
+        // Subscribers whose receiver was dropped are removed.
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|s| events.iter().all(|e| s.send(e.clone()).is_ok()));
+
+        events
+    }
+
+    /// Returns the latest risk of each monitored loan.
+    pub fn get_risks(&self) -> Vec<LoanRisk> {
+        self.risks.lock().unwrap().values().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1_zkp::XOnlyPublicKey;
+    use std::str::FromStr;
+
+    fn get_risk(contract_id: ContractId, collateral_ratio: u64) -> LoanRisk {
+        LoanRisk {
+            contract_id,
+            is_lender: true,
+            price: 60_000,
+            collateral_ratio,
+            liquidation_ratio: 125,
+            level: LoanMonitorConfig::default().get_risk_level(collateral_ratio, 125),
+            timestamp: 1,
+        }
+    }
+
+    #[test]
+    fn collateral_ratio_test() {
+        // 1 BTC at 60000 for a debt of 44000.
+        assert_eq!(
+            136,
+            get_collateral_ratio(Amount::ONE_BTC, 60_000, 44_000).unwrap()
+        );
+        get_collateral_ratio(Amount::ONE_BTC, 60_000, 0).expect_err("should not have zero debt");
+    }
+
+    #[test]
+    fn risk_level_test() {
+        let config = LoanMonitorConfig::default();
+        assert_eq!(LoanRiskLevel::Healthy, config.get_risk_level(155, 125));
+        assert_eq!(LoanRiskLevel::Warning, config.get_risk_level(154, 125));
+        assert_eq!(LoanRiskLevel::Warning, config.get_risk_level(135, 125));
+        assert_eq!(LoanRiskLevel::MarginCall, config.get_risk_level(134, 125));
+        assert_eq!(LoanRiskLevel::MarginCall, config.get_risk_level(100, 125));
+
+        LoanMonitorConfig {
+            margin_call_margin: 30,
+            ..config
+        }
+        .validate()
+        .expect_err("margin call margin should be lower than the warning one");
+    }
+
+    #[test]
+    fn attested_value_test() {
+        let get_attestation = |outcomes: &[&str]| OracleAttestation {
+            event_id: "btcusd".to_string(),
+            oracle_public_key: XOnlyPublicKey::from_str(
+                "e6642fd69bd211f93f7f1f36ca51a26a5290eb2dd1b0d8279a87bb0d480c8443",
+            )
+            .unwrap(),
+            signatures: Vec::new(),
+            outcomes: outcomes.iter().map(|x| x.to_string()).collect(),
+        };
+
+        assert_eq!(
+            11,
+            get_attested_value(&get_attestation(&["1", "0", "1", "1"]), 2).unwrap()
+        );
+        assert_eq!(
+            11,
+            get_attested_value(&get_attestation(&["+", "1", "0", "1", "1"]), 2).unwrap()
+        );
+        get_attested_value(&get_attestation(&["-", "1"]), 2).expect_err("should be negative");
+        get_attested_value(&get_attestation(&["1", "2"]), 2).expect_err("should not be binary");
+    }
+
+    #[test]
+    fn monitor_emits_events_on_level_changes_test() {
+        let monitor = LoanMonitor::new(LoanMonitorConfig::default());
+        let receiver = monitor.subscribe();
+        let (first, second) = ([1; 32], [2; 32]);
+
+        let events = monitor.update(
+            &[first, second],
+            vec![get_risk(first, 200), get_risk(second, 140)],
+        );
+        assert_eq!(1, events.len());
+        assert_eq!(LoanRiskLevel::Healthy, events[0].previous_level);
+        assert_eq!(LoanRiskLevel::Warning, events[0].risk.level);
+        assert_eq!(events[0], receiver.try_recv().unwrap());
+
+        assert!(monitor
+            .update(&[first, second], vec![get_risk(second, 145)])
+            .is_empty());
+        let events = monitor.update(&[first, second], vec![get_risk(second, 130)]);
+        assert_eq!(LoanRiskLevel::MarginCall, events[0].risk.level);
+        assert_eq!(LoanRiskLevel::Warning, events[0].previous_level);
+
+        monitor.update(&[first], Vec::new());
+        assert_eq!(1, monitor.get_risks().len());
+
+        drop(receiver);
+        monitor.update(&[first], vec![get_risk(first, 100)]);
+        assert!(monitor.subscribers.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn monitor_check_interval_test() {
+        let monitor = LoanMonitor::new(LoanMonitorConfig::default());
+        assert!(monitor.start_check(100));
+        assert!(!monitor.start_check(159));
+        assert!(monitor.start_check(160));
+    }
+}
@@ -1,7 +1,8 @@
 //! #Manager a component to create and update DLCs.
 
 use super::{
-    Blockchain, CachedContractSignerProvider, ContractSigner, Oracle, Storage, Time, Wallet,
+    Blockchain, CachedContractSignerProvider, ContractSigner, LendingBackend, Oracle, Storage,
+    Time, Wallet,
 };
 use crate::chain_monitor::{ChainMonitor, ChannelInfo, RevokedTxType, TxType};
 use crate::channel::offered_channel::OfferedChannel;
@@ -10,35 +11,48 @@ use crate::channel::{Channel, ClosedChannel, ClosedPunishedChannel};
 use crate::channel_updater::get_signed_channel_state;
 use crate::channel_updater::verify_signed_channel;
 use crate::contract::contract_input::LoanContractInput;
+use crate::contract::loan_negotiation::{LoanNegotiation, LoanNegotiationState, LoanTermsRevision};
+use crate::contract::loan_renewal::{
+    get_repaid_stable_amount, validate_loan_renewal, LoanRenewal, LoanRenewalState,
+};
+use crate::contract::loan_request::{LoanRequest, LoanRequestState};
+use crate::contract::loan_state::{LoanLifecycle, LoanState};
 use crate::contract::offered_contract::OfferedLoanContract;
 use crate::contract::{
     accepted_contract::AcceptedContract, contract_info::ContractInfo,
     contract_input::ContractInput, contract_input::OracleInput, offered_contract::OfferedContract,
-    signed_contract::SignedContract, AdaptorInfo, ClosedContract, Contract, FailedAcceptContract,
-    FailedSignContract, PreClosedContract,
+    signed_contract::SignedContract, AdaptorInfo, ClosedContract, Contract, ContractDescriptor,
+    FailedAcceptContract, EscrowReclaimedContract, FailedLoanContract, FailedSignContract,
+    LoanTerms, PreClosedContract, RepaidContract,
 };
-use crate::contract_updater::{accept_contract, accept_loan_contract, send_escrow_transaction, verify_accepted_and_sign_contract, verify_accepted_and_sign_loan_contract};
+use crate::contract_updater::{accept_contract, accept_loan_contract, get_escrow_output, get_stable_loan_amount, create_escrow_transaction, renew_loan_contract, sign_loan_renewal, verify_accepted_and_sign_contract, verify_accepted_and_sign_loan_contract, verify_collateral_transaction, verify_escrow_transaction, verify_renew_loan_accept, verify_renew_loan_confirm_and_sign, verify_loan_principal, verify_renew_loan_offer_and_sign, verify_stable_loan};
 use crate::error::Error;
+use crate::loan_marketplace::{
+    sign_loan_listing, validate_listing_request, validate_loan_listing, LoanListingFilter,
+    LoanListingInput, LoanOrderBook,
+};
+use crate::loan_math::{get_stable_loan_amount_due, percent_to_bps, AmountDue, LoanAccrual};
+use crate::loan_monitor::{
+    get_attested_value, get_collateral_ratio, LoanMonitor, LoanMonitorConfig, LoanPriceSource,
+    LoanRisk, LoanRiskEvent,
+};
 use crate::utils::get_object_in_state;
 use crate::{ChannelId, ContractId, ContractSignerProvider};
-use alloy::primitives::{self, Uint};
-use alloy::providers::ProviderBuilder;
-use alloy::signers::local::PrivateKeySigner;
-use alloy_sol_types::{sol};
 use bitcoin::absolute::Height;
 use bitcoin::consensus::encode::serialize_hex;
 use bitcoin::consensus::{Decodable, Encodable};
 use bitcoin::hashes::Hash;
 use bitcoin::{Address, Amount, EcdsaSighashType, SignedAmount};
-use bitcoin::{OutPoint, Transaction};
+use bitcoin::{relative, OutPoint, ScriptBuf, Sequence, Transaction, Txid};
 use dlc::util::get_sig_for_tx_input;
 use dlc_messages::channel::{
     AcceptChannel, CollaborativeCloseOffer, OfferChannel, Reject, RenewAccept, RenewConfirm,
     RenewFinalize, RenewOffer, RenewRevoke, SettleAccept, SettleConfirm, SettleFinalize,
     SettleOffer, SignChannel,
 };
-use dlc_messages::oracle_msgs::{OracleAnnouncement, OracleAttestation};
-use dlc_messages::{AcceptDlc, AcceptLoanDlc, InitiateAcceptLoanDlc, Message as DlcMessage, OfferDlc, OfferLoanDlc, SignDlc};
+use dlc_messages::oracle_msgs::{EventDescriptor, OracleAnnouncement, OracleAttestation};
+use dlc_messages::{AcceptDlc, AcceptLoanDlc, CancelLoanDlc, CounterOfferLoanDlc, InitiateAcceptLoanDlc, LoanListingDlc, LoanOfferReason, Message as DlcMessage, OfferDlc, OfferLoanDlc, RejectLoanCounterOfferDlc, RejectLoanDlc, RejectLoanRequestDlc, RenewLoanAcceptDlc, RenewLoanConfirmDlc, RenewLoanOfferDlc, RepayLoanAckDlc, RepayLoanDlc, RequestLoanDlc, SignDlc};
+use futures::future::join_all;
 use hex::DisplayHex;
 use lightning::chain::chaininterface::FeeEstimator;
 use lightning::ln::chan_utils::{
@@ -52,20 +66,10 @@ use secp256k1_zkp::{
 use std::collections::HashMap;
 use std::ops::Deref;
 use std::string::ToString;
+use std::sync::mpsc::Receiver;
 use std::sync::{Arc, Mutex};
 use rand::Rng;
 
-extern crate dotenv;
-use dotenv::dotenv;
-use std::env;
-
-sol!(
-    #[allow(missing_docs)]
-    #[sol(rpc)]
-    P2PBTCLending,
-    "../../../out/P2PBTCLending.sol/P2PBTCLending.json",
-);
-
 /// The number of confirmations required before moving the the confirmed state.
 pub const NB_CONFIRMATIONS: u32 = 6;
 /// The delay to set the refund value to.
@@ -75,6 +79,44 @@ pub const CET_NSEQUENCE: u32 = 288;
 /// Timeout in seconds when waiting for a peer's reply, after which a DLC channel
 /// is forced closed.
 pub const PEER_TIMEOUT: u64 = 3600;
+/// The maximum number of loan requests received from peers that can await an
+/// answer, so that peers cannot fill the storage of the node.
+pub const MAX_PENDING_LOAN_REQUESTS: usize = 1000;
+/// The maximum number of loan requests received from a single peer that can
+/// await an answer.
+pub const MAX_PENDING_LOAN_REQUESTS_PER_PEER: usize = 10;
+
+/// The maximum time in seconds a loan can stay in each stage of its setup
+/// before it is aborted by [`Manager::periodic_check`]. The stage preceding the
+/// publication of the escrow is bounded by the expiry of the offer. Loans are
+/// only aborted until the stables are claimed, unclaimed stables being first
+/// reclaimed by [`Manager::periodic_loan_check`]. Accepted loans that are not
+/// signed in time are marked as [`LoanState::Stalled`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LoanTimeouts {
+    /// Time for the escrow of an accepted offer to get the required number of
+    /// confirmations, counted from the offer.
+    pub escrow_confirmation: u64,
+    /// Time for the lender to lend the stables once the escrow is confirmed.
+    pub stable_lending: u64,
+    /// Time for the borrower to claim the stables once they are lent, after
+    /// which the lender reclaims them. It should not be shorter than the claim
+    /// period of the lending contract.
+    pub stable_claim: u64,
+    /// Time for the contract to be signed once the stables are claimed.
+    pub contract_signing: u64,
+}
+
+impl Default for LoanTimeouts {
+    fn default() -> Self {
+        LoanTimeouts {
+            escrow_confirmation: 86400,
+            stable_lending: PEER_TIMEOUT,
+            stable_claim: 86400,
+            contract_signing: PEER_TIMEOUT,
+        }
+    }
+}
 
 type ClosableContractInfo<'a> = Option<(
     &'a ContractInfo,
@@ -91,6 +133,7 @@ pub struct Manager<
     O: Deref,
     T: Deref,
     F: Deref,
+    L: Deref,
     X: ContractSigner,
 > where
     W::Target: Wallet,
@@ -100,6 +143,7 @@ pub struct Manager<
     O::Target: Oracle,
     T::Target: Time,
     F::Target: FeeEstimator,
+    L::Target: LendingBackend,
 {
     oracles: HashMap<XOnlyPublicKey, O>,
     wallet: W,
@@ -110,6 +154,10 @@ pub struct Manager<
     chain_monitor: Mutex<ChainMonitor>,
     time: T,
     fee_estimator: F,
+    lending_backend: L,
+    loan_timeouts: LoanTimeouts,
+    loan_monitor: LoanMonitor,
+    loan_order_book: LoanOrderBook,
 }
 
 macro_rules! get_contract_in_state {
@@ -167,8 +215,17 @@ macro_rules! check_for_timed_out_channels {
     };
 }
 
-impl<W: Deref, SP: Deref, B: Deref, S: Deref, O: Deref, T: Deref, F: Deref, X: ContractSigner>
-    Manager<W, Arc<CachedContractSignerProvider<SP, X>>, B, S, O, T, F, X>
+impl<
+        W: Deref,
+        SP: Deref,
+        B: Deref,
+        S: Deref,
+        O: Deref,
+        T: Deref,
+        F: Deref,
+        L: Deref,
+        X: ContractSigner,
+    > Manager<W, Arc<CachedContractSignerProvider<SP, X>>, B, S, O, T, F, L, X>
 where
     W::Target: Wallet,
     SP::Target: ContractSignerProvider<Signer = X>,
@@ -177,6 +234,7 @@ where
     O::Target: Oracle,
     T::Target: Time,
     F::Target: FeeEstimator,
+    L::Target: LendingBackend,
 {
     /// Create a new Manager struct.
     pub fn new(
@@ -187,6 +245,7 @@ where
         oracles: HashMap<XOnlyPublicKey, O>,
         time: T,
         fee_estimator: F,
+        lending_backend: L,
     ) -> Result<Self, Error> {
         let init_height = blockchain.get_blockchain_height()?;
         let chain_monitor = Mutex::new(
@@ -206,21 +265,113 @@ where
             oracles,
             time,
             fee_estimator,
+            lending_backend,
             chain_monitor,
+            loan_timeouts: LoanTimeouts::default(),
+            loan_monitor: LoanMonitor::new(LoanMonitorConfig::default()),
+            loan_order_book: LoanOrderBook::new(),
         })
     }
 
+    /// Sets the timeouts after which loans stuck in their setup are aborted.
+    pub fn set_loan_timeouts(&mut self, loan_timeouts: LoanTimeouts) {
+        self.loan_timeouts = loan_timeouts;
+    }
+
+    /// Sets the price source and the thresholds used to monitor the
+    /// liquidation risk of signed loans.
+    pub fn set_loan_monitor_config(&mut self, config: LoanMonitorConfig) -> Result<(), Error> {
+        config.validate()?;
+        self.loan_monitor.set_config(config);
+        Ok(())
+    }
+
+    /// Returns a receiver of the events emitted when the risk level of a
+    /// signed loan changes.
+    pub fn subscribe_loan_risk_events(&self) -> Receiver<LoanRiskEvent> {
+        self.loan_monitor.subscribe()
+    }
+
+    /// Returns the latest collateral ratio computed for each signed loan.
+    pub fn get_loan_risks(&self) -> Vec<LoanRisk> {
+        self.loan_monitor.get_risks()
+    }
+
     /// Get the store from the Manager to access contracts.
     pub fn get_store(&self) -> &S {
         &self.store
     }
 
+    /// Returns the loan terms of the given contract, or None if it is not a
+    /// loan contract.
+    pub fn get_loan_terms(&self, contract: &Contract) -> Result<Option<LoanTerms>, Error> {
+        if let Some(loan_terms) = self.store.get_loan_terms(&contract.get_temporary_id())? {
+            return Ok(Some(loan_terms));
+        }
+        let loan_terms = match contract {
+            Contract::OfferedLoan(c) | Contract::OfferedLoanEscrowConfirmed(c) => {
+                Some(c.get_loan_terms())
+            }
+            Contract::EscrowReclaimed(c) => Some(c.offered_loan_contract.get_loan_terms()),
+            Contract::FailedLoan(c) => Some(c.offered_loan_contract.get_loan_terms()),
+            Contract::RejectedLoan(c) | Contract::CancelledLoan(c) => Some(c.get_loan_terms()),
+            _ => None,
+        };
+        Ok(loan_terms)
+    }
+
+    /// Returns the lifecycle of the given contract, or None if it is not a
+    /// loan contract.
+    pub fn get_loan_lifecycle(&self, contract: &Contract) -> Result<Option<LoanLifecycle>, Error> {
+        self.store.get_loan_lifecycle(&contract.get_temporary_id())
+    }
+
+    /// Returns the amounts owed at the given time on the stable loan of the
+    /// given loan contract, exactly as the lending contract computes them from
+    /// the loan it records.
+    pub async fn get_loan_amount_due(
+        &self,
+        contract_id: &ContractId,
+        timestamp: u64,
+    ) -> Result<AmountDue, Error> {
+        let contract = self
+            .store
+            .get_contract(contract_id)?
+            .ok_or_else(|| Error::InvalidParameters("Unknown contract id".to_string()))?;
+        let loan_terms = self
+            .get_loan_terms(&contract)?
+            .ok_or_else(|| Error::InvalidParameters("Not a loan contract".to_string()))?;
+        let borrower_hash = loan_terms
+            .borrower_hash
+            .ok_or_else(|| Error::InvalidState("Borrower hash not found".to_string()))?;
+        let stable_loan = self.lending_backend.get_stable_loan(&borrower_hash).await?;
+
+        get_stable_loan_amount_due(&stable_loan, timestamp)
+    }
+
+    /// Moves the loan with the given temporary contract id to the given state.
+    /// Contracts that are not loans are left untouched.
+    fn transition_loan_state(
+        &self,
+        temporary_contract_id: &ContractId,
+        state: LoanState,
+    ) -> Result<(), Error> {
+        let mut lifecycle = match self.store.get_loan_lifecycle(temporary_contract_id)? {
+            Some(lifecycle) => lifecycle,
+            None => return Ok(()),
+        };
+        if lifecycle.transition(state, self.time.unix_time_now())? {
+            self.store
+                .update_loan_lifecycle(temporary_contract_id, &lifecycle)?;
+        }
+        Ok(())
+    }
+
     /// Function called to pass a DlcMessage to the Manager.
     pub async fn on_dlc_message(
         &self,
         msg: &DlcMessage,
         counter_party: PublicKey,
-        evm_pk: &str,
     ) -> Result<Option<DlcMessage>, Error> {
         match msg {
             DlcMessage::Offer(o) => {
@@ -228,19 +379,64 @@ where
                 Ok(None)
             }
             DlcMessage::OfferLoan(o) => {
-                self.on_offer_loan_message(o, counter_party)?;
+                self.on_offer_loan_message(o, counter_party).await?;
                 Ok(None)
             }
             DlcMessage::Accept(a) => Ok(Some(self.on_accept_message(a, &counter_party)?)),
             DlcMessage::AcceptLoan(a) => Ok(Some(self.on_accept_loan_message(a, &counter_party).await?)),
             DlcMessage::InitiateAcceptLoan(a) => {
-                self.on_initiate_accept_loan_message(a, &counter_party, evm_pk).await?;
+                self.on_initiate_accept_loan_message(a, &counter_party)?;
                 Ok(None)
             },
             DlcMessage::Sign(s) => {
                 self.on_sign_message(s, &counter_party)?;
                 Ok(None)
             }
+            DlcMessage::RepayLoan(r) => Ok(Some(DlcMessage::RepayLoanAck(
+                self.on_repay_loan(r, &counter_party).await?,
+            ))),
+            DlcMessage::RepayLoanAck(r) => {
+                self.on_repay_loan_ack(r, &counter_party)?;
+                Ok(None)
+            }
+            DlcMessage::RenewLoanOffer(r) => Ok(Some(DlcMessage::RenewLoanAccept(
+                self.on_renew_loan_offer(r, &counter_party).await?,
+            ))),
+            DlcMessage::RenewLoanAccept(r) => Ok(Some(DlcMessage::RenewLoanConfirm(
+                self.on_renew_loan_accept(r, &counter_party).await?,
+            ))),
+            DlcMessage::RenewLoanConfirm(r) => {
+                self.on_renew_loan_confirm(r, &counter_party).await?;
+                Ok(None)
+            }
+            DlcMessage::RejectLoan(r) => {
+                self.on_reject_loan_message(r, &counter_party).await?;
+                Ok(None)
+            }
+            DlcMessage::CancelLoan(c) => {
+                self.on_cancel_loan_message(c, &counter_party).await?;
+                Ok(None)
+            }
+            DlcMessage::RequestLoan(r) => {
+                self.on_request_loan_message(r, counter_party)?;
+                Ok(None)
+            }
+            DlcMessage::RejectLoanRequest(r) => {
+                self.on_reject_loan_request_message(r, &counter_party)?;
+                Ok(None)
+            }
+            DlcMessage::CounterOfferLoan(c) => {
+                self.on_counter_offer_loan_message(c, &counter_party).await?;
+                Ok(None)
+            }
+            DlcMessage::RejectLoanCounterOffer(r) => {
+                self.on_reject_loan_counter_offer_message(r, &counter_party)?;
+                Ok(None)
+            }
+            DlcMessage::LoanListing(l) => {
+                self.on_loan_listing_message(l, counter_party)?;
+                Ok(None)
+            }
             DlcMessage::OfferChannel(o) => {
                 self.on_offer_channel(o, counter_party)?;
                 Ok(None)
@@ -319,11 +515,7 @@ where
         loan_contract_input: &LoanContractInput,
         counter_party: PublicKey,
     ) -> Result<OfferLoanDlc, Error> {
-        let oracle_announcements = loan_contract_input
-            .contract_infos
-            .iter()
-            .map(|x| self.get_oracle_announcements(&x.oracles))
-            .collect::<Result<Vec<_>, Error>>()?;
+        let oracle_announcements = self.get_loan_oracle_announcements(loan_contract_input)?;
         self.send_offer_loan_with_announcements(
             loan_contract_input,
             counter_party,
@@ -367,11 +559,237 @@ where
         loan_contract_input: &LoanContractInput,
         counter_party: PublicKey,
         oracle_announcements: Vec<Vec<OracleAnnouncement>>,
+    ) -> Result<OfferLoanDlc, Error> {
+        self.offer_loan_with_announcements(
+            loan_contract_input,
+            counter_party,
+            oracle_announcements,
+            None,
+            None,
+        )
+    }
+
+    /// Sends a request for a loan with the given terms to the given lender.
+    /// The request is stored and a RequestLoanDlc message returned.
+    pub fn send_loan_request(
+        &self,
+        counter_party: PublicKey,
+        collateral: Amount,
+        principal: u64,
+        max_interest_rate: u64,
+        duration: u64,
+    ) -> Result<RequestLoanDlc, Error> {
+        let loan_request = LoanRequest::new(
+            crate::utils::get_new_temporary_id(),
+            counter_party,
+            collateral,
+            principal,
+            max_interest_rate,
+            duration,
+        );
+        loan_request.validate()?;
+
+        self.store.upsert_loan_request(&loan_request)?;
+
+        Ok((&loan_request).into())
+    }
+
+    /// Answers the loan request with the given id with a loan offer created
+    /// from the given input, which must match the requested terms. The offered
+    /// contract is stored and the OfferLoanDlc message returned along with the
+    /// public key of the borrower.
+    pub fn send_offer_loan_for_request(
+        &self,
+        request_id: &[u8; 32],
+        loan_contract_input: &LoanContractInput,
+    ) -> Result<(PublicKey, OfferLoanDlc), Error> {
+        let mut loan_request = self.get_pending_loan_request(request_id, false, None)?;
+        if loan_contract_input.principal != loan_request.principal {
+            return Err(Error::InvalidParameters(
+                "Loan principal does not match the request".to_string(),
+            ));
+        }
+
+        let oracle_announcements = self.get_loan_oracle_announcements(loan_contract_input)?;
+        let offer_msg = self.offer_loan_with_announcements(
+            loan_contract_input,
+            loan_request.counter_party,
+            oracle_announcements,
+            Some(&loan_request),
+            None,
+        )?;
+
+        loan_request.state = LoanRequestState::Offered;
+        loan_request.contract_id = Some(offer_msg.offer_dlc.temporary_contract_id);
+        self.store.upsert_loan_request(&loan_request)?;
+
+        Ok((loan_request.counter_party, offer_msg))
+    }
+
+    /// Declines the loan request with the given id and returns the
+    /// [`RejectLoanRequestDlc`] message to send to the borrower.
+    pub fn reject_loan_request(
+        &self,
+        request_id: &[u8; 32],
+        reason: LoanOfferReason,
+    ) -> Result<(PublicKey, RejectLoanRequestDlc), Error> {
+        let mut loan_request = self.get_pending_loan_request(request_id, false, None)?;
+
+        loan_request.state = LoanRequestState::Rejected;
+        self.store.upsert_loan_request(&loan_request)?;
+
+        let reject_msg = RejectLoanRequestDlc {
+            protocol_version: crate::conversion_utils::PROTOCOL_VERSION,
+            request_id: *request_id,
+            reason,
+        };
+
+        Ok((loan_request.counter_party, reject_msg))
+    }
+
+    /// Publishes a listing of the loans the local node is willing to make
+    /// with the given terms, signed with the given secret key of the node.
+    /// The listing is added to the order book and queued to be relayed to the
+    /// peers, see [`Manager::get_and_clear_loan_listings_to_relay`].
+    pub fn publish_loan_listing(
+        &self,
+        listing_input: &LoanListingInput,
+        node_secret_key: &SecretKey,
+    ) -> Result<LoanListingDlc, Error> {
+        let now = self.time.unix_time_now();
+        let listing = sign_loan_listing(
+            &self.secp,
+            listing_input,
+            crate::utils::get_new_temporary_id(),
+            node_secret_key,
+            now,
+        )?;
+        validate_loan_listing(&self.secp, &listing, now)?;
+
+        self.loan_order_book
+            .add_listing(listing.clone(), None, now)?;
+
+        Ok(listing)
+    }
+
+    /// Returns the listings of the order book matching the given filter, from
+    /// the lowest interest rate to the highest.
+    pub fn get_loan_listings(&self, filter: &LoanListingFilter) -> Vec<LoanListingDlc> {
+        self.loan_order_book
+            .get_listings(filter, self.time.unix_time_now())
+    }
+
+    /// Returns the listing of the order book of the given lender with the
+    /// given id, if it did not expire.
+    pub fn get_loan_listing(
+        &self,
+        lender: &PublicKey,
+        listing_id: &[u8; 32],
+    ) -> Option<LoanListingDlc> {
+        self.loan_order_book
+            .get_listing(lender, listing_id, self.time.unix_time_now())
+            .map(|(listing, _)| listing)
+    }
+
+    /// Returns the listings published or received since the last call, along
+    /// with the peer they were received from, or None for the local ones.
+    /// They are to be sent to all the other connected peers.
+    pub fn get_and_clear_loan_listings_to_relay(
+        &self,
+    ) -> Vec<(Option<PublicKey>, LoanListingDlc)> {
+        self.loan_order_book.get_and_clear_listings_to_relay()
+    }
+
+    /// Sends a request for a loan of the given principal against the given
+    /// collateral to the given lender for its listing with the given id, at
+    /// the listed interest rate and duration. The request is stored and a
+    /// RequestLoanDlc message returned along with the public key of the
+    /// lender.
+    pub fn send_loan_request_for_listing(
+        &self,
+        lender: &PublicKey,
+        listing_id: &[u8; 32],
+        collateral: Amount,
+        principal: u64,
+    ) -> Result<(PublicKey, RequestLoanDlc), Error> {
+        let (listing, is_local) = self
+            .loan_order_book
+            .get_listing(lender, listing_id, self.time.unix_time_now())
+            .ok_or_else(|| {
+                Error::InvalidParameters("Unknown or expired loan listing".to_string())
+            })?;
+        if is_local {
+            return Err(Error::InvalidParameters(
+                "Cannot request a loan from a local listing".to_string(),
+            ));
+        }
+        let loan_request = LoanRequest {
+            listing_id: Some(*listing_id),
+            ..LoanRequest::new(
+                crate::utils::get_new_temporary_id(),
+                listing.terms.lender,
+                collateral,
+                principal,
+                listing.terms.interest_rate,
+                listing.terms.duration,
+            )
+        };
+        loan_request.validate()?;
+        validate_listing_request(&listing, &loan_request)?;
+
+        self.store.upsert_loan_request(&loan_request)?;
+
+        Ok((listing.terms.lender, (&loan_request).into()))
+    }
+
+    /// Returns the loan request with the given id if it awaits an answer and
+    /// the local party has the given role in it.
+    fn get_pending_loan_request(
+        &self,
+        request_id: &[u8; 32],
+        is_borrower: bool,
+        counter_party: Option<PublicKey>,
+    ) -> Result<LoanRequest, Error> {
+        let loan_request = self
+            .store
+            .get_loan_request(request_id)?
+            .ok_or_else(|| Error::InvalidParameters("Unknown loan request id".to_string()))?;
+        if loan_request.is_borrower != is_borrower {
+            return Err(Error::InvalidState(if is_borrower {
+                "Loan request was not sent by the local party".to_string()
+            } else {
+                "Loan request was not received by the local party".to_string()
+            }));
+        }
+        if let Some(p) = counter_party {
+            if loan_request.counter_party != p {
+                return Err(Error::InvalidParameters(
+                    "Loan request involves another counter party".to_string(),
+                ));
+            }
+        }
+        if loan_request.state != LoanRequestState::Pending {
+            return Err(Error::InvalidState(format!(
+                "Loan request is already {}",
+                loan_request.state
+            )));
+        }
+        Ok(loan_request)
+    }
+
+    /// Creates and stores a loan offer, bound to the given loan request or
+    /// recorded in the given negotiation if any.
+    fn offer_loan_with_announcements(
+        &self,
+        loan_contract_input: &LoanContractInput,
+        counter_party: PublicKey,
+        oracle_announcements: Vec<Vec<OracleAnnouncement>>,
+        loan_request: Option<&LoanRequest>,
+        negotiation: Option<&mut LoanNegotiation>,
     ) -> Result<OfferLoanDlc, Error> {
         // Generate lender's preimage
         let mut rng = rand::thread_rng();
         let lender_preimage: u128 = rng.gen();
-        println!("Lender preimage: {}", lender_preimage);
         let (offered_contract, offer_msg) = crate::contract_updater::offer_loan_contract(
             &self.secp,
             loan_contract_input,
@@ -379,6 +797,8 @@ where
             REFUND_DELAY,
             &counter_party,
             lender_preimage,
+            loan_request.map(|r| r.id),
+            negotiation.as_ref().map(|n| n.id),
             &self.wallet,
             &self.blockchain,
             &self.time,
@@ -386,14 +806,22 @@ where
         )?;
 
         // Store preimage in the store
-        self.store.set_loan_preimage(
-            &offered_contract.offered_contract.id,
-            lender_preimage,
-        );
+        self.store
+            .set_loan_preimage(&offered_contract.offered_contract.id, lender_preimage)?;
 
         offered_contract.validate()?;
+        if let Some(loan_request) = loan_request {
+            loan_request.validate_offer(&offered_contract)?;
+        }
+        if let Some(negotiation) = negotiation {
+            negotiation.add_offer(&offered_contract)?;
+        }
 
         self.store.create_loan_contract(&offered_contract)?;
+        self.store.update_loan_lifecycle(
+            &offered_contract.offered_contract.id,
+            &LoanLifecycle::new(true, self.time.unix_time_now()),
+        )?;
 
         Ok(offer_msg)
     }
@@ -436,41 +864,46 @@ where
     ) -> Result<(PublicKey, InitiateAcceptLoanDlc), Error> {
         let offered_loan_contract =
             get_contract_in_state!(self, contract_id, OfferedLoan, None as Option<PublicKey>)?;
+        self.check_loan_offer_expiry(&offered_loan_contract)?;
 
         let mut rng = rand::thread_rng();
         let borrower_preimage: u128 = rng.gen();
 
-        let escrow_txid = send_escrow_transaction(
+        let (escrow_tx, escrow_outpoint) = create_escrow_transaction(
             &self.secp,
             &offered_loan_contract,
             borrower_preimage,
             &self.wallet,
             &self.signer_provider,
             &self.blockchain,
-        ).unwrap();
+        )?;
+
+        // The escrow can only be found and reclaimed with its outpoint and the
+        // preimage, so both must be stored before the escrow is broadcast.
+        self.store.set_escrow_outpoint(contract_id, escrow_outpoint)?;
+        self.store.set_loan_preimage(contract_id, borrower_preimage)?;
+        self.blockchain.send_transaction(&escrow_tx)?;
 
         // self.wallet.import_address(&Address::p2wsh(
         //     &accepted_contract.dlc_transactions.funding_script_pubkey,
         //     self.blockchain.get_network()?,
         // ))?;
 
-        self.store.set_escrow_txid(
-            &contract_id,
-            escrow_txid,
-        );
-
         let borrower_preimage_bytes = borrower_preimage.to_be_bytes();
         let borrower_hash = bitcoin::hashes::sha256::Hash::hash(&borrower_preimage_bytes).to_byte_array();
+        let borrower_pubkey = self
+            .signer_provider
+            .derive_contract_signer(offered_loan_contract.offered_contract.keys_id)?
+            .get_public_key(&self.secp)?;
 
         let initiate_accept_msg = InitiateAcceptLoanDlc { 
             protocol_version: crate::conversion_utils::PROTOCOL_VERSION, 
             contract_id: contract_id.clone(),
             borrower_hash: borrower_hash,
-            escrow_txid: escrow_txid,
+            borrower_pubkey,
+            escrow_txid: escrow_outpoint.txid,
         };
 
-        // let contract_id = accepted_contract.get_contract_id();
-        self.store.set_loan_preimage(&contract_id, borrower_preimage);
         Ok((offered_loan_contract.offered_contract.counter_party, initiate_accept_msg))
     }
 
@@ -482,15 +915,21 @@ where
         let offered_loan_contract =
             get_contract_in_state!(self, contract_id, OfferedLoanEscrowConfirmed, None as Option<PublicKey>)?;
 
-        let escrow_txid = self.store.get_escrow_txid(contract_id).unwrap().expect("");
-        let borrower_preimage = self.store.get_loan_preimage(contract_id).unwrap().expect("");
+        let escrow_outpoint = self
+            .store
+            .get_escrow_outpoint(contract_id)?
+            .ok_or_else(|| Error::InvalidState("Escrow outpoint not found".to_string()))?;
+        let borrower_preimage = self
+            .store
+            .get_loan_preimage(contract_id)?
+            .ok_or_else(|| Error::InvalidState("Borrower preimage not found".to_string()))?;
         let borrower_preimage_bytes = borrower_preimage.to_be_bytes();
         let borrower_hash = bitcoin::hashes::sha256::Hash::hash(&borrower_preimage_bytes).to_byte_array();
 
         let (accepted_loan_contract, accept_msg) = accept_loan_contract(
             &self.secp,
             &offered_loan_contract,
-            escrow_txid,
+            escrow_outpoint,
             borrower_hash,
             &borrower_preimage_bytes,
             &self.wallet,
@@ -503,6 +942,15 @@ where
             self.blockchain.get_network()?,
         ))?;
 
+        self.store.set_loan_terms(
+            contract_id,
+            &LoanTerms {
+                escrow_txid: Some(escrow_outpoint.txid),
+                borrower_hash: Some(borrower_hash),
+                ..offered_loan_contract.get_loan_terms()
+            },
+        )?;
+
         let contract_id = accepted_loan_contract.get_contract_id();
 
         self.store
@@ -510,105 +958,630 @@ where
         Ok((contract_id, offered_loan_contract.offered_contract.counter_party, accept_msg))
     }
 
-    /// Function to update the state of the [`ChainMonitor`] with new
-    /// blocks.
-    ///
-    /// Consumers **MUST** call this periodically in order to
-    /// determine when pending transactions reach confirmation.
-    pub fn periodic_chain_monitor(&self) -> Result<(), Error> {
-        let cur_height = self.blockchain.get_blockchain_height()?;
-        let last_height = self.chain_monitor.lock().unwrap().last_height;
-
-        // TODO(luckysori): We could end up reprocessing a block at
-        // the same height if there is a reorg.
-        if cur_height < last_height {
+    /// Repays the stable loan of a confirmed loan contract on the lending
+    /// contract and returns the [`RepayLoanDlc`] message requesting the lender
+    /// preimage that unlocks the collateral.
+    pub async fn repay_loan(
+        &self,
+        contract_id: &ContractId,
+    ) -> Result<(PublicKey, RepayLoanDlc), Error> {
+        let contract =
+            get_contract_in_state!(self, contract_id, Confirmed, None as Option<PublicKey>)?;
+        let offered_contract = &contract.accepted_contract.offered_contract;
+        if offered_contract.is_offer_party {
             return Err(Error::InvalidState(
-                "Current height is lower than last height.".to_string(),
+                "Only the borrower can repay a loan".to_string(),
             ));
         }
 
-        for height in last_height + 1..=cur_height {
-            let block = self.blockchain.get_block_at_height(height)?;
-
-            self.chain_monitor
-                .lock()
-                .unwrap()
-                .process_block(&block, height);
+        let borrower_preimage = self
+            .store
+            .get_loan_preimage(&offered_contract.id)?
+            .ok_or_else(|| Error::InvalidState("Borrower preimage not found".to_string()))?;
+        let borrower_hash =
+            bitcoin::hashes::sha256::Hash::hash(&borrower_preimage.to_be_bytes()).to_byte_array();
+
+        let stable_loan = self.lending_backend.get_stable_loan(&borrower_hash).await?;
+        if !stable_loan.repaid {
+            self.lending_backend.repay_stable(&borrower_hash).await?;
         }
 
-        Ok(())
-    }
-
-    /// Function to call to check the state of the currently executing DLCs and
-    /// update them if possible.
-    pub fn periodic_check(&self, check_channels: bool, evm_pk: &str) -> Result<(), Error> {
-        self.check_offered_loan_contracts_for_escrow(evm_pk)?;
-        println!("periodic check");
-        self.check_signed_contracts()?;
-        self.check_confirmed_contracts()?;
-        self.check_preclosed_contracts()?;
-
-        if check_channels {
-            self.channel_checks()?;
-        }
+        let repay_msg = RepayLoanDlc {
+            protocol_version: crate::conversion_utils::PROTOCOL_VERSION,
+            contract_id: *contract_id,
+            borrower_hash,
+        };
 
-        Ok(())
+        Ok((offered_contract.counter_party, repay_msg))
     }
 
-    fn on_offer_message(
+    /// Proposes to renew the collateral of the active loan with the given
+    /// contract id, adding the given collateral and repaying the given part of
+    /// the principal, and returns the [`RenewLoanOfferDlc`] message to send to
+    /// the lender. The matching stable amount is repaid on the lending contract
+    /// once the lender signed the renewed contract.
+    pub async fn renew_loan(
         &self,
-        offered_message: &OfferDlc,
-        counter_party: PublicKey,
-    ) -> Result<(), Error> {
-        println!("on_offer_message");
-        offered_message.validate(&self.secp, REFUND_DELAY, REFUND_DELAY * 2)?;
-        let keys_id = self
-            .signer_provider
-            .derive_signer_key_id(false, offered_message.temporary_contract_id);
-        let contract: OfferedContract =
-            OfferedContract::try_from_offer_dlc(offered_message, counter_party, keys_id)?;
-        contract.validate()?;
-
-        if self.store.get_contract(&contract.id)?.is_some() {
-            return Err(Error::InvalidParameters(
-                "Contract with identical id already exists".to_string(),
+        contract_id: &ContractId,
+        additional_collateral: Amount,
+        repaid_principal: u64,
+    ) -> Result<(PublicKey, RenewLoanOfferDlc), Error> {
+        let contract =
+            get_contract_in_state!(self, contract_id, Confirmed, None as Option<PublicKey>)?;
+        let offered_contract = &contract.accepted_contract.offered_contract;
+        if offered_contract.is_offer_party {
+            return Err(Error::InvalidState(
+                "Only the borrower can renew a loan".to_string(),
             ));
         }
+        self.check_loan_renewal_replaceable(contract_id)?;
 
-        self.store.create_contract(&contract)?;
+        let loan_terms = self
+            .store
+            .get_loan_terms(&offered_contract.id)?
+            .ok_or_else(|| Error::InvalidState("Loan terms not found".to_string()))?;
+        validate_loan_renewal(&loan_terms, additional_collateral, repaid_principal)?;
+        let stable_loan_amount = self
+            .get_renewed_stable_loan_amount(offered_contract, &loan_terms, repaid_principal)
+            .await?;
+
+        let (accepted_contract, offer_msg) = renew_loan_contract(
+            &self.secp,
+            &contract,
+            &loan_terms,
+            additional_collateral,
+            repaid_principal,
+            &self.wallet,
+            &self.signer_provider,
+            &self.blockchain,
+        )?;
 
-        Ok(())
+        self.store.upsert_loan_renewal(&LoanRenewal {
+            contract_id: *contract_id,
+            counter_party: offered_contract.counter_party,
+            is_borrower: true,
+            additional_collateral,
+            repaid_principal,
+            stable_loan_amount,
+            state: LoanRenewalState::Offered,
+            accepted_contract,
+            signed_contract: None,
+        })?;
+
+        Ok((offered_contract.counter_party, offer_msg))
     }
 
-    fn on_offer_loan_message(
+    /// Declines the loan offer with the given temporary contract id and
+    /// returns the [`RejectLoanDlc`] message to send to the lender. Offers can
+    /// only be rejected before the escrow is published.
+    pub fn reject_loan_offer(
         &self,
-        offered_message: &OfferLoanDlc,
-        counter_party: PublicKey,
-    ) -> Result<(), Error> {
-        println!("on_offer_loan_message");
-        offered_message.validate(&self.secp, REFUND_DELAY, REFUND_DELAY * 2)?;
-        let keys_id = self
-            .signer_provider
-            .derive_signer_key_id(false, offered_message.offer_dlc.temporary_contract_id);
-        let contract: OfferedLoanContract =
-            OfferedLoanContract::try_from_offer_loan_dlc(offered_message, counter_party, keys_id)?;
-        contract.validate()?;
-
-        if self.store.get_contract(&contract.offered_contract.id)?.is_some() {
-            return Err(Error::InvalidParameters(
-                "Contract with identical id already exists".to_string(),
+        contract_id: &ContractId,
+        reason: LoanOfferReason,
+    ) -> Result<(PublicKey, RejectLoanDlc), Error> {
+        let offered_loan_contract =
+            get_contract_in_state!(self, contract_id, OfferedLoan, None as Option<PublicKey>)?;
+        let offered_contract = &offered_loan_contract.offered_contract;
+        if offered_contract.is_offer_party {
+            return Err(Error::InvalidState(
+                "Only the borrower can reject a loan offer".to_string(),
+            ));
+        }
+        if self.store.get_escrow_txid(contract_id)?.is_some() {
+            return Err(Error::InvalidState(
+                "Loan escrow was already published".to_string(),
             ));
         }
 
-        self.store.create_loan_contract(&contract)?;
+        self.abort_loan_offer(
+            &offered_loan_contract,
+            Contract::RejectedLoan(offered_loan_contract.clone()),
+        )?;
 
-        Ok(())
+        let reject_msg = RejectLoanDlc {
+            protocol_version: crate::conversion_utils::PROTOCOL_VERSION,
+            temporary_contract_id: *contract_id,
+            reason,
+        };
+
+        Ok((offered_contract.counter_party, reject_msg))
     }
 
-    fn on_accept_message(
+    /// Withdraws the loan offer with the given temporary contract id and
+    /// returns the [`CancelLoanDlc`] message to send to the borrower. Offers
+    /// can only be cancelled before the stable loan is lent.
+    pub async fn cancel_loan_offer(
         &self,
-        accept_msg: &AcceptDlc,
-        counter_party: &PublicKey,
+        contract_id: &ContractId,
+        reason: LoanOfferReason,
+    ) -> Result<(PublicKey, CancelLoanDlc), Error> {
+        let offered_loan_contract =
+            get_contract_in_state!(self, contract_id, OfferedLoan, None as Option<PublicKey>)?;
+        let offered_contract = &offered_loan_contract.offered_contract;
+        if !offered_contract.is_offer_party {
+            return Err(Error::InvalidState(
+                "Only the lender can cancel a loan offer".to_string(),
+            ));
+        }
+        if self.is_stable_loan_lent(&offered_loan_contract).await? {
+            return Err(Error::InvalidState(
+                "Stable loan was already lent".to_string(),
+            ));
+        }
+
+        self.abort_loan_offer(
+            &offered_loan_contract,
+            Contract::CancelledLoan(offered_loan_contract.clone()),
+        )?;
+
+        let cancel_msg = CancelLoanDlc {
+            protocol_version: crate::conversion_utils::PROTOCOL_VERSION,
+            temporary_contract_id: *contract_id,
+            reason,
+        };
+
+        Ok((offered_contract.counter_party, cancel_msg))
+    }
+
+    /// Declines the loan offer with the given temporary contract id while
+    /// proposing other terms, and returns the [`CounterOfferLoanDlc`] message
+    /// to send to the lender. A negotiation can have at most
+    /// [`MAX_LOAN_NEGOTIATION_ROUNDS`] counter offers.
+    ///
+    /// [`MAX_LOAN_NEGOTIATION_ROUNDS`]: crate::contract::loan_negotiation::MAX_LOAN_NEGOTIATION_ROUNDS
+    pub fn counter_loan_offer(
+        &self,
+        contract_id: &ContractId,
+        collateral_ratio: u64,
+        interest_rate: u64,
+        duration: u64,
+    ) -> Result<(PublicKey, CounterOfferLoanDlc), Error> {
+        let offered_loan_contract =
+            get_contract_in_state!(self, contract_id, OfferedLoan, None as Option<PublicKey>)?;
+        let offered_contract = &offered_loan_contract.offered_contract;
+        if offered_contract.is_offer_party {
+            return Err(Error::InvalidState(
+                "Only the borrower can counter a loan offer".to_string(),
+            ));
+        }
+        if self.store.get_escrow_txid(contract_id)?.is_some() {
+            return Err(Error::InvalidState(
+                "Loan escrow was already published".to_string(),
+            ));
+        }
+        self.check_loan_offer_expiry(&offered_loan_contract)?;
+
+        let negotiation = self.get_revised_loan_negotiation(
+            &offered_loan_contract,
+            LoanTermsRevision {
+                countered_contract_id: *contract_id,
+                collateral_ratio,
+                interest_rate,
+                duration,
+            },
+        )?;
+        self.store.upsert_loan_negotiation(&negotiation)?;
+        self.abort_loan_offer(
+            &offered_loan_contract,
+            Contract::RejectedLoan(offered_loan_contract.clone()),
+        )?;
+
+        let counter_msg = CounterOfferLoanDlc {
+            protocol_version: crate::conversion_utils::PROTOCOL_VERSION,
+            temporary_contract_id: *contract_id,
+            negotiation_id: negotiation.id,
+            round: negotiation.get_round() as u32,
+            collateral_ratio,
+            interest_rate,
+            duration,
+        };
+
+        Ok((offered_contract.counter_party, counter_msg))
+    }
+
+    /// Answers the last counter offer of the loan negotiation with the given
+    /// id with a fresh loan offer created from the given input, which must
+    /// have the countered terms. The offered contract is stored and the
+    /// OfferLoanDlc message returned along with the public key of the
+    /// borrower.
+    pub fn send_offer_loan_for_counter_offer(
+        &self,
+        negotiation_id: &[u8; 32],
+        loan_contract_input: &LoanContractInput,
+    ) -> Result<(PublicKey, OfferLoanDlc), Error> {
+        let mut negotiation = self.get_countered_loan_negotiation(negotiation_id, true, None)?;
+        let counter_party = negotiation.counter_party;
+
+        let oracle_announcements = self.get_loan_oracle_announcements(loan_contract_input)?;
+        let offer_msg = self.offer_loan_with_announcements(
+            loan_contract_input,
+            counter_party,
+            oracle_announcements,
+            None,
+            Some(&mut negotiation),
+        )?;
+
+        self.store.upsert_loan_negotiation(&negotiation)?;
+
+        Ok((counter_party, offer_msg))
+    }
+
+    /// Declines the last counter offer of the loan negotiation with the given
+    /// id, ending the negotiation, and returns the
+    /// [`RejectLoanCounterOfferDlc`] message to send to the borrower.
+    pub fn reject_loan_counter_offer(
+        &self,
+        negotiation_id: &[u8; 32],
+        reason: LoanOfferReason,
+    ) -> Result<(PublicKey, RejectLoanCounterOfferDlc), Error> {
+        let mut negotiation = self.get_countered_loan_negotiation(negotiation_id, true, None)?;
+
+        negotiation.state = LoanNegotiationState::Rejected;
+        self.store.upsert_loan_negotiation(&negotiation)?;
+
+        let reject_msg = RejectLoanCounterOfferDlc {
+            protocol_version: crate::conversion_utils::PROTOCOL_VERSION,
+            negotiation_id: *negotiation_id,
+            reason,
+        };
+
+        Ok((negotiation.counter_party, reject_msg))
+    }
+
+    /// Returns the negotiation of the given loan offer with the given revision
+    /// of its terms recorded.
+    fn get_revised_loan_negotiation(
+        &self,
+        offered_loan_contract: &OfferedLoanContract,
+        revision: LoanTermsRevision,
+    ) -> Result<LoanNegotiation, Error> {
+        revision.validate(offered_loan_contract)?;
+        let mut negotiation = match self
+            .store
+            .get_loan_negotiation(&offered_loan_contract.get_negotiation_id())?
+        {
+            Some(negotiation) => negotiation,
+            None => LoanNegotiation::new(offered_loan_contract),
+        };
+        negotiation.add_revision(revision)?;
+        Ok(negotiation)
+    }
+
+    /// Returns the loan negotiation with the given id if it awaits an answer
+    /// to a counter offer and the local party has the given role in it.
+    fn get_countered_loan_negotiation(
+        &self,
+        negotiation_id: &[u8; 32],
+        is_lender: bool,
+        counter_party: Option<PublicKey>,
+    ) -> Result<LoanNegotiation, Error> {
+        let negotiation = self
+            .store
+            .get_loan_negotiation(negotiation_id)?
+            .ok_or_else(|| Error::InvalidParameters("Unknown loan negotiation id".to_string()))?;
+        if negotiation.is_lender != is_lender {
+            return Err(Error::InvalidState(if is_lender {
+                "Local party is not the lender of the loan negotiation".to_string()
+            } else {
+                "Local party is not the borrower of the loan negotiation".to_string()
+            }));
+        }
+        if let Some(p) = counter_party {
+            if negotiation.counter_party != p {
+                return Err(Error::InvalidParameters(
+                    "Loan negotiation involves another counter party".to_string(),
+                ));
+            }
+        }
+        if negotiation.state != LoanNegotiationState::Countered {
+            return Err(Error::InvalidState(format!(
+                "Loan negotiation is {}",
+                negotiation.state
+            )));
+        }
+        Ok(negotiation)
+    }
+
+    /// Function to update the state of the [`ChainMonitor`] with new
+    /// blocks.
+    ///
+    /// Consumers **MUST** call this periodically in order to
+    /// determine when pending transactions reach confirmation.
+    pub fn periodic_chain_monitor(&self) -> Result<(), Error> {
+        let cur_height = self.blockchain.get_blockchain_height()?;
+        let last_height = self.chain_monitor.lock().unwrap().last_height;
+
+        // TODO(luckysori): We could end up reprocessing a block at
+        // the same height if there is a reorg.
+        if cur_height < last_height {
+            return Err(Error::InvalidState(
+                "Current height is lower than last height.".to_string(),
+            ));
+        }
+
+        for height in last_height + 1..=cur_height {
+            let block = self.blockchain.get_block_at_height(height)?;
+
+            self.chain_monitor
+                .lock()
+                .unwrap()
+                .process_block(&block, height);
+        }
+
+        Ok(())
+    }
+
+    /// Function to call to check the state of the currently executing DLCs and
+    /// update them if possible.
+    ///
+    /// The stable side of loans is checked separately by
+    /// [`Manager::periodic_loan_check`].
+    pub fn periodic_check(&self, check_channels: bool) -> Result<(), Error> {
+        self.check_loan_timeouts()?;
+        self.check_loan_renewals()?;
+        self.check_signed_contracts()?;
+        self.check_confirmed_contracts()?;
+        self.check_preclosed_contracts()?;
+        self.check_prerepaid_contracts()?;
+        self.check_loan_risks()?;
+
+        if check_channels {
+            self.channel_checks()?;
+        }
+
+        Ok(())
+    }
+
+    /// Function to call to check the loan contracts waiting on the lending
+    /// backend and update them if possible. The checks of all the loans are
+    /// run concurrently on the caller's runtime.
+    pub async fn periodic_loan_check(&self) -> Result<(), Error> {
+        self.check_offered_loan_contracts_for_escrow().await?;
+        self.check_unclaimed_stable_loans().await?;
+        self.check_abandoned_loan_escrows().await?;
+        self.check_loan_risks_at_lending_price().await
+    }
+
+    fn on_offer_message(
+        &self,
+        offered_message: &OfferDlc,
+        counter_party: PublicKey,
+    ) -> Result<(), Error> {
+        println!("on_offer_message");
+        offered_message.validate(&self.secp, REFUND_DELAY, REFUND_DELAY * 2)?;
+        let keys_id = self
+            .signer_provider
+            .derive_signer_key_id(false, offered_message.temporary_contract_id);
+        let contract: OfferedContract =
+            OfferedContract::try_from_offer_dlc(offered_message, counter_party, keys_id)?;
+        contract.validate()?;
+
+        if self.store.get_contract(&contract.id)?.is_some() {
+            return Err(Error::InvalidParameters(
+                "Contract with identical id already exists".to_string(),
+            ));
+        }
+
+        self.store.create_contract(&contract)?;
+
+        Ok(())
+    }
+
+    async fn on_offer_loan_message(
+        &self,
+        offered_message: &OfferLoanDlc,
+        counter_party: PublicKey,
+    ) -> Result<(), Error> {
+        println!("on_offer_loan_message");
+        offered_message.validate(&self.secp, REFUND_DELAY, REFUND_DELAY * 2)?;
+        let keys_id = self
+            .signer_provider
+            .derive_signer_key_id(false, offered_message.offer_dlc.temporary_contract_id);
+        let contract: OfferedLoanContract =
+            OfferedLoanContract::try_from_offer_loan_dlc(offered_message, counter_party, keys_id)?;
+        contract.validate()?;
+        self.check_loan_offer_expiry(&contract)?;
+        self.check_lending_deployment(&contract).await?;
+        self.check_loan_principal(&contract).await?;
+
+        if self.store.get_contract(&contract.offered_contract.id)?.is_some() {
+            return Err(Error::InvalidParameters(
+                "Contract with identical id already exists".to_string(),
+            ));
+        }
+        let loan_request = match contract.request_id {
+            Some(request_id) => {
+                let mut loan_request =
+                    self.get_pending_loan_request(&request_id, true, Some(counter_party))?;
+                loan_request.validate_offer(&contract)?;
+                loan_request.state = LoanRequestState::Offered;
+                loan_request.contract_id = Some(contract.offered_contract.id);
+                Some(loan_request)
+            }
+            None => None,
+        };
+        let negotiation = match contract.negotiation_id {
+            Some(negotiation_id) => {
+                let mut negotiation = self.get_countered_loan_negotiation(
+                    &negotiation_id,
+                    false,
+                    Some(counter_party),
+                )?;
+                negotiation.add_offer(&contract)?;
+                Some(negotiation)
+            }
+            None => None,
+        };
+
+        self.store.create_loan_contract(&contract)?;
+        self.store.update_loan_lifecycle(
+            &contract.offered_contract.id,
+            &LoanLifecycle::new(false, self.time.unix_time_now()),
+        )?;
+        if let Some(loan_request) = loan_request {
+            self.store.upsert_loan_request(&loan_request)?;
+        }
+        if let Some(negotiation) = negotiation {
+            self.store.upsert_loan_negotiation(&negotiation)?;
+        }
+
+        Ok(())
+    }
+
+    fn on_request_loan_message(
+        &self,
+        request_msg: &RequestLoanDlc,
+        counter_party: PublicKey,
+    ) -> Result<(), Error> {
+        let loan_request = LoanRequest::from_request_loan_dlc(request_msg, counter_party);
+        loan_request.validate()?;
+        if let Some(listing_id) = &loan_request.listing_id {
+            match self
+                .loan_order_book
+                .get_local_listing(listing_id, self.time.unix_time_now())
+            {
+                Some(listing) => validate_listing_request(&listing, &loan_request)?,
+                None => {
+                    return Err(Error::InvalidParameters(
+                        "Loan request refers to an unknown or expired listing".to_string(),
+                    ))
+                }
+            }
+        }
+
+        if self.store.get_loan_request(&loan_request.id)?.is_some() {
+            return Err(Error::InvalidParameters(
+                "Loan request with identical id already exists".to_string(),
+            ));
+        }
+        let pending_requests = self
+            .store
+            .get_loan_requests()?
+            .into_iter()
+            .filter(|x| !x.is_borrower && x.state == LoanRequestState::Pending)
+            .collect::<Vec<_>>();
+        if pending_requests.len() >= MAX_PENDING_LOAN_REQUESTS {
+            return Err(Error::InvalidState(
+                "Too many loan requests await an answer".to_string(),
+            ));
+        }
+        if pending_requests
+            .iter()
+            .filter(|x| x.counter_party == counter_party)
+            .count()
+            >= MAX_PENDING_LOAN_REQUESTS_PER_PEER
+        {
+            return Err(Error::InvalidState(
+                "Too many loan requests from the peer await an answer".to_string(),
+            ));
+        }
+
+        self.store.upsert_loan_request(&loan_request)
+    }
+
+    fn on_loan_listing_message(
+        &self,
+        listing: &LoanListingDlc,
+        counter_party: PublicKey,
+    ) -> Result<(), Error> {
+        let now = self.time.unix_time_now();
+        validate_loan_listing(&self.secp, listing, now)?;
+
+        self.loan_order_book
+            .add_listing(listing.clone(), Some(counter_party), now)?;
+        Ok(())
+    }
+
+    fn on_reject_loan_request_message(
+        &self,
+        reject_msg: &RejectLoanRequestDlc,
+        counter_party: &PublicKey,
+    ) -> Result<(), Error> {
+        let mut loan_request =
+            self.get_pending_loan_request(&reject_msg.request_id, true, Some(*counter_party))?;
+
+        warn!(
+            "Loan request {} rejected: {}",
+            reject_msg.request_id.to_lower_hex_string(),
+            reject_msg.reason
+        );
+        loan_request.state = LoanRequestState::Rejected;
+        self.store.upsert_loan_request(&loan_request)
+    }
+
+    async fn on_counter_offer_loan_message(
+        &self,
+        counter_msg: &CounterOfferLoanDlc,
+        counter_party: &PublicKey,
+    ) -> Result<(), Error> {
+        let offered_loan_contract = get_contract_in_state!(
+            self,
+            &counter_msg.temporary_contract_id,
+            OfferedLoan,
+            Some(*counter_party)
+        )?;
+        if !offered_loan_contract.offered_contract.is_offer_party {
+            return Err(Error::InvalidState(
+                "Only the lender can receive a loan counter offer".to_string(),
+            ));
+        }
+        if offered_loan_contract.get_negotiation_id() != counter_msg.negotiation_id {
+            return Err(Error::InvalidParameters(
+                "Counter offer does not belong to the negotiation of the offer".to_string(),
+            ));
+        }
+        if self.is_stable_loan_lent(&offered_loan_contract).await? {
+            return Err(Error::InvalidState(
+                "Stable loan was already lent".to_string(),
+            ));
+        }
+
+        let negotiation = self.get_revised_loan_negotiation(
+            &offered_loan_contract,
+            LoanTermsRevision {
+                countered_contract_id: counter_msg.temporary_contract_id,
+                collateral_ratio: counter_msg.collateral_ratio,
+                interest_rate: counter_msg.interest_rate,
+                duration: counter_msg.duration,
+            },
+        )?;
+        if negotiation.get_round() != counter_msg.round as usize {
+            return Err(Error::InvalidParameters(
+                "Unexpected loan counter offer round".to_string(),
+            ));
+        }
+
+        self.store.upsert_loan_negotiation(&negotiation)?;
+        self.abort_loan_offer(
+            &offered_loan_contract,
+            Contract::RejectedLoan(offered_loan_contract.clone()),
+        )
+    }
+
+    fn on_reject_loan_counter_offer_message(
+        &self,
+        reject_msg: &RejectLoanCounterOfferDlc,
+        counter_party: &PublicKey,
+    ) -> Result<(), Error> {
+        let mut negotiation = self.get_countered_loan_negotiation(
+            &reject_msg.negotiation_id,
+            false,
+            Some(*counter_party),
+        )?;
+
+        warn!(
+            "Loan counter offer {} rejected: {}",
+            reject_msg.negotiation_id.to_lower_hex_string(),
+            reject_msg.reason
+        );
+        negotiation.state = LoanNegotiationState::Rejected;
+        self.store.upsert_loan_negotiation(&negotiation)
+    }
+
+    fn on_accept_message(
+        &self,
+        accept_msg: &AcceptDlc,
+        counter_party: &PublicKey,
     ) -> Result<DlcMessage, Error> {
         let offered_contract = get_contract_in_state!(
             self,
@@ -648,7 +1621,6 @@ where
         counter_party: &PublicKey,
     ) -> Result<DlcMessage, Error> {
         println!("on_accept_loan_message");
-        dotenv().ok();
         let mut collateral_tx = accept_msg.signed_escrow_spend_tx.clone();
 
         let offered_loan_contract = get_contract_in_state!(
@@ -658,68 +1630,86 @@ where
             Some(*counter_party)
         )?;
         let signer = self.signer_provider.derive_contract_signer(offered_loan_contract.offered_contract.keys_id)?;
-        println!("signer: {}", signer.get_public_key(&self.secp).unwrap());
-
-        // Read the preimage from contract
-        let citrea_rpc_url = env::var("CITREA_RPC")
-                .map_err(|_| Error::InvalidParameters("CITREA_RPC environment variable not set".to_string()))?;
-        let provider = ProviderBuilder::new()
-                .connect(&citrea_rpc_url).await.unwrap();
-        let lending_contract_address_str = env::var("LENDING_CONTRACT_ADDRESS")
-            .map_err(|_| Error::InvalidParameters("LENDING_CONTRACT_ADDRESS environment variable not set".to_string()))?;
-        let lending_contract_address = primitives::Address::parse_checksummed(&lending_contract_address_str, None).unwrap();
-        let lending_contract = P2PBTCLending::new(
-            lending_contract_address,
-            provider
-        );
-        let borrower_hash = primitives::FixedBytes::from(accept_msg.borrower_hash.clone());
-        // Get the return value of `stableLoans(borrowerHash)` which is a three tuple, get the last value
-        let stable_loans = lending_contract.stableLoans(borrower_hash);
-        let stable_loans_result = stable_loans.call().await.unwrap();
-        let borrower_preimage = stable_loans_result.borrowerPreimage;
 
-        // Sign the collateral transaction
-        let lender_signature = get_sig_for_tx_input(
-            &self.secp,
+        let temporary_contract_id = offered_loan_contract.offered_contract.id;
+        let borrower_hash = self
+            .store
+            .get_loan_borrower_hash(&temporary_contract_id)?
+            .ok_or_else(|| Error::InvalidState("Borrower hash not found".to_string()))?;
+        if accept_msg.borrower_hash != borrower_hash {
+            return Err(Error::InvalidParameters(
+                "Borrower hash does not match the one of the loan escrow".to_string(),
+            ));
+        }
+
+        // Read the preimage revealed by the borrower when claiming the stable loan
+        let borrower_preimage = self
+            .lending_backend
+            .get_stable_loan(&borrower_hash)
+            .await?
+            .borrower_preimage;
+        if borrower_preimage == 0 {
+            return Err(Error::InvalidState(
+                "Borrower has not claimed the stable loan yet".to_string(),
+            ));
+        }
+        self.transition_loan_state(&temporary_contract_id, LoanState::StableClaimed)?;
+
+        // Only sign a collateral transaction spending the verified escrow into
+        // the collateral script, using the escrow script and amount stored on
+        // our side rather than the ones sent by the borrower.
+        let escrow_outpoint = self
+            .store
+            .get_escrow_outpoint(&temporary_contract_id)?
+            .ok_or_else(|| Error::InvalidState("Escrow outpoint not found".to_string()))?;
+        let escrow_output = self
+            .blockchain
+            .get_transaction(&escrow_outpoint.txid)?
+            .output
+            .get(escrow_outpoint.vout as usize)
+            .cloned()
+            .ok_or_else(|| Error::InvalidState("Escrow output not found".to_string()))?;
+        let (escrow_script, collateral_script) = verify_collateral_transaction(
+            &offered_loan_contract,
+            &collateral_tx,
+            &escrow_outpoint,
+            &escrow_output,
+            &accept_msg.funding_pubkey,
+            &borrower_hash,
+        )?;
+
+        // Sign the collateral transaction
+        let lender_signature = get_sig_for_tx_input(
+            &self.secp,
             &collateral_tx,
             0,
-            &accept_msg.escrow_spk,
-            accept_msg.escrow_amount,
+            &escrow_script,
+            escrow_output.value,
             EcdsaSighashType::All,
-            &signer.get_secret_key().unwrap()
-        ).unwrap();
+            &signer.get_secret_key()?,
+        )?;
         let mut witness_items: Vec<Vec<u8>> = collateral_tx.input[0].witness.clone().to_vec();
         witness_items[0] = lender_signature;
         witness_items[1] = borrower_preimage.to_be_bytes().to_vec();
         collateral_tx.input[0].witness = bitcoin::Witness::from_slice(&witness_items);
-        let mut writer = Vec::new();
-        let _ = collateral_tx.consensus_encode(&mut writer);
-        println!("raw tx {}", writer.to_hex_string(hex::Case::Lower));
-        // Send the collateral transaction to the blockchain
-        self.blockchain.send_transaction(&collateral_tx)?;
-        // Get txid of the collateral transaction
-        let collateral_txid = collateral_tx.compute_txid();
-        println!(
-            "Collateral transaction sent with txid: {}",
-            collateral_txid
-        );
 
+        // Verify the borrower signatures before releasing the collateral
+        // transaction, so that the collateral cannot get locked without valid
+        // CETs and refund transaction.
         let offered_contract = offered_loan_contract.offered_contract.clone();
         let accept_dlc_msg = accept_msg.clone().to_accept_dlc();
-        println!("a1");
         let (signed_contract, signed_msg) = match verify_accepted_and_sign_loan_contract(
             &self.secp,
             &offered_contract,
             &accept_dlc_msg,
             collateral_tx,
-            accept_msg.collateral_spk.clone(),
+            collateral_script,
             &self.wallet,
             &self.signer_provider,
         ) {
             Ok(contract) => contract,
             Err(e) => return self.accept_fail_on_error(offered_contract, accept_dlc_msg.clone(), e),
         };
-        println!("a2");
 
         self.wallet.import_address(&Address::p2wsh(
             &signed_contract
@@ -728,209 +1718,1365 @@ where
                 .funding_script_pubkey,
             self.blockchain.get_network()?,
         ))?;
-        println!("a3");
 
-        self.store
-            .update_contract(&Contract::Signed(signed_contract))?;
+        self.store.set_loan_terms(
+            &temporary_contract_id,
+            &LoanTerms {
+                escrow_txid: self.store.get_escrow_txid(&temporary_contract_id)?,
+                borrower_hash: Some(borrower_hash),
+                ..offered_loan_contract.get_loan_terms()
+            },
+        )?;
+
+        let collateral_tx = signed_contract.accepted_contract.dlc_transactions.fund.clone();
+        self.store
+            .update_contract(&Contract::Signed(signed_contract))?;
+        self.transition_loan_state(&temporary_contract_id, LoanState::CollateralLocked)?;
+
+        // A failed broadcast is retried when checking signed contracts.
+        match self.blockchain.send_transaction(&collateral_tx) {
+            Ok(()) => println!(
+                "Collateral transaction sent with txid: {}",
+                collateral_tx.compute_txid()
+            ),
+            Err(e) => error!("Error broadcasting collateral transaction: {}", e),
+        }
+
+        Ok(DlcMessage::Sign(signed_msg))
+    }
+
+    fn on_initiate_accept_loan_message(
+        &self,
+        initiate_accept_msg: &InitiateAcceptLoanDlc,
+        counter_party: &PublicKey,
+    ) -> Result<(), Error> {
+        println!("on_initiate_accept_loan_message");
+
+        let offered_loan_contract = get_contract_in_state!(
+            self,
+            &initiate_accept_msg.contract_id,
+            OfferedLoan,
+            Some(*counter_party)
+        )?;
+        let offered_contract = &offered_loan_contract.offered_contract;
+        if !offered_contract.is_offer_party {
+            return Err(Error::InvalidState(
+                "Only the lender can receive an escrow".to_string(),
+            ));
+        }
+        self.check_loan_offer_expiry(&offered_loan_contract)?;
+
+        let escrow_tx = self
+            .blockchain
+            .get_transaction(&initiate_accept_msg.escrow_txid)?;
+        let escrow_vout = verify_escrow_transaction(
+            &offered_loan_contract,
+            &escrow_tx,
+            &initiate_accept_msg.borrower_pubkey,
+            &initiate_accept_msg.borrower_hash,
+        )?;
+
+        // The stable loan is lent once the escrow is confirmed, see
+        // `lend_on_confirmed_loan_escrow`.
+        self.store.set_escrow_outpoint(
+            &offered_contract.id,
+            OutPoint {
+                txid: initiate_accept_msg.escrow_txid,
+                vout: escrow_vout,
+            },
+        )?;
+        self.store
+            .set_loan_borrower_hash(&offered_contract.id, &initiate_accept_msg.borrower_hash)
+    }
+
+    fn on_sign_message(&self, sign_message: &SignDlc, peer_id: &PublicKey) -> Result<(), Error> {
+        let accepted_contract =
+            get_contract_in_state!(self, &sign_message.contract_id, Accepted, Some(*peer_id))?;
+
+        let (signed_contract, fund_tx) = match crate::contract_updater::verify_signed_contract(
+            &self.secp,
+            &accepted_contract,
+            sign_message,
+            &self.wallet,
+        ) {
+            Ok(contract) => contract,
+            Err(e) => return self.sign_fail_on_error(accepted_contract, sign_message.clone(), e),
+        };
+
+        self.store
+            .update_contract(&Contract::Signed(signed_contract))?;
+        self.transition_loan_state(
+            &accepted_contract.offered_contract.id,
+            LoanState::CollateralLocked,
+        )?;
+
+        // self.blockchain.send_transaction(&fund_tx)?;
+
+        Ok(())
+    }
+
+    async fn on_repay_loan(
+        &self,
+        repay_msg: &RepayLoanDlc,
+        counter_party: &PublicKey,
+    ) -> Result<RepayLoanAckDlc, Error> {
+        // The acknowledgement may have been lost, in which case the borrower
+        // asks again for the preimage already revealed.
+        if let Some(Contract::PreRepaid(c)) = self.store.get_contract(&repay_msg.contract_id)? {
+            let offered_contract = &c.signed_contract.accepted_contract.offered_contract;
+            if offered_contract.counter_party != *counter_party
+                || !offered_contract.is_offer_party
+                || self.get_borrower_hash(offered_contract)? != Some(repay_msg.borrower_hash)
+            {
+                return Err(Error::InvalidParameters(
+                    "Repayment does not match the pre-repaid loan".to_string(),
+                ));
+            }
+            return Ok(RepayLoanAckDlc {
+                protocol_version: crate::conversion_utils::PROTOCOL_VERSION,
+                contract_id: repay_msg.contract_id,
+                lender_preimage: c.lender_preimage,
+            });
+        }
+
+        let contract = get_contract_in_state!(
+            self,
+            &repay_msg.contract_id,
+            Confirmed,
+            Some(*counter_party)
+        )?;
+        let accepted_contract = &contract.accepted_contract;
+        let offered_contract = &accepted_contract.offered_contract;
+        if !offered_contract.is_offer_party {
+            return Err(Error::InvalidState(
+                "Only the lender can acknowledge a loan repayment".to_string(),
+            ));
+        }
+
+        // Make sure the borrower hash is the one locked in the loan escrow. The
+        // collateral may have been renewed since, so it cannot be read from
+        // the current collateral transaction.
+        if self.get_borrower_hash(offered_contract)? != Some(repay_msg.borrower_hash) {
+            return Err(Error::InvalidParameters(
+                "Borrower hash does not match the loan escrow".to_string(),
+            ));
+        }
+
+        let stable_loan = self
+            .lending_backend
+            .get_stable_loan(&repay_msg.borrower_hash)
+            .await?;
+        if !stable_loan.repaid {
+            return Err(Error::InvalidState(
+                "Stable loan was not repaid on the lending contract".to_string(),
+            ));
+        }
+
+        let lender_preimage = self
+            .store
+            .get_loan_preimage(&offered_contract.id)?
+            .ok_or_else(|| Error::InvalidState("Lender preimage not found".to_string()))?;
+
+        // Keep watching the collateral until the borrower sweep confirms. The
+        // sweep is fully determined by the contract so its txid is known.
+        let (collateral_sweep_tx, _) =
+            get_collateral_sweep_transaction(&contract, lender_preimage)?;
+        self.store
+            .update_contract(&Contract::PreRepaid(RepaidContract {
+                signed_contract: contract.clone(),
+                lender_preimage,
+                collateral_sweep_tx: Some(collateral_sweep_tx),
+            }))?;
+        self.transition_loan_state(&offered_contract.id, LoanState::Repaid)?;
+
+        Ok(RepayLoanAckDlc {
+            protocol_version: crate::conversion_utils::PROTOCOL_VERSION,
+            contract_id: repay_msg.contract_id,
+            lender_preimage,
+        })
+    }
+
+    fn on_repay_loan_ack(
+        &self,
+        repay_ack_msg: &RepayLoanAckDlc,
+        counter_party: &PublicKey,
+    ) -> Result<(), Error> {
+        let contract = get_contract_in_state!(
+            self,
+            &repay_ack_msg.contract_id,
+            Confirmed,
+            Some(*counter_party)
+        )?;
+        let offered_contract = &contract.accepted_contract.offered_contract;
+        let collateral_amount = contract
+            .accepted_contract
+            .dlc_transactions
+            .get_fund_output()
+            .value;
+
+        let (mut collateral_sweep_tx, collateral_redeemscript) =
+            get_collateral_sweep_transaction(&contract, repay_ack_msg.lender_preimage)?;
+
+        let signer = self.signer_provider.derive_contract_signer(offered_contract.keys_id)?;
+        dlc::sign_collateral_sweep_input(
+            &self.secp,
+            &mut collateral_sweep_tx,
+            0,
+            &signer.get_secret_key()?,
+            repay_ack_msg.lender_preimage,
+            &collateral_redeemscript,
+            collateral_amount,
+        )?;
+
+        // Persist the preimage before broadcasting, the lender will not send
+        // it again and the sweep is rebroadcast until it confirms.
+        self.store
+            .update_contract(&Contract::PreRepaid(RepaidContract {
+                signed_contract: contract.clone(),
+                lender_preimage: repay_ack_msg.lender_preimage,
+                collateral_sweep_tx: Some(collateral_sweep_tx.clone()),
+            }))?;
+        self.transition_loan_state(&offered_contract.id, LoanState::Repaid)?;
+
+        if let Err(e) = self.blockchain.send_transaction(&collateral_sweep_tx) {
+            error!(
+                "Error broadcasting the collateral sweep of contract {}, will retry: {}",
+                contract.accepted_contract.get_contract_id_string(),
+                e
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn on_reject_loan_message(
+        &self,
+        reject_msg: &RejectLoanDlc,
+        counter_party: &PublicKey,
+    ) -> Result<(), Error> {
+        let offered_loan_contract = get_contract_in_state!(
+            self,
+            &reject_msg.temporary_contract_id,
+            OfferedLoan,
+            Some(*counter_party)
+        )?;
+        if !offered_loan_contract.offered_contract.is_offer_party {
+            return Err(Error::InvalidState(
+                "Only the lender can receive a loan offer rejection".to_string(),
+            ));
+        }
+        if self.is_stable_loan_lent(&offered_loan_contract).await? {
+            return Err(Error::InvalidState(
+                "Stable loan was already lent".to_string(),
+            ));
+        }
+
+        warn!(
+            "Loan offer {} rejected: {}",
+            reject_msg.temporary_contract_id.to_lower_hex_string(),
+            reject_msg.reason
+        );
+        self.abort_loan_offer(
+            &offered_loan_contract,
+            Contract::RejectedLoan(offered_loan_contract.clone()),
+        )
+    }
+
+    async fn on_cancel_loan_message(
+        &self,
+        cancel_msg: &CancelLoanDlc,
+        counter_party: &PublicKey,
+    ) -> Result<(), Error> {
+        let offered_loan_contract = get_contract_in_state!(
+            self,
+            &cancel_msg.temporary_contract_id,
+            OfferedLoan,
+            Some(*counter_party)
+        )?;
+        if offered_loan_contract.offered_contract.is_offer_party {
+            return Err(Error::InvalidState(
+                "Only the borrower can receive a loan offer cancellation".to_string(),
+            ));
+        }
+        if self.is_stable_loan_lent(&offered_loan_contract).await? {
+            return Err(Error::InvalidState(
+                "Stable loan was already lent".to_string(),
+            ));
+        }
+
+        warn!(
+            "Loan offer {} cancelled: {}",
+            cancel_msg.temporary_contract_id.to_lower_hex_string(),
+            cancel_msg.reason
+        );
+        // A published escrow is reclaimed once its relative locktime expires,
+        // see `check_abandoned_loan_escrows`.
+        self.abort_loan_offer(
+            &offered_loan_contract,
+            Contract::CancelledLoan(offered_loan_contract.clone()),
+        )
+    }
+
+    async fn on_renew_loan_offer(
+        &self,
+        offer_msg: &RenewLoanOfferDlc,
+        counter_party: &PublicKey,
+    ) -> Result<RenewLoanAcceptDlc, Error> {
+        let contract = get_contract_in_state!(
+            self,
+            &offer_msg.contract_id,
+            Confirmed,
+            Some(*counter_party)
+        )?;
+        let offered_contract = &contract.accepted_contract.offered_contract;
+        if !offered_contract.is_offer_party {
+            return Err(Error::InvalidState(
+                "Only the lender can receive a loan renewal".to_string(),
+            ));
+        }
+        self.check_loan_renewal_replaceable(&offer_msg.contract_id)?;
+
+        let loan_terms = self
+            .store
+            .get_loan_terms(&offered_contract.id)?
+            .ok_or_else(|| Error::InvalidState("Loan terms not found".to_string()))?;
+        validate_loan_renewal(
+            &loan_terms,
+            offer_msg.additional_collateral,
+            offer_msg.repaid_principal,
+        )?;
+        let stable_loan_amount = self
+            .get_renewed_stable_loan_amount(
+                offered_contract,
+                &loan_terms,
+                offer_msg.repaid_principal,
+            )
+            .await?;
+
+        let (renewed_contract, accept_msg) = verify_renew_loan_offer_and_sign(
+            &self.secp,
+            &contract,
+            &loan_terms,
+            offer_msg,
+            &self.wallet,
+            &self.signer_provider,
+        )?;
+
+        self.store.upsert_loan_renewal(&LoanRenewal {
+            contract_id: offer_msg.contract_id,
+            counter_party: *counter_party,
+            is_borrower: false,
+            additional_collateral: offer_msg.additional_collateral,
+            repaid_principal: offer_msg.repaid_principal,
+            stable_loan_amount,
+            state: LoanRenewalState::Accepted,
+            accepted_contract: renewed_contract.accepted_contract.clone(),
+            signed_contract: Some(renewed_contract),
+        })?;
+
+        Ok(accept_msg)
+    }
+
+    async fn on_renew_loan_accept(
+        &self,
+        accept_msg: &RenewLoanAcceptDlc,
+        counter_party: &PublicKey,
+    ) -> Result<RenewLoanConfirmDlc, Error> {
+        let contract = get_contract_in_state!(
+            self,
+            &accept_msg.contract_id,
+            Confirmed,
+            Some(*counter_party)
+        )?;
+        let mut renewal = self.get_loan_renewal_in_state(
+            &accept_msg.contract_id,
+            LoanRenewalState::Offered,
+            true,
+        )?;
+
+        let renewed_contract = verify_renew_loan_accept(
+            &self.secp,
+            &renewal.accepted_contract,
+            accept_msg,
+            &self.wallet,
+        )?;
+
+        // Only repay once the renewed contract is signed by the lender, so
+        // that the partial repayment is reflected in the payouts.
+        if renewal.repaid_principal > 0 {
+            let offered_contract = &contract.accepted_contract.offered_contract;
+            let borrower_hash = self
+                .get_borrower_hash(offered_contract)?
+                .ok_or_else(|| Error::InvalidState("Borrower hash not found".to_string()))?;
+            let stable_loan = self.lending_backend.get_stable_loan(&borrower_hash).await?;
+            if stable_loan.stable_loan_amount > renewal.stable_loan_amount {
+                self.lending_backend
+                    .repay_stable_partially(
+                        &borrower_hash,
+                        stable_loan.stable_loan_amount - renewal.stable_loan_amount,
+                    )
+                    .await?;
+            }
+        }
+
+        let confirm_msg = sign_loan_renewal(
+            &self.secp,
+            &contract,
+            &renewed_contract,
+            &self.wallet,
+            &self.signer_provider,
+        )?;
+
+        renewal.state = LoanRenewalState::Confirmed;
+        renewal.signed_contract = Some(renewed_contract);
+        self.store.upsert_loan_renewal(&renewal)?;
+
+        Ok(confirm_msg)
+    }
+
+    async fn on_renew_loan_confirm(
+        &self,
+        confirm_msg: &RenewLoanConfirmDlc,
+        counter_party: &PublicKey,
+    ) -> Result<(), Error> {
+        let contract = get_contract_in_state!(
+            self,
+            &confirm_msg.contract_id,
+            Confirmed,
+            Some(*counter_party)
+        )?;
+        let mut renewal = self.get_loan_renewal_in_state(
+            &confirm_msg.contract_id,
+            LoanRenewalState::Accepted,
+            false,
+        )?;
+
+        let offered_contract = &contract.accepted_contract.offered_contract;
+        let borrower_hash = self
+            .get_borrower_hash(offered_contract)?
+            .ok_or_else(|| Error::InvalidState("Borrower hash not found".to_string()))?;
+        let stable_loan = self.lending_backend.get_stable_loan(&borrower_hash).await?;
+        if stable_loan.stable_loan_amount > renewal.stable_loan_amount {
+            return Err(Error::InvalidState(format!(
+                "Stable loan amount {} was not repaid down to {}",
+                stable_loan.stable_loan_amount, renewal.stable_loan_amount
+            )));
+        }
+
+        let mut renewed_contract = renewal
+            .signed_contract
+            .clone()
+            .ok_or_else(|| Error::InvalidState("Renewed contract not found".to_string()))?;
+        let renewal_tx = verify_renew_loan_confirm_and_sign(
+            &self.secp,
+            &contract,
+            &renewed_contract,
+            confirm_msg,
+            &self.signer_provider,
+        )?;
+        self.blockchain.send_transaction(&renewal_tx)?;
+
+        // Keep the signed transaction so that it can be rebroadcast.
+        renewed_contract.accepted_contract.dlc_transactions.fund = renewal_tx;
+        renewal.state = LoanRenewalState::Confirmed;
+        renewal.signed_contract = Some(renewed_contract);
+        self.store.upsert_loan_renewal(&renewal)
+    }
+
+    /// Returns the stable amount that will be owed on the given loan once the
+    /// given part of its principal is repaid.
+    async fn get_renewed_stable_loan_amount(
+        &self,
+        offered_contract: &OfferedContract,
+        loan_terms: &LoanTerms,
+        repaid_principal: u64,
+    ) -> Result<u128, Error> {
+        let borrower_hash = self
+            .get_borrower_hash(offered_contract)?
+            .ok_or_else(|| Error::InvalidState("Borrower hash not found".to_string()))?;
+        let stable_loan = self.lending_backend.get_stable_loan(&borrower_hash).await?;
+        if stable_loan.repaid {
+            return Err(Error::InvalidState(
+                "Stable loan was already repaid".to_string(),
+            ));
+        }
+        let repaid_amount = get_repaid_stable_amount(
+            stable_loan.stable_loan_amount,
+            loan_terms.principal,
+            repaid_principal,
+        )?;
+        Ok(stable_loan.stable_loan_amount - repaid_amount)
+    }
+
+    /// Fails if the loan with the given contract id has a renewal that can no
+    /// longer be replaced by a new one.
+    fn check_loan_renewal_replaceable(&self, contract_id: &ContractId) -> Result<(), Error> {
+        match self.store.get_loan_renewal(contract_id)? {
+            Some(renewal) if !renewal.can_be_replaced() => Err(Error::InvalidState(format!(
+                "Loan already has a renewal that is {}",
+                renewal.state
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    fn get_loan_renewal_in_state(
+        &self,
+        contract_id: &ContractId,
+        state: LoanRenewalState,
+        is_borrower: bool,
+    ) -> Result<LoanRenewal, Error> {
+        let renewal = self
+            .store
+            .get_loan_renewal(contract_id)?
+            .ok_or_else(|| Error::InvalidParameters("Unknown loan renewal".to_string()))?;
+        if renewal.is_borrower != is_borrower || renewal.state != state {
+            return Err(Error::InvalidState(format!(
+                "Loan renewal is {}, expected {}",
+                renewal.state, state
+            )));
+        }
+        Ok(renewal)
+    }
+
+    /// Returns the borrower hash of the loan of the given contract, if known.
+    fn get_borrower_hash(
+        &self,
+        offered_contract: &OfferedContract,
+    ) -> Result<Option<[u8; 32]>, Error> {
+        let contract_id = &offered_contract.id;
+        if offered_contract.is_offer_party {
+            self.store.get_loan_borrower_hash(contract_id)
+        } else {
+            Ok(self.store.get_loan_preimage(contract_id)?.map(|preimage| {
+                bitcoin::hashes::sha256::Hash::hash(&preimage.to_be_bytes()).to_byte_array()
+            }))
+        }
+    }
+
+    /// Returns whether the stable loan of the given offered loan contract was
+    /// already lent on the lending contract.
+    async fn is_stable_loan_lent(&self, contract: &OfferedLoanContract) -> Result<bool, Error> {
+        match self.get_borrower_hash(&contract.offered_contract)? {
+            Some(borrower_hash) => Ok(self
+                .lending_backend
+                .get_stable_loan(&borrower_hash)
+                .await?
+                .collateral_amount
+                > Amount::ZERO),
+            None => Ok(false),
+        }
+    }
+
+    /// Moves an offered loan contract that will not be taken to the given
+    /// state, releasing the UTXOs reserved for its offer.
+    fn abort_loan_offer(
+        &self,
+        offered_loan_contract: &OfferedLoanContract,
+        contract: Contract,
+    ) -> Result<(), Error> {
+        let offered_contract = &offered_loan_contract.offered_contract;
+        if offered_contract.is_offer_party {
+            let utxos = offered_contract
+                .funding_inputs
+                .iter()
+                .map(|funding_input| dlc::TxInputInfo::from(funding_input).outpoint)
+                .collect::<Vec<_>>();
+            self.wallet.unreserve_utxos(&utxos)?;
+        }
+
+        self.store.update_contract(&contract)?;
+        self.transition_loan_state(&offered_contract.id, LoanState::Aborted)
+    }
+
+    fn get_loan_oracle_announcements(
+        &self,
+        loan_contract_input: &LoanContractInput,
+    ) -> Result<Vec<Vec<OracleAnnouncement>>, Error> {
+        loan_contract_input
+            .get_oracle_inputs()
+            .into_iter()
+            .map(|x| self.get_oracle_announcements(x))
+            .collect()
+    }
+
+    fn get_oracle_announcements(
+        &self,
+        oracle_inputs: &OracleInput,
+    ) -> Result<Vec<OracleAnnouncement>, Error> {
+        let mut announcements = Vec::new();
+        for pubkey in &oracle_inputs.public_keys {
+            let oracle = self
+                .oracles
+                .get(pubkey)
+                .ok_or_else(|| Error::InvalidParameters("Unknown oracle public key".to_string()))?;
+            announcements.push(oracle.get_announcement(&oracle_inputs.event_id)?.clone());
+        }
+
+        Ok(announcements)
+    }
+
+    fn sign_fail_on_error<R>(
+        &self,
+        accepted_contract: AcceptedContract,
+        sign_message: SignDlc,
+        e: Error,
+    ) -> Result<R, Error> {
+        error!("Error in on_sign {}", e);
+        self.store
+            .update_contract(&Contract::FailedSign(FailedSignContract {
+                accepted_contract,
+                sign_message,
+                error_message: e.to_string(),
+            }))?;
+        Err(e)
+    }
+
+    fn accept_fail_on_error<R>(
+        &self,
+        offered_contract: OfferedContract,
+        accept_message: AcceptDlc,
+        e: Error,
+    ) -> Result<R, Error> {
+        error!("Error in on_accept {}", e);
+        self.store
+            .update_contract(&Contract::FailedAccept(FailedAcceptContract {
+                offered_contract: offered_contract.clone(),
+                accept_message,
+                error_message: e.to_string(),
+            }))?;
+        self.transition_loan_state(&offered_contract.id, LoanState::Aborted)?;
+        Err(e)
+    }
+
+    async fn check_offered_loan_contracts_for_escrow(&self) -> Result<(), Error> {
+        let contracts = self.store.get_loan_contract_offers()?;
+        let results = join_all(
+            contracts
+                .iter()
+                .map(|c| self.check_offered_loan_contract_for_escrow(c)),
+        )
+        .await;
+        for (c, result) in contracts.iter().zip(results) {
+            if let Err(e) = result {
+                error!(
+                    "Error checking escrow of offered loan contract {}: {}",
+                    c.offered_contract.id.to_lower_hex_string(),
+                    e
+                )
+            }
+        }
+
+        Ok(())
+    }
+
+    // TODO: Check if what this do if tx fails, can it be safely retried?
+    async fn check_offered_loan_contract_for_escrow(
+        &self,
+        contract: &OfferedLoanContract,
+    ) -> Result<(), Error> {
+        if contract.offered_contract.is_offer_party {
+            return self.lend_on_confirmed_loan_escrow(contract).await;
+        }
+
+        let escrow_txid = match self.store.get_escrow_txid(&contract.offered_contract.id)? {
+            Some(txid) => txid,
+            None => return Ok(()),
+        };
+        let borrower_preimage = self
+            .store
+            .get_loan_preimage(&contract.offered_contract.id)?
+            .ok_or_else(|| Error::InvalidState("Borrower preimage not found".to_string()))?;
+        let borrower_hash =
+            bitcoin::hashes::sha256::Hash::hash(&borrower_preimage.to_be_bytes()).to_byte_array();
+
+        let stable_loan = self.lending_backend.get_stable_loan(&borrower_hash).await?;
+        let is_lend_recorded = stable_loan.collateral_amount > Amount::ZERO;
+        if is_lend_recorded && stable_loan.borrower_preimage == 0 {
+            self.transition_loan_state(&contract.offered_contract.id, LoanState::StableLent)?;
+            let stable_unit = self.lending_backend.get_stable_unit().await?;
+            if let Err(e) = verify_stable_loan(contract, &stable_loan, stable_unit) {
+                // Keep the preimage secret, the escrow can still be reclaimed
+                // once its relative locktime expires.
+                self.store
+                    .update_contract(&Contract::FailedLoan(FailedLoanContract {
+                        offered_loan_contract: contract.clone(),
+                        error_message: e.to_string(),
+                    }))?;
+                self.transition_loan_state(&contract.offered_contract.id, LoanState::Aborted)?;
+                return Err(e);
+            }
+            // Claim the stables, revealing the preimage
+            self.lending_backend.claim_stable(borrower_preimage).await?;
+        }
+        if is_lend_recorded {
+            self.transition_loan_state(&contract.offered_contract.id, LoanState::StableClaimed)?;
+        }
+        let confirmations = self.blockchain.get_transaction_confirmations(&escrow_txid)?;
+        if confirmations >= contract.escrow_confirmations && !is_lend_recorded {
+            self.transition_loan_state(&contract.offered_contract.id, LoanState::EscrowConfirmed)?;
+        }
+        if (confirmations >= contract.escrow_confirmations) && is_lend_recorded {
+            self.store
+                .update_contract(&Contract::OfferedLoanEscrowConfirmed(contract.clone()))?;
+        }
+
+        Ok(())
+    }
+
+    async fn lend_on_confirmed_loan_escrow(
+        &self,
+        contract: &OfferedLoanContract,
+    ) -> Result<(), Error> {
+        let offered_contract = &contract.offered_contract;
+        let (escrow_txid, borrower_hash) = match (
+            self.store.get_escrow_txid(&offered_contract.id)?,
+            self.store.get_loan_borrower_hash(&offered_contract.id)?,
+        ) {
+            (Some(escrow_txid), Some(borrower_hash)) => (escrow_txid, borrower_hash),
+            _ => return Ok(()),
+        };
+
+        let confirmations = self.blockchain.get_transaction_confirmations(&escrow_txid)?;
+        if confirmations < contract.escrow_confirmations {
+            return Ok(());
+        }
+
+        let stable_loan = self.lending_backend.get_stable_loan(&borrower_hash).await?;
+        if stable_loan.collateral_amount > Amount::ZERO {
+            return Ok(());
+        }
+        self.transition_loan_state(&offered_contract.id, LoanState::EscrowConfirmed)?;
+        // Never lend on another deployment than the one the borrower agreed to.
+        self.check_lending_deployment(contract).await?;
+
+        let stable_unit = self.lending_backend.get_stable_unit().await?;
+        self.lending_backend
+            .lend_stable(
+                &borrower_hash,
+                offered_contract.total_collateral,
+                get_stable_loan_amount(contract, stable_unit)?,
+                contract.interest_rate,
+                contract.collateral_ratio,
+            )
+            .await?;
+        self.transition_loan_state(&offered_contract.id, LoanState::StableLent)
+    }
+
+    async fn check_loan_principal(&self, contract: &OfferedLoanContract) -> Result<(), Error> {
+        let collateral_value = self
+            .lending_backend
+            .get_collateral_value(contract.offered_contract.total_collateral)
+            .await?;
+        let stable_unit = self.lending_backend.get_stable_unit().await?;
+        verify_loan_principal(contract, collateral_value, stable_unit)
+    }
+
+    async fn check_lending_deployment(&self, contract: &OfferedLoanContract) -> Result<(), Error> {
+        let local_deployment = self.lending_backend.get_deployment().await?;
+        contract.lending_deployment.validate(&local_deployment)
+    }
+
+    fn check_loan_offer_expiry(&self, contract: &OfferedLoanContract) -> Result<(), Error> {
+        if contract.offer_expiry <= self.time.unix_time_now() {
+            return Err(Error::InvalidState("Loan offer has expired".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Checks the liquidation risk of signed loans at the price of the oracles
+    /// or of the configured feed, once per check interval.
+    fn check_loan_risks(&self) -> Result<(), Error> {
+        let price = match &self.loan_monitor.get_config().price_source {
+            // Read by the loan check, as the lending backend is async.
+            LoanPriceSource::LendingContract => return Ok(()),
+            _ if !self.loan_monitor.start_check(self.time.unix_time_now()) => return Ok(()),
+            LoanPriceSource::Oracle => None,
+            LoanPriceSource::Feed(feed) => match feed.get_price() {
+                Ok(price) => Some(price),
+                Err(e) => {
+                    error!("Error getting the price from the feed: {}", e);
+                    return Ok(());
+                }
+            },
+        };
+        self.monitor_loans(price)
+    }
+
+    /// Checks the liquidation risk of signed loans at the price of the BTC
+    /// price feed of the lending contract, once per check interval.
+    async fn check_loan_risks_at_lending_price(&self) -> Result<(), Error> {
+        if !matches!(
+            self.loan_monitor.get_config().price_source,
+            LoanPriceSource::LendingContract
+        ) || !self.loan_monitor.start_check(self.time.unix_time_now())
+        {
+            return Ok(());
+        }
+        match self.lending_backend.get_btc_price().await {
+            Ok(price) => self.monitor_loans(Some(price)),
+            Err(e) => {
+                error!("Error getting the price from the lending contract: {}", e);
+                Ok(())
+            }
+        }
+    }
+
+    /// Computes the collateral ratio of the signed loans at the given price,
+    /// or at the latest price attested by the oracles of each loan if none is
+    /// given, and notifies the subscribers of the risk level changes.
+    fn monitor_loans(&self, price: Option<u64>) -> Result<(), Error> {
+        let now = self.time.unix_time_now();
+        let contracts = self
+            .store
+            .get_signed_contracts()?
+            .into_iter()
+            .chain(self.store.get_confirmed_contracts()?)
+            .filter(|c| c.channel_id.is_none());
+
+        let mut monitored = Vec::new();
+        let mut risks = Vec::new();
+        for contract in contracts {
+            let loan_terms = match self
+                .store
+                .get_loan_terms(&contract.accepted_contract.offered_contract.id)?
+            {
+                Some(loan_terms) => loan_terms,
+                None => continue,
+            };
+            monitored.push(contract.accepted_contract.get_contract_id());
+            match self.get_loan_risk(&contract, &loan_terms, price, now) {
+                Ok(Some(risk)) => risks.push(risk),
+                Ok(None) => {}
+                Err(e) => error!(
+                    "Error computing the collateral ratio of loan {}: {}",
+                    contract.accepted_contract.get_contract_id_string(),
+                    e
+                ),
+            }
+        }
+
+        for event in self.loan_monitor.update(&monitored, risks) {
+            warn!(
+                "Loan {} is now at {} with a collateral ratio of {}% at price {} (liquidation at {}%)",
+                event.risk.contract_id.to_lower_hex_string(),
+                event.risk.level,
+                event.risk.collateral_ratio,
+                event.risk.price,
+                event.risk.liquidation_ratio
+            );
+        }
+
+        Ok(())
+    }
 
-        Ok(DlcMessage::Sign(signed_msg))
+    /// Returns the risk of the given loan at the given price, or at the latest
+    /// price attested by its oracles if none is given. Returns None if no
+    /// price was attested yet.
+    fn get_loan_risk(
+        &self,
+        contract: &SignedContract,
+        loan_terms: &LoanTerms,
+        price: Option<u64>,
+        timestamp: u64,
+    ) -> Result<Option<LoanRisk>, Error> {
+        let price = match price {
+            Some(price) => price,
+            None => match self.get_loan_oracle_price(contract, timestamp)? {
+                Some(price) => price,
+                None => return Ok(None),
+            },
+        };
+        let offered_contract = &contract.accepted_contract.offered_contract;
+        let start_time = self
+            .store
+            .get_loan_lifecycle(&offered_contract.id)?
+            .and_then(|l| l.get_start_time())
+            .unwrap_or(timestamp);
+        let debt = LoanAccrual {
+            principal: loan_terms.principal as u128,
+            interest_rate_bps: percent_to_bps(loan_terms.interest_rate)?,
+            start_time,
+            duration: loan_terms.duration,
+        }
+        .get_amount_due(timestamp)?
+        .total_due;
+        let collateral_ratio =
+            get_collateral_ratio(offered_contract.total_collateral, price, debt)?;
+
+        Ok(Some(LoanRisk {
+            contract_id: contract.accepted_contract.get_contract_id(),
+            is_lender: offered_contract.is_offer_party,
+            price,
+            collateral_ratio,
+            liquidation_ratio: loan_terms.liquidation_ratio,
+            level: self
+                .loan_monitor
+                .get_config()
+                .get_risk_level(collateral_ratio, loan_terms.liquidation_ratio),
+            timestamp,
+        }))
     }
 
-    async fn on_initiate_accept_loan_message(
+    /// Returns the price attested for the latest matured numerical event of
+    /// the given contract, or None if none of its events was attested yet.
+    fn get_loan_oracle_price(
         &self,
-        initiate_accept_msg: &InitiateAcceptLoanDlc,
-        counter_party: &PublicKey,
-        evm_pk: &str,
-    ) -> Result<(), Error> {
-        println!("on_initiate_accept_loan_message");
-        dotenv().ok();
-        
-        let lender_evm_signer: PrivateKeySigner = evm_pk.parse()
-            .map_err(|_| Error::InvalidParameters("Invalid EVM private key".to_string()))?;
-        
-        let offered_loan_contract = get_contract_in_state!(
-            self,
-            &initiate_accept_msg.contract_id,
-            OfferedLoan,
-            Some(*counter_party)
-        )?;
-        
-        let collateral_amount = offered_loan_contract.offered_contract.total_collateral;
-        let citrea_rpc_url: String = env::var("CITREA_RPC")
-            .map_err(|_| Error::InvalidParameters("CITREA_RPC environment variable not set".to_string()))?;
-        
-        let provider = ProviderBuilder::new()
-            .wallet(lender_evm_signer)
-            .connect(&citrea_rpc_url)
-            .await
-            .unwrap();
-        
-        let lending_contract_address_str = env::var("LENDING_CONTRACT_ADDRESS")
-            .map_err(|_| Error::InvalidParameters("LENDING_CONTRACT_ADDRESS environment variable not set".to_string()))?;
-        
-        let lending_contract_address = primitives::Address::parse_checksummed(&lending_contract_address_str, None).unwrap();
-        
-        let lending_contract = P2PBTCLending::new(lending_contract_address, provider);
-        
-        let borrower_hash = primitives::FixedBytes::from(initiate_accept_msg.borrower_hash);
-        let collateral_sat = primitives::U256::from(collateral_amount.to_sat());
-        
-        println!("lendStable arguments {} {}", borrower_hash, collateral_sat);
-        let lend_stable = lending_contract.lendStable(borrower_hash, collateral_sat);
-        let lend_stable_tx = lend_stable.send()
-            .await
-            .map_err(|_| Error::BlockchainError("Failed to send lendStable transaction".to_string()))?;
-        
-        lend_stable_tx.get_receipt()
-            .await
-            .map_err(|e| Error::BlockchainError(format!("Failed to get transaction receipt: {}", e)))?;
-        
+        contract: &SignedContract,
+        timestamp: u64,
+    ) -> Result<Option<u64>, Error> {
+        let mut latest: Option<(u32, u64)> = None;
+        let contract_infos = &contract.accepted_contract.offered_contract.contract_info;
+        for announcement in contract_infos.iter().flat_map(|x| &x.oracle_announcements) {
+            let maturity = announcement.oracle_event.event_maturity_epoch;
+            if maturity as u64 > timestamp || latest.map_or(false, |(m, _)| m >= maturity) {
+                continue;
+            }
+            let base = match &announcement.oracle_event.event_descriptor {
+                EventDescriptor::DigitDecompositionEvent(d) => d.base,
+                EventDescriptor::EnumEvent(_) => continue,
+            };
+            let attestation = match self
+                .oracles
+                .get(&announcement.oracle_public_key)
+                .and_then(|o| o.get_attestation(&announcement.oracle_event.event_id).ok())
+            {
+                Some(attestation) if attestation.validate(&self.secp, announcement).is_ok() => {
+                    attestation
+                }
+                _ => continue,
+            };
+            latest = Some((maturity, get_attested_value(&attestation, base)?));
+        }
+        Ok(latest.map(|(_, price)| price))
+    }
+
+    fn check_loan_timeouts(&self) -> Result<(), Error> {
+        for contract in self.store.get_contracts()? {
+            let result = match &contract {
+                Contract::OfferedLoan(c) | Contract::OfferedLoanEscrowConfirmed(c) => {
+                    self.check_offered_loan_timeout(c)
+                }
+                Contract::Accepted(c) => self.check_accepted_loan_timeout(c),
+                _ => continue,
+            };
+            if let Err(e) = result {
+                error!(
+                    "Error checking timeout of loan contract {}: {}",
+                    contract.get_id().to_lower_hex_string(),
+                    e
+                )
+            }
+        }
+
         Ok(())
     }
 
-    fn on_sign_message(&self, sign_message: &SignDlc, peer_id: &PublicKey) -> Result<(), Error> {
-        let accepted_contract =
-            get_contract_in_state!(self, &sign_message.contract_id, Accepted, Some(*peer_id))?;
+    /// Returns the state of the given loan if it was held for longer than
+    /// allowed.
+    fn get_timed_out_loan_state(
+        &self,
+        contract: &OfferedLoanContract,
+    ) -> Result<Option<LoanState>, Error> {
+        let contract_id = &contract.offered_contract.id;
+        let lifecycle = match self.store.get_loan_lifecycle(contract_id)? {
+            Some(lifecycle) => lifecycle,
+            None => return Ok(None),
+        };
+        let state = lifecycle.get_state();
+        let now = self.time.unix_time_now();
+        let deadline = match state {
+            LoanState::AwaitingEscrow if self.store.get_escrow_txid(contract_id)?.is_none() => {
+                contract.offer_expiry
+            }
+            LoanState::AwaitingEscrow => {
+                lifecycle.get_state_timestamp() + self.loan_timeouts.escrow_confirmation
+            }
+            LoanState::EscrowConfirmed => {
+                lifecycle.get_state_timestamp() + self.loan_timeouts.stable_lending
+            }
+            LoanState::StableLent => {
+                lifecycle.get_state_timestamp() + self.loan_timeouts.stable_claim
+            }
+            LoanState::StableClaimed => {
+                lifecycle.get_state_timestamp() + self.loan_timeouts.contract_signing
+            }
+            _ => return Ok(None),
+        };
 
-        let (signed_contract, fund_tx) = match crate::contract_updater::verify_signed_contract(
-            &self.secp,
-            &accepted_contract,
-            sign_message,
-            &self.wallet,
+        Ok(if now >= deadline { Some(state) } else { None })
+    }
+
+    fn check_offered_loan_timeout(&self, contract: &OfferedLoanContract) -> Result<(), Error> {
+        let state = match self.get_timed_out_loan_state(contract)? {
+            Some(state) => state,
+            None => return Ok(()),
+        };
+        // Unclaimed stables are reclaimed by the loan check, as the lending
+        // backend is async. Once they are claimed the lender can only wait for
+        // the borrower to accept the loan.
+        if !matches!(
+            state,
+            LoanState::AwaitingEscrow | LoanState::EscrowConfirmed
         ) {
-            Ok(contract) => contract,
-            Err(e) => return self.sign_fail_on_error(accepted_contract, sign_message.clone(), e),
+            return Ok(());
+        }
+
+        self.abort_timed_out_loan(contract, state)
+    }
+
+    /// Marks the given accepted loan as stalled if the lender did not sign it
+    /// in time. The contract is kept, as the lender holds the signatures of the
+    /// borrower and can publish the collateral transaction at any time, and is
+    /// moved forward when the collateral transaction or the signatures of the
+    /// lender are received.
+    fn check_accepted_loan_timeout(
+        &self,
+        accepted_contract: &AcceptedContract,
+    ) -> Result<(), Error> {
+        let offered_contract = &accepted_contract.offered_contract;
+        let lifecycle = match self.store.get_loan_lifecycle(&offered_contract.id)? {
+            Some(lifecycle) => lifecycle,
+            None => return Ok(()),
         };
+        let fund_txid = accepted_contract.dlc_transactions.fund.compute_txid();
+        if self.blockchain.get_transaction(&fund_txid).is_ok() {
+            return self.transition_loan_state(&offered_contract.id, LoanState::CollateralLocked);
+        }
+        if lifecycle.get_state() != LoanState::StableClaimed
+            || self.time.unix_time_now()
+                < lifecycle.get_state_timestamp() + self.loan_timeouts.contract_signing
+        {
+            return Ok(());
+        }
 
-        self.store
-            .update_contract(&Contract::Signed(signed_contract))?;
+        warn!(
+            "Loan contract {} was not signed by the lender in time",
+            accepted_contract.get_contract_id().to_lower_hex_string()
+        );
+        self.transition_loan_state(&offered_contract.id, LoanState::Stalled)
+    }
 
-        // self.blockchain.send_transaction(&fund_tx)?;
+    fn abort_timed_out_loan(
+        &self,
+        contract: &OfferedLoanContract,
+        state: LoanState,
+    ) -> Result<(), Error> {
+        warn!(
+            "Aborting loan contract {} that timed out in the {} state",
+            contract.offered_contract.id.to_lower_hex_string(),
+            state
+        );
+        self.abort_loan_offer(
+            contract,
+            Contract::FailedLoan(FailedLoanContract {
+                offered_loan_contract: contract.clone(),
+                error_message: format!("Loan timed out in the {} state", state),
+            }),
+        )
+    }
+
+    async fn check_unclaimed_stable_loans(&self) -> Result<(), Error> {
+        let contracts = self
+            .store
+            .get_loan_contract_offers()?
+            .into_iter()
+            .filter(|c| c.offered_contract.is_offer_party)
+            .collect::<Vec<_>>();
+        let results = join_all(
+            contracts
+                .iter()
+                .map(|c| self.check_unclaimed_stable_loan(c)),
+        )
+        .await;
+        for (c, result) in contracts.iter().zip(results) {
+            if let Err(e) = result {
+                error!(
+                    "Error reclaiming stables of loan contract {}: {}",
+                    c.offered_contract.id.to_lower_hex_string(),
+                    e
+                )
+            }
+        }
 
         Ok(())
     }
 
-    fn get_oracle_announcements(
+    /// Takes back the stables lent for the given loan offer if the borrower
+    /// did not claim them in time, and aborts the loan. The borrower can then
+    /// only reclaim its escrow.
+    async fn check_unclaimed_stable_loan(
         &self,
-        oracle_inputs: &OracleInput,
-    ) -> Result<Vec<OracleAnnouncement>, Error> {
-        let mut announcements = Vec::new();
-        for pubkey in &oracle_inputs.public_keys {
-            let oracle = self
-                .oracles
-                .get(pubkey)
-                .ok_or_else(|| Error::InvalidParameters("Unknown oracle public key".to_string()))?;
-            announcements.push(oracle.get_announcement(&oracle_inputs.event_id)?.clone());
+        contract: &OfferedLoanContract,
+    ) -> Result<(), Error> {
+        if self.get_timed_out_loan_state(contract)? != Some(LoanState::StableLent) {
+            return Ok(());
+        }
+        let borrower_hash = self
+            .store
+            .get_loan_borrower_hash(&contract.offered_contract.id)?
+            .ok_or_else(|| Error::InvalidState("Borrower hash not found".to_string()))?;
+        let stable_loan = self.lending_backend.get_stable_loan(&borrower_hash).await?;
+        // Claimed stables reveal the preimage, the borrower accepting the loan
+        // moves it forward.
+        if stable_loan.borrower_preimage != 0 {
+            return Ok(());
         }
 
-        Ok(announcements)
+        self.lending_backend.reclaim_stable(&borrower_hash).await?;
+        self.abort_timed_out_loan(contract, LoanState::StableLent)
     }
 
-    fn sign_fail_on_error<R>(
-        &self,
-        accepted_contract: AcceptedContract,
-        sign_message: SignDlc,
-        e: Error,
-    ) -> Result<R, Error> {
-        error!("Error in on_sign {}", e);
-        self.store
-            .update_contract(&Contract::FailedSign(FailedSignContract {
-                accepted_contract,
-                sign_message,
-                error_message: e.to_string(),
-            }))?;
-        Err(e)
+    async fn check_abandoned_loan_escrows(&self) -> Result<(), Error> {
+        // Offers that are still pending get their stables claimed as soon as
+        // they are lent, the other contracts are never claimed by this node.
+        let loan_contracts = self
+            .store
+            .get_loan_contract_offers()?
+            .into_iter()
+            .map(|c| (c, true))
+            .chain(
+                self.store
+                    .get_escrow_confirmed_loan_contracts()?
+                    .into_iter()
+                    .map(|c| (c, true)),
+            )
+            .chain(
+                self.store
+                    .get_failed_loan_contracts()?
+                    .into_iter()
+                    .map(|c| (c.offered_loan_contract, false)),
+            )
+            .chain(
+                self.store
+                    .get_cancelled_loan_contracts()?
+                    .into_iter()
+                    .map(|c| (c, false)),
+            )
+            .filter(|(c, _)| !c.offered_contract.is_offer_party)
+            .collect::<Vec<_>>();
+        let results = join_all(
+            loan_contracts
+                .iter()
+                .map(|(c, may_claim)| self.check_abandoned_loan_escrow(c, *may_claim)),
+        )
+        .await;
+        for ((c, _), result) in loan_contracts.iter().zip(results) {
+            if let Err(e) = result {
+                error!(
+                    "Error checking escrow of loan contract {}: {}",
+                    c.offered_contract.id.to_lower_hex_string(),
+                    e
+                )
+            }
+        }
+
+        Ok(())
     }
 
-    fn accept_fail_on_error<R>(
+    /// Reclaims the escrow of the given loan contract once its relative
+    /// locktime expired, provided the lending contract shows that the stables
+    /// were never claimed. Contracts for which the node may still claim the
+    /// stables are only reclaimed if the stables were never lent either, as
+    /// the lender could otherwise take the escrow with the revealed preimage.
+    async fn check_abandoned_loan_escrow(
         &self,
-        offered_contract: OfferedContract,
-        accept_message: AcceptDlc,
-        e: Error,
-    ) -> Result<R, Error> {
-        error!("Error in on_accept {}", e);
+        contract: &OfferedLoanContract,
+        may_claim: bool,
+    ) -> Result<(), Error> {
+        let offered_contract = &contract.offered_contract;
+        let escrow_txid = match self.store.get_escrow_txid(&offered_contract.id)? {
+            Some(txid) => txid,
+            None => return Ok(()),
+        };
+        let escrow_relative_locktime = contract.get_escrow_relative_locktime()?;
+        if !self.is_relative_locktime_elapsed(&escrow_txid, escrow_relative_locktime)? {
+            return Ok(());
+        }
+
+        let borrower_preimage = self
+            .store
+            .get_loan_preimage(&offered_contract.id)?
+            .ok_or_else(|| Error::InvalidState("Borrower preimage not found".to_string()))?;
+        let borrower_hash =
+            bitcoin::hashes::sha256::Hash::hash(&borrower_preimage.to_be_bytes()).to_byte_array();
+        let stable_loan = self.lending_backend.get_stable_loan(&borrower_hash).await?;
+        let is_lent = stable_loan.collateral_amount > Amount::ZERO;
+        if stable_loan.borrower_preimage != 0 || (is_lent && may_claim) {
+            return Ok(());
+        }
+
+        let signer = self.signer_provider.derive_contract_signer(offered_contract.keys_id)?;
+        let escrow_redeemscript = dlc::make_loan_funding_redeemscript(
+            &signer.get_public_key(&self.secp)?,
+            &offered_contract.offer_params.fund_pubkey,
+            escrow_relative_locktime,
+            &borrower_hash,
+        );
+
+        let escrow_tx = self.blockchain.get_transaction(&escrow_txid)?;
+        let (escrow_vout, escrow_output) = get_escrow_output(&escrow_tx, &escrow_redeemscript)?;
+
+        let mut escrow_reclaim_tx = dlc::create_escrow_reclaim_transaction(
+            OutPoint {
+                txid: escrow_txid,
+                vout: escrow_vout,
+            },
+            escrow_output.value,
+            &escrow_redeemscript,
+            escrow_relative_locktime,
+            self.wallet.get_new_address()?.script_pubkey(),
+            offered_contract.fee_rate_per_vb,
+        )?;
+        dlc::sign_escrow_reclaim_input(
+            &self.secp,
+            &mut escrow_reclaim_tx,
+            0,
+            &signer.get_secret_key()?,
+            &escrow_redeemscript,
+            escrow_output.value,
+        )?;
+
+        self.blockchain.send_transaction(&escrow_reclaim_tx)?;
+
         self.store
-            .update_contract(&Contract::FailedAccept(FailedAcceptContract {
-                offered_contract,
-                accept_message,
-                error_message: e.to_string(),
+            .update_contract(&Contract::EscrowReclaimed(EscrowReclaimedContract {
+                offered_loan_contract: contract.clone(),
+                escrow_reclaim_tx,
             }))?;
-        Err(e)
+        self.transition_loan_state(&offered_contract.id, LoanState::Aborted)
     }
 
-    // TODO: Check if what this do if tx fails, can it be safely retried?
-    fn check_offered_loan_contracts_for_escrow(&self, evm_pk: &str) -> Result<(), Error> {
-        dotenv().ok();
-        let borrower_evm_signer: PrivateKeySigner = evm_pk.parse()
-            .map_err(|_| Error::InvalidParameters("Invalid EVM private key".to_string()))?;
-        for contract in self.store.get_loan_contract_offers()? {
-            let escrow_txid = self.store.get_escrow_txid(&contract.offered_contract.id)?;
-            if escrow_txid.is_none() {
-                continue;
-            }
-            let borrower_preimage = self.store.get_loan_preimage(&contract.offered_contract.id)?.unwrap();
-            let borrower_preimage_bytes = borrower_preimage.to_be_bytes();
-            let borrower_hash = bitcoin::hashes::sha256::Hash::hash(&borrower_preimage_bytes).to_byte_array();
-
-            let citrea_rpc_url = env::var("CITREA_RPC")
-                .map_err(|_| Error::InvalidParameters("CITREA_RPC environment variable not set".to_string()))?;
-            let rt = tokio::runtime::Runtime::new().unwrap();
-            let provider = rt.block_on(async {
-                ProviderBuilder::new()
-                    .wallet(borrower_evm_signer.clone())
-                    .connect(&citrea_rpc_url).await.unwrap()
-            });
+    /// Returns whether an input spending an output of the given transaction
+    /// with the given relative locktime would be final in the next block, as
+    /// defined by BIP68.
+    fn is_relative_locktime_elapsed(
+        &self,
+        txid: &Txid,
+        relative_locktime: Sequence,
+    ) -> Result<bool, Error> {
+        let confirmations = self.blockchain.get_transaction_confirmations(txid)?;
+        if confirmations == 0 {
+            return Ok(false);
+        }
 
-            let lending_contract_address_str = env::var("LENDING_CONTRACT_ADDRESS")
-                .map_err(|_| Error::InvalidParameters("LENDING_CONTRACT_ADDRESS environment variable not set".to_string()))?;
-            let lending_contract_address = primitives::Address::parse_checksummed(&lending_contract_address_str, None).unwrap();
-            let lending_contract = P2PBTCLending::new(
-                lending_contract_address,
-                provider
-            );
-            // Get the return value of `stableLoans(borrowerHash)` which is a three tuple, get the last value
-            let stable_loans = lending_contract.stableLoans(primitives::FixedBytes(borrower_hash));
-            let stable_loans_result = rt.block_on(async {
-                stable_loans.call().await
-            }).map_err(|_| Error::BlockchainError("Failed to call stableLoans".to_string()))?;
-            let collateral_amount = stable_loans_result.collateralAmount;
-            let is_lend_recorded = collateral_amount > Uint::ZERO;
-            if is_lend_recorded {
-                // Call `claimStable` to claim the stables, pass the preimage
-                let claim_stable = lending_contract.claimStable(borrower_preimage);
-                let claim_stable_tx = rt.block_on(async {
-                    claim_stable.send()
-                        .await
-                        .map_err(|_| Error::BlockchainError("Failed to send claimStable transaction".to_string()))
-                })?;
-                rt.block_on(async {
-                    claim_stable_tx.get_receipt().await
-                        .map_err(|e| Error::BlockchainError(format!("Failed to get transaction receipt: {}", e)))
-                })?;
+        match relative_locktime.to_relative_lock_time() {
+            Some(relative::LockTime::Blocks(height)) => {
+                Ok(confirmations >= u32::from(height.value()))
             }
-            let confirmations = self
-                .blockchain
-                .get_transaction_confirmations(&escrow_txid.unwrap())?;
-            if (confirmations >= NB_CONFIRMATIONS) && is_lend_recorded {
-                self.store
-                    .update_contract(&Contract::OfferedLoanEscrowConfirmed(contract.clone()))?;
+            Some(relative::LockTime::Time(time)) => {
+                let tip_height = self.blockchain.get_blockchain_height()?;
+                let confirmation_height = (tip_height + 1).saturating_sub(confirmations as u64);
+                // Time based locks start at the median time past of the block
+                // preceding the one including the transaction.
+                let start = self.get_median_time_past(confirmation_height.saturating_sub(1))?;
+                let now = self.get_median_time_past(tip_height)?;
+                Ok(now >= start + u64::from(time.value()) * 512)
             }
+            None => Err(Error::InvalidParameters(
+                "Relative locktime is disabled".to_string(),
+            )),
         }
-        Ok(())
+    }
+
+    fn get_median_time_past(&self, height: u64) -> Result<u64, Error> {
+        let mut times = (height.saturating_sub(10)..=height)
+            .map(|h| {
+                self.blockchain
+                    .get_block_at_height(h)
+                    .map(|b| u64::from(b.header.time))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        times.sort_unstable();
+        Ok(times[times.len() / 2])
     }
 
     fn check_signed_contract(&self, contract: &SignedContract) -> Result<(), Error> {
-        let confirmations = self.blockchain.get_transaction_confirmations(
-            &contract
-                .accepted_contract
-                .dlc_transactions
-                .fund
-                .compute_txid(),
-        )?;
+        let fund_tx = &contract.accepted_contract.dlc_transactions.fund;
+        let confirmations = self
+            .blockchain
+            .get_transaction_confirmations(&fund_tx.compute_txid())?;
+        if confirmations == 0 && self.is_lender_of_loan(contract)? {
+            // The lender only releases the collateral transaction once the
+            // contract is signed, make sure it was not lost on the way.
+            self.blockchain.send_transaction(fund_tx)?;
+        }
         if confirmations >= NB_CONFIRMATIONS {
             self.store
                 .update_contract(&Contract::Confirmed(contract.clone()))?;
+            self.transition_loan_state(
+                &contract.accepted_contract.offered_contract.id,
+                LoanState::Active,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn is_lender_of_loan(&self, contract: &SignedContract) -> Result<bool, Error> {
+        let offered_contract = &contract.accepted_contract.offered_contract;
+        Ok(offered_contract.is_offer_party
+            && self.store.get_escrow_txid(&offered_contract.id)?.is_some())
+    }
+
+    fn check_loan_renewal(&self, renewal: &LoanRenewal) -> Result<(), Error> {
+        let renewed_contract = renewal
+            .signed_contract
+            .as_ref()
+            .ok_or_else(|| Error::InvalidState("Renewed contract not found".to_string()))?;
+        let renewal_tx = &renewed_contract.accepted_contract.dlc_transactions.fund;
+        let confirmations = self
+            .blockchain
+            .get_transaction_confirmations(&renewal_tx.compute_txid())?;
+        if confirmations == 0 {
+            // Only the lender has the fully signed renewal transaction.
+            if !renewal.is_borrower {
+                self.blockchain.send_transaction(renewal_tx)?;
+            }
+            return Ok(());
+        }
+
+        match self.store.get_contract(&renewal.contract_id)? {
+            Some(Contract::Confirmed(_)) => {}
+            _ => {
+                return Err(Error::InvalidState(
+                    "Renewed loan contract is no longer confirmed".to_string(),
+                ))
+            }
+        }
+
+        // The renewed contract keeps the temporary id of the loan, so that its
+        // terms and lifecycle carry over.
+        let temporary_id = renewed_contract.accepted_contract.offered_contract.id;
+        let mut loan_terms = self
+            .store
+            .get_loan_terms(&temporary_id)?
+            .ok_or_else(|| Error::InvalidState("Loan terms not found".to_string()))?;
+        loan_terms.principal -= renewal.repaid_principal;
+
+        self.store
+            .update_contract(&Contract::Signed(renewed_contract.clone()))?;
+        self.store.delete_contract(&renewal.contract_id)?;
+        self.store.set_loan_terms(&temporary_id, &loan_terms)?;
+
+        let mut renewal = renewal.clone();
+        renewal.state = LoanRenewalState::Completed;
+        self.store.upsert_loan_renewal(&renewal)
+    }
+
+    fn check_loan_renewals(&self) -> Result<(), Error> {
+        for renewal in self.store.get_loan_renewals()? {
+            if renewal.state != LoanRenewalState::Confirmed {
+                continue;
+            }
+            if let Err(e) = self.check_loan_renewal(&renewal) {
+                error!(
+                    "Error checking renewal of loan {}: {}",
+                    renewal.contract_id.to_lower_hex_string(),
+                    e
+                )
+            }
         }
+
         Ok(())
     }
 
@@ -967,25 +3113,33 @@ where
         Ok(())
     }
 
+    /// Returns the contract info of the earliest matured event whose
+    /// attestations can close the contract. Events attested with an outcome
+    /// that has no CET, like the liquidation events of a loan whose price is
+    /// above the liquidation price, are skipped.
     fn get_closable_contract_info<'a>(
         &'a self,
         contract: &'a SignedContract,
     ) -> ClosableContractInfo<'a> {
         let contract_infos = &contract.accepted_contract.offered_contract.contract_info;
         let adaptor_infos = &contract.accepted_contract.adaptor_infos;
-        for (contract_info, adaptor_info) in contract_infos.iter().zip(adaptor_infos.iter()) {
-        let matured: Vec<_> = contract_info
-            .oracle_announcements
-            .iter()
-            .filter(|x| {
-                let maturity_epoch = x.oracle_event.event_maturity_epoch as u64;
-                let current_time = self.time.unix_time_now();
-                println!("Maturity epoch: {}, Current time: {}, Matured: {}", 
-                        maturity_epoch, current_time, maturity_epoch <= current_time);
-                maturity_epoch <= current_time
-            })
-            .enumerate()
-            .collect();
+        let mut infos: Vec<_> = contract_infos.iter().zip(adaptor_infos.iter()).collect();
+        infos.sort_by_key(|(contract_info, _)| {
+            contract_info
+                .oracle_announcements
+                .iter()
+                .map(|x| x.oracle_event.event_maturity_epoch)
+                .min()
+        });
+        for (contract_info, adaptor_info) in infos {
+            let matured: Vec<_> = contract_info
+                .oracle_announcements
+                .iter()
+                .filter(|x| {
+                    (x.oracle_event.event_maturity_epoch as u64) <= self.time.unix_time_now()
+                })
+                .enumerate()
+                .collect();
             if matured.len() >= contract_info.threshold {
                 let attestations: Vec<_> = matured
                     .iter()
@@ -1007,7 +3161,14 @@ where
                         Some((*i, attestation))
                     })
                     .collect();
-                if attestations.len() >= contract_info.threshold {
+                if attestations.len() >= contract_info.threshold
+                    && crate::utils::get_range_info_and_oracle_sigs(
+                        contract_info,
+                        adaptor_info,
+                        &attestations,
+                    )
+                    .is_ok()
+                {
                     return Some((contract_info, adaptor_info, attestations));
                 }
             }
@@ -1036,6 +3197,7 @@ where
             ) {
                 Ok(closed_contract) => {
                     self.store.update_contract(&closed_contract)?;
+                    self.transition_loan_state(&offer.id, get_closed_loan_state(contract_info))?;
                     return Ok(());
                 }
                 Err(e) => {
@@ -1108,6 +3270,7 @@ where
             ) {
                 Ok(closed_contract) => {
                     self.store.update_contract(&closed_contract)?;
+                    self.transition_loan_state(&offer.id, get_closed_loan_state(contract_info))?;
                     Ok(closed_contract)
                 }
                 Err(e) => {
@@ -1171,6 +3334,45 @@ where
         Ok(())
     }
 
+    fn check_prerepaid_contracts(&self) -> Result<(), Error> {
+        for c in self.store.get_prerepaid_contracts()? {
+            if let Err(e) = self.check_prerepaid_contract(&c) {
+                error!(
+                    "Error checking pre-repaid contract {}: {}",
+                    c.signed_contract.accepted_contract.get_contract_id_string(),
+                    e
+                )
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_prerepaid_contract(&self, contract: &RepaidContract) -> Result<(), Error> {
+        let collateral_sweep_tx = contract.collateral_sweep_tx.as_ref().ok_or_else(|| {
+            Error::InvalidState("Pre-repaid contract without collateral sweep".to_string())
+        })?;
+        let confirmations = self
+            .blockchain
+            .get_transaction_confirmations(&collateral_sweep_tx.compute_txid())?;
+        if confirmations >= NB_CONFIRMATIONS {
+            self.store
+                .update_contract(&Contract::Repaid(contract.clone()))?;
+        } else if confirmations == 0
+            && !contract
+                .signed_contract
+                .accepted_contract
+                .offered_contract
+                .is_offer_party
+        {
+            // Only the borrower holds the signed sweep, the lender just
+            // watches for it.
+            self.blockchain.send_transaction(collateral_sweep_tx)?;
+        }
+
+        Ok(())
+    }
+
     fn close_contract(
         &self,
         contract: &SignedContract,
@@ -1244,6 +3446,7 @@ where
 
             self.store
                 .update_contract(&Contract::Refunded(contract.clone()))?;
+            self.transition_loan_state(&accepted_contract.offered_contract.id, LoanState::Expired)?;
         }
 
         Ok(())
@@ -1280,6 +3483,10 @@ where
         {
             let refunded = Contract::Refunded(contract.clone());
             self.store.update_contract(&refunded)?;
+            self.transition_loan_state(
+                &contract.accepted_contract.offered_contract.id,
+                LoanState::Expired,
+            )?;
             return Ok(refunded);
         }
 
@@ -1301,13 +3508,23 @@ where
         };
 
         self.store.update_contract(&contract)?;
+        self.transition_loan_state(&contract.get_temporary_id(), LoanState::Expired)?;
 
         Ok(contract)
     }
 }
 
-impl<W: Deref, SP: Deref, B: Deref, S: Deref, O: Deref, T: Deref, F: Deref, X: ContractSigner>
-    Manager<W, Arc<CachedContractSignerProvider<SP, X>>, B, S, O, T, F, X>
+impl<
+        W: Deref,
+        SP: Deref,
+        B: Deref,
+        S: Deref,
+        O: Deref,
+        T: Deref,
+        F: Deref,
+        L: Deref,
+        X: ContractSigner,
+    > Manager<W, Arc<CachedContractSignerProvider<SP, X>>, B, S, O, T, F, L, X>
 where
     W::Target: Wallet,
     SP::Target: ContractSignerProvider<Signer = X>,
@@ -1316,6 +3533,7 @@ where
     O::Target: Oracle,
     T::Target: Time,
     F::Target: FeeEstimator,
+    L::Target: LendingBackend,
 {
     /// Create a new channel offer and return the [`dlc_messages::channel::OfferChannel`]
     /// message to be sent to the `counter_party`.
@@ -3002,6 +5220,52 @@ where
     }
 }
 
+/// Returns the state of a loan closed through the given contract info, which
+/// is liquidated when it is one of its intermediate liquidation events.
+fn get_closed_loan_state(contract_info: &ContractInfo) -> LoanState {
+    match &contract_info.contract_descriptor {
+        ContractDescriptor::Numerical(n) if n.is_truncated() => LoanState::Liquidated,
+        _ => LoanState::Expired,
+    }
+}
+
+/// Returns the unsigned transaction sweeping the collateral of the given loan
+/// contract to the payout script of the borrower through the lender hashlock
+/// branch, along with the collateral redeem script. The transaction only
+/// depends on the contract so both parties compute the same txid. Returns an
+/// error if the preimage does not unlock the collateral.
+fn get_collateral_sweep_transaction(
+    contract: &SignedContract,
+    lender_preimage: u128,
+) -> Result<(Transaction, ScriptBuf), Error> {
+    let accepted_contract = &contract.accepted_contract;
+    let offered_contract = &accepted_contract.offered_contract;
+    let dlc_transactions = &accepted_contract.dlc_transactions;
+
+    let lender_hash =
+        bitcoin::hashes::sha256::Hash::hash(&lender_preimage.to_be_bytes()).to_byte_array();
+    let collateral_redeemscript = dlc::make_collateral_redeemscript(
+        &accepted_contract.accept_params.fund_pubkey,
+        &offered_contract.offer_params.fund_pubkey,
+        &lender_hash,
+    );
+    if collateral_redeemscript != dlc_transactions.funding_script_pubkey {
+        return Err(Error::InvalidParameters(
+            "Lender preimage does not unlock the collateral".to_string(),
+        ));
+    }
+
+    let collateral_sweep_tx = dlc::create_collateral_sweep_transaction(
+        dlc_transactions.get_fund_outpoint(),
+        dlc_transactions.get_fund_output().value,
+        &collateral_redeemscript,
+        accepted_contract.accept_params.payout_script_pubkey.clone(),
+        offered_contract.fee_rate_per_vb,
+    )?;
+
+    Ok((collateral_sweep_tx, collateral_redeemscript))
+}
+
 #[cfg(test)]
 mod test {
     use bitcoin::Amount;
@@ -3010,6 +5274,7 @@ mod test {
         dlc_manager::{manager::Manager, CachedContractSignerProvider, Oracle, SimpleSigner},
         memory_storage_provider::MemoryStorage,
         mock_blockchain::MockBlockchain,
+        mock_lending::{MockLending, MockLendingContract},
         mock_oracle_provider::MockOracle,
         mock_time::MockTime,
         mock_wallet::MockWallet,
@@ -3025,6 +5290,7 @@ mod test {
         Rc<MockOracle>,
         Rc<MockTime>,
         Rc<MockBlockchain>,
+        Rc<MockLending>,
         SimpleSigner,
     >;
 
@@ -3044,6 +5310,10 @@ mod test {
             .map(|x| (x.get_public_key(), Rc::new(x)))
             .collect();
         let time = Rc::new(MockTime {});
+        let lending = Rc::new(MockLending::new(
+            Arc::new(MockLendingContract::new(1, 1)),
+            "lender",
+        ));
 
         mocks::mock_time::set_time(0);
 
@@ -3055,6 +5325,7 @@ mod test {
             oracles,
             time,
             blockchain,
+            lending,
         )
         .unwrap()
     }
@@ -3073,12 +5344,10 @@ mod test {
 
         let manager = get_manager();
 
-        manager
-            .on_dlc_message(&offer_message, pubkey())
+        futures::executor::block_on(manager.on_dlc_message(&offer_message, pubkey()))
             .expect("To accept the first offer message");
 
-        manager
-            .on_dlc_message(&offer_message, pubkey())
+        futures::executor::block_on(manager.on_dlc_message(&offer_message, pubkey()))
             .expect_err("To reject the second offer message");
     }
 
@@ -3090,12 +5359,10 @@ mod test {
 
         let manager = get_manager();
 
-        manager
-            .on_dlc_message(&offer_message, pubkey())
+        futures::executor::block_on(manager.on_dlc_message(&offer_message, pubkey()))
             .expect("To accept the first offer message");
 
-        manager
-            .on_dlc_message(&offer_message, pubkey())
+        futures::executor::block_on(manager.on_dlc_message(&offer_message, pubkey()))
             .expect_err("To reject the second offer message");
     }
 }
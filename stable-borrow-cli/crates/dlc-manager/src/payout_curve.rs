@@ -1,10 +1,13 @@
 //! #PayoutFunction
 
+use std::convert::TryFrom;
 use std::ops::Deref;
 
 use crate::error::Error;
+use crate::loan_math::{percent_to_bps, LoanAccrual};
 use bitcoin::Amount;
 use dlc::{Payout, RangePayout};
+use dlc_trie::OracleNumericInfo;
 #[cfg(feature = "use-serde")]
 use serde::{Deserialize, Serialize};
 
@@ -97,6 +100,15 @@ impl PayoutFunction {
         }
     }
 
+    /// Returns the last outcome covered by the function.
+    pub fn get_last_outcome(&self) -> u64 {
+        self.payout_function_pieces
+            .last()
+            .expect("to have at least one piece")
+            .get_last_point()
+            .event_outcome
+    }
+
     /// Generate the range payouts from the function.
     pub fn to_range_payouts(
         &self,
@@ -486,6 +498,167 @@ impl Evaluable for HyperbolaPayoutCurvePiece {
     }
 }
 
+const SATS_PER_BTC: f64 = 100_000_000.0;
+
+/// Builds the payout function of a loan from its terms. For each attested
+/// price, the lender (offer party) gets the bitcoin value of the debt, capped
+/// at the total collateral, and the borrower gets the remainder. Principal and
+/// attested prices must be expressed in the same unit.
+#[derive(Clone, Debug)]
+pub struct LoanPayoutCurveBuilder {
+    /// The principal of the loan.
+    pub principal: u64,
+    /// The yearly interest rate in percent.
+    pub interest_rate: u64,
+    /// The duration of the loan in seconds.
+    pub duration: u64,
+    /// The liquidation ratio in percent.
+    pub liquidation_ratio: u64,
+    /// The total collateral locked in the contract.
+    pub total_collateral: Amount,
+    /// Information about base and number of digits of the oracles.
+    pub oracle_numeric_info: OracleNumericInfo,
+}
+
+impl LoanPayoutCurveBuilder {
+    /// Returns the debt owed to the lender at the end of the loan, that is the
+    /// principal plus the interest accrued over its duration.
+    pub fn get_debt(&self) -> Result<u64, Error> {
+        let amount_due = LoanAccrual {
+            principal: self.principal as u128,
+            interest_rate_bps: percent_to_bps(self.interest_rate)?,
+            start_time: 0,
+            duration: self.duration,
+        }
+        .get_amount_due_at_maturity()?;
+        u64::try_from(amount_due.total_due).map_err(|_| Error::OutOfRange)
+    }
+
+    /// Returns the price under which the collateral value drops below the
+    /// liquidation ratio of the debt.
+    pub fn get_liquidation_price(&self) -> Result<u64, Error> {
+        Ok((self.get_debt()? as f64 * SATS_PER_BTC * self.liquidation_ratio as f64
+            / (self.total_collateral.to_sat() as f64 * 100.0))
+            .ceil() as u64)
+    }
+
+    /// Returns the maximum outcome that the oracles can attest.
+    pub fn get_max_outcome(&self) -> u64 {
+        (self.oracle_numeric_info.base as u64)
+            .checked_pow(self.oracle_numeric_info.get_min_nb_digits() as u32)
+            .map(|x| x - 1)
+            .unwrap_or(u64::MAX)
+    }
+
+    /// Returns the highest outcome for which the loan is liquidated, which is
+    /// the one right below the liquidation price.
+    pub fn get_liquidation_outcome(&self) -> Result<u64, Error> {
+        let liquidation_price = self.get_liquidation_price()?;
+        if liquidation_price <= 1 {
+            return Err(Error::InvalidParameters(
+                "Liquidation price is too low to liquidate the loan.".to_string(),
+            ));
+        }
+        if liquidation_price > self.get_max_outcome() {
+            return Err(Error::InvalidParameters(
+                "Liquidation price is above the maximum outcome.".to_string(),
+            ));
+        }
+        Ok(liquidation_price - 1)
+    }
+
+    /// Creates the payout function of the loan.
+    pub fn build(&self) -> Result<PayoutFunction, Error> {
+        self.validate()?;
+        self.build_until(self.get_max_outcome())
+    }
+
+    /// Creates the payout function of an intermediate liquidation event of
+    /// the loan. It only covers the outcomes below the liquidation price, so
+    /// that the contract cannot be closed on this event for other outcomes.
+    pub fn build_liquidation(&self) -> Result<PayoutFunction, Error> {
+        self.validate()?;
+        self.build_until(self.get_liquidation_outcome()?)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        if self.principal == 0 {
+            return Err(Error::InvalidParameters(
+                "Loan principal must be greater than zero.".to_string(),
+            ));
+        }
+        if self.liquidation_ratio <= 100 {
+            return Err(Error::InvalidParameters(
+                "Liquidation ratio must be greater than 100.".to_string(),
+            ));
+        }
+        if self.total_collateral == Amount::ZERO {
+            return Err(Error::InvalidParameters(
+                "Loan collateral must be greater than zero.".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn build_until(&self, last_outcome: u64) -> Result<PayoutFunction, Error> {
+        // The lender payout is debt / price, which is worth the whole
+        // collateral up to this price.
+        let debt_sats = self.get_debt()? as f64 * SATS_PER_BTC;
+        let cap_outcome = (debt_sats / self.total_collateral.to_sat() as f64).ceil() as u64;
+        if cap_outcome >= last_outcome {
+            return PayoutFunction::new(vec![PayoutFunctionPiece::PolynomialPayoutCurvePiece(
+                PolynomialPayoutCurvePiece::new(vec![
+                    PayoutPoint {
+                        event_outcome: 0,
+                        outcome_payout: self.total_collateral,
+                        extra_precision: 0,
+                    },
+                    PayoutPoint {
+                        event_outcome: last_outcome,
+                        outcome_payout: self.total_collateral,
+                        extra_precision: 0,
+                    },
+                ])?,
+            )]);
+        }
+
+        let cap_point = PayoutPoint {
+            event_outcome: cap_outcome,
+            outcome_payout: self.total_collateral,
+            extra_precision: 0,
+        };
+        PayoutFunction::new(vec![
+            PayoutFunctionPiece::PolynomialPayoutCurvePiece(PolynomialPayoutCurvePiece::new(
+                vec![
+                    PayoutPoint {
+                        event_outcome: 0,
+                        outcome_payout: self.total_collateral,
+                        extra_precision: 0,
+                    },
+                    cap_point.clone(),
+                ],
+            )?),
+            PayoutFunctionPiece::HyperbolaPayoutCurvePiece(HyperbolaPayoutCurvePiece::new(
+                cap_point,
+                PayoutPoint {
+                    event_outcome: last_outcome,
+                    outcome_payout: Amount::from_sat(
+                        (debt_sats / last_outcome as f64).round() as u64
+                    ),
+                    extra_precision: 0,
+                },
+                true,
+                0.0,
+                0.0,
+                1.0,
+                0.0,
+                0.0,
+                debt_sats,
+            )?),
+        ])
+    }
+}
+
 /// Provides information on if and how to round the payouts of a payout function
 /// to reduce the number of adaptor signatures required. A `rounding_mod` value
 /// of 1 indicates that no rounding is performed.
@@ -574,6 +747,7 @@ impl RoundingIntervals {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::loan_math::SECONDS_PER_YEAR;
     use secp256k1_zkp::rand::{thread_rng, RngCore};
 
     #[test]
@@ -1294,4 +1468,145 @@ mod test {
         assert_eq!(polynomial.evaluate(0), 10.0);
         assert_eq!(polynomial.evaluate(1), 8.0);
     }
+
+    fn get_loan_payout_curve_builder(nb_digits: usize) -> LoanPayoutCurveBuilder {
+        LoanPayoutCurveBuilder {
+            principal: 40000,
+            interest_rate: 10,
+            duration: SECONDS_PER_YEAR,
+            liquidation_ratio: 125,
+            total_collateral: Amount::ONE_BTC,
+            oracle_numeric_info: OracleNumericInfo {
+                base: 2,
+                nb_digits: vec![nb_digits],
+            },
+        }
+    }
+
+    #[test]
+    fn loan_payout_curve_test() {
+        let builder = get_loan_payout_curve_builder(20);
+        assert_eq!(44000, builder.get_debt().unwrap());
+        assert_eq!(55000, builder.get_liquidation_price().unwrap());
+
+        let payout_function = builder.build().expect("to be able to build the payout function");
+        payout_function
+            .validate(builder.get_max_outcome())
+            .expect("payout function to be valid");
+        let range_payouts = payout_function
+            .to_range_payouts(
+                Amount::ONE_BTC,
+                &RoundingIntervals {
+                    intervals: vec![RoundingInterval {
+                        begin_interval: 0,
+                        rounding_mod: 1,
+                    }],
+                },
+            )
+            .expect("to be able to compute the range payouts");
+        let get_payout = |outcome: usize| {
+            range_payouts
+                .iter()
+                .find(|r| r.start <= outcome && outcome < r.start + r.count)
+                .expect("outcome to be covered")
+                .payout
+                .clone()
+        };
+
+        assert_eq!(
+            Payout {
+                offer: Amount::ONE_BTC,
+                accept: Amount::ZERO
+            },
+            get_payout(30000)
+        );
+        assert_eq!(
+            Payout {
+                offer: Amount::ONE_BTC,
+                accept: Amount::ZERO
+            },
+            get_payout(44000)
+        );
+        assert_eq!(
+            Payout {
+                offer: Amount::from_sat(50_000_000),
+                accept: Amount::from_sat(50_000_000)
+            },
+            get_payout(88000)
+        );
+        assert_eq!(
+            Payout {
+                offer: Amount::from_sat(4_196_175),
+                accept: Amount::from_sat(95_803_825)
+            },
+            get_payout(1048575)
+        );
+    }
+
+    #[test]
+    fn loan_payout_curve_debt_above_max_outcome_test() {
+        let builder = get_loan_payout_curve_builder(10);
+        let payout_function = builder.build().expect("to be able to build the payout function");
+        assert_eq!(1, payout_function.payout_function_pieces.len());
+        payout_function
+            .validate(builder.get_max_outcome())
+            .expect("payout function to be valid");
+    }
+
+    #[test]
+    fn loan_liquidation_payout_curve_test() {
+        let builder = get_loan_payout_curve_builder(20);
+        assert_eq!(54999, builder.get_liquidation_outcome().unwrap());
+
+        let payout_function = builder
+            .build_liquidation()
+            .expect("to be able to build the liquidation payout function");
+        assert_eq!(54999, payout_function.get_last_outcome());
+        payout_function
+            .validate(builder.get_max_outcome())
+            .expect_err("liquidation payout function not to cover all outcomes");
+        payout_function
+            .validate(54999)
+            .expect("liquidation payout function to be valid");
+
+        let range_payouts = payout_function
+            .to_range_payouts(
+                Amount::ONE_BTC,
+                &RoundingIntervals {
+                    intervals: vec![RoundingInterval {
+                        begin_interval: 0,
+                        rounding_mod: 1,
+                    }],
+                },
+            )
+            .expect("to be able to compute the range payouts");
+        let last = range_payouts.last().expect("to have range payouts");
+        assert_eq!(55000, last.start + last.count);
+        assert_eq!(
+            Payout {
+                offer: Amount::ONE_BTC,
+                accept: Amount::ZERO
+            },
+            range_payouts[0].payout
+        );
+    }
+
+    #[test]
+    fn loan_liquidation_price_above_max_outcome_test() {
+        let builder = get_loan_payout_curve_builder(10);
+        builder
+            .build_liquidation()
+            .expect_err("liquidation price above the maximum outcome to be invalid");
+    }
+
+    #[test]
+    fn loan_payout_curve_invalid_liquidation_ratio_test() {
+        let builder = LoanPayoutCurveBuilder {
+            liquidation_ratio: 100,
+            ..get_loan_payout_curve_builder(20)
+        };
+        builder
+            .build()
+            .expect_err("liquidation ratio of 100 to be invalid");
+    }
 }
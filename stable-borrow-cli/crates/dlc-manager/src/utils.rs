@@ -49,6 +49,21 @@ macro_rules! get_object_in_state {
 
 pub(crate) use get_object_in_state;
 
+/// Approximate weight of the escrow transaction with a single P2WPKH input,
+/// of the collateral transaction and of a CET of a loan, used to select enough
+/// borrower UTXOs before the exact fees are known. With P2WPKH change and
+/// payout outputs, the escrow transaction weighs 609 (base 42, input 164 + 107,
+/// P2WSH output 172, change output 124), the collateral transaction 667 (base
+/// 42, input 164, witness 289 with the 122 bytes escrow script, P2WSH output
+/// 172) and a CET 726 (base 42, input 164, witness 272 with the 121 bytes
+/// collateral script, two payout outputs 248). The sum is rounded up to leave
+/// room for larger input scripts.
+const LOAN_TXS_APPROXIMATE_WEIGHT: usize = 2100;
+
+/// Approximate weight of a loan collateral renewal transaction with a single
+/// P2WPKH input from the borrower.
+const LOAN_RENEWAL_TX_APPROXIMATE_WEIGHT: usize = 1100;
+
 #[cfg(not(feature = "fuzztarget"))]
 pub(crate) fn get_new_serial_id() -> u64 {
     thread_rng().next_u64()
@@ -97,6 +112,88 @@ pub(crate) fn get_party_params<W: Deref, B: Deref, X: ContractSigner, C: Signing
     signer: &X,
     blockchain: &B,
 ) -> Result<(PartyParams, Vec<FundingInput>), Error>
+where
+    W::Target: Wallet,
+    B::Target: Blockchain,
+{
+    // Add base cost of fund tx + CET / 2 and a CET output to the collateral.
+    let appr_required_amount =
+        own_collateral + get_half_common_fee(fee_rate)? + dlc::util::weight_to_fee(124, fee_rate)?;
+    get_party_params_for_amount(
+        secp,
+        own_collateral,
+        appr_required_amount,
+        fee_rate,
+        wallet,
+        signer,
+        blockchain,
+    )
+}
+
+/// Same as [`get_party_params`] but for the borrower of a loan, who pays for
+/// the whole escrow, collateral and CET transactions.
+pub(crate) fn get_borrower_party_params<W: Deref, B: Deref, X: ContractSigner, C: Signing>(
+    secp: &Secp256k1<C>,
+    collateral: Amount,
+    fee_rate: u64,
+    wallet: &W,
+    signer: &X,
+    blockchain: &B,
+) -> Result<(PartyParams, Vec<FundingInput>), Error>
+where
+    W::Target: Wallet,
+    B::Target: Blockchain,
+{
+    let appr_required_amount =
+        collateral + dlc::util::weight_to_fee(LOAN_TXS_APPROXIMATE_WEIGHT, fee_rate)?;
+    get_party_params_for_amount(
+        secp,
+        collateral,
+        appr_required_amount,
+        fee_rate,
+        wallet,
+        signer,
+        blockchain,
+    )
+}
+
+/// Same as [`get_borrower_party_params`] but for the renewal of the collateral
+/// of a loan, for which the borrower funds the additional collateral and the
+/// renewal transaction fee.
+pub(crate) fn get_loan_renewal_party_params<W: Deref, B: Deref, X: ContractSigner, C: Signing>(
+    secp: &Secp256k1<C>,
+    additional_collateral: Amount,
+    fee_rate: u64,
+    wallet: &W,
+    signer: &X,
+    blockchain: &B,
+) -> Result<(PartyParams, Vec<FundingInput>), Error>
+where
+    W::Target: Wallet,
+    B::Target: Blockchain,
+{
+    let appr_required_amount = additional_collateral
+        + dlc::util::weight_to_fee(LOAN_RENEWAL_TX_APPROXIMATE_WEIGHT, fee_rate)?;
+    get_party_params_for_amount(
+        secp,
+        additional_collateral,
+        appr_required_amount,
+        fee_rate,
+        wallet,
+        signer,
+        blockchain,
+    )
+}
+
+fn get_party_params_for_amount<W: Deref, B: Deref, X: ContractSigner, C: Signing>(
+    secp: &Secp256k1<C>,
+    own_collateral: Amount,
+    appr_required_amount: Amount,
+    fee_rate: u64,
+    wallet: &W,
+    signer: &X,
+    blockchain: &B,
+) -> Result<(PartyParams, Vec<FundingInput>), Error>
 where
     W::Target: Wallet,
     B::Target: Blockchain,
@@ -110,9 +207,6 @@ where
     let change_spk = change_addr.script_pubkey();
     let change_serial_id = get_new_serial_id();
 
-    // Add base cost of fund tx + CET / 2 and a CET output to the collateral.
-    let appr_required_amount =
-        own_collateral + get_half_common_fee(fee_rate)? + dlc::util::weight_to_fee(124, fee_rate)?;
     let utxos = wallet.get_utxos_for_amount(appr_required_amount, fee_rate, true)?;
 
     let mut funding_inputs: Vec<FundingInput> = Vec::new();
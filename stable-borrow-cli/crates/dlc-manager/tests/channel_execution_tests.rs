@@ -15,6 +15,7 @@ use dlc_messages::Message;
 use electrs_blockchain_provider::ElectrsBlockchainProvider;
 use lightning::util::ser::Writeable;
 use mocks::memory_storage_provider::MemoryStorage;
+use mocks::mock_lending::{MockLending, MockLendingContract};
 use mocks::mock_oracle_provider::MockOracle;
 use mocks::mock_time::MockTime;
 use secp256k1_zkp::rand::{thread_rng, RngCore};
@@ -52,6 +53,7 @@ type DlcParty = Arc<
             Arc<MockOracle>,
             Arc<MockTime>,
             Arc<ElectrsBlockchainProvider>,
+            Arc<MockLending>,
             SimpleSigner,
         >,
     >,
@@ -304,6 +306,7 @@ fn channel_execution_test(test_params: TestParams, path: TestPath) {
     let alice_store = Arc::new(mocks::memory_storage_provider::MemoryStorage::new());
     let bob_store = Arc::new(mocks::memory_storage_provider::MemoryStorage::new());
     let mock_time = Arc::new(mocks::mock_time::MockTime {});
+    let lending_contract = Arc::new(MockLendingContract::new(1, 1));
     mocks::mock_time::set_time((EVENT_MATURITY as u64) - 1);
 
     let electrs = Arc::new(ElectrsBlockchainProvider::new(
@@ -385,6 +388,7 @@ fn channel_execution_test(test_params: TestParams, path: TestPath) {
             alice_oracles,
             Arc::clone(&mock_time),
             Arc::clone(&electrs),
+            Arc::new(MockLending::new(Arc::clone(&lending_contract), "alice")),
         )
         .unwrap(),
     ));
@@ -401,6 +405,7 @@ fn channel_execution_test(test_params: TestParams, path: TestPath) {
             bob_oracles,
             Arc::clone(&mock_time),
             Arc::clone(&electrs),
+            Arc::new(MockLending::new(Arc::clone(&lending_contract), "bob")),
         )
         .unwrap(),
     ));
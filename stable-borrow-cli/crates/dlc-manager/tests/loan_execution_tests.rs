@@ -0,0 +1,1123 @@
+//! Runs the loan flow between a lender and a borrower manager against the
+//! in memory mocks, without requiring a bitcoin node or an EVM chain.
+
+extern crate dlc_manager;
+
+use bitcoin::consensus::encode::serialize;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::{Amount, OutPoint};
+use dlc_manager::contract::contract_input::{
+    LoanContractInput, LoanContractInputInfo, OracleInput,
+};
+use dlc_manager::contract::loan_state::LoanState;
+use dlc_manager::contract::Contract;
+use dlc_manager::loan_math::{calculate_interest, percent_to_bps};
+use dlc_manager::manager::{Manager, MAX_PENDING_LOAN_REQUESTS_PER_PEER, PEER_TIMEOUT};
+use dlc_manager::payout_curve::{RoundingInterval, RoundingIntervals};
+use dlc_manager::{
+    Blockchain, CachedContractSignerProvider, ContractId, LendingBackend, Oracle, SimpleSigner,
+    StableLoan, Storage, Wallet,
+};
+use dlc_messages::oracle_msgs::{DigitDecompositionEventDescriptor, EventDescriptor};
+use dlc_messages::{
+    AcceptLoanDlc, FundingInput, InitiateAcceptLoanDlc, LoanOfferReason, Message, OfferLoanDlc,
+    RepayLoanAckDlc, RepayLoanDlc,
+};
+use dlc_trie::OracleNumericInfo;
+use futures::executor::block_on;
+use mocks::memory_storage_provider::MemoryStorage;
+use mocks::mock_blockchain::MockBlockchain;
+use mocks::mock_lending::{
+    MockLending, MockLendingContract, MOCK_LENDING_DEPLOYMENT, STABLE_CLAIM_PERIOD,
+};
+use mocks::mock_oracle_provider::MockOracle;
+use mocks::mock_time::{set_time, MockTime};
+use mocks::mock_wallet::MockWallet;
+use secp256k1_zkp::{Message as SecpMessage, PublicKey, SecretKey, SECP256K1};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+
+type TestManager = Manager<
+    Rc<MockWallet>,
+    Arc<CachedContractSignerProvider<Rc<MockWallet>, SimpleSigner>>,
+    Rc<MockBlockchain>,
+    Rc<MemoryStorage>,
+    Rc<MockOracle>,
+    Rc<MockTime>,
+    Rc<MockBlockchain>,
+    Rc<MockLending>,
+    SimpleSigner,
+>;
+
+const LENDER: &str = "lender";
+const BORROWER: &str = "borrower";
+const EVENT_ID: &str = "btcusd";
+const START_TIME: u64 = 1_700_000_000;
+const DURATION: u64 = 86400 * 30;
+const NB_DIGITS: u16 = 10;
+const ESCROW_CONFIRMATIONS: u32 = 2;
+const NB_CONFIRMATIONS: u64 = 6;
+/// The principal of the loan, in the unit of the price attested by the oracles.
+const PRINCIPAL: u64 = 400;
+/// The stable token units making up one unit of principal.
+const STABLE_UNIT: u128 = 500_000;
+/// The value of a sat of collateral, in stable token units.
+const STABLE_PER_SAT: u128 = 3;
+/// The stable loan lent for the principal, which is also what the mock lending
+/// contract lends at most for one bitcoin of collateral at a 150% collateral
+/// ratio.
+const STABLE_LOAN_AMOUNT: u128 = PRINCIPAL as u128 * STABLE_UNIT;
+
+struct LoanTest {
+    blockchain: Rc<MockBlockchain>,
+    lending_contract: Arc<MockLendingContract>,
+    lender: TestManager,
+    borrower: TestManager,
+    lender_wallet: Rc<MockWallet>,
+    borrower_wallet: Rc<MockWallet>,
+    lender_id: PublicKey,
+    borrower_id: PublicKey,
+    loan_input: LoanContractInput,
+}
+
+fn get_node_id(seed: u8) -> PublicKey {
+    PublicKey::from_secret_key(SECP256K1, &SecretKey::from_slice(&[seed; 32]).unwrap())
+}
+
+fn get_wallet(blockchain: &Rc<MockBlockchain>) -> Rc<MockWallet> {
+    Rc::new(MockWallet::new(
+        blockchain,
+        &(0..10).map(|_| Amount::ONE_BTC).collect::<Vec<_>>(),
+    ))
+}
+
+fn get_manager(
+    blockchain: &Rc<MockBlockchain>,
+    oracle: &Rc<MockOracle>,
+    lending_contract: &Arc<MockLendingContract>,
+    wallet: &Rc<MockWallet>,
+    account: &str,
+) -> TestManager {
+    let mut oracles = HashMap::new();
+    oracles.insert(oracle.get_public_key(), oracle.clone());
+
+    Manager::new(
+        wallet.clone(),
+        wallet.clone(),
+        blockchain.clone(),
+        Rc::new(MemoryStorage::new()),
+        oracles,
+        Rc::new(MockTime {}),
+        blockchain.clone(),
+        Rc::new(MockLending::new(lending_contract.clone(), account)),
+    )
+    .unwrap()
+}
+
+impl LoanTest {
+    fn new() -> Self {
+        set_time(START_TIME);
+        let blockchain = Rc::new(MockBlockchain::new());
+        let mut oracle = MockOracle::new();
+        oracle.add_event(
+            EVENT_ID,
+            &EventDescriptor::DigitDecompositionEvent(DigitDecompositionEventDescriptor {
+                base: 2,
+                is_signed: false,
+                unit: "usd/btc".to_string(),
+                precision: 0,
+                nb_digits: NB_DIGITS,
+            }),
+            (START_TIME + DURATION) as u32,
+        );
+        let oracle = Rc::new(oracle);
+        let lending_contract = Arc::new(MockLendingContract::new(STABLE_PER_SAT, STABLE_UNIT));
+        lending_contract.mint(LENDER, STABLE_LOAN_AMOUNT);
+
+        let loan_input = LoanContractInput {
+            collateral_ratio: 150,
+            liquidation_ratio: 125,
+            interest_rate: 10,
+            duration: DURATION,
+            escrow_relative_locktime: 86400 * 2,
+            escrow_confirmations: ESCROW_CONFIRMATIONS,
+            offer_validity: 86400,
+            collateral: Amount::ONE_BTC,
+            principal: PRINCIPAL,
+            stable_token_address: MOCK_LENDING_DEPLOYMENT.stable_token_address,
+            lending_contract_address: MOCK_LENDING_DEPLOYMENT.lending_contract_address,
+            chain_id: MOCK_LENDING_DEPLOYMENT.chain_id,
+            fee_rate: 2,
+            contract_infos: vec![LoanContractInputInfo {
+                oracle_numeric_infos: OracleNumericInfo {
+                    base: 2,
+                    nb_digits: vec![NB_DIGITS as usize],
+                },
+                rounding_intervals: RoundingIntervals {
+                    intervals: vec![RoundingInterval {
+                        begin_interval: 0,
+                        rounding_mod: 1,
+                    }],
+                },
+                oracles: OracleInput {
+                    public_keys: vec![oracle.get_public_key()],
+                    event_id: EVENT_ID.to_string(),
+                    threshold: 1,
+                },
+                liquidation_oracles: Vec::new(),
+            }],
+        };
+
+        let lender_wallet = get_wallet(&blockchain);
+        let borrower_wallet = get_wallet(&blockchain);
+        LoanTest {
+            lender: get_manager(
+                &blockchain,
+                &oracle,
+                &lending_contract,
+                &lender_wallet,
+                LENDER,
+            ),
+            borrower: get_manager(
+                &blockchain,
+                &oracle,
+                &lending_contract,
+                &borrower_wallet,
+                BORROWER,
+            ),
+            lender_wallet,
+            borrower_wallet,
+            blockchain,
+            lending_contract,
+            lender_id: get_node_id(1),
+            borrower_id: get_node_id(2),
+            loan_input,
+        }
+    }
+
+    /// Offers the loan to the borrower, returning the temporary id of the
+    /// contract.
+    fn offer_loan(&self) -> ContractId {
+        let offer = self
+            .lender
+            .send_offer_loan(&self.loan_input, self.borrower_id)
+            .expect("to be able to offer a loan");
+        let temporary_contract_id = offer.offer_dlc.temporary_contract_id;
+        block_on(
+            self.borrower
+                .on_dlc_message(&Message::OfferLoan(offer), self.lender_id),
+        )
+        .expect("the borrower to accept the loan offer");
+
+        temporary_contract_id
+    }
+
+    /// Offers the loan and has the borrower lock its escrow, returning the
+    /// temporary id of the contract and the escrow message for the lender.
+    fn offer_and_send_escrow(&self) -> (ContractId, InitiateAcceptLoanDlc) {
+        let temporary_contract_id = self.offer_loan();
+        let (_, initiate_accept) = self
+            .borrower
+            .initiate_accept_loan_contract_offer(&temporary_contract_id)
+            .expect("to be able to send the escrow");
+
+        (temporary_contract_id, initiate_accept)
+    }
+
+    /// Offers the loan and has the lender accept the escrow of the borrower,
+    /// returning the temporary id of the contract.
+    fn offer_and_escrow(&self) -> ContractId {
+        let (temporary_contract_id, initiate_accept) = self.offer_and_send_escrow();
+        block_on(self.lender.on_dlc_message(
+            &Message::InitiateAcceptLoan(initiate_accept),
+            self.borrower_id,
+        ))
+        .expect("the lender to accept the escrow");
+
+        temporary_contract_id
+    }
+
+    /// Runs the loan setup until the borrower claimed the stables, returning
+    /// the temporary id of the contract.
+    fn set_up_claimed_loan(&self) -> ContractId {
+        let temporary_contract_id = self.offer_and_escrow();
+        self.blockchain.generate_blocks(ESCROW_CONFIRMATIONS as u64);
+        block_on(self.lender.periodic_loan_check()).unwrap();
+        block_on(self.borrower.periodic_loan_check()).unwrap();
+
+        temporary_contract_id
+    }
+
+    /// Runs the loan setup until the collateral is confirmed, returning the
+    /// id of the contract.
+    fn set_up_loan(&self) -> ContractId {
+        let temporary_contract_id = self.set_up_claimed_loan();
+        let (contract_id, _, accept) = self
+            .borrower
+            .accept_loan_contract_offer(&temporary_contract_id)
+            .expect("to be able to accept the loan once the stables are claimed");
+        let sign = match block_on(
+            self.lender
+                .on_dlc_message(&Message::AcceptLoan(accept), self.borrower_id),
+        )
+        .expect("the lender to sign the loan contract")
+        {
+            Some(Message::Sign(sign)) => sign,
+            _ => panic!("expected a sign message"),
+        };
+        block_on(
+            self.borrower
+                .on_dlc_message(&Message::Sign(sign), self.lender_id),
+        )
+        .expect("the borrower to verify the lender signatures");
+
+        self.blockchain.generate_blocks(NB_CONFIRMATIONS);
+        self.lender.periodic_check(false).unwrap();
+        self.borrower.periodic_check(false).unwrap();
+        assert_contract_state(&self.lender, &contract_id, "confirmed");
+        assert_contract_state(&self.borrower, &contract_id, "confirmed");
+
+        contract_id
+    }
+}
+
+impl LoanTest {
+    /// Moves the time past the escrow relative locktime and mines enough
+    /// blocks for the median time past to reach it.
+    fn expire_escrow_locktime(&self) {
+        set_time(START_TIME + self.loan_input.escrow_relative_locktime as u64 + 3600);
+        self.blockchain.generate_blocks(11);
+    }
+
+    fn get_borrower_hash(&self, temporary_contract_id: &ContractId) -> [u8; 32] {
+        let borrower_preimage = self
+            .borrower
+            .get_store()
+            .get_loan_preimage(temporary_contract_id)
+            .unwrap()
+            .expect("the borrower preimage to be stored");
+        sha256::Hash::hash(&borrower_preimage.to_be_bytes()).to_byte_array()
+    }
+
+    fn get_stable_loan(&self, temporary_contract_id: &ContractId) -> StableLoan {
+        block_on(
+            MockLending::new(self.lending_contract.clone(), BORROWER)
+                .get_stable_loan(&self.get_borrower_hash(temporary_contract_id)),
+        )
+        .unwrap()
+    }
+
+    /// Reserves a UTXO of the lender wallet and adds it to the funding inputs
+    /// of the offered loan contract, returning its outpoint.
+    fn reserve_lender_funding_input(&self, temporary_contract_id: &ContractId) -> OutPoint {
+        let utxo = self
+            .lender_wallet
+            .get_utxos_for_amount(Amount::ONE_BTC, self.loan_input.fee_rate, true)
+            .unwrap()
+            .remove(0);
+        let prev_tx = self
+            .blockchain
+            .get_transaction(&utxo.outpoint.txid)
+            .unwrap();
+
+        let mut offered_loan_contract = match self
+            .lender
+            .get_store()
+            .get_contract(temporary_contract_id)
+            .unwrap()
+        {
+            Some(Contract::OfferedLoan(o)) => o,
+            _ => panic!("expected an offered loan contract"),
+        };
+        offered_loan_contract
+            .offered_contract
+            .funding_inputs
+            .push(FundingInput {
+                input_serial_id: 0,
+                prev_tx: serialize(&prev_tx),
+                prev_tx_vout: utxo.outpoint.vout,
+                sequence: u32::MAX,
+                max_witness_len: 107,
+                redeem_script: utxo.redeem_script,
+            });
+        self.lender
+            .get_store()
+            .update_contract(&Contract::OfferedLoan(offered_loan_contract))
+            .unwrap();
+
+        utxo.outpoint
+    }
+
+    fn get_temporary_id(&self, contract_id: &ContractId) -> ContractId {
+        self.borrower
+            .get_store()
+            .get_contract(contract_id)
+            .unwrap()
+            .expect("the contract to exist")
+            .get_temporary_id()
+    }
+}
+
+fn assert_contract_state(manager: &TestManager, contract_id: &ContractId, state: &str) {
+    let contract = manager
+        .get_store()
+        .get_contract(contract_id)
+        .unwrap()
+        .expect("the contract to exist");
+    assert_eq!(
+        format!("Contract {{ state: \"{}\" }}", state),
+        format!("{:?}", contract)
+    );
+}
+
+fn assert_loan_state(manager: &TestManager, temporary_contract_id: &ContractId, state: LoanState) {
+    let lifecycle = manager
+        .get_store()
+        .get_loan_lifecycle(temporary_contract_id)
+        .unwrap()
+        .expect("the loan lifecycle to exist");
+    assert_eq!(state, lifecycle.get_state());
+}
+
+#[test]
+fn loan_is_lent_only_once_test() {
+    let test = LoanTest::new();
+    test.offer_and_escrow();
+
+    block_on(test.lender.periodic_loan_check()).unwrap();
+    assert_eq!(STABLE_LOAN_AMOUNT, test.lending_contract.balance_of(LENDER));
+
+    test.blockchain.generate_blocks(ESCROW_CONFIRMATIONS as u64);
+    block_on(test.lender.periodic_loan_check()).unwrap();
+    assert_eq!(0, test.lending_contract.balance_of(LENDER));
+
+    test.lending_contract.mint(LENDER, STABLE_LOAN_AMOUNT);
+    block_on(test.lender.periodic_loan_check()).unwrap();
+    assert_eq!(
+        STABLE_LOAN_AMOUNT,
+        test.lending_contract.balance_of(LENDER),
+        "the stable loan should not be lent twice"
+    );
+}
+
+#[test]
+fn loans_are_checked_inside_a_runtime_test() {
+    let test = LoanTest::new();
+    test.lending_contract.mint(LENDER, STABLE_LOAN_AMOUNT);
+    let first_contract_id = test.offer_and_escrow();
+    let second_contract_id = test.offer_and_escrow();
+    test.blockchain.generate_blocks(ESCROW_CONFIRMATIONS as u64);
+
+    // The loan checks must not start a runtime of their own, which panics
+    // when called from an async context.
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    runtime.block_on(test.lender.periodic_loan_check()).unwrap();
+    runtime
+        .block_on(test.borrower.periodic_loan_check())
+        .unwrap();
+
+    assert_eq!(0, test.lending_contract.balance_of(LENDER));
+    assert_eq!(
+        2 * STABLE_LOAN_AMOUNT,
+        test.lending_contract.balance_of(BORROWER)
+    );
+    for contract_id in &[first_contract_id, second_contract_id] {
+        assert_contract_state(&test.borrower, contract_id, "offered loan escrow confirmed");
+    }
+}
+
+#[test]
+fn failed_lend_does_not_stop_other_loan_checks_test() {
+    let test = LoanTest::new();
+    let first_contract_id = test.offer_and_escrow();
+    let second_contract_id = test.offer_and_escrow();
+    test.blockchain.generate_blocks(ESCROW_CONFIRMATIONS as u64);
+
+    // The lender only holds the stables for one of the loans.
+    block_on(test.lender.periodic_loan_check()).expect("a failed lend not to fail the whole check");
+    assert_eq!(0, test.lending_contract.balance_of(LENDER));
+    let nb_lent = [first_contract_id, second_contract_id]
+        .iter()
+        .filter(|id| test.get_stable_loan(id).collateral_amount > Amount::ZERO)
+        .count();
+    assert_eq!(1, nb_lent);
+}
+
+#[test]
+fn escrow_to_another_script_is_rejected_test() {
+    let test = LoanTest::new();
+    let (_, initiate_accept) = test.offer_and_send_escrow();
+
+    let wrong_escrow = InitiateAcceptLoanDlc {
+        borrower_hash: [0; 32],
+        ..initiate_accept.clone()
+    };
+    block_on(
+        test.lender
+            .on_dlc_message(&Message::InitiateAcceptLoan(wrong_escrow), test.borrower_id),
+    )
+    .expect_err("an escrow not paying the loan escrow script should be rejected");
+    test.blockchain.generate_blocks(ESCROW_CONFIRMATIONS as u64);
+    block_on(test.lender.periodic_loan_check()).unwrap();
+    assert_eq!(STABLE_LOAN_AMOUNT, test.lending_contract.balance_of(LENDER));
+
+    block_on(test.lender.on_dlc_message(
+        &Message::InitiateAcceptLoan(initiate_accept),
+        test.borrower_id,
+    ))
+    .expect("the actual escrow to be accepted");
+    block_on(test.lender.periodic_loan_check()).unwrap();
+    assert_eq!(0, test.lending_contract.balance_of(LENDER));
+}
+
+#[test]
+fn underfunded_escrow_is_rejected_test() {
+    let test = LoanTest::new();
+    let (_, initiate_accept) = test.offer_and_send_escrow();
+
+    let mut escrow_tx = test
+        .blockchain
+        .get_transaction(&initiate_accept.escrow_txid)
+        .unwrap();
+    escrow_tx.output[0].value = escrow_tx.output[0].value / 2;
+    test.blockchain.send_transaction(&escrow_tx).unwrap();
+    let underfunded_escrow = InitiateAcceptLoanDlc {
+        escrow_txid: escrow_tx.compute_txid(),
+        ..initiate_accept
+    };
+    block_on(test.lender.on_dlc_message(
+        &Message::InitiateAcceptLoan(underfunded_escrow),
+        test.borrower_id,
+    ))
+    .expect_err("an underfunded escrow should be rejected");
+    test.blockchain.generate_blocks(ESCROW_CONFIRMATIONS as u64);
+    block_on(test.lender.periodic_loan_check()).unwrap();
+    assert_eq!(STABLE_LOAN_AMOUNT, test.lending_contract.balance_of(LENDER));
+}
+
+#[test]
+fn escrow_output_is_found_after_change_test() {
+    let test = LoanTest::new();
+    let (temporary_contract_id, initiate_accept) = test.offer_and_send_escrow();
+
+    let mut escrow_tx = test
+        .blockchain
+        .get_transaction(&initiate_accept.escrow_txid)
+        .unwrap();
+    escrow_tx.output.swap(0, 1);
+    test.blockchain.send_transaction(&escrow_tx).unwrap();
+    let escrow_txid = escrow_tx.compute_txid();
+    block_on(test.lender.on_dlc_message(
+        &Message::InitiateAcceptLoan(InitiateAcceptLoanDlc {
+            escrow_txid,
+            ..initiate_accept
+        }),
+        test.borrower_id,
+    ))
+    .expect("an escrow with its change output first to be accepted");
+    assert_eq!(
+        Some(OutPoint {
+            txid: escrow_txid,
+            vout: 1,
+        }),
+        test.lender
+            .get_store()
+            .get_escrow_outpoint(&temporary_contract_id)
+            .unwrap()
+    );
+}
+
+#[test]
+fn invalid_borrower_signatures_do_not_release_collateral_test() {
+    let test = LoanTest::new();
+    let temporary_contract_id = test.set_up_claimed_loan();
+    let (_, _, accept) = test
+        .borrower
+        .accept_loan_contract_offer(&temporary_contract_id)
+        .unwrap();
+    let collateral_txid = accept.signed_escrow_spend_tx.compute_txid();
+
+    let invalid_accept = AcceptLoanDlc {
+        refund_signature: SECP256K1.sign_ecdsa(
+            &SecpMessage::from_digest([1; 32]),
+            &SecretKey::from_slice(&[3; 32]).unwrap(),
+        ),
+        ..accept
+    };
+    block_on(
+        test.lender
+            .on_dlc_message(&Message::AcceptLoan(invalid_accept), test.borrower_id),
+    )
+    .expect_err("an invalid refund signature should be rejected");
+    assert!(!test.blockchain.has_transaction(&collateral_txid));
+    assert_contract_state(&test.lender, &temporary_contract_id, "failed accept");
+}
+
+#[test]
+fn lost_collateral_transaction_is_broadcast_again_test() {
+    let test = LoanTest::new();
+    let temporary_contract_id = test.set_up_claimed_loan();
+    let (contract_id, _, accept) = test
+        .borrower
+        .accept_loan_contract_offer(&temporary_contract_id)
+        .unwrap();
+    let collateral_txid = accept.signed_escrow_spend_tx.compute_txid();
+
+    block_on(
+        test.lender
+            .on_dlc_message(&Message::AcceptLoan(accept), test.borrower_id),
+    )
+    .expect("the lender to sign the loan contract");
+    assert_contract_state(&test.lender, &contract_id, "signed");
+    assert!(test.blockchain.has_transaction(&collateral_txid));
+
+    // The signed contract is persisted, so the collateral transaction is sent
+    // again if it gets lost before confirming.
+    test.blockchain.clear_mempool();
+    test.lender.periodic_check(false).unwrap();
+    assert!(test.blockchain.has_transaction(&collateral_txid));
+}
+
+#[test]
+fn loan_repayment_test() {
+    let test = LoanTest::new();
+    let contract_id = test.set_up_loan();
+    assert_eq!(
+        STABLE_LOAN_AMOUNT,
+        test.lending_contract.balance_of(BORROWER)
+    );
+
+    // Repaying halfway through the loan pays the interest accrued since the
+    // stables were claimed.
+    set_time(START_TIME + DURATION / 2);
+    let interest = calculate_interest(
+        STABLE_LOAN_AMOUNT,
+        percent_to_bps(test.loan_input.interest_rate).unwrap(),
+        DURATION / 2,
+    )
+    .unwrap();
+    assert!(interest > 0);
+    test.lending_contract.mint(BORROWER, interest);
+
+    let (_, repay) = block_on(test.borrower.repay_loan(&contract_id))
+        .expect("the borrower to repay the stable loan");
+    assert_eq!(0, test.lending_contract.balance_of(BORROWER));
+    assert_eq!(
+        STABLE_LOAN_AMOUNT + interest,
+        test.lending_contract.balance_of(LENDER)
+    );
+    let repay_ack = match block_on(
+        test.lender
+            .on_dlc_message(&Message::RepayLoan(repay), test.borrower_id),
+    )
+    .expect("the lender to acknowledge the repayment")
+    {
+        Some(Message::RepayLoanAck(repay_ack)) => repay_ack,
+        _ => panic!("expected a repay loan ack message"),
+    };
+    assert_contract_state(&test.lender, &contract_id, "pre-repaid");
+
+    // The lender keeps watching the collateral until the sweep confirms.
+    test.blockchain.generate_blocks(NB_CONFIRMATIONS);
+    test.lender.periodic_check(false).unwrap();
+    assert_contract_state(&test.lender, &contract_id, "pre-repaid");
+
+    block_on(
+        test.borrower
+            .on_dlc_message(&Message::RepayLoanAck(repay_ack), test.lender_id),
+    )
+    .expect("the borrower to sweep the collateral");
+    assert_contract_state(&test.borrower, &contract_id, "pre-repaid");
+
+    test.blockchain.generate_blocks(NB_CONFIRMATIONS);
+    test.borrower.periodic_check(false).unwrap();
+    test.lender.periodic_check(false).unwrap();
+    assert_contract_state(&test.borrower, &contract_id, "repaid");
+    assert_contract_state(&test.lender, &contract_id, "repaid");
+}
+
+#[test]
+fn amount_due_matches_repayment_past_maturity_test() {
+    let test = LoanTest::new();
+    let contract_id = test.set_up_loan();
+
+    // Interest keeps accruing on the lending contract after maturity.
+    let timestamp = START_TIME + 2 * DURATION;
+    set_time(timestamp);
+    let amount_due = block_on(test.borrower.get_loan_amount_due(&contract_id, timestamp))
+        .expect("to be able to get the amount due");
+    assert_eq!(STABLE_LOAN_AMOUNT, amount_due.principal);
+    assert_eq!(
+        calculate_interest(
+            STABLE_LOAN_AMOUNT,
+            percent_to_bps(test.loan_input.interest_rate).unwrap(),
+            2 * DURATION,
+        )
+        .unwrap(),
+        amount_due.accrued_interest
+    );
+    test.lending_contract
+        .mint(BORROWER, amount_due.accrued_interest);
+
+    block_on(test.borrower.repay_loan(&contract_id))
+        .expect("the borrower to repay the stable loan");
+    assert_eq!(0, test.lending_contract.balance_of(BORROWER));
+    assert_eq!(
+        amount_due.total_due,
+        test.lending_contract.balance_of(LENDER)
+    );
+}
+
+#[test]
+fn lender_does_not_reveal_preimage_before_repayment_test() {
+    let test = LoanTest::new();
+    let contract_id = test.set_up_loan();
+
+    // The borrower spent the stables and cannot pay the interest.
+    set_time(START_TIME + DURATION / 2);
+    block_on(test.borrower.repay_loan(&contract_id))
+        .expect_err("the repayment to fail without the interest");
+
+    let repay = RepayLoanDlc {
+        protocol_version: 1,
+        contract_id,
+        borrower_hash: test.get_borrower_hash(&test.get_temporary_id(&contract_id)),
+    };
+    block_on(
+        test.lender
+            .on_dlc_message(&Message::RepayLoan(repay), test.borrower_id),
+    )
+    .expect_err("the lender not to reveal its preimage before the repayment");
+    assert_contract_state(&test.lender, &contract_id, "confirmed");
+}
+
+#[test]
+fn wrong_lender_preimage_does_not_unlock_collateral_test() {
+    let test = LoanTest::new();
+    let contract_id = test.set_up_loan();
+
+    let (_, repay) = block_on(test.borrower.repay_loan(&contract_id)).unwrap();
+    let repay_ack = match block_on(
+        test.lender
+            .on_dlc_message(&Message::RepayLoan(repay), test.borrower_id),
+    )
+    .unwrap()
+    {
+        Some(Message::RepayLoanAck(repay_ack)) => repay_ack,
+        _ => panic!("expected a repay loan ack message"),
+    };
+
+    let wrong_repay_ack = RepayLoanAckDlc {
+        lender_preimage: repay_ack.lender_preimage.wrapping_add(1),
+        ..repay_ack.clone()
+    };
+    block_on(
+        test.borrower
+            .on_dlc_message(&Message::RepayLoanAck(wrong_repay_ack), test.lender_id),
+    )
+    .expect_err("a wrong lender preimage should be rejected");
+    assert_contract_state(&test.borrower, &contract_id, "confirmed");
+
+    block_on(
+        test.borrower
+            .on_dlc_message(&Message::RepayLoanAck(repay_ack), test.lender_id),
+    )
+    .expect("the right lender preimage to unlock the collateral");
+    assert_contract_state(&test.borrower, &contract_id, "pre-repaid");
+}
+
+#[test]
+fn failed_collateral_sweep_is_broadcast_again_test() {
+    let test = LoanTest::new();
+    let contract_id = test.set_up_loan();
+
+    let (_, repay) = block_on(test.borrower.repay_loan(&contract_id)).unwrap();
+    let repay_ack = match block_on(
+        test.lender
+            .on_dlc_message(&Message::RepayLoan(repay), test.borrower_id),
+    )
+    .unwrap()
+    {
+        Some(Message::RepayLoanAck(repay_ack)) => repay_ack,
+        _ => panic!("expected a repay loan ack message"),
+    };
+
+    // The lender preimage is kept even though the sweep could not be sent.
+    test.blockchain.fail_next_broadcasts(1);
+    block_on(
+        test.borrower
+            .on_dlc_message(&Message::RepayLoanAck(repay_ack), test.lender_id),
+    )
+    .expect("the borrower to keep the lender preimage");
+    assert_contract_state(&test.borrower, &contract_id, "pre-repaid");
+
+    test.borrower.periodic_check(false).unwrap();
+    test.blockchain.generate_blocks(NB_CONFIRMATIONS);
+    test.borrower.periodic_check(false).unwrap();
+    assert_contract_state(&test.borrower, &contract_id, "repaid");
+}
+
+#[test]
+fn lost_repay_ack_is_sent_again_test() {
+    let test = LoanTest::new();
+    let contract_id = test.set_up_loan();
+    let get_repay_ack = |repay| match block_on(
+        test.lender
+            .on_dlc_message(&Message::RepayLoan(repay), test.borrower_id),
+    )
+    .expect("the lender to acknowledge the repayment")
+    {
+        Some(Message::RepayLoanAck(repay_ack)) => repay_ack,
+        _ => panic!("expected a repay loan ack message"),
+    };
+
+    let (_, repay) = block_on(test.borrower.repay_loan(&contract_id)).unwrap();
+    let repay_ack = get_repay_ack(repay);
+    assert_contract_state(&test.lender, &contract_id, "pre-repaid");
+
+    // The first acknowledgement never reached the borrower, which asks again.
+    let (_, repay) = block_on(test.borrower.repay_loan(&contract_id))
+        .expect("the borrower to ask again for the lender preimage");
+    let repay_ack_again = get_repay_ack(repay);
+    assert_eq!(repay_ack, repay_ack_again);
+
+    block_on(
+        test.borrower
+            .on_dlc_message(&Message::RepayLoanAck(repay_ack_again), test.lender_id),
+    )
+    .expect("the borrower to sweep the collateral");
+    assert_contract_state(&test.borrower, &contract_id, "pre-repaid");
+}
+
+#[test]
+fn escrow_is_stored_before_broadcast_test() {
+    let test = LoanTest::new();
+    let temporary_contract_id = test.offer_loan();
+
+    test.blockchain.fail_next_broadcasts(1);
+    test.borrower
+        .initiate_accept_loan_contract_offer(&temporary_contract_id)
+        .expect_err("the escrow broadcast to fail");
+    assert!(test
+        .borrower
+        .get_store()
+        .get_loan_preimage(&temporary_contract_id)
+        .unwrap()
+        .is_some());
+    assert!(test
+        .borrower
+        .get_store()
+        .get_escrow_outpoint(&temporary_contract_id)
+        .unwrap()
+        .is_some());
+}
+
+#[test]
+fn abandoned_escrow_is_reclaimed_when_never_lent_test() {
+    let test = LoanTest::new();
+    let temporary_contract_id = test.offer_and_escrow();
+    test.blockchain.generate_blocks(ESCROW_CONFIRMATIONS as u64);
+
+    block_on(test.borrower.periodic_loan_check()).unwrap();
+    assert_contract_state(&test.borrower, &temporary_contract_id, "offered loan");
+
+    test.expire_escrow_locktime();
+    block_on(test.borrower.periodic_loan_check()).unwrap();
+    assert_contract_state(&test.borrower, &temporary_contract_id, "escrow reclaimed");
+}
+
+#[test]
+fn abandoned_escrow_is_not_reclaimed_once_lent_test() {
+    let test = LoanTest::new();
+    let temporary_contract_id = test.offer_and_escrow();
+    test.blockchain.generate_blocks(ESCROW_CONFIRMATIONS as u64);
+    test.expire_escrow_locktime();
+
+    // The stables are lent just before the borrower checks its escrow, which
+    // it then claims instead of reclaiming the escrow.
+    block_on(test.lender.periodic_loan_check()).unwrap();
+    block_on(test.borrower.periodic_loan_check()).unwrap();
+    assert_contract_state(
+        &test.borrower,
+        &temporary_contract_id,
+        "offered loan escrow confirmed",
+    );
+    assert_eq!(
+        STABLE_LOAN_AMOUNT,
+        test.lending_contract.balance_of(BORROWER)
+    );
+}
+
+#[test]
+fn abandoned_escrow_is_not_reclaimed_once_claimed_test() {
+    let test = LoanTest::new();
+    let temporary_contract_id = test.offer_and_escrow();
+    test.blockchain.generate_blocks(ESCROW_CONFIRMATIONS as u64);
+    block_on(test.lender.periodic_loan_check()).unwrap();
+    block_on(test.borrower.periodic_loan_check()).unwrap();
+    assert_ne!(
+        0,
+        test.get_stable_loan(&temporary_contract_id)
+            .borrower_preimage
+    );
+
+    test.expire_escrow_locktime();
+    block_on(test.borrower.periodic_loan_check()).unwrap();
+    assert_contract_state(
+        &test.borrower,
+        &temporary_contract_id,
+        "offered loan escrow confirmed",
+    );
+}
+
+#[test]
+fn refused_stable_loan_escrow_is_reclaimed_test() {
+    let test = LoanTest::new();
+    let temporary_contract_id = test.offer_and_escrow();
+    test.blockchain.generate_blocks(ESCROW_CONFIRMATIONS as u64);
+
+    // The stables are lent at another interest rate than the offered one, so
+    // the borrower refuses to claim them.
+    block_on(
+        MockLending::new(test.lending_contract.clone(), LENDER).lend_stable(
+            &test.get_borrower_hash(&temporary_contract_id),
+            test.loan_input.collateral,
+            STABLE_LOAN_AMOUNT,
+            test.loan_input.interest_rate + 1,
+            test.loan_input.collateral_ratio,
+        ),
+    )
+    .unwrap();
+    block_on(test.borrower.periodic_loan_check()).unwrap();
+    assert_contract_state(&test.borrower, &temporary_contract_id, "failed loan");
+
+    test.expire_escrow_locktime();
+    block_on(test.borrower.periodic_loan_check()).unwrap();
+    assert_contract_state(&test.borrower, &temporary_contract_id, "escrow reclaimed");
+    assert_eq!(0, test.lending_contract.balance_of(BORROWER));
+}
+
+#[test]
+fn loan_offer_above_collateral_ratio_is_rejected_test() {
+    let test = LoanTest::new();
+    let offer = test
+        .lender
+        .send_offer_loan(&test.loan_input, test.borrower_id)
+        .expect("to be able to offer a loan");
+
+    // One bitcoin of collateral covers exactly the principal at the offered
+    // collateral ratio.
+    let offer = OfferLoanDlc {
+        principal: PRINCIPAL + 1,
+        ..offer
+    };
+    block_on(
+        test.borrower
+            .on_dlc_message(&Message::OfferLoan(offer), test.lender_id),
+    )
+    .expect_err("a principal not covered by the collateral should be rejected");
+}
+
+#[test]
+fn rejected_loan_offer_unreserves_utxos_test() {
+    let test = LoanTest::new();
+    let temporary_contract_id = test.offer_loan();
+    let outpoint = test.reserve_lender_funding_input(&temporary_contract_id);
+
+    let (_, reject) = test
+        .borrower
+        .reject_loan_offer(&temporary_contract_id, LoanOfferReason::UnacceptableTerms)
+        .expect("the borrower to be able to reject the offer");
+    assert_contract_state(&test.borrower, &temporary_contract_id, "rejected loan");
+    assert!(test.borrower_wallet.get_unreserved_utxos().is_empty());
+
+    block_on(
+        test.lender
+            .on_dlc_message(&Message::RejectLoan(reject), test.borrower_id),
+    )
+    .expect("the lender to process the rejection");
+    assert_contract_state(&test.lender, &temporary_contract_id, "rejected loan");
+    assert_eq!(vec![outpoint], test.lender_wallet.get_unreserved_utxos());
+    assert!(test.lender_wallet.get_reserved_utxos().is_empty());
+}
+
+#[test]
+fn cancelled_loan_offer_unreserves_utxos_test() {
+    let test = LoanTest::new();
+    let temporary_contract_id = test.offer_and_escrow();
+    let outpoint = test.reserve_lender_funding_input(&temporary_contract_id);
+
+    let (_, cancel) = block_on(
+        test.lender
+            .cancel_loan_offer(&temporary_contract_id, LoanOfferReason::Expired),
+    )
+    .expect("the lender to be able to cancel the offer");
+    assert_contract_state(&test.lender, &temporary_contract_id, "cancelled loan");
+    assert_eq!(vec![outpoint], test.lender_wallet.get_unreserved_utxos());
+    assert!(test.lender_wallet.get_reserved_utxos().is_empty());
+
+    block_on(
+        test.borrower
+            .on_dlc_message(&Message::CancelLoan(cancel), test.lender_id),
+    )
+    .expect("the borrower to process the cancellation");
+    assert_contract_state(&test.borrower, &temporary_contract_id, "cancelled loan");
+    assert!(test.borrower_wallet.get_unreserved_utxos().is_empty());
+}
+
+#[test]
+fn lent_loan_offer_is_not_cancelled_test() {
+    let test = LoanTest::new();
+    let temporary_contract_id = test.offer_and_escrow();
+    let outpoint = test.reserve_lender_funding_input(&temporary_contract_id);
+    test.blockchain.generate_blocks(ESCROW_CONFIRMATIONS as u64);
+    block_on(test.lender.periodic_loan_check()).unwrap();
+
+    assert!(block_on(
+        test.lender
+            .cancel_loan_offer(&temporary_contract_id, LoanOfferReason::Unspecified)
+    )
+    .is_err());
+    assert_eq!(
+        test.loan_input.collateral,
+        test.get_stable_loan(&temporary_contract_id)
+            .collateral_amount
+    );
+    assert_contract_state(&test.lender, &temporary_contract_id, "offered loan");
+    assert!(test.lender_wallet.get_unreserved_utxos().is_empty());
+    assert_eq!(vec![outpoint], test.lender_wallet.get_reserved_utxos());
+}
+
+#[test]
+fn pending_loan_requests_per_peer_are_capped_test() {
+    let test = LoanTest::new();
+    let send_request = || {
+        let request = test
+            .borrower
+            .send_loan_request(
+                test.lender_id,
+                test.loan_input.collateral,
+                PRINCIPAL,
+                test.loan_input.interest_rate,
+                DURATION,
+            )
+            .unwrap();
+        block_on(
+            test.lender
+                .on_dlc_message(&Message::RequestLoan(request), test.borrower_id),
+        )
+    };
+    for _ in 0..MAX_PENDING_LOAN_REQUESTS_PER_PEER {
+        send_request().expect("the lender to store the loan request");
+    }
+    send_request().expect_err("the lender should not store more requests from the peer");
+    assert_eq!(
+        MAX_PENDING_LOAN_REQUESTS_PER_PEER,
+        test.lender.get_store().get_loan_requests().unwrap().len()
+    );
+}
+
+#[test]
+fn unclaimed_stable_loan_is_reclaimed_on_timeout_test() {
+    let test = LoanTest::new();
+    let temporary_contract_id = test.offer_and_escrow();
+    test.blockchain.generate_blocks(ESCROW_CONFIRMATIONS as u64);
+    block_on(test.lender.periodic_loan_check()).unwrap();
+    assert_loan_state(&test.lender, &temporary_contract_id, LoanState::StableLent);
+    assert_eq!(0, test.lending_contract.balance_of(LENDER));
+
+    // The borrower never claims the stables.
+    set_time(START_TIME + STABLE_CLAIM_PERIOD - 1);
+    block_on(test.lender.periodic_loan_check()).unwrap();
+    assert_contract_state(&test.lender, &temporary_contract_id, "offered loan");
+
+    set_time(START_TIME + STABLE_CLAIM_PERIOD);
+    block_on(test.lender.periodic_loan_check()).unwrap();
+    assert_contract_state(&test.lender, &temporary_contract_id, "failed loan");
+    assert_loan_state(&test.lender, &temporary_contract_id, LoanState::Aborted);
+    assert_eq!(STABLE_LOAN_AMOUNT, test.lending_contract.balance_of(LENDER));
+
+    test.expire_escrow_locktime();
+    block_on(test.borrower.periodic_loan_check()).unwrap();
+    assert_contract_state(&test.borrower, &temporary_contract_id, "escrow reclaimed");
+    assert_eq!(0, test.lending_contract.balance_of(BORROWER));
+}
+
+#[test]
+fn unconfirmed_escrow_times_out_test() {
+    let test = LoanTest::new();
+    let temporary_contract_id = test.offer_and_escrow();
+
+    set_time(START_TIME + 86400);
+    test.lender.periodic_check(false).unwrap();
+    test.borrower.periodic_check(false).unwrap();
+    assert_contract_state(&test.lender, &temporary_contract_id, "failed loan");
+    assert_contract_state(&test.borrower, &temporary_contract_id, "failed loan");
+    assert_loan_state(&test.lender, &temporary_contract_id, LoanState::Aborted);
+    assert_loan_state(&test.borrower, &temporary_contract_id, LoanState::Aborted);
+}
+
+#[test]
+fn claimed_loan_is_not_aborted_on_timeout_test() {
+    let test = LoanTest::new();
+    let temporary_contract_id = test.set_up_claimed_loan();
+
+    set_time(START_TIME + PEER_TIMEOUT);
+    test.lender.periodic_check(false).unwrap();
+    test.borrower.periodic_check(false).unwrap();
+    assert_contract_state(&test.lender, &temporary_contract_id, "offered loan");
+    assert_contract_state(
+        &test.borrower,
+        &temporary_contract_id,
+        "offered loan escrow confirmed",
+    );
+    assert_loan_state(
+        &test.borrower,
+        &temporary_contract_id,
+        LoanState::StableClaimed,
+    );
+}
+
+#[test]
+fn unsigned_accepted_loan_is_stalled_test() {
+    let test = LoanTest::new();
+    let temporary_contract_id = test.set_up_claimed_loan();
+    let (contract_id, _, accept) = test
+        .borrower
+        .accept_loan_contract_offer(&temporary_contract_id)
+        .unwrap();
+
+    set_time(START_TIME + PEER_TIMEOUT);
+    test.borrower.periodic_check(false).unwrap();
+    assert_contract_state(&test.borrower, &contract_id, "accepted");
+    assert_loan_state(&test.borrower, &temporary_contract_id, LoanState::Stalled);
+
+    // The lender publishes the collateral transaction, but its signatures do
+    // not reach the borrower right away.
+    let sign = match block_on(
+        test.lender
+            .on_dlc_message(&Message::AcceptLoan(accept), test.borrower_id),
+    )
+    .unwrap()
+    {
+        Some(Message::Sign(sign)) => sign,
+        _ => panic!("expected a sign message"),
+    };
+    test.borrower.periodic_check(false).unwrap();
+    assert_contract_state(&test.borrower, &contract_id, "accepted");
+    assert_loan_state(
+        &test.borrower,
+        &temporary_contract_id,
+        LoanState::CollateralLocked,
+    );
+
+    block_on(
+        test.borrower
+            .on_dlc_message(&Message::Sign(sign), test.lender_id),
+    )
+    .expect("the borrower to verify the late lender signatures");
+    assert_contract_state(&test.borrower, &contract_id, "signed");
+}
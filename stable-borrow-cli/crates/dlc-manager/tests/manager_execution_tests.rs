@@ -22,6 +22,7 @@ use dlc_manager::{Blockchain, Oracle, Storage, Wallet};
 use dlc_messages::oracle_msgs::OracleAttestation;
 use dlc_messages::{AcceptDlc, OfferDlc, SignDlc};
 use dlc_messages::{CetAdaptorSignatures, Message};
+use mocks::mock_lending::{MockLending, MockLendingContract};
 use lightning::ln::wire::Type;
 use lightning::util::ser::Writeable;
 use secp256k1_zkp::rand::{thread_rng, RngCore};
@@ -577,6 +578,7 @@ fn manager_execution_test(test_params: TestParams, path: TestPath, manual_close:
     let alice_store = Arc::new(mocks::memory_storage_provider::MemoryStorage::new());
     let bob_store = Arc::new(mocks::memory_storage_provider::MemoryStorage::new());
     let mock_time = Arc::new(mocks::mock_time::MockTime {});
+    let lending_contract = Arc::new(MockLendingContract::new(1, 1));
     mocks::mock_time::set_time((EVENT_MATURITY as u64) - 1);
 
     let electrs = Arc::new(ElectrsBlockchainProvider::new(
@@ -658,6 +660,7 @@ fn manager_execution_test(test_params: TestParams, path: TestPath, manual_close:
             alice_oracles,
             Arc::clone(&mock_time),
             Arc::clone(&electrs),
+            Arc::new(MockLending::new(Arc::clone(&lending_contract), "alice")),
         )
         .unwrap(),
     ));
@@ -674,6 +677,7 @@ fn manager_execution_test(test_params: TestParams, path: TestPath, manual_close:
             bob_oracles,
             Arc::clone(&mock_time),
             Arc::clone(&electrs),
+            Arc::new(MockLending::new(Arc::clone(&lending_contract), "bob")),
         )
         .unwrap(),
     ));
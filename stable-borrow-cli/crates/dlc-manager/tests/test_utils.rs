@@ -49,11 +49,13 @@ macro_rules! receive_loop {
     ($receive:expr, $manager:expr, $send:expr, $expect_err:expr, $sync_send:expr, $rcv_callback: expr, $msg_callback: expr) => {
         thread::spawn(move || loop {
             match $receive.recv() {
-                Ok(Some(msg)) => match $manager.lock().unwrap().on_dlc_message(
-                    &msg,
-                    "0218845781f631c48f1c9709e23092067d06837f30aa0cd0544ac887fe91ddd166"
-                        .parse()
-                        .unwrap(),
+                Ok(Some(msg)) => match futures::executor::block_on(
+                    $manager.lock().unwrap().on_dlc_message(
+                        &msg,
+                        "0218845781f631c48f1c9709e23092067d06837f30aa0cd0544ac887fe91ddd166"
+                            .parse()
+                            .unwrap(),
+                    ),
                 ) {
                     Ok(opt) => {
                         if $expect_err.load(Ordering::Relaxed) != false {
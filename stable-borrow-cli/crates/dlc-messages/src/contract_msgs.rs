@@ -64,6 +64,22 @@ impl ContractInfo {
                 .expect("to have at least one element"),
         }
     }
+
+    /// Return the latest maturity date amongst all events and oracle announcements
+    /// used in the contract.
+    pub fn get_latest_maturity_date(&self) -> u32 {
+        match self {
+            ContractInfo::SingleContractInfo(s) => {
+                s.contract_info.oracle_info.get_latest_maturity_date()
+            }
+            ContractInfo::DisjointContractInfo(d) => d
+                .contract_infos
+                .iter()
+                .map(|x| x.oracle_info.get_latest_maturity_date())
+                .max()
+                .expect("to have at least one element"),
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -35,7 +35,11 @@ pub mod serde_utils;
 
 use std::fmt::Display;
 
-use crate::ser_impls::{read_ecdsa_adaptor_signature, write_ecdsa_adaptor_signature};
+use crate::ser_impls::{
+    read_ecdsa_adaptor_signature, read_evm_address, read_schnorr_pubkey,
+    write_ecdsa_adaptor_signature, write_evm_address, write_schnorr_pubkey,
+};
+use bitcoin::hashes::Hash;
 use bitcoin::{consensus::Decodable, OutPoint, Transaction};
 use bitcoin::{Amount, ScriptBuf, Txid};
 use channel::{
@@ -49,7 +53,10 @@ use lightning::ln::msgs::DecodeError;
 use lightning::ln::wire::Type;
 use lightning::util::ser::{Readable, Writeable, Writer};
 use secp256k1_zkp::Verification;
-use secp256k1_zkp::{ecdsa::Signature, EcdsaAdaptorSignature, PublicKey, Secp256k1};
+use secp256k1_zkp::{
+    ecdsa::Signature, EcdsaAdaptorSignature, Message as SecpMessage, PublicKey, Secp256k1,
+    XOnlyPublicKey,
+};
 use segmentation::{SegmentChunk, SegmentStart};
 
 macro_rules! impl_type {
@@ -71,6 +78,18 @@ impl_type!(ACCEPT_TYPE, AcceptDlc, 42780);
 impl_type!(ACCEPT_LOAN_TYPE, AcceptLoanDlc, 42781);
 impl_type!(INITIATE_ACCEPT_LOAN_TYPE, InitiateAcceptLoanDlc, 42783);
 impl_type!(SIGN_TYPE, SignDlc, 42782);
+impl_type!(REPAY_LOAN_TYPE, RepayLoanDlc, 42784);
+impl_type!(REPAY_LOAN_ACK_TYPE, RepayLoanAckDlc, 42785);
+impl_type!(REJECT_LOAN_TYPE, RejectLoanDlc, 42786);
+impl_type!(CANCEL_LOAN_TYPE, CancelLoanDlc, 42787);
+impl_type!(REQUEST_LOAN_TYPE, RequestLoanDlc, 42788);
+impl_type!(REJECT_LOAN_REQUEST_TYPE, RejectLoanRequestDlc, 42789);
+impl_type!(COUNTER_OFFER_LOAN_TYPE, CounterOfferLoanDlc, 42790);
+impl_type!(REJECT_LOAN_COUNTER_OFFER_TYPE, RejectLoanCounterOfferDlc, 42791);
+impl_type!(RENEW_LOAN_OFFER_TYPE, RenewLoanOfferDlc, 42792);
+impl_type!(RENEW_LOAN_ACCEPT_TYPE, RenewLoanAcceptDlc, 42793);
+impl_type!(RENEW_LOAN_CONFIRM_TYPE, RenewLoanConfirmDlc, 42794);
+impl_type!(LOAN_LISTING_TYPE, LoanListingDlc, 42795);
 impl_type!(OFFER_CHANNEL_TYPE, OfferChannel, 43000);
 impl_type!(ACCEPT_CHANNEL_TYPE, AcceptChannel, 43002);
 impl_type!(SIGN_CHANNEL_TYPE, SignChannel, 43004);
@@ -364,6 +383,15 @@ impl OfferDlc {
         min_timeout_interval: u32,
         max_timeout_interval: u32,
     ) -> Result<(), Error> {
+        self.validate_oracle_infos(secp)?;
+        self.validate_dates(
+            self.contract_info.get_closest_maturity_date(),
+            min_timeout_interval,
+            max_timeout_interval,
+        )
+    }
+
+    fn validate_oracle_infos<C: Verification>(&self, secp: &Secp256k1<C>) -> Result<(), Error> {
         match &self.contract_info {
             ContractInfo::SingleContractInfo(s) => s.contract_info.oracle_info.validate(secp)?,
             ContractInfo::DisjointContractInfo(d) => {
@@ -377,10 +405,22 @@ impl OfferDlc {
             }
         }
 
+        Ok(())
+    }
+
+    /// Checks that the CETs can be broadcast from the closest maturity date
+    /// and that the refund timeout is in the given interval after the given
+    /// maturity date.
+    fn validate_dates(
+        &self,
+        refund_maturity_date: u32,
+        min_timeout_interval: u32,
+        max_timeout_interval: u32,
+    ) -> Result<(), Error> {
         let closest_maturity_date = self.contract_info.get_closest_maturity_date();
         let valid_dates = self.cet_locktime <= closest_maturity_date
-            && closest_maturity_date + min_timeout_interval <= self.refund_locktime
-            && self.refund_locktime <= closest_maturity_date + max_timeout_interval;
+            && refund_maturity_date + min_timeout_interval <= self.refund_locktime
+            && self.refund_locktime <= refund_maturity_date + max_timeout_interval;
         if !valid_dates {
             return Err(Error::InvalidArgument);
         }
@@ -424,10 +464,30 @@ pub struct OfferLoanDlc {
     pub interest_rate: u64,
     /// Duration of the loan in seconds.
     pub duration: u64,
+    /// The principal of the loan, in the unit of the price attested by the
+    /// oracles.
+    pub principal: u64,
+    /// The address of the ERC-20 token lent as the stable side of the loan.
+    pub stable_token_address: [u8; 20],
+    /// The address of the lending contract holding the stable side of the loan.
+    pub lending_contract_address: [u8; 20],
+    /// The id of the EVM chain on which the lending contract is deployed.
+    pub chain_id: u64,
     /// Lender's hashvalue to be used in collateral transaction.
     pub lender_hash: [u8; 32],
+    /// Delay in seconds after which the borrower can reclaim the escrow.
+    pub escrow_relative_locktime: u32,
+    /// Number of confirmations of the escrow required before lending.
+    pub escrow_confirmations: u32,
+    /// Unix timestamp after which the offer can no longer be accepted.
+    pub offer_expiry: u64,
     /// OfferDlc message.
     pub offer_dlc: OfferDlc,
+    /// The id of the [`RequestLoanDlc`] answered by the offer, if any.
+    pub request_id: Option<[u8; 32]>,
+    /// The id of the negotiation the offer was re-issued for after a
+    /// [`CounterOfferLoanDlc`], if any.
+    pub negotiation_id: Option<[u8; 32]>,
 }
 
 impl OfferLoanDlc {
@@ -443,7 +503,15 @@ impl OfferLoanDlc {
         min_timeout_interval: u32,
         max_timeout_interval: u32,
     ) -> Result<(), Error> {
-        self.offer_dlc.validate(secp, min_timeout_interval, max_timeout_interval)
+        dlc::get_loan_escrow_relative_locktime(self.escrow_relative_locktime)?;
+        self.offer_dlc.validate_oracle_infos(secp)?;
+        // Liquidation events mature before the expiration of the loan, from
+        // which the refund timeout is counted.
+        self.offer_dlc.validate_dates(
+            self.offer_dlc.contract_info.get_latest_maturity_date(),
+            min_timeout_interval,
+            max_timeout_interval,
+        )
     }
 }
 
@@ -452,8 +520,17 @@ impl_dlc_writeable!(OfferLoanDlc, {
     (liquidation_ratio, writeable),
     (interest_rate, writeable),
     (duration, writeable),
+    (principal, writeable),
+    (stable_token_address, {cb_writeable, write_evm_address, read_evm_address}),
+    (lending_contract_address, {cb_writeable, write_evm_address, read_evm_address}),
+    (chain_id, writeable),
     (lender_hash, writeable),
-    (offer_dlc, writeable)
+    (escrow_relative_locktime, writeable),
+    (escrow_confirmations, writeable),
+    (offer_expiry, writeable),
+    (offer_dlc, writeable),
+    (request_id, option),
+    (negotiation_id, option)
 });
 
 /// Contains information about a party wishing to accept a DLC offer. The contained
@@ -667,6 +744,8 @@ pub struct InitiateAcceptLoanDlc {
     pub contract_id: [u8; 32],
     /// The borrower hash value.
     pub borrower_hash: [u8; 32],
+    /// The public key of the borrower used to lock the escrow.
+    pub borrower_pubkey: PublicKey,
     /// Txid of the escrow transaction
     pub escrow_txid: Txid,
 }
@@ -675,9 +754,545 @@ impl_dlc_writeable!(InitiateAcceptLoanDlc, {
     (protocol_version, writeable),
     (contract_id, writeable),
     (borrower_hash, writeable),
+    (borrower_pubkey, writeable),
     (escrow_txid, writeable)
 });
 
+/// Sent by the borrower once the stable loan was repaid on the lending contract,
+/// to request the preimage unlocking the collateral.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "use-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct RepayLoanDlc {
+    /// The version of the protocol used by the peer.
+    pub protocol_version: u32,
+    #[cfg_attr(
+        feature = "use-serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the contract referred to by this message.
+    pub contract_id: [u8; 32],
+    #[cfg_attr(
+        feature = "use-serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The borrower hash value under which the stable loan was recorded.
+    pub borrower_hash: [u8; 32],
+}
+
+impl_dlc_writeable!(RepayLoanDlc, {
+    (protocol_version, writeable),
+    (contract_id, writeable),
+    (borrower_hash, writeable)
+});
+
+/// Sent by the lender in response to a [`RepayLoanDlc`] message, revealing the
+/// preimage of the lender hash used in the collateral transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "use-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct RepayLoanAckDlc {
+    /// The version of the protocol used by the peer.
+    pub protocol_version: u32,
+    #[cfg_attr(
+        feature = "use-serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the contract referred to by this message.
+    pub contract_id: [u8; 32],
+    /// The lender preimage.
+    pub lender_preimage: u128,
+}
+
+impl_dlc_writeable!(RepayLoanAckDlc, {
+    (protocol_version, writeable),
+    (contract_id, writeable),
+    (lender_preimage, writeable)
+});
+
+/// The reason given when declining or withdrawing a loan offer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "use-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub enum LoanOfferReason {
+    /// No reason was given.
+    Unspecified,
+    /// The terms of the loan are not acceptable.
+    UnacceptableTerms,
+    /// The party does not have the funds required by the loan.
+    InsufficientFunds,
+    /// The offer was not followed up on in time.
+    Expired,
+}
+
+impl_dlc_writeable_enum!(LoanOfferReason,;;;
+    (0, Unspecified),
+    (1, UnacceptableTerms),
+    (2, InsufficientFunds),
+    (3, Expired)
+);
+
+impl Display for LoanOfferReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            LoanOfferReason::Unspecified => "unspecified",
+            LoanOfferReason::UnacceptableTerms => "unacceptable terms",
+            LoanOfferReason::InsufficientFunds => "insufficient funds",
+            LoanOfferReason::Expired => "expired",
+        };
+        f.write_str(reason)
+    }
+}
+
+/// Sent by the borrower to decline a loan offer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "use-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct RejectLoanDlc {
+    /// The version of the protocol used by the peer.
+    pub protocol_version: u32,
+    #[cfg_attr(
+        feature = "use-serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The temporary id of the loan contract referred to by this message.
+    pub temporary_contract_id: [u8; 32],
+    /// The reason for declining the offer.
+    pub reason: LoanOfferReason,
+}
+
+impl_dlc_writeable!(RejectLoanDlc, {
+    (protocol_version, writeable),
+    (temporary_contract_id, writeable),
+    (reason, writeable)
+});
+
+/// Sent by the lender to withdraw a loan offer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "use-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct CancelLoanDlc {
+    /// The version of the protocol used by the peer.
+    pub protocol_version: u32,
+    #[cfg_attr(
+        feature = "use-serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The temporary id of the loan contract referred to by this message.
+    pub temporary_contract_id: [u8; 32],
+    /// The reason for withdrawing the offer.
+    pub reason: LoanOfferReason,
+}
+
+impl_dlc_writeable!(CancelLoanDlc, {
+    (protocol_version, writeable),
+    (temporary_contract_id, writeable),
+    (reason, writeable)
+});
+
+/// Sent by a borrower to ask a lender for a loan. The lender can answer with
+/// an [`OfferLoanDlc`] bound to the request id or a [`RejectLoanRequestDlc`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "use-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct RequestLoanDlc {
+    /// The version of the protocol used by the peer.
+    pub protocol_version: u32,
+    #[cfg_attr(
+        feature = "use-serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the loan request.
+    pub request_id: [u8; 32],
+    /// The collateral the borrower proposes to lock.
+    pub collateral: Amount,
+    /// The desired principal of the loan, in the unit of the price attested
+    /// by the oracles.
+    pub principal: u64,
+    /// The maximum interest rate in percent accepted by the borrower.
+    pub max_interest_rate: u64,
+    /// Duration of the loan in seconds.
+    pub duration: u64,
+    /// The id of the [`LoanListingDlc`] of the lender the request answers,
+    /// if any.
+    pub listing_id: Option<[u8; 32]>,
+}
+
+impl_dlc_writeable!(RequestLoanDlc, {
+    (protocol_version, writeable),
+    (request_id, writeable),
+    (collateral, writeable),
+    (principal, writeable),
+    (max_interest_rate, writeable),
+    (duration, writeable),
+    (listing_id, option)
+});
+
+/// Sent by the lender to decline a loan request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "use-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct RejectLoanRequestDlc {
+    /// The version of the protocol used by the peer.
+    pub protocol_version: u32,
+    #[cfg_attr(
+        feature = "use-serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the loan request referred to by this message.
+    pub request_id: [u8; 32],
+    /// The reason for declining the request.
+    pub reason: LoanOfferReason,
+}
+
+impl_dlc_writeable!(RejectLoanRequestDlc, {
+    (protocol_version, writeable),
+    (request_id, writeable),
+    (reason, writeable)
+});
+
+/// The terms of the loans a lender is willing to make, as published in a
+/// [`LoanListingDlc`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "use-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct LoanListingTerms {
+    #[cfg_attr(
+        feature = "use-serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the listing.
+    pub listing_id: [u8; 32],
+    /// The node id of the lender.
+    pub lender: PublicKey,
+    /// The address at which the lender node accepts connections, as
+    /// `host:port`.
+    pub lender_address: String,
+    /// The minimum principal lent, in the unit of the price attested by the
+    /// oracle.
+    pub min_principal: u64,
+    /// The maximum principal lent, in the unit of the price attested by the
+    /// oracle.
+    pub max_principal: u64,
+    /// The interest rate in percent.
+    pub interest_rate: u64,
+    /// The collateral ratio in percent required by the lender.
+    pub collateral_ratio: u64,
+    /// Duration of the loans in seconds.
+    pub duration: u64,
+    /// The public key of the oracle attesting the price the loans are
+    /// settled at.
+    pub oracle_public_key: XOnlyPublicKey,
+    /// Unix timestamp after which the listing is no longer valid.
+    pub expiry: u64,
+}
+
+impl_dlc_writeable!(LoanListingTerms, {
+    (listing_id, writeable),
+    (lender, writeable),
+    (lender_address, string),
+    (min_principal, writeable),
+    (max_principal, writeable),
+    (interest_rate, writeable),
+    (collateral_ratio, writeable),
+    (duration, writeable),
+    (oracle_public_key, {cb_writeable, write_schnorr_pubkey, read_schnorr_pubkey}),
+    (expiry, writeable)
+});
+
+impl LoanListingTerms {
+    /// Returns the message signed by the lender, the hash of the serialized
+    /// terms.
+    pub fn get_signature_message(&self) -> SecpMessage {
+        let mut terms = Vec::new();
+        self.write(&mut terms)
+            .expect("Error writing loan listing terms");
+
+        let hash = bitcoin::hashes::sha256::Hash::hash(&terms);
+        SecpMessage::from_digest(hash.to_byte_array())
+    }
+}
+
+/// Published by a lender to advertise the terms of the loans it is willing to
+/// make, without binding them to a borrower. Listings are relayed between
+/// peers, and a borrower answers one with a [`RequestLoanDlc`] referring to
+/// its id.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "use-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct LoanListingDlc {
+    /// The version of the protocol used by the peer.
+    pub protocol_version: u32,
+    /// The terms of the listing.
+    pub terms: LoanListingTerms,
+    /// The signature of the terms by the lender node.
+    pub signature: Signature,
+}
+
+impl_dlc_writeable!(LoanListingDlc, {
+    (protocol_version, writeable),
+    (terms, writeable),
+    (signature, writeable)
+});
+
+impl LoanListingDlc {
+    /// Returns whether the listing was signed by its lender, so that peers
+    /// relaying it cannot alter its terms.
+    pub fn validate<C: Verification>(&self, secp: &Secp256k1<C>) -> Result<(), Error> {
+        secp.verify_ecdsa(
+            &self.terms.get_signature_message(),
+            &self.signature,
+            &self.terms.lender,
+        )?;
+        Ok(())
+    }
+}
+
+/// Sent by the borrower to decline a loan offer while proposing other terms.
+/// The lender can answer with a fresh [`OfferLoanDlc`] bound to the
+/// negotiation id or a [`RejectLoanCounterOfferDlc`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "use-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct CounterOfferLoanDlc {
+    /// The version of the protocol used by the peer.
+    pub protocol_version: u32,
+    #[cfg_attr(
+        feature = "use-serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The temporary id of the loan contract whose offer is countered.
+    pub temporary_contract_id: [u8; 32],
+    #[cfg_attr(
+        feature = "use-serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the negotiation, that is the temporary id of its first offer.
+    pub negotiation_id: [u8; 32],
+    /// The number of the counter offer in the negotiation, starting at one.
+    pub round: u32,
+    /// Collateralization ratio in percent.
+    pub collateral_ratio: u64,
+    /// Interest rate in percent.
+    pub interest_rate: u64,
+    /// Duration of the loan in seconds.
+    pub duration: u64,
+}
+
+impl_dlc_writeable!(CounterOfferLoanDlc, {
+    (protocol_version, writeable),
+    (temporary_contract_id, writeable),
+    (negotiation_id, writeable),
+    (round, writeable),
+    (collateral_ratio, writeable),
+    (interest_rate, writeable),
+    (duration, writeable)
+});
+
+/// Sent by the lender to decline a loan counter offer, ending the negotiation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "use-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct RejectLoanCounterOfferDlc {
+    /// The version of the protocol used by the peer.
+    pub protocol_version: u32,
+    #[cfg_attr(
+        feature = "use-serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the negotiation referred to by this message.
+    pub negotiation_id: [u8; 32],
+    /// The reason for declining the counter offer.
+    pub reason: LoanOfferReason,
+}
+
+impl_dlc_writeable!(RejectLoanCounterOfferDlc, {
+    (protocol_version, writeable),
+    (negotiation_id, writeable),
+    (reason, writeable)
+});
+
+/// Sent by the borrower of an active loan to renew its collateral, adding
+/// collateral from the given inputs and/or lowering the principal after a
+/// partial repayment on the lending contract. Carries the borrower signatures
+/// for the CETs and refund transaction of the renewed contract.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "use-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct RenewLoanOfferDlc {
+    /// The version of the protocol used by the peer.
+    pub protocol_version: u32,
+    #[cfg_attr(
+        feature = "use-serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the loan contract whose collateral is renewed.
+    pub contract_id: [u8; 32],
+    /// The collateral added by the borrower.
+    pub additional_collateral: Amount,
+    /// The part of the principal repaid on the lending contract.
+    pub repaid_principal: u64,
+    /// Inputs used by the borrower to fund the additional collateral and the
+    /// renewal fee.
+    pub funding_inputs: Vec<FundingInput>,
+    /// The SPK where the borrower will receive their change.
+    pub change_spk: ScriptBuf,
+    /// The set of adaptor signatures of the borrower for the renewed contract.
+    pub cet_adaptor_signatures: CetAdaptorSignatures,
+    /// The refund signature of the borrower for the renewed contract.
+    pub refund_signature: Signature,
+}
+
+impl_dlc_writeable!(RenewLoanOfferDlc, {
+    (protocol_version, writeable),
+    (contract_id, writeable),
+    (additional_collateral, writeable),
+    (repaid_principal, writeable),
+    (funding_inputs, vec),
+    (change_spk, writeable),
+    (cet_adaptor_signatures, writeable),
+    (refund_signature, writeable)
+});
+
+/// Sent by the lender to accept a [`RenewLoanOfferDlc`], with its signatures
+/// for the CETs and refund transaction of the renewed contract.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "use-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct RenewLoanAcceptDlc {
+    /// The version of the protocol used by the peer.
+    pub protocol_version: u32,
+    #[cfg_attr(
+        feature = "use-serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the loan contract whose collateral is renewed.
+    pub contract_id: [u8; 32],
+    /// The set of adaptor signatures of the lender for the renewed contract.
+    pub cet_adaptor_signatures: CetAdaptorSignatures,
+    /// The refund signature of the lender for the renewed contract.
+    pub refund_signature: Signature,
+}
+
+impl_dlc_writeable!(RenewLoanAcceptDlc, {
+    (protocol_version, writeable),
+    (contract_id, writeable),
+    (cet_adaptor_signatures, writeable),
+    (refund_signature, writeable)
+});
+
+/// Sent by the borrower once the renewed contract is signed, with its
+/// signatures for the collateral renewal transaction so that the lender can
+/// complete and broadcast it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "use-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct RenewLoanConfirmDlc {
+    /// The version of the protocol used by the peer.
+    pub protocol_version: u32,
+    #[cfg_attr(
+        feature = "use-serde",
+        serde(
+            serialize_with = "crate::serde_utils::serialize_hex",
+            deserialize_with = "crate::serde_utils::deserialize_hex_array"
+        )
+    )]
+    /// The id of the loan contract whose collateral is renewed.
+    pub contract_id: [u8; 32],
+    /// The signature of the borrower spending the current collateral output.
+    pub collateral_signature: Signature,
+    /// The signatures of the borrower for its funding inputs.
+    pub funding_signatures: FundingSignatures,
+}
+
+impl_dlc_writeable!(RenewLoanConfirmDlc, {
+    (protocol_version, writeable),
+    (contract_id, writeable),
+    (collateral_signature, writeable),
+    (funding_signatures, writeable)
+});
+
 #[allow(missing_docs)]
 #[derive(Debug, Clone)]
 pub enum Message {
@@ -687,6 +1302,18 @@ pub enum Message {
     AcceptLoan(AcceptLoanDlc),
     InitiateAcceptLoan(InitiateAcceptLoanDlc),
     Sign(SignDlc),
+    RepayLoan(RepayLoanDlc),
+    RepayLoanAck(RepayLoanAckDlc),
+    RejectLoan(RejectLoanDlc),
+    CancelLoan(CancelLoanDlc),
+    RequestLoan(RequestLoanDlc),
+    RejectLoanRequest(RejectLoanRequestDlc),
+    CounterOfferLoan(CounterOfferLoanDlc),
+    RejectLoanCounterOffer(RejectLoanCounterOfferDlc),
+    RenewLoanOffer(RenewLoanOfferDlc),
+    RenewLoanAccept(RenewLoanAcceptDlc),
+    RenewLoanConfirm(RenewLoanConfirmDlc),
+    LoanListing(LoanListingDlc),
     OfferChannel(OfferChannel),
     AcceptChannel(AcceptChannel),
     SignChannel(SignChannel),
@@ -731,6 +1358,18 @@ impl_type_writeable_for_enum!(Message,
     AcceptLoan,
     InitiateAcceptLoan,
     Sign,
+    RepayLoan,
+    RepayLoanAck,
+    RejectLoan,
+    CancelLoan,
+    RequestLoan,
+    RejectLoanRequest,
+    CounterOfferLoan,
+    RejectLoanCounterOffer,
+    RenewLoanOffer,
+    RenewLoanAccept,
+    RenewLoanConfirm,
+    LoanListing,
     OfferChannel,
     AcceptChannel,
     SignChannel,
@@ -810,6 +1449,168 @@ mod tests {
         roundtrip_test!(SignDlc, input);
     }
 
+    #[test]
+    fn offer_loan_msg_roundtrip() {
+        let input = include_str!("./test_inputs/offer_msg.json");
+        test_roundtrip(OfferLoanDlc {
+            collateral_ratio: 150,
+            liquidation_ratio: 125,
+            interest_rate: 10,
+            duration: 86_400 * 30,
+            principal: 40_000,
+            stable_token_address: [6u8; 20],
+            lending_contract_address: [7u8; 20],
+            chain_id: 31_337,
+            lender_hash: [8u8; 32],
+            escrow_relative_locktime: 8_388_608,
+            escrow_confirmations: 6,
+            offer_expiry: 1_748_553_660,
+            offer_dlc: serde_json::from_str(input).unwrap(),
+            request_id: Some([3u8; 32]),
+            negotiation_id: None,
+        });
+    }
+
+    #[test]
+    fn reject_and_cancel_loan_msg_roundtrip() {
+        test_roundtrip(RejectLoanDlc {
+            protocol_version: 1,
+            temporary_contract_id: [1u8; 32],
+            reason: LoanOfferReason::UnacceptableTerms,
+        });
+        test_roundtrip(CancelLoanDlc {
+            protocol_version: 1,
+            temporary_contract_id: [2u8; 32],
+            reason: LoanOfferReason::Expired,
+        });
+    }
+
+    #[test]
+    fn loan_request_msg_roundtrip() {
+        test_roundtrip(RequestLoanDlc {
+            protocol_version: 1,
+            request_id: [3u8; 32],
+            collateral: Amount::from_sat(150_000_000),
+            principal: 40_000,
+            max_interest_rate: 12,
+            duration: 86_400 * 30,
+            listing_id: None,
+        });
+        test_roundtrip(RequestLoanDlc {
+            protocol_version: 1,
+            request_id: [3u8; 32],
+            collateral: Amount::from_sat(150_000_000),
+            principal: 40_000,
+            max_interest_rate: 12,
+            duration: 86_400 * 30,
+            listing_id: Some([5u8; 32]),
+        });
+        test_roundtrip(RejectLoanRequestDlc {
+            protocol_version: 1,
+            request_id: [3u8; 32],
+            reason: LoanOfferReason::InsufficientFunds,
+        });
+    }
+
+    #[test]
+    fn loan_listing_msg_roundtrip_and_validate() {
+        let secret_key = secp256k1_zkp::SecretKey::from_slice(&[9u8; 32]).unwrap();
+        let (oracle_public_key, _) = secret_key.x_only_public_key(SECP256K1);
+        let terms = LoanListingTerms {
+            listing_id: [10u8; 32],
+            lender: PublicKey::from_secret_key(SECP256K1, &secret_key),
+            lender_address: "127.0.0.1:9000".to_string(),
+            min_principal: 10_000,
+            max_principal: 40_000,
+            interest_rate: 10,
+            collateral_ratio: 150,
+            duration: 86_400 * 30,
+            oracle_public_key,
+            expiry: 1_748_553_660,
+        };
+        let listing = LoanListingDlc {
+            protocol_version: 1,
+            signature: SECP256K1.sign_ecdsa(&terms.get_signature_message(), &secret_key),
+            terms,
+        };
+        test_roundtrip(listing.clone());
+        listing
+            .validate(SECP256K1)
+            .expect("to be signed by the lender");
+
+        let mut altered = listing;
+        altered.terms.interest_rate = 5;
+        altered
+            .validate(SECP256K1)
+            .expect_err("altered terms should not be valid");
+    }
+
+    #[test]
+    fn loan_counter_offer_msg_roundtrip() {
+        test_roundtrip(CounterOfferLoanDlc {
+            protocol_version: 1,
+            temporary_contract_id: [4u8; 32],
+            negotiation_id: [5u8; 32],
+            round: 2,
+            collateral_ratio: 160,
+            interest_rate: 8,
+            duration: 86_400 * 60,
+        });
+        test_roundtrip(RejectLoanCounterOfferDlc {
+            protocol_version: 1,
+            negotiation_id: [5u8; 32],
+            reason: LoanOfferReason::UnacceptableTerms,
+        });
+    }
+
+    #[test]
+    fn loan_renewal_msg_roundtrip() {
+        let accept: AcceptDlc =
+            serde_json::from_str(include_str!("./test_inputs/accept_msg.json")).unwrap();
+        let sign: SignDlc =
+            serde_json::from_str(include_str!("./test_inputs/sign_msg.json")).unwrap();
+        test_roundtrip(RenewLoanOfferDlc {
+            protocol_version: 1,
+            contract_id: [6u8; 32],
+            additional_collateral: Amount::from_sat(50_000_000),
+            repaid_principal: 10_000,
+            funding_inputs: accept.funding_inputs,
+            change_spk: accept.change_spk,
+            cet_adaptor_signatures: accept.cet_adaptor_signatures,
+            refund_signature: accept.refund_signature,
+        });
+        test_roundtrip(RenewLoanAcceptDlc {
+            protocol_version: 1,
+            contract_id: [6u8; 32],
+            cet_adaptor_signatures: sign.cet_adaptor_signatures,
+            refund_signature: sign.refund_signature,
+        });
+        test_roundtrip(RenewLoanConfirmDlc {
+            protocol_version: 1,
+            contract_id: [6u8; 32],
+            collateral_signature: sign.refund_signature,
+            funding_signatures: sign.funding_signatures,
+        });
+    }
+
+    #[test]
+    fn repay_loan_msg_roundtrip() {
+        let repay = RepayLoanDlc {
+            protocol_version: 1,
+            contract_id: [6u8; 32],
+            borrower_hash: [9u8; 32],
+        };
+        test_roundtrip(repay.clone());
+        let json = serde_json::to_string(&repay).unwrap();
+        assert!(json.contains(&format!("\"borrowerHash\":\"{}\"", "09".repeat(32))));
+        assert_eq!(repay, serde_json::from_str(&json).unwrap());
+        test_roundtrip(RepayLoanAckDlc {
+            protocol_version: 1,
+            contract_id: [6u8; 32],
+            lender_preimage: 42,
+        });
+    }
+
     #[test]
     fn valid_offer_message_passes_validation() {
         let input = include_str!("./test_inputs/offer_msg.json");
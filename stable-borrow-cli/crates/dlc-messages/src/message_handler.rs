@@ -108,6 +108,18 @@ pub fn read_dlc_message<R: ::lightning::io::Read>(
         (ACCEPT_LOAN_TYPE, AcceptLoan),
         (INITIATE_ACCEPT_LOAN_TYPE, InitiateAcceptLoan),
         (SIGN_TYPE, Sign),
+        (REPAY_LOAN_TYPE, RepayLoan),
+        (REPAY_LOAN_ACK_TYPE, RepayLoanAck),
+        (REJECT_LOAN_TYPE, RejectLoan),
+        (CANCEL_LOAN_TYPE, CancelLoan),
+        (REQUEST_LOAN_TYPE, RequestLoan),
+        (REJECT_LOAN_REQUEST_TYPE, RejectLoanRequest),
+        (COUNTER_OFFER_LOAN_TYPE, CounterOfferLoan),
+        (REJECT_LOAN_COUNTER_OFFER_TYPE, RejectLoanCounterOffer),
+        (RENEW_LOAN_OFFER_TYPE, RenewLoanOffer),
+        (RENEW_LOAN_ACCEPT_TYPE, RenewLoanAccept),
+        (RENEW_LOAN_CONFIRM_TYPE, RenewLoanConfirm),
+        (LOAN_LISTING_TYPE, LoanListing),
         (OFFER_CHANNEL_TYPE, OfferChannel),
         (ACCEPT_CHANNEL_TYPE, AcceptChannel),
         (SIGN_CHANNEL_TYPE, SignChannel),
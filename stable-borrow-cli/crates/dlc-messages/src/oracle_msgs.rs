@@ -59,6 +59,19 @@ impl OracleInfo {
         }
     }
 
+    /// Returns the latest maturity date amongst all events
+    pub fn get_latest_maturity_date(&self) -> u32 {
+        match self {
+            OracleInfo::Single(s) => s.oracle_announcement.oracle_event.event_maturity_epoch,
+            OracleInfo::Multi(m) => m
+                .oracle_announcements
+                .iter()
+                .map(|x| x.oracle_event.event_maturity_epoch)
+                .max()
+                .expect("to have at least one event"),
+        }
+    }
+
     /// Checks that the info satisfies the validity conditions.
     pub fn validate<C: Verification>(&self, secp: &Secp256k1<C>) -> Result<(), Error> {
         match self {
@@ -193,6 +193,27 @@ pub fn read_f64<R: ::lightning::io::Read>(
     Ok(f64::from_be_bytes(buf))
 }
 
+/// Writes an EVM address to the given writer.
+pub fn write_evm_address<W: Writer>(
+    address: &[u8; 20],
+    writer: &mut W,
+) -> Result<(), ::lightning::io::Error> {
+    for b in address {
+        b.write(writer)?;
+    }
+
+    Ok(())
+}
+
+/// Reads an EVM address from the given reader.
+pub fn read_evm_address<R: Read>(reader: &mut R) -> Result<[u8; 20], DecodeError> {
+    let mut buf = [0u8; 20];
+    for b in &mut buf {
+        *b = Readable::read(reader)?;
+    }
+    Ok(buf)
+}
+
 /// Writes a [`secp256k1_zkp::schnorrsig::Signature`] value to the given writer.
 pub fn write_schnorrsig<W: lightning::util::ser::Writer>(
     signature: &secp256k1_zkp::schnorr::Signature,
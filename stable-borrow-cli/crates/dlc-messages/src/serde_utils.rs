@@ -49,6 +49,41 @@ where
     }
 }
 
+/// Serialize an EVM address as a 0x prefixed hexadecimal string.
+pub fn serialize_evm_address<S>(address: &[u8; 20], s: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    if s.is_human_readable() {
+        let string = address.iter().fold(String::from("0x"), |mut s, e| {
+            write!(s, "{:02x}", e).unwrap();
+            s
+        });
+        s.serialize_str(&string)
+    } else {
+        s.serialize_bytes(address)
+    }
+}
+
+/// Deserialize an EVM address represented as an hexadecimal string, with or
+/// without a 0x prefix.
+pub fn deserialize_evm_address<'de, D>(deserializer: D) -> Result<[u8; 20], D::Error>
+where
+    D: serde::de::Deserializer<'de>,
+{
+    if deserializer.is_human_readable() {
+        let string: String = serde::de::Deserialize::deserialize(deserializer)?;
+        let string = string.strip_prefix("0x").unwrap_or(&string);
+        let mut address = [0u8; 20];
+        if from_hex(string, &mut address).map_err(serde::de::Error::custom)? != 20 {
+            return Err(serde::de::Error::custom("Invalid EVM address length"));
+        }
+        Ok(address)
+    } else {
+        serde::de::Deserialize::deserialize(deserializer)
+    }
+}
+
 fn from_hex(hex: &str, target: &mut [u8]) -> Result<usize, String> {
     if hex.len() % 2 == 1 || hex.len() > target.len() * 2 {
         return Err("Invalid hex length".to_string());
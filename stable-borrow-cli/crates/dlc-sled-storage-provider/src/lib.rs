@@ -24,11 +24,16 @@ use dlc_manager::channel::{
     Channel, ClosedChannel, ClosedPunishedChannel, ClosingChannel, FailedAccept, FailedSign,
 };
 use dlc_manager::contract::accepted_contract::AcceptedContract;
+use dlc_manager::contract::loan_negotiation::LoanNegotiation;
+use dlc_manager::contract::loan_renewal::LoanRenewal;
+use dlc_manager::contract::loan_request::LoanRequest;
+use dlc_manager::contract::loan_state::LoanLifecycle;
 use dlc_manager::contract::offered_contract::{OfferedContract, OfferedLoanContract};
 use dlc_manager::contract::ser::Serializable;
 use dlc_manager::contract::signed_contract::SignedContract;
 use dlc_manager::contract::{
-    ClosedContract, Contract, FailedAcceptContract, FailedSignContract, PreClosedContract,
+    ClosedContract, Contract, EscrowReclaimedContract, FailedAcceptContract, FailedLoanContract,
+    FailedSignContract, LoanTerms, PreClosedContract, RepaidContract,
 };
 #[cfg(feature = "wallet")]
 use dlc_manager::Utxo;
@@ -56,7 +61,16 @@ const KEY_PAIR_TREE: u8 = 7;
 #[cfg(feature = "wallet")]
 const ADDRESS_TREE: u8 = 8;
 const PREIMAGE_TREE: u8 = 9;
-const ESCROW_TXID_TREE: u8 = 10;
+const ESCROW_OUTPOINT_TREE: u8 = 10;
+const BORROWER_HASH_TREE: u8 = 11;
+const LOAN_TERMS_TREE: u8 = 12;
+const LOAN_LIFECYCLE_TREE: u8 = 13;
+const LOAN_REQUEST_TREE: u8 = 14;
+const LOAN_NEGOTIATION_TREE: u8 = 15;
+const LOAN_RENEWAL_TREE: u8 = 16;
+
+/// The version of the loan terms record, written as its first byte.
+const LOAN_TERMS_VERSION: u8 = 1;
 
 /// Implementation of Storage interface using the sled DB backend.
 pub struct SledStorageProvider {
@@ -117,7 +131,13 @@ convertible_enum!(
         FailedAccept,
         FailedSign,
         Refunded,
-        Rejected,;
+        Rejected,
+        PreRepaid,
+        Repaid,
+        EscrowReclaimed,
+        FailedLoan,
+        RejectedLoan,
+        CancelledLoan,;
     },
     Contract
 );
@@ -211,8 +231,32 @@ impl SledStorageProvider {
         self.open_tree(&[PREIMAGE_TREE])
     }
 
-    fn escrow_txid_tree(&self) -> Result<Tree, Error> {
-        self.open_tree(&[ESCROW_TXID_TREE])
+    fn escrow_outpoint_tree(&self) -> Result<Tree, Error> {
+        self.open_tree(&[ESCROW_OUTPOINT_TREE])
+    }
+
+    fn borrower_hash_tree(&self) -> Result<Tree, Error> {
+        self.open_tree(&[BORROWER_HASH_TREE])
+    }
+
+    fn loan_terms_tree(&self) -> Result<Tree, Error> {
+        self.open_tree(&[LOAN_TERMS_TREE])
+    }
+
+    fn loan_lifecycle_tree(&self) -> Result<Tree, Error> {
+        self.open_tree(&[LOAN_LIFECYCLE_TREE])
+    }
+
+    fn loan_request_tree(&self) -> Result<Tree, Error> {
+        self.open_tree(&[LOAN_REQUEST_TREE])
+    }
+
+    fn loan_negotiation_tree(&self) -> Result<Tree, Error> {
+        self.open_tree(&[LOAN_NEGOTIATION_TREE])
+    }
+
+    fn loan_renewal_tree(&self) -> Result<Tree, Error> {
+        self.open_tree(&[LOAN_RENEWAL_TREE])
     }
 
     fn channel_tree(&self) -> Result<Tree, Error> {
@@ -295,24 +339,232 @@ impl Storage for SledStorageProvider {
         }
     }
 
-    fn set_escrow_txid(&self, contract_id: &ContractId, txid: lightning::bitcoin::Txid) -> Result<(), Error> {
-        self.escrow_txid_tree()?
-            .insert(contract_id, &txid.to_byte_array())
+    fn set_escrow_outpoint(
+        &self,
+        contract_id: &ContractId,
+        outpoint: lightning::bitcoin::OutPoint,
+    ) -> Result<(), Error> {
+        let mut value = outpoint.txid.to_byte_array().to_vec();
+        value.extend_from_slice(&outpoint.vout.to_be_bytes());
+        self.escrow_outpoint_tree()?
+            .insert(contract_id, value)
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    fn get_escrow_outpoint(
+        &self,
+        contract_id: &ContractId,
+    ) -> Result<Option<lightning::bitcoin::OutPoint>, Error> {
+        let res = match self
+            .escrow_outpoint_tree()?
+            .get(contract_id)
+            .map_err(to_storage_error)?
+        {
+            Some(res) => res,
+            None => return Ok(None),
+        };
+        let vout = match res.len() {
+            // Escrows stored with their txid only had the escrow output first.
+            32 => 0,
+            36 => u32::from_be_bytes([res[32], res[33], res[34], res[35]]),
+            _ => return Err(Error::StorageError("Invalid escrow outpoint".to_string())),
+        };
+        let txid = lightning::bitcoin::Txid::from_slice(&res[..32])
+            .map_err(|e| Error::StorageError(format!("Invalid escrow txid: {}", e)))?;
+        Ok(Some(lightning::bitcoin::OutPoint { txid, vout }))
+    }
+
+    fn set_loan_borrower_hash(
+        &self,
+        contract_id: &ContractId,
+        borrower_hash: &[u8; 32],
+    ) -> Result<(), Error> {
+        self.borrower_hash_tree()?
+            .insert(contract_id, borrower_hash)
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    fn get_loan_borrower_hash(&self, contract_id: &ContractId) -> Result<Option<[u8; 32]>, Error> {
+        match self
+            .borrower_hash_tree()?
+            .get(contract_id)
+            .map_err(to_storage_error)?
+        {
+            Some(res) => {
+                let hash: [u8; 32] = res.as_ref().try_into().map_err(|_| {
+                    Error::StorageError("Invalid borrower hash data length, expected 32 bytes".to_string())
+                })?;
+                Ok(Some(hash))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn set_loan_terms(&self, contract_id: &ContractId, loan_terms: &LoanTerms) -> Result<(), Error> {
+        let mut serialized = vec![LOAN_TERMS_VERSION];
+        serialized.append(&mut loan_terms.serialize()?);
+        self.loan_terms_tree()?
+            .insert(contract_id, serialized)
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    fn get_loan_terms(&self, contract_id: &ContractId) -> Result<Option<LoanTerms>, Error> {
+        let res = match self
+            .loan_terms_tree()?
+            .get(contract_id)
+            .map_err(to_storage_error)?
+        {
+            Some(res) => res,
+            None => return Ok(None),
+        };
+        match res.first() {
+            Some(&LOAN_TERMS_VERSION) => Ok(Some(
+                LoanTerms::deserialize(&mut Cursor::new(&res[1..])).map_err(to_storage_error)?,
+            )),
+            _ => Err(Error::StorageError(
+                "Unknown loan terms version".to_string(),
+            )),
+        }
+    }
+
+    fn update_loan_lifecycle(
+        &self,
+        contract_id: &ContractId,
+        lifecycle: &LoanLifecycle,
+    ) -> Result<(), Error> {
+        self.loan_lifecycle_tree()?
+            .insert(contract_id, lifecycle.serialize()?)
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    fn get_loan_lifecycle(&self, contract_id: &ContractId) -> Result<Option<LoanLifecycle>, Error> {
+        match self
+            .loan_lifecycle_tree()?
+            .get(contract_id)
+            .map_err(to_storage_error)?
+        {
+            Some(res) => Ok(Some(
+                LoanLifecycle::deserialize(&mut Cursor::new(&res)).map_err(to_storage_error)?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    fn get_loan_lifecycles(&self) -> Result<Vec<(ContractId, LoanLifecycle)>, Error> {
+        self.loan_lifecycle_tree()?
+            .iter()
+            .map(|x| {
+                let (key, value) = x.map_err(to_storage_error)?;
+                let contract_id: ContractId = key.as_ref().try_into().map_err(|_| {
+                    Error::StorageError("Invalid loan lifecycle key length".to_string())
+                })?;
+                let lifecycle = LoanLifecycle::deserialize(&mut Cursor::new(&value))
+                    .map_err(to_storage_error)?;
+                Ok((contract_id, lifecycle))
+            })
+            .collect()
+    }
+
+    fn upsert_loan_request(&self, loan_request: &LoanRequest) -> Result<(), Error> {
+        self.loan_request_tree()?
+            .insert(loan_request.id, loan_request.serialize()?)
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    fn get_loan_request(&self, request_id: &[u8; 32]) -> Result<Option<LoanRequest>, Error> {
+        match self
+            .loan_request_tree()?
+            .get(request_id)
+            .map_err(to_storage_error)?
+        {
+            Some(res) => Ok(Some(
+                LoanRequest::deserialize(&mut Cursor::new(&res)).map_err(to_storage_error)?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    fn get_loan_requests(&self) -> Result<Vec<LoanRequest>, Error> {
+        self.loan_request_tree()?
+            .iter()
+            .values()
+            .map(|x| {
+                let value = x.map_err(to_storage_error)?;
+                LoanRequest::deserialize(&mut Cursor::new(&value)).map_err(to_storage_error)
+            })
+            .collect()
+    }
+
+    fn upsert_loan_negotiation(&self, negotiation: &LoanNegotiation) -> Result<(), Error> {
+        self.loan_negotiation_tree()?
+            .insert(negotiation.id, negotiation.serialize()?)
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    fn get_loan_negotiation(
+        &self,
+        negotiation_id: &[u8; 32],
+    ) -> Result<Option<LoanNegotiation>, Error> {
+        match self
+            .loan_negotiation_tree()?
+            .get(negotiation_id)
+            .map_err(to_storage_error)?
+        {
+            Some(res) => Ok(Some(
+                LoanNegotiation::deserialize(&mut Cursor::new(&res)).map_err(to_storage_error)?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    fn get_loan_negotiations(&self) -> Result<Vec<LoanNegotiation>, Error> {
+        self.loan_negotiation_tree()?
+            .iter()
+            .values()
+            .map(|x| {
+                let value = x.map_err(to_storage_error)?;
+                LoanNegotiation::deserialize(&mut Cursor::new(&value)).map_err(to_storage_error)
+            })
+            .collect()
+    }
+
+    fn upsert_loan_renewal(&self, renewal: &LoanRenewal) -> Result<(), Error> {
+        self.loan_renewal_tree()?
+            .insert(renewal.contract_id, renewal.serialize()?)
             .map_err(to_storage_error)?;
         Ok(())
     }
 
-    fn get_escrow_txid(&self, contract_id: &ContractId) -> Result<Option<lightning::bitcoin::Txid>, Error> {
+    fn get_loan_renewal(&self, contract_id: &ContractId) -> Result<Option<LoanRenewal>, Error> {
         match self
-            .escrow_txid_tree()?
+            .loan_renewal_tree()?
             .get(contract_id)
             .map_err(to_storage_error)?
-        {   
-            Some(res) => Ok(Some(lightning::bitcoin::Txid::from_slice(&res).unwrap())),
+        {
+            Some(res) => Ok(Some(
+                LoanRenewal::deserialize(&mut Cursor::new(&res)).map_err(to_storage_error)?,
+            )),
             None => Ok(None),
         }
     }
 
+    fn get_loan_renewals(&self) -> Result<Vec<LoanRenewal>, Error> {
+        self.loan_renewal_tree()?
+            .iter()
+            .values()
+            .map(|x| {
+                let value = x.map_err(to_storage_error)?;
+                LoanRenewal::deserialize(&mut Cursor::new(&value)).map_err(to_storage_error)
+            })
+            .collect()
+    }
+
     fn delete_contract(&self, contract_id: &ContractId) -> Result<(), Error> {
         self.contract_tree()?
             .remove(contract_id)
@@ -370,6 +622,30 @@ impl Storage for SledStorageProvider {
         )
     }
 
+    fn get_escrow_confirmed_loan_contracts(&self) -> Result<Vec<OfferedLoanContract>, Error> {
+        self.get_data_with_prefix(
+            &self.contract_tree()?,
+            &[ContractPrefix::OfferedLoanEscrowConfirmed.into()],
+            None,
+        )
+    }
+
+    fn get_failed_loan_contracts(&self) -> Result<Vec<FailedLoanContract>, Error> {
+        self.get_data_with_prefix(
+            &self.contract_tree()?,
+            &[ContractPrefix::FailedLoan.into()],
+            None,
+        )
+    }
+
+    fn get_cancelled_loan_contracts(&self) -> Result<Vec<OfferedLoanContract>, Error> {
+        self.get_data_with_prefix(
+            &self.contract_tree()?,
+            &[ContractPrefix::CancelledLoan.into()],
+            None,
+        )
+    }
+
     fn get_preclosed_contracts(&self) -> Result<Vec<PreClosedContract>, Error> {
         self.get_data_with_prefix(
             &self.contract_tree()?,
@@ -378,6 +654,14 @@ impl Storage for SledStorageProvider {
         )
     }
 
+    fn get_prerepaid_contracts(&self) -> Result<Vec<RepaidContract>, Error> {
+        self.get_data_with_prefix(
+            &self.contract_tree()?,
+            &[ContractPrefix::PreRepaid.into()],
+            None,
+        )
+    }
+
     fn upsert_channel(&self, channel: Channel, contract: Option<Contract>) -> Result<(), Error> {
         let serialized = serialize_channel(&channel)?;
         let serialized_contract = match contract.as_ref() {
@@ -620,13 +904,19 @@ fn insert_contract(
 fn serialize_contract(contract: &Contract) -> Result<Vec<u8>, lightning::io::Error> {
     let serialized = match contract {
         Contract::Offered(o) | Contract::Rejected(o) => o.serialize(),
-        Contract::OfferedLoan(o) | Contract::OfferedLoanEscrowConfirmed(o) => o.serialize(),
+        Contract::OfferedLoan(o)
+        | Contract::OfferedLoanEscrowConfirmed(o)
+        | Contract::RejectedLoan(o)
+        | Contract::CancelledLoan(o) => o.serialize(),
         Contract::Accepted(o) => o.serialize(),
         Contract::Signed(o) | Contract::Confirmed(o) | Contract::Refunded(o) => o.serialize(),
         Contract::FailedAccept(c) => c.serialize(),
         Contract::FailedSign(c) => c.serialize(),
         Contract::PreClosed(c) => c.serialize(),
         Contract::Closed(c) => c.serialize(),
+        Contract::PreRepaid(c) | Contract::Repaid(c) => c.serialize(),
+        Contract::EscrowReclaimed(c) => c.serialize(),
+        Contract::FailedLoan(c) => c.serialize(),
     };
     let mut serialized = serialized?;
     let mut res = Vec::with_capacity(serialized.len() + 1);
@@ -679,6 +969,24 @@ fn deserialize_contract(buff: &sled::IVec) -> Result<Contract, Error> {
         ContractPrefix::Rejected => {
             Contract::Rejected(OfferedContract::deserialize(&mut cursor).map_err(to_storage_error)?)
         }
+        ContractPrefix::PreRepaid => Contract::PreRepaid(
+            RepaidContract::deserialize(&mut cursor).map_err(to_storage_error)?,
+        ),
+        ContractPrefix::Repaid => {
+            Contract::Repaid(RepaidContract::deserialize(&mut cursor).map_err(to_storage_error)?)
+        }
+        ContractPrefix::EscrowReclaimed => Contract::EscrowReclaimed(
+            EscrowReclaimedContract::deserialize(&mut cursor).map_err(to_storage_error)?,
+        ),
+        ContractPrefix::FailedLoan => Contract::FailedLoan(
+            FailedLoanContract::deserialize(&mut cursor).map_err(to_storage_error)?,
+        ),
+        ContractPrefix::RejectedLoan => Contract::RejectedLoan(
+            OfferedLoanContract::deserialize(&mut cursor).map_err(to_storage_error)?,
+        ),
+        ContractPrefix::CancelledLoan => Contract::CancelledLoan(
+            OfferedLoanContract::deserialize(&mut cursor).map_err(to_storage_error)?,
+        ),
     };
     Ok(contract)
 }
@@ -769,6 +1077,11 @@ fn get_utxo_key(txid: &Txid, vout: u32) -> Vec<u8> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use dlc_manager::contract::loan_negotiation::{LoanNegotiationState, LoanTermsRevision};
+    use dlc_manager::contract::loan_renewal::LoanRenewalState;
+    use dlc_manager::contract::loan_request::LoanRequestState;
+    use dlc_manager::contract::loan_state::LoanState;
+    use dlc_manager::LendingDeployment;
 
     macro_rules! sled_test {
         ($name: ident, $body: expr) => {
@@ -862,6 +1175,303 @@ mod tests {
         }
     );
 
+    sled_test!(
+        loan_terms_can_be_updated_and_retrieved,
+        |storage: SledStorageProvider| {
+            let contract_id = [1u8; 32];
+            assert!(storage
+                .get_loan_terms(&contract_id)
+                .expect("Error querying loan terms")
+                .is_none());
+
+            let mut loan_terms = get_loan_terms();
+            storage
+                .set_loan_terms(&contract_id, &loan_terms)
+                .expect("Error storing loan terms");
+            loan_terms.escrow_txid = Some(lightning::bitcoin::Txid::all_zeros());
+            storage
+                .set_loan_terms(&contract_id, &loan_terms)
+                .expect("Error updating loan terms");
+
+            let retrieved = storage
+                .get_loan_terms(&contract_id)
+                .expect("Error retrieving loan terms");
+            assert_eq!(Some(loan_terms), retrieved);
+        }
+    );
+
+    sled_test!(
+        loan_terms_survive_contract_state_transitions,
+        |storage: SledStorageProvider| {
+            let signed_contract: SignedContract =
+                deserialize_object(include_bytes!("../test_files/Signed"));
+            let offered_contract = signed_contract.accepted_contract.offered_contract.clone();
+            storage
+                .create_contract(&offered_contract)
+                .expect("Error creating contract");
+            let loan_terms = get_loan_terms();
+            storage
+                .set_loan_terms(&offered_contract.id, &loan_terms)
+                .expect("Error storing loan terms");
+
+            let signed_contract = Contract::Signed(signed_contract);
+            storage
+                .update_contract(&signed_contract)
+                .expect("Error updating contract");
+            let retrieved = storage
+                .get_loan_terms(&signed_contract.get_temporary_id())
+                .expect("Error retrieving loan terms");
+            assert_eq!(Some(loan_terms.clone()), retrieved);
+
+            let closed_contract = Contract::Closed(ClosedContract {
+                attestations: None,
+                signed_cet: None,
+                contract_id: signed_contract.get_id(),
+                temporary_contract_id: offered_contract.id,
+                counter_party_id: offered_contract.counter_party,
+                pnl: lightning::bitcoin::SignedAmount::ZERO,
+            });
+            storage
+                .update_contract(&closed_contract)
+                .expect("Error updating contract");
+            let retrieved = storage
+                .get_loan_terms(&closed_contract.get_temporary_id())
+                .expect("Error retrieving loan terms");
+            assert_eq!(Some(loan_terms), retrieved);
+        }
+    );
+
+    sled_test!(
+        unknown_loan_terms_version_is_rejected,
+        |storage: SledStorageProvider| {
+            let contract_id = [1u8; 32];
+            let mut serialized = vec![LOAN_TERMS_VERSION + 1];
+            serialized.append(&mut get_loan_terms().serialize().unwrap());
+            storage
+                .loan_terms_tree()
+                .unwrap()
+                .insert(contract_id, serialized)
+                .unwrap();
+
+            storage
+                .get_loan_terms(&contract_id)
+                .expect_err("an unknown version should not be read");
+        }
+    );
+
+    fn get_loan_terms() -> LoanTerms {
+        LoanTerms {
+            collateral_ratio: 150,
+            liquidation_ratio: 125,
+            interest_rate: 5,
+            duration: 604800,
+            principal: 40_000,
+            lending_deployment: LendingDeployment {
+                chain_id: 31337,
+                lending_contract_address: [4u8; 20],
+                stable_token_address: [5u8; 20],
+            },
+            lender_hash: [2u8; 32],
+            escrow_relative_locktime: 86400,
+            escrow_confirmations: 6,
+            offer_expiry: 1_700_000_000,
+            escrow_txid: None,
+            borrower_hash: Some([3u8; 32]),
+        }
+    }
+
+    sled_test!(
+        escrow_outpoint_can_be_updated_and_retrieved,
+        |storage: SledStorageProvider| {
+            let contract_id = [1u8; 32];
+            assert!(storage
+                .get_escrow_outpoint(&contract_id)
+                .expect("Error querying escrow outpoint")
+                .is_none());
+
+            let outpoint = lightning::bitcoin::OutPoint {
+                txid: lightning::bitcoin::Txid::from_byte_array([3; 32]),
+                vout: 2,
+            };
+            storage
+                .set_escrow_outpoint(&contract_id, outpoint)
+                .expect("Error storing escrow outpoint");
+            assert_eq!(
+                Some(outpoint),
+                storage.get_escrow_outpoint(&contract_id).unwrap()
+            );
+            assert_eq!(
+                Some(outpoint.txid),
+                storage.get_escrow_txid(&contract_id).unwrap()
+            );
+
+            // Escrow txids stored without their output index spent the first
+            // output.
+            storage
+                .escrow_outpoint_tree()
+                .unwrap()
+                .insert(contract_id, &outpoint.txid.to_byte_array())
+                .unwrap();
+            assert_eq!(
+                Some(lightning::bitcoin::OutPoint {
+                    vout: 0,
+                    ..outpoint
+                }),
+                storage.get_escrow_outpoint(&contract_id).unwrap()
+            );
+        }
+    );
+
+    sled_test!(
+        loan_lifecycle_can_be_updated_and_retrieved,
+        |storage: SledStorageProvider| {
+            let contract_id = [1u8; 32];
+            assert!(storage
+                .get_loan_lifecycle(&contract_id)
+                .expect("Error querying loan lifecycle")
+                .is_none());
+
+            let mut lifecycle = LoanLifecycle::new(true, 1);
+            storage
+                .update_loan_lifecycle(&contract_id, &lifecycle)
+                .expect("Error storing loan lifecycle");
+            lifecycle
+                .transition(LoanState::EscrowConfirmed, 2)
+                .expect("Error transitioning loan state");
+            storage
+                .update_loan_lifecycle(&contract_id, &lifecycle)
+                .expect("Error updating loan lifecycle");
+
+            let retrieved = storage
+                .get_loan_lifecycle(&contract_id)
+                .expect("Error retrieving loan lifecycle");
+            assert_eq!(Some(lifecycle.clone()), retrieved);
+
+            let lifecycles = storage
+                .get_loan_lifecycles()
+                .expect("Error retrieving loan lifecycles");
+            assert_eq!(vec![(contract_id, lifecycle)], lifecycles);
+        }
+    );
+
+    sled_test!(
+        loan_request_can_be_updated_and_retrieved,
+        |storage: SledStorageProvider| {
+            let counter_party = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+                .parse()
+                .unwrap();
+            let mut loan_request = LoanRequest::new(
+                [1u8; 32],
+                counter_party,
+                lightning::bitcoin::Amount::ONE_BTC,
+                40_000,
+                10,
+                86_400,
+            );
+            storage
+                .upsert_loan_request(&loan_request)
+                .expect("Error storing loan request");
+            loan_request.state = LoanRequestState::Offered;
+            loan_request.contract_id = Some([2u8; 32]);
+            storage
+                .upsert_loan_request(&loan_request)
+                .expect("Error updating loan request");
+
+            let retrieved = storage
+                .get_loan_request(&loan_request.id)
+                .expect("Error retrieving loan request");
+            assert_eq!(Some(loan_request.clone()), retrieved);
+
+            let loan_requests = storage
+                .get_loan_requests()
+                .expect("Error retrieving loan requests");
+            assert_eq!(vec![loan_request], loan_requests);
+        }
+    );
+
+    sled_test!(
+        loan_negotiation_can_be_updated_and_retrieved,
+        |storage: SledStorageProvider| {
+            let mut negotiation = LoanNegotiation {
+                id: [1u8; 32],
+                counter_party: "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+                    .parse()
+                    .unwrap(),
+                is_lender: true,
+                revisions: Vec::new(),
+                state: LoanNegotiationState::Offered,
+                contract_id: [1u8; 32],
+            };
+            storage
+                .upsert_loan_negotiation(&negotiation)
+                .expect("Error storing loan negotiation");
+            negotiation
+                .add_revision(LoanTermsRevision {
+                    countered_contract_id: [1u8; 32],
+                    collateral_ratio: 160,
+                    interest_rate: 8,
+                    duration: 86_400,
+                })
+                .expect("Error adding revision");
+            storage
+                .upsert_loan_negotiation(&negotiation)
+                .expect("Error updating loan negotiation");
+
+            let retrieved = storage
+                .get_loan_negotiation(&negotiation.id)
+                .expect("Error retrieving loan negotiation");
+            assert_eq!(Some(negotiation.clone()), retrieved);
+
+            let negotiations = storage
+                .get_loan_negotiations()
+                .expect("Error retrieving loan negotiations");
+            assert_eq!(vec![negotiation], negotiations);
+        }
+    );
+
+    sled_test!(
+        loan_renewal_can_be_updated_and_retrieved,
+        |storage: SledStorageProvider| {
+            let accepted_contract: AcceptedContract =
+                deserialize_object(include_bytes!("../test_files/Accepted"));
+            let signed_contract: SignedContract =
+                deserialize_object(include_bytes!("../test_files/Signed"));
+            let mut renewal = LoanRenewal {
+                contract_id: [1u8; 32],
+                counter_party: "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+                    .parse()
+                    .unwrap(),
+                is_borrower: true,
+                additional_collateral: lightning::bitcoin::Amount::from_sat(50_000),
+                repaid_principal: 10_000,
+                stable_loan_amount: 375_000,
+                state: LoanRenewalState::Offered,
+                accepted_contract,
+                signed_contract: None,
+            };
+            storage
+                .upsert_loan_renewal(&renewal)
+                .expect("Error storing loan renewal");
+            renewal.state = LoanRenewalState::Accepted;
+            renewal.signed_contract = Some(signed_contract);
+            storage
+                .upsert_loan_renewal(&renewal)
+                .expect("Error updating loan renewal");
+
+            let retrieved = storage
+                .get_loan_renewal(&renewal.contract_id)
+                .expect("Error retrieving loan renewal")
+                .expect("Loan renewal not found");
+            assert_eq!(renewal.serialize().unwrap(), retrieved.serialize().unwrap());
+            assert_eq!(LoanRenewalState::Accepted, retrieved.state);
+
+            let renewals = storage
+                .get_loan_renewals()
+                .expect("Error retrieving loan renewals");
+            assert_eq!(1, renewals.len());
+        }
+    );
+
     fn insert_offered_signed_and_confirmed(storage: &mut SledStorageProvider) {
         let serialized = include_bytes!("../test_files/Offered");
         let offered_contract = deserialize_object(serialized);
@@ -69,10 +69,27 @@ const CET_BASE_WEIGHT: usize = 500;
 /// See: <https://github.com/discreetlogcontracts/dlcspecs/blob/master/Transactions.md#fees>
 const TX_INPUT_BASE_WEIGHT: usize = 164;
 
+/// The base weight of a segwit transaction with less than 253 inputs and outputs computed as:
+/// (nVersion(4) + nLockTime(4) + input count(1) + output count(1)) * 4 + segwit marker and flag(2)
+const TX_BASE_WEIGHT: usize = 42;
+
+/// The weight of a P2WSH output computed as: (value(8) + scriptPubKeySize(1) + scriptPubKey(34)) * 4
+const P2WSH_OUTPUT_WEIGHT: usize = 172;
+
+/// The maximum size of an ECDSA signature with its sighash byte, including its length prefix
+const SIGNATURE_WITNESS_ITEM_SIZE: usize = 74;
+
+/// The size of a loan preimage including its length prefix
+const PREIMAGE_WITNESS_ITEM_SIZE: usize = 17;
+
 /// The witness size of a P2WPKH input
 /// See: <https://github.com/discreetlogcontracts/dlcspecs/blob/master/Transactions.md#fees>
 pub const P2WPKH_WITNESS_SIZE: usize = 107;
 
+/// The minimum delay in seconds after which the borrower can reclaim the loan
+/// escrow output, leaving the lender enough time to spend it into the collateral.
+pub const MIN_LOAN_ESCROW_RELATIVE_LOCKTIME: u32 = 86_400;
+
 macro_rules! checked_add {
     ($a: expr, $b: expr) => {
         $a.checked_add($b).ok_or(Error::InvalidArgument)
@@ -329,16 +346,6 @@ impl PartyParams {
         )?;
         let fund_fee = util::weight_to_fee(total_fund_weight, fee_rate_per_vb)?;
 
-        let collateral_input_weight = TX_INPUT_BASE_WEIGHT + (32 * 4); // P2WSH input weight
-        let collateral_output_weight = 8 + 4 + (32 + 2) * 4; // value + script_len + P2WSH script weight
-        let collateral_base_weight: usize = 8 + 4 + 4; // version + input_count + output_count + locktime
-        let total_collateral_weight = checked_add!(
-            collateral_base_weight,
-            collateral_input_weight,
-            collateral_output_weight
-        )?;
-        let collateral_fee = util::weight_to_fee(total_collateral_weight, fee_rate_per_vb)?;
-
         // Base weight (nLocktime, nVersion, funding input ...) is distributed
         // among parties independently of output types
         let this_party_cet_base_weight = CET_BASE_WEIGHT / 2;
@@ -352,19 +359,82 @@ impl PartyParams {
         let total_cet_weight = checked_add!(this_party_cet_base_weight, output_spk_weight)?;
         let cet_or_refund_fee = util::weight_to_fee(total_cet_weight, fee_rate_per_vb)?;
         let required_input_funds =
-            checked_add!(checked_add!(self.collateral, fund_fee, collateral_fee)?, cet_or_refund_fee, extra_fee)?;
+            checked_add!(self.collateral, fund_fee, cet_or_refund_fee, extra_fee)?;
         if self.input_amount < required_input_funds {
             return Err(Error::InvalidArgument);
         }
 
         let change_output = TxOut {
-            value: self.input_amount - required_input_funds - Amount::from_sat(100),
+            value: self.input_amount - required_input_funds,
             script_pubkey: self.change_script_pubkey.clone(),
         };
 
         Ok((change_output, fund_fee, cet_or_refund_fee))
     }
 
+    /// Returns the change output of the borrower of a loan as well as the fees
+    /// they are required to pay for the escrow transaction, the collateral
+    /// transaction spending it and the cet or refund transaction spending the
+    /// collateral. As the lender does not contribute any input, the borrower
+    /// pays for the whole weight of these transactions. The change output
+    /// value already accounts for the required fees. If input amount is lower
+    /// than the collateral plus the required fees, an error is returned.
+    pub(crate) fn get_loan_change_output_and_fees(
+        &self,
+        lender_params: &PartyParams,
+        escrow_redeemscript: &Script,
+        collateral_redeemscript: &Script,
+        fee_rate_per_vb: u64,
+        extra_fee: Amount,
+    ) -> Result<(TxOut, Amount, Amount, Amount), Error> {
+        let mut inputs_weight: usize = 0;
+
+        for w in &self.inputs {
+            let script_weight = util::redeem_script_to_script_sig(&w.redeem_script)
+                .len()
+                .checked_mul(4)
+                .ok_or(Error::InvalidArgument)?;
+            inputs_weight = checked_add!(
+                inputs_weight,
+                TX_INPUT_BASE_WEIGHT,
+                script_weight,
+                w.max_witness_len
+            )?;
+        }
+
+        let change_weight = get_output_weight(&self.change_script_pubkey)?;
+        let total_escrow_weight = checked_add!(
+            TX_BASE_WEIGHT,
+            inputs_weight,
+            P2WSH_OUTPUT_WEIGHT,
+            change_weight
+        )?;
+        let escrow_fee = util::weight_to_fee(total_escrow_weight, fee_rate_per_vb)?;
+
+        let collateral_fee = get_collateral_transaction_fee(escrow_redeemscript, fee_rate_per_vb)?;
+        let cet_or_refund_fee = get_loan_cet_fee(
+            collateral_redeemscript,
+            &lender_params.payout_script_pubkey,
+            fee_rate_per_vb,
+        )?;
+
+        let required_input_funds = checked_add!(
+            checked_add!(self.collateral, escrow_fee, collateral_fee)?,
+            cet_or_refund_fee,
+            extra_fee
+        )?;
+        if self.input_amount < required_input_funds {
+            return Err(Error::InvalidArgument);
+        }
+
+        let change_output = TxOut {
+            value: self.input_amount - required_input_funds,
+            script_pubkey: self.change_script_pubkey.clone(),
+        };
+
+        Ok((change_output, escrow_fee, collateral_fee, cet_or_refund_fee))
+    }
+
     fn get_unsigned_tx_inputs_and_serial_ids(&self, sequence: Sequence) -> (Vec<TxIn>, Vec<u64>) {
         let mut tx_ins = Vec::with_capacity(self.inputs.len());
         let mut serial_ids = Vec::with_capacity(self.inputs.len());
@@ -534,61 +604,67 @@ pub(crate) fn create_fund_transaction_with_fees(
     Ok((fund_tx, funding_script_pubkey))
 }
 
-/// Create the escrow transaction that is used for loan initiation.
+/// Create the escrow transaction that is used for loan initiation. The escrow
+/// output covers the collateral as well as the fees of the collateral
+/// transaction and of the cet or refund transaction, all paid by the borrower.
 pub fn create_escrow_transaction(
     offer_params: &PartyParams,
     accept_params: &PartyParams,
     fee_rate_per_vb: u64,
     fund_lock_time: u32,
-    fund_output_serial_id: u64,
     extra_fee: Amount,
-    borrower_preimage: u128
+    borrower_preimage: u128,
+    lender_hash: &[u8; 32],
+    escrow_relative_locktime: Sequence,
 ) -> Result<(Transaction, ScriptBuf), Error> {
     let total_collateral = accept_params.collateral;
-    println!("total_collateral: {}", total_collateral);
 
-    let (accept_change_output, accept_fund_fee, accept_cet_fee) =
-        accept_params.get_change_output_and_fees(fee_rate_per_vb, extra_fee)?;
+    let borrower_preimage_bytes = borrower_preimage.to_be_bytes();
+    let borrower_hash = bitcoin::hashes::sha256::Hash::hash(&borrower_preimage_bytes).to_byte_array();
+    let funding_script_pubkey = make_loan_funding_redeemscript(
+        &accept_params.fund_pubkey,
+        &offer_params.fund_pubkey,
+        escrow_relative_locktime,
+        &borrower_hash,
+    );
+    let collateral_script_pubkey = make_collateral_redeemscript(
+        &accept_params.fund_pubkey,
+        &offer_params.fund_pubkey,
+        lender_hash,
+    );
 
-    let fund_output_value = checked_add!(offer_params.input_amount, accept_params.input_amount)?
-        - accept_change_output.value
-        - accept_fund_fee
-        - accept_cet_fee
-        - extra_fee
-        + Amount::from_sat(100); // TODO: Fix this later, this is a hack
-
-    println!("offer_params.input_amount: {}", offer_params.input_amount);
-    println!("accept_params.input_amount: {}", accept_params.input_amount);
-
-    // assert_eq!(
-    //     total_collateral + accept_cet_fee + extra_fee,
-    //     fund_output_value
-    // ); TODO: Fix this later
-
-    // assert_eq!(
-    //     offer_params.input_amount + accept_params.input_amount,
-    //     fund_output_value
-    //         + accept_change_output.value
-    //         + accept_fund_fee
-    //         + extra_fee
-    // );
+    let (accept_change_output, accept_escrow_fee, accept_collateral_fee, accept_cet_fee) =
+        accept_params.get_loan_change_output_and_fees(
+            offer_params,
+            &funding_script_pubkey,
+            &collateral_script_pubkey,
+            fee_rate_per_vb,
+            extra_fee,
+        )?;
+
+    let fund_output_value = accept_params
+        .input_amount
+        .checked_sub(checked_add!(
+            accept_change_output.value,
+            accept_escrow_fee,
+            extra_fee
+        )?)
+        .ok_or(Error::InvalidArgument)?;
+
+    if checked_add!(
+        total_collateral,
+        accept_collateral_fee,
+        accept_cet_fee,
+        extra_fee
+    )? != fund_output_value
+    {
+        return Err(Error::InvalidArgument);
+    }
 
     let fund_sequence = util::get_sequence(fund_lock_time);
     let (accept_tx_ins, accept_inputs_serial_ids) =
         accept_params.get_unsigned_tx_inputs_and_serial_ids(fund_sequence);
 
-    println!("accept_tx_ins: {:?}", accept_tx_ins);
-
-
-    let borrower_preimage_bytes = borrower_preimage.to_be_bytes();
-    let borrower_hash = bitcoin::hashes::sha256::Hash::hash(&borrower_preimage_bytes).to_byte_array();
-    // Write the hex string 0x64004000
-    // TODO: Check if this is the correct value for the locktime
-    let locktime = Sequence::from_hex("0x64004000").unwrap(); // 51200 seconds
-    println!("lender pubkey: {}", offer_params.fund_pubkey);
-    let funding_script_pubkey = 
-        make_loan_funding_redeemscript(&accept_params.fund_pubkey, &offer_params.fund_pubkey, locktime, &borrower_hash);
-
     let fund_tx = create_escrow_transaction_internal(
         &funding_script_pubkey,
         fund_output_value,
@@ -598,24 +674,76 @@ pub fn create_escrow_transaction(
         0,
     );
 
-    println!("escrow_tx: {:?}", fund_tx);
+    Ok((fund_tx, funding_script_pubkey))
+}
+
+/// Returns the fee required for the collateral transaction spending a loan
+/// escrow output locked with the given redeem script through the lender branch.
+pub fn get_collateral_transaction_fee(
+    escrow_redeemscript: &Script,
+    fee_rate_per_vb: u64,
+) -> Result<Amount, Error> {
+    // Item count, lender signature, borrower preimage, borrower signature and redeem script.
+    let witness_weight = checked_add!(
+        1 + 2 * SIGNATURE_WITNESS_ITEM_SIZE,
+        PREIMAGE_WITNESS_ITEM_SIZE,
+        get_witness_script_weight(escrow_redeemscript)
+    )?;
+    let total_weight = checked_add!(
+        TX_BASE_WEIGHT,
+        TX_INPUT_BASE_WEIGHT,
+        witness_weight,
+        P2WSH_OUTPUT_WEIGHT
+    )?;
+    util::weight_to_fee(total_weight, fee_rate_per_vb)
+}
 
-    Ok((fund_tx, funding_script_pubkey))    
+/// Returns the fee required for the CET or refund transaction spending a loan
+/// collateral output locked with the given redeem script. The borrower payout
+/// script is only known once the loan is accepted, so the fee assumes the
+/// largest standard one, a P2WSH or P2TR output.
+pub fn get_loan_cet_fee(
+    collateral_redeemscript: &Script,
+    lender_payout_script_pubkey: &Script,
+    fee_rate_per_vb: u64,
+) -> Result<Amount, Error> {
+    // Empty item, two signatures and the collateral redeem script.
+    let cet_witness_weight = checked_add!(
+        2 * SIGNATURE_WITNESS_ITEM_SIZE,
+        2,
+        get_witness_script_weight(collateral_redeemscript)
+    )?;
+    let total_cet_weight = checked_add!(
+        TX_BASE_WEIGHT,
+        TX_INPUT_BASE_WEIGHT,
+        cet_witness_weight,
+        checked_add!(
+            get_output_weight(lender_payout_script_pubkey)?,
+            P2WSH_OUTPUT_WEIGHT
+        )?
+    )?;
+    util::weight_to_fee(total_cet_weight, fee_rate_per_vb)
 }
 
-/// Creates the collateral transaction that spends the escrow
+/// Creates the collateral transaction that spends the escrow. The fee is
+/// deducted from the escrow amount.
 pub fn create_collateral_transaction(
     borrower_pubkey: &PublicKey,
     lender_pubkey: &PublicKey,
-    collateral_amount: Amount,
+    escrow_amount: Amount,
     escrow_input: TxIn,
+    escrow_redeemscript: &Script,
     lender_hash: [u8; 32],
+    fee_rate_per_vb: u64,
 ) -> Result<(Transaction, ScriptBuf), Error> {
     let collateral_script_pubkey = 
         make_collateral_redeemscript(borrower_pubkey, lender_pubkey, &lender_hash);
 
+    let collateral_fee = get_collateral_transaction_fee(escrow_redeemscript, fee_rate_per_vb)?;
     let collateral_tx_out = TxOut {
-        value: collateral_amount - Amount::from_sat(166), // TODO: Fix hardcoded fees
+        value: escrow_amount
+            .checked_sub(collateral_fee)
+            .ok_or(Error::InvalidArgument)?,
         script_pubkey: collateral_script_pubkey.to_p2wsh(),
     };
 
@@ -629,6 +757,21 @@ pub fn create_collateral_transaction(
     Ok((collateral_tx, collateral_script_pubkey))
 }
 
+fn get_output_weight(script_pubkey: &Script) -> Result<usize, Error> {
+    // Value, script pubkey length prefix and script pubkey, scaled by 4 from vBytes to weight units.
+    checked_add!(
+        script_pubkey.len(),
+        8,
+        util::compute_var_int_prefix_size(script_pubkey.len())
+    )?
+    .checked_mul(4)
+    .ok_or(Error::InvalidArgument)
+}
+
+fn get_witness_script_weight(script: &Script) -> usize {
+    util::compute_var_int_prefix_size(script.len()) + script.len()
+}
+
 pub(crate) fn create_cets_and_refund_tx(
     offer_params: &PartyParams,
     accept_params: &PartyParams,
@@ -900,6 +1043,17 @@ pub fn make_loan_funding_redeemscript(borrower_pubkey: &PublicKey, lender_pubkey
         .into_script()
 }
 
+/// Converts the delay in seconds after which the borrower can reclaim the loan
+/// escrow output into the relative locktime of [`make_loan_funding_redeemscript`].
+/// The delay must be at least [`MIN_LOAN_ESCROW_RELATIVE_LOCKTIME`] and fit in
+/// a time based relative locktime.
+pub fn get_loan_escrow_relative_locktime(delay_secs: u32) -> Result<Sequence, Error> {
+    if delay_secs < MIN_LOAN_ESCROW_RELATIVE_LOCKTIME {
+        return Err(Error::InvalidArgument);
+    }
+    Sequence::from_seconds_ceil(delay_secs).map_err(|_| Error::InvalidArgument)
+}
+
 /// Create the script for the loan escrow after codesep
 pub fn make_loan_escrow_after_codesep_script(lender_pubkey: &PublicKey, borrower_hashlock_hash: &[u8; 32]) -> ScriptBuf {
     Builder::new()
@@ -916,7 +1070,11 @@ pub fn make_loan_escrow_after_codesep_script(lender_pubkey: &PublicKey, borrower
         .into_script()
 }
 
-/// Create the redeem script for the collateral transaction
+/// Create the redeem script for the collateral transaction. An empty branch
+/// selector spends through the 2-of-2 branch with the borrower and lender
+/// signatures, and a selector of `1` through the lender hashlock branch with
+/// the borrower signature and the lender preimage. Both selectors are minimally
+/// encoded so that spends are standard under `MINIMALIF`.
 pub fn make_collateral_redeemscript(borrower_pubkey: &PublicKey, lender_pubkey: &PublicKey, lender_hashlock_hash: &[u8; 32]) -> ScriptBuf {
     Builder::new()
         .push_slice(lender_pubkey.serialize())
@@ -931,11 +1089,7 @@ pub fn make_collateral_redeemscript(borrower_pubkey: &PublicKey, lender_pubkey:
         .push_opcode(opcodes::all::OP_ELSE)
         .push_opcode(opcodes::all::OP_CODESEPARATOR)
         .push_opcode(opcodes::all::OP_CHECKSIGVERIFY)
-        .push_opcode(opcodes::all::OP_1SUB)
-        .push_opcode(opcodes::all::OP_NOTIF)
-        .push_opcode(opcodes::all::OP_CHECKSIG)
-        .push_opcode(opcodes::all::OP_ELSE)
-        .push_opcode(opcodes::all::OP_DROP)
+        .push_opcode(opcodes::all::OP_2DROP)
         .push_opcode(opcodes::all::OP_SIZE)
         .push_int(16)
         .push_opcode(opcodes::all::OP_EQUALVERIFY)
@@ -943,7 +1097,6 @@ pub fn make_collateral_redeemscript(borrower_pubkey: &PublicKey, lender_pubkey:
         .push_slice(lender_hashlock_hash)
         .push_opcode(opcodes::all::OP_EQUAL)
         .push_opcode(opcodes::all::OP_ENDIF)
-        .push_opcode(opcodes::all::OP_ENDIF)
         .into_script()
 }
 
@@ -951,11 +1104,7 @@ pub fn make_collateral_redeemscript(borrower_pubkey: &PublicKey, lender_pubkey:
 pub fn make_collateral_after_codesep_script(lender_hashlock_hash: &[u8; 32]) -> ScriptBuf {
     Builder::new()
         .push_opcode(opcodes::all::OP_CHECKSIGVERIFY)
-        .push_opcode(opcodes::all::OP_1SUB)
-        .push_opcode(opcodes::all::OP_NOTIF)
-        .push_opcode(opcodes::all::OP_CHECKSIG)
-        .push_opcode(opcodes::all::OP_ELSE)
-        .push_opcode(opcodes::all::OP_DROP)
+        .push_opcode(opcodes::all::OP_2DROP)
         .push_opcode(opcodes::all::OP_SIZE)
         .push_int(16)
         .push_opcode(opcodes::all::OP_EQUALVERIFY)
@@ -963,11 +1112,294 @@ pub fn make_collateral_after_codesep_script(lender_hashlock_hash: &[u8; 32]) ->
         .push_slice(lender_hashlock_hash)
         .push_opcode(opcodes::all::OP_EQUAL)
         .push_opcode(opcodes::all::OP_ENDIF)
-        .push_opcode(opcodes::all::OP_ENDIF)
         .into_script()
     }
 
+/// Creates the transaction sweeping a loan collateral output to the borrower
+/// through the lender hashlock branch of [`make_collateral_redeemscript`]. The
+/// fee is deducted from the collateral amount and the returned transaction is
+/// unsigned.
+pub fn create_collateral_sweep_transaction(
+    collateral_outpoint: OutPoint,
+    collateral_amount: Amount,
+    collateral_redeemscript: &Script,
+    destination_script_pubkey: ScriptBuf,
+    fee_rate_per_vb: u64,
+) -> Result<Transaction, Error> {
+    // Item count, branch selector, signature, preimage and redeem script.
+    let witness_weight = checked_add!(
+        1 + 2 + SIGNATURE_WITNESS_ITEM_SIZE,
+        PREIMAGE_WITNESS_ITEM_SIZE,
+        get_witness_script_weight(collateral_redeemscript)
+    )?;
+    let total_weight = checked_add!(
+        TX_BASE_WEIGHT,
+        TX_INPUT_BASE_WEIGHT,
+        get_output_weight(&destination_script_pubkey)?,
+        witness_weight
+    )?;
+    let fee = util::weight_to_fee(total_weight, fee_rate_per_vb)?;
 
+    let sweep_value = collateral_amount
+        .checked_sub(fee)
+        .ok_or(Error::InvalidArgument)?;
+    if sweep_value < DUST_LIMIT {
+        return Err(Error::InvalidArgument);
+    }
+
+    Ok(Transaction {
+        version: TX_VERSION,
+        lock_time: LockTime::from_consensus(0),
+        input: vec![TxIn {
+            previous_output: collateral_outpoint,
+            script_sig: ScriptBuf::new(),
+            sequence: util::DISABLE_LOCKTIME,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: sweep_value,
+            script_pubkey: destination_script_pubkey,
+        }],
+    })
+}
+
+/// Signs the given input of a collateral sweep transaction with the borrower
+/// key and places the witness for the lender hashlock branch of
+/// [`make_collateral_redeemscript`] on it.
+pub fn sign_collateral_sweep_input<C: secp256k1_zkp::Signing>(
+    secp: &Secp256k1<C>,
+    sweep_tx: &mut Transaction,
+    input_index: usize,
+    borrower_sk: &SecretKey,
+    lender_preimage: u128,
+    collateral_redeemscript: &Script,
+    collateral_amount: Amount,
+) -> Result<(), Error> {
+    let lender_preimage_bytes = lender_preimage.to_be_bytes();
+    let lender_hash = bitcoin::hashes::sha256::Hash::hash(&lender_preimage_bytes).to_byte_array();
+    let borrower_signature = util::get_sig_for_tx_input(
+        secp,
+        sweep_tx,
+        input_index,
+        &make_collateral_after_codesep_script(&lender_hash),
+        collateral_amount,
+        bitcoin::EcdsaSighashType::All,
+        borrower_sk,
+    )?;
+
+    sweep_tx
+        .input
+        .get_mut(input_index)
+        .ok_or(Error::InvalidArgument)?
+        .witness = Witness::from_slice(&[
+        vec![1],
+        borrower_signature,
+        lender_preimage_bytes.to_vec(),
+        collateral_redeemscript.to_bytes(),
+    ]);
+
+    Ok(())
+}
+
+/// Creates the transaction returning a loan escrow output to the borrower
+/// through the relative timelock branch of [`make_loan_funding_redeemscript`].
+/// The input sequence is set to the escrow relative locktime so the
+/// transaction only becomes valid once it has elapsed. The fee is deducted
+/// from the escrow amount and the returned transaction is unsigned.
+pub fn create_escrow_reclaim_transaction(
+    escrow_outpoint: OutPoint,
+    escrow_amount: Amount,
+    escrow_redeemscript: &Script,
+    relative_locktime: Sequence,
+    destination_script_pubkey: ScriptBuf,
+    fee_rate_per_vb: u64,
+) -> Result<Transaction, Error> {
+    // Item count, signature and redeem script.
+    let witness_weight = checked_add!(
+        SIGNATURE_WITNESS_ITEM_SIZE,
+        1,
+        get_witness_script_weight(escrow_redeemscript)
+    )?;
+    let total_weight = checked_add!(
+        TX_BASE_WEIGHT,
+        TX_INPUT_BASE_WEIGHT,
+        get_output_weight(&destination_script_pubkey)?,
+        witness_weight
+    )?;
+    let fee = util::weight_to_fee(total_weight, fee_rate_per_vb)?;
+
+    let reclaim_value = escrow_amount
+        .checked_sub(fee)
+        .ok_or(Error::InvalidArgument)?;
+    if reclaim_value < DUST_LIMIT {
+        return Err(Error::InvalidArgument);
+    }
+
+    Ok(Transaction {
+        version: TX_VERSION,
+        lock_time: LockTime::from_consensus(0),
+        input: vec![TxIn {
+            previous_output: escrow_outpoint,
+            script_sig: ScriptBuf::new(),
+            sequence: relative_locktime,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: reclaim_value,
+            script_pubkey: destination_script_pubkey,
+        }],
+    })
+}
+
+/// Signs the given input of an escrow reclaim transaction with the borrower
+/// key and places the witness for the relative timelock branch of
+/// [`make_loan_funding_redeemscript`] on it. That branch is selected by the
+/// stack holding exactly the signature and the pushed borrower public key.
+pub fn sign_escrow_reclaim_input<C: secp256k1_zkp::Signing>(
+    secp: &Secp256k1<C>,
+    reclaim_tx: &mut Transaction,
+    input_index: usize,
+    borrower_sk: &SecretKey,
+    escrow_redeemscript: &Script,
+    escrow_amount: Amount,
+) -> Result<(), Error> {
+    let borrower_signature = util::get_sig_for_tx_input(
+        secp,
+        reclaim_tx,
+        input_index,
+        escrow_redeemscript,
+        escrow_amount,
+        bitcoin::EcdsaSighashType::All,
+        borrower_sk,
+    )?;
+
+    reclaim_tx
+        .input
+        .get_mut(input_index)
+        .ok_or(Error::InvalidArgument)?
+        .witness = Witness::from_slice(&[borrower_signature, escrow_redeemscript.to_bytes()]);
+
+    Ok(())
+}
+
+
+/// Creates the transaction renewing a loan collateral output. It spends the
+/// current collateral output through the 2-of-2 branch of
+/// [`make_collateral_redeemscript`] along with the given borrower inputs, and
+/// locks the current collateral plus the additional collateral in a new output
+/// with the same redeem script at index 0. The borrower pays the fee from its
+/// inputs and gets the remainder back on the change output, which is omitted
+/// when below the dust limit. The returned transaction is unsigned.
+pub fn create_collateral_renewal_transaction(
+    collateral_outpoint: OutPoint,
+    collateral_amount: Amount,
+    collateral_redeemscript: &Script,
+    additional_collateral: Amount,
+    borrower_inputs: &[TxInputInfo],
+    borrower_input_amount: Amount,
+    change_script_pubkey: ScriptBuf,
+    fee_rate_per_vb: u64,
+) -> Result<Transaction, Error> {
+    // Item count, empty item, two signatures and the redeem script.
+    let collateral_witness_weight = checked_add!(
+        2 + 2 * SIGNATURE_WITNESS_ITEM_SIZE,
+        get_witness_script_weight(collateral_redeemscript)
+    )?;
+    let mut inputs_weight = checked_add!(TX_INPUT_BASE_WEIGHT, collateral_witness_weight)?;
+    for input in borrower_inputs {
+        let script_weight = util::redeem_script_to_script_sig(&input.redeem_script)
+            .len()
+            .checked_mul(4)
+            .ok_or(Error::InvalidArgument)?;
+        inputs_weight = checked_add!(
+            inputs_weight,
+            TX_INPUT_BASE_WEIGHT,
+            script_weight,
+            input.max_witness_len
+        )?;
+    }
+    let total_weight = checked_add!(
+        TX_BASE_WEIGHT,
+        inputs_weight,
+        P2WSH_OUTPUT_WEIGHT,
+        get_output_weight(&change_script_pubkey)?
+    )?;
+    let fee = util::weight_to_fee(total_weight, fee_rate_per_vb)?;
+
+    let change_value = borrower_input_amount
+        .checked_sub(additional_collateral)
+        .and_then(|x| x.checked_sub(fee))
+        .ok_or(Error::InvalidArgument)?;
+
+    let mut sorted_inputs = borrower_inputs.to_vec();
+    sorted_inputs.sort_by_key(|x| x.serial_id);
+    let mut input = vec![TxIn {
+        previous_output: collateral_outpoint,
+        script_sig: ScriptBuf::new(),
+        sequence: util::DISABLE_LOCKTIME,
+        witness: Witness::new(),
+    }];
+    input.extend(sorted_inputs.iter().map(|x| TxIn {
+        previous_output: x.outpoint,
+        script_sig: util::redeem_script_to_script_sig(&x.redeem_script),
+        sequence: util::DISABLE_LOCKTIME,
+        witness: Witness::new(),
+    }));
+
+    let mut output = vec![TxOut {
+        value: checked_add!(collateral_amount, additional_collateral)?,
+        script_pubkey: collateral_redeemscript.to_p2wsh(),
+    }];
+    if change_value >= DUST_LIMIT {
+        output.push(TxOut {
+            value: change_value,
+            script_pubkey: change_script_pubkey,
+        });
+    }
+
+    Ok(Transaction {
+        version: TX_VERSION,
+        lock_time: LockTime::from_consensus(0),
+        input,
+        output,
+    })
+}
+
+/// Signs the collateral input of a collateral renewal transaction with the
+/// lender key and places it on the witness along with the given borrower
+/// signature, for the 2-of-2 branch of [`make_collateral_redeemscript`].
+pub fn sign_collateral_renewal_input<C: secp256k1_zkp::Signing>(
+    secp: &Secp256k1<C>,
+    renewal_tx: &mut Transaction,
+    input_index: usize,
+    borrower_signature: &Signature,
+    lender_sk: &SecretKey,
+    collateral_redeemscript: &Script,
+    collateral_amount: Amount,
+) -> Result<(), Error> {
+    let lender_signature = util::get_sig_for_tx_input(
+        secp,
+        renewal_tx,
+        input_index,
+        collateral_redeemscript,
+        collateral_amount,
+        bitcoin::EcdsaSighashType::All,
+        lender_sk,
+    )?;
+
+    renewal_tx
+        .input
+        .get_mut(input_index)
+        .ok_or(Error::InvalidArgument)?
+        .witness = Witness::from_slice(&[
+        Vec::new(),
+        util::finalize_sig(borrower_signature, bitcoin::EcdsaSighashType::All),
+        lender_signature,
+        collateral_redeemscript.to_bytes(),
+    ]);
+
+    Ok(())
+}
 
 fn get_oracle_sig_point<C: secp256k1_zkp::Verification>(
     secp: &Secp256k1<C>,
@@ -1289,6 +1721,197 @@ mod tests {
         assert_eq!(3, refund_transaction.input[0].sequence.0);
     }
 
+    #[test]
+    fn get_loan_escrow_relative_locktime_test() {
+        assert!(get_loan_escrow_relative_locktime(MIN_LOAN_ESCROW_RELATIVE_LOCKTIME - 1).is_err());
+        assert!(get_loan_escrow_relative_locktime(u32::MAX).is_err());
+        let relative_locktime = get_loan_escrow_relative_locktime(8_388_608).unwrap();
+        assert!(relative_locktime.is_time_locked());
+        assert_eq!(Sequence(0x00404000), relative_locktime);
+    }
+
+    #[test]
+    fn create_escrow_reclaim_transaction_test() {
+        let secp = Secp256k1::new();
+        let mut rng = secp256k1_zkp::rand::thread_rng();
+        let borrower_sk = SecretKey::new(&mut rng);
+        let borrower_pk = PublicKey::from_secret_key(&secp, &borrower_sk);
+        let lender_pk = PublicKey::from_secret_key(&secp, &SecretKey::new(&mut rng));
+        let escrow_relative_locktime =
+            get_loan_escrow_relative_locktime(MIN_LOAN_ESCROW_RELATIVE_LOCKTIME).unwrap();
+        let escrow_redeemscript = make_loan_funding_redeemscript(
+            &borrower_pk,
+            &lender_pk,
+            escrow_relative_locktime,
+            &[1; 32],
+        );
+        let escrow_amount = Amount::from_sat(100_000);
+
+        let mut reclaim_tx = create_escrow_reclaim_transaction(
+            OutPoint::default(),
+            escrow_amount,
+            &escrow_redeemscript,
+            escrow_relative_locktime,
+            ScriptBuf::new(),
+            2,
+        )
+        .expect("to be able to create the reclaim transaction");
+        sign_escrow_reclaim_input(
+            &secp,
+            &mut reclaim_tx,
+            0,
+            &borrower_sk,
+            &escrow_redeemscript,
+            escrow_amount,
+        )
+        .expect("to be able to sign the reclaim transaction");
+
+        assert_eq!(escrow_relative_locktime, reclaim_tx.input[0].sequence);
+        assert!(reclaim_tx.output[0].value < escrow_amount);
+        assert_eq!(2, reclaim_tx.input[0].witness.len());
+        assert_eq!(
+            escrow_redeemscript.as_bytes(),
+            reclaim_tx.input[0].witness.last().unwrap()
+        );
+    }
+
+    #[test]
+    fn create_collateral_renewal_transaction_test() {
+        let secp = Secp256k1::new();
+        let mut rng = secp256k1_zkp::rand::thread_rng();
+        let borrower_sk = SecretKey::new(&mut rng);
+        let lender_sk = SecretKey::new(&mut rng);
+        let borrower_pk = PublicKey::from_secret_key(&secp, &borrower_sk);
+        let lender_pk = PublicKey::from_secret_key(&secp, &lender_sk);
+        let collateral_redeemscript =
+            make_collateral_redeemscript(&borrower_pk, &lender_pk, &[1; 32]);
+        let collateral_amount = Amount::from_sat(100_000);
+        let additional_collateral = Amount::from_sat(50_000);
+        let borrower_inputs = vec![
+            TxInputInfo {
+                outpoint: OutPoint::default(),
+                max_witness_len: P2WPKH_WITNESS_SIZE,
+                redeem_script: ScriptBuf::new(),
+                serial_id: 2,
+            },
+            TxInputInfo {
+                outpoint: OutPoint::default(),
+                max_witness_len: P2WPKH_WITNESS_SIZE,
+                redeem_script: ScriptBuf::new(),
+                serial_id: 1,
+            },
+        ];
+
+        create_collateral_renewal_transaction(
+            OutPoint::default(),
+            collateral_amount,
+            &collateral_redeemscript,
+            additional_collateral,
+            &borrower_inputs,
+            additional_collateral,
+            ScriptBuf::new(),
+            2,
+        )
+        .expect_err("should not create the renewal without funds for the fee");
+
+        let mut renewal_tx = create_collateral_renewal_transaction(
+            OutPoint::default(),
+            collateral_amount,
+            &collateral_redeemscript,
+            additional_collateral,
+            &borrower_inputs,
+            Amount::from_sat(80_000),
+            ScriptBuf::new(),
+            2,
+        )
+        .expect("to be able to create the renewal transaction");
+
+        assert_eq!(3, renewal_tx.input.len());
+        assert_eq!(
+            collateral_amount + additional_collateral,
+            renewal_tx.output[0].value
+        );
+        assert_eq!(
+            collateral_redeemscript.to_p2wsh(),
+            renewal_tx.output[0].script_pubkey
+        );
+        assert!(renewal_tx.output[1].value < Amount::from_sat(30_000));
+
+        let borrower_signature = util::get_raw_sig_for_tx_input(
+            &secp,
+            &renewal_tx,
+            0,
+            &collateral_redeemscript,
+            collateral_amount,
+            &borrower_sk,
+        )
+        .unwrap();
+        sign_collateral_renewal_input(
+            &secp,
+            &mut renewal_tx,
+            0,
+            &borrower_signature,
+            &lender_sk,
+            &collateral_redeemscript,
+            collateral_amount,
+        )
+        .expect("to be able to sign the renewal transaction");
+
+        assert_eq!(4, renewal_tx.input[0].witness.len());
+        assert_eq!(
+            collateral_redeemscript.as_bytes(),
+            renewal_tx.input[0].witness.last().unwrap()
+        );
+    }
+
+    #[test]
+    fn create_collateral_sweep_transaction_test() {
+        let secp = Secp256k1::new();
+        let mut rng = secp256k1_zkp::rand::thread_rng();
+        let borrower_sk = SecretKey::new(&mut rng);
+        let borrower_pk = PublicKey::from_secret_key(&secp, &borrower_sk);
+        let lender_pk = PublicKey::from_secret_key(&secp, &SecretKey::new(&mut rng));
+        let lender_preimage: u128 = 42;
+        let lender_hash = sha256::Hash::hash(&lender_preimage.to_be_bytes()).to_byte_array();
+        let collateral_redeemscript =
+            make_collateral_redeemscript(&borrower_pk, &lender_pk, &lender_hash);
+        let collateral_amount = Amount::from_sat(100_000);
+
+        let mut sweep_tx = create_collateral_sweep_transaction(
+            OutPoint::default(),
+            collateral_amount,
+            &collateral_redeemscript,
+            ScriptBuf::new(),
+            2,
+        )
+        .expect("to be able to create the sweep transaction");
+        assert!(sweep_tx.output[0].value < collateral_amount);
+
+        sign_collateral_sweep_input(
+            &secp,
+            &mut sweep_tx,
+            0,
+            &borrower_sk,
+            lender_preimage,
+            &collateral_redeemscript,
+            collateral_amount,
+        )
+        .expect("to be able to sign the sweep transaction");
+
+        let witness = &sweep_tx.input[0].witness;
+        assert_eq!(4, witness.len());
+        // The branch selector must be minimally encoded to satisfy MINIMALIF.
+        assert_eq!(Some(&[1u8][..]), witness.nth(0));
+        assert_eq!(Some(&lender_preimage.to_be_bytes()[..]), witness.nth(2));
+        assert_eq!(
+            collateral_redeemscript.as_bytes(),
+            witness.last().unwrap()
+        );
+        assert!(collateral_redeemscript
+            .as_bytes()
+            .ends_with(make_collateral_after_codesep_script(&lender_hash).as_bytes()));
+    }
+
     #[test]
     fn create_funding_transaction_test() {
         let (pk, pk1) = create_multi_party_pub_keys();
@@ -1581,6 +2204,89 @@ mod tests {
         assert!(res.is_err());
     }
 
+    #[test]
+    fn create_escrow_and_collateral_transactions_pay_fees_test() {
+        // Arrange
+        let (lender_params, _) = get_party_params(Amount::ZERO, Amount::ZERO, None);
+        let (borrower_params, _) =
+            get_party_params(Amount::from_sat(200_000), Amount::from_sat(100_000), Some(2));
+        let lender_hash = [2; 32];
+        let fee_rate_per_vb = 10;
+
+        // Act
+        let (escrow_tx, escrow_redeemscript) = create_escrow_transaction(
+            &lender_params,
+            &borrower_params,
+            fee_rate_per_vb,
+            0,
+            Amount::ZERO,
+            1,
+            &lender_hash,
+            Sequence::from_height(144),
+        )
+        .unwrap();
+        let escrow_amount = escrow_tx.output[0].value;
+        let (collateral_tx, _) = create_collateral_transaction(
+            &borrower_params.fund_pubkey,
+            &lender_params.fund_pubkey,
+            escrow_amount,
+            TxIn {
+                previous_output: OutPoint {
+                    txid: escrow_tx.compute_txid(),
+                    vout: 0,
+                },
+                ..Default::default()
+            },
+            &escrow_redeemscript,
+            lender_hash,
+            fee_rate_per_vb,
+        )
+        .unwrap();
+
+        // Assert
+        let collateral_fee =
+            get_collateral_transaction_fee(&escrow_redeemscript, fee_rate_per_vb).unwrap();
+        assert!(collateral_fee > Amount::ZERO);
+        assert_eq!(escrow_amount - collateral_fee, collateral_tx.output[0].value);
+        let cet_fee = get_loan_cet_fee(
+            &make_collateral_redeemscript(
+                &borrower_params.fund_pubkey,
+                &lender_params.fund_pubkey,
+                &lender_hash,
+            ),
+            &lender_params.payout_script_pubkey,
+            fee_rate_per_vb,
+        )
+        .unwrap();
+        assert_eq!(
+            borrower_params.collateral + cet_fee,
+            collateral_tx.output[0].value
+        );
+        assert!(
+            escrow_amount + escrow_tx.output[1].value < borrower_params.input_amount
+        );
+    }
+
+    #[test]
+    fn create_escrow_transaction_not_enough_funds_test() {
+        let (lender_params, _) = get_party_params(Amount::ZERO, Amount::ZERO, None);
+        let (borrower_params, _) =
+            get_party_params(Amount::from_sat(100_000), Amount::from_sat(100_000), Some(2));
+
+        let res = create_escrow_transaction(
+            &lender_params,
+            &borrower_params,
+            10,
+            0,
+            Amount::ZERO,
+            1,
+            &[2; 32],
+            Sequence::from_height(144),
+        );
+
+        assert!(res.is_err());
+    }
+
     #[test]
     fn create_dlc_transactions_no_error() {
         // Arrange
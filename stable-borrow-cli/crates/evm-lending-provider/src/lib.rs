@@ -0,0 +1,539 @@
+//! # evm-lending-provider
+//! Implementation of the lending backend interface on top of the P2PBTCLending
+//! contract deployed on an EVM chain.
+
+#![crate_name = "evm_lending_provider"]
+// Coding conventions
+#![deny(non_upper_case_globals)]
+#![deny(non_camel_case_types)]
+#![deny(non_snake_case)]
+#![deny(unused_mut)]
+#![deny(dead_code)]
+#![deny(unused_imports)]
+#![deny(missing_docs)]
+
+extern crate alloy;
+extern crate async_trait;
+extern crate bitcoin;
+extern crate dlc_manager;
+
+use alloy::primitives::{Address, FixedBytes, I256, U256};
+use alloy::providers::{DynProvider, Provider, ProviderBuilder};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::sol;
+use bitcoin::Amount;
+use dlc_manager::error::Error as ManagerError;
+use dlc_manager::{LendingBackend, LendingDeployment, StableLoan};
+
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    P2PBTCLending,
+    "../../../out/P2PBTCLending.sol/P2PBTCLending.json",
+);
+
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    interface IERC20 {
+        function approve(address spender, uint256 amount) external returns (bool);
+        function decimals() external view returns (uint8);
+    }
+);
+
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    interface AggregatorV3Interface {
+        function decimals() external view returns (uint8);
+        function latestRoundData() external view returns (uint80 roundId, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound);
+    }
+);
+
+/// The number of seconds after the current time up to which the interest
+/// owed on a repayment is approved, covering the time for the repayment
+/// transaction to be mined.
+const REPAYMENT_APPROVAL_MARGIN_SECS: u64 = 3600;
+
+/// Enables interacting with the P2PBTCLending contract through an EVM node.
+pub struct EvmLendingProvider {
+    lending_contract: P2PBTCLending::P2PBTCLendingInstance<DynProvider>,
+}
+
+impl EvmLendingProvider {
+    /// Creates a new [`EvmLendingProvider`] for the lending contract at the
+    /// given address, sending transactions signed with the given private key
+    /// to the node at `rpc_url`.
+    pub fn new(
+        rpc_url: &str,
+        lending_contract_address: &str,
+        private_key: &str,
+    ) -> Result<Self, ManagerError> {
+        let signer: PrivateKeySigner = private_key
+            .parse()
+            .map_err(|_| ManagerError::InvalidParameters("Invalid EVM private key".to_string()))?;
+        let rpc_url = rpc_url
+            .parse()
+            .map_err(|_| ManagerError::InvalidParameters("Invalid EVM RPC url".to_string()))?;
+        let lending_contract_address = Address::parse_checksummed(lending_contract_address, None)
+            .map_err(|_| {
+                ManagerError::InvalidParameters("Invalid lending contract address".to_string())
+            })?;
+        let provider = ProviderBuilder::new()
+            .wallet(signer)
+            .connect_http(rpc_url)
+            .erased();
+
+        Ok(Self::with_provider(lending_contract_address, provider))
+    }
+
+    fn with_provider(lending_contract_address: Address, provider: DynProvider) -> Self {
+        EvmLendingProvider {
+            lending_contract: P2PBTCLending::new(lending_contract_address, provider),
+        }
+    }
+}
+
+fn lending_err<E: std::fmt::Display>(msg: &str, e: E) -> ManagerError {
+    ManagerError::LendingError(format!("{}: {}", msg, e))
+}
+
+#[async_trait::async_trait]
+impl LendingBackend for EvmLendingProvider {
+    async fn get_stable_loan(&self, borrower_hash: &[u8; 32]) -> Result<StableLoan, ManagerError> {
+        let stable_loan = self
+            .lending_contract
+            .stableLoans(FixedBytes(*borrower_hash))
+            .call()
+            .await
+            .map_err(|e| lending_err("Failed to call stableLoans", e))?;
+        let collateral_amount: u64 = stable_loan
+            .collateralAmount
+            .try_into()
+            .map_err(|_| ManagerError::OutOfRange)?;
+        let stable_loan_amount: u128 = stable_loan
+            .stableLoanAmount
+            .try_into()
+            .map_err(|_| ManagerError::OutOfRange)?;
+        let interest_rate: u64 = stable_loan
+            .interestRate
+            .try_into()
+            .map_err(|_| ManagerError::OutOfRange)?;
+        let start_time: u64 = stable_loan
+            .startTime
+            .try_into()
+            .map_err(|_| ManagerError::OutOfRange)?;
+        let accrued_interest: u128 = stable_loan
+            .accruedInterest
+            .try_into()
+            .map_err(|_| ManagerError::OutOfRange)?;
+
+        Ok(StableLoan {
+            collateral_amount: Amount::from_sat(collateral_amount),
+            stable_loan_amount,
+            borrower_preimage: stable_loan.borrowerPreimage,
+            repaid: stable_loan.repaid,
+            interest_rate,
+            start_time,
+            accrued_interest,
+        })
+    }
+
+    async fn lend_stable(
+        &self,
+        borrower_hash: &[u8; 32],
+        collateral: Amount,
+        stable_loan_amount: u128,
+        interest_rate: u64,
+        collateral_ratio: u64,
+    ) -> Result<(), ManagerError> {
+        self.lending_contract
+            .lendStable(
+                FixedBytes(*borrower_hash),
+                U256::from(collateral.to_sat()),
+                U256::from(stable_loan_amount),
+                U256::from(interest_rate),
+                U256::from(collateral_ratio),
+            )
+            .send()
+            .await
+            .map_err(|e| lending_err("Failed to send lendStable transaction", e))?
+            .get_receipt()
+            .await
+            .map_err(|e| lending_err("Failed to get lendStable transaction receipt", e))?;
+
+        Ok(())
+    }
+
+    async fn claim_stable(&self, borrower_preimage: u128) -> Result<(), ManagerError> {
+        self.lending_contract
+            .claimStable(borrower_preimage)
+            .send()
+            .await
+            .map_err(|e| lending_err("Failed to send claimStable transaction", e))?
+            .get_receipt()
+            .await
+            .map_err(|e| lending_err("Failed to get claimStable transaction receipt", e))?;
+
+        Ok(())
+    }
+
+    async fn reclaim_stable(&self, borrower_hash: &[u8; 32]) -> Result<(), ManagerError> {
+        self.lending_contract
+            .reclaimStable(FixedBytes(*borrower_hash))
+            .send()
+            .await
+            .map_err(|e| lending_err("Failed to send reclaimStable transaction", e))?
+            .get_receipt()
+            .await
+            .map_err(|e| lending_err("Failed to get reclaimStable transaction receipt", e))?;
+
+        Ok(())
+    }
+
+    async fn repay_stable(&self, borrower_hash: &[u8; 32]) -> Result<(), ManagerError> {
+        let borrower_hash = FixedBytes(*borrower_hash);
+        // Interest accrues until the repayment is mined, so approve the amount
+        // due a little later than now.
+        let approval_time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|e| lending_err("Invalid system time", e))?
+            .as_secs()
+            + REPAYMENT_APPROVAL_MARGIN_SECS;
+        let amount_due = self
+            .lending_contract
+            .getStableAmountDue(borrower_hash, U256::from(approval_time))
+            .call()
+            .await
+            .map_err(|e| lending_err("Failed to call getStableAmountDue", e))?;
+        let stable_token_address = self
+            .lending_contract
+            .stableToken()
+            .call()
+            .await
+            .map_err(|e| lending_err("Failed to call stableToken", e))?;
+
+        // The lending contract pulls the repayment from the borrower.
+        IERC20::new(stable_token_address, self.lending_contract.provider().clone())
+            .approve(*self.lending_contract.address(), amount_due)
+            .send()
+            .await
+            .map_err(|e| lending_err("Failed to send approve transaction", e))?
+            .get_receipt()
+            .await
+            .map_err(|e| lending_err("Failed to get approve transaction receipt", e))?;
+
+        self.lending_contract
+            .repayStable(borrower_hash)
+            .send()
+            .await
+            .map_err(|e| lending_err("Failed to send repayStable transaction", e))?
+            .get_receipt()
+            .await
+            .map_err(|e| lending_err("Failed to get repayStable transaction receipt", e))?;
+
+        Ok(())
+    }
+
+    async fn repay_stable_partially(
+        &self,
+        borrower_hash: &[u8; 32],
+        amount: u128,
+    ) -> Result<(), ManagerError> {
+        let stable_token_address = self
+            .lending_contract
+            .stableToken()
+            .call()
+            .await
+            .map_err(|e| lending_err("Failed to call stableToken", e))?;
+
+        IERC20::new(stable_token_address, self.lending_contract.provider().clone())
+            .approve(*self.lending_contract.address(), U256::from(amount))
+            .send()
+            .await
+            .map_err(|e| lending_err("Failed to send approve transaction", e))?
+            .get_receipt()
+            .await
+            .map_err(|e| lending_err("Failed to get approve transaction receipt", e))?;
+
+        self.lending_contract
+            .repayStablePartially(FixedBytes(*borrower_hash), U256::from(amount))
+            .send()
+            .await
+            .map_err(|e| lending_err("Failed to send repayStablePartially transaction", e))?
+            .get_receipt()
+            .await
+            .map_err(|e| {
+                lending_err("Failed to get repayStablePartially transaction receipt", e)
+            })?;
+
+        Ok(())
+    }
+
+    async fn get_collateral_value(&self, collateral: Amount) -> Result<u128, ManagerError> {
+        self.lending_contract
+            .getStableCollateralValue(U256::from(collateral.to_sat()))
+            .call()
+            .await
+            .map_err(|e| lending_err("Failed to call getStableCollateralValue", e))?
+            .try_into()
+            .map_err(|_| ManagerError::OutOfRange)
+    }
+
+    async fn get_stable_unit(&self) -> Result<u128, ManagerError> {
+        let stable_token_address = self
+            .lending_contract
+            .stableToken()
+            .call()
+            .await
+            .map_err(|e| lending_err("Failed to call stableToken", e))?;
+        let decimals = IERC20::new(stable_token_address, self.lending_contract.provider().clone())
+            .decimals()
+            .call()
+            .await
+            .map_err(|e| lending_err("Failed to call decimals", e))?;
+
+        // Principals are expressed in whole stable tokens.
+        10u128
+            .checked_pow(decimals as u32)
+            .ok_or(ManagerError::OutOfRange)
+    }
+
+    async fn get_btc_price(&self) -> Result<u64, ManagerError> {
+        let btc_price_feed_address = self
+            .lending_contract
+            .btcPriceFeed()
+            .call()
+            .await
+            .map_err(|e| lending_err("Failed to call btcPriceFeed", e))?;
+        let btc_price_feed = AggregatorV3Interface::new(
+            btc_price_feed_address,
+            self.lending_contract.provider().clone(),
+        );
+        let round_data = btc_price_feed
+            .latestRoundData()
+            .call()
+            .await
+            .map_err(|e| lending_err("Failed to call latestRoundData", e))?;
+        let decimals = btc_price_feed
+            .decimals()
+            .call()
+            .await
+            .map_err(|e| lending_err("Failed to call decimals", e))?;
+
+        if round_data.answer <= I256::ZERO {
+            return Err(ManagerError::LendingError(
+                "Invalid BTC price data".to_string(),
+            ));
+        }
+        // The feed answer is scaled by its number of decimals.
+        (round_data.answer.into_raw() / U256::from(10).pow(U256::from(decimals)))
+            .try_into()
+            .map_err(|_| ManagerError::OutOfRange)
+    }
+
+    async fn get_deployment(&self) -> Result<LendingDeployment, ManagerError> {
+        let chain_id = self
+            .lending_contract
+            .provider()
+            .get_chain_id()
+            .await
+            .map_err(|e| lending_err("Failed to get chain id", e))?;
+        let stable_token_address = self
+            .lending_contract
+            .stableToken()
+            .call()
+            .await
+            .map_err(|e| lending_err("Failed to call stableToken", e))?;
+
+        Ok(LendingDeployment {
+            chain_id,
+            lending_contract_address: self.lending_contract.address().into_array(),
+            stable_token_address: stable_token_address.into_array(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Bytes, U64};
+    use alloy::sol_types::SolValue;
+    use alloy::transports::mock::Asserter;
+
+    fn setup() -> (EvmLendingProvider, Asserter) {
+        let asserter = Asserter::new();
+        let provider = ProviderBuilder::new()
+            .connect_mocked_client(asserter.clone())
+            .erased();
+        (
+            EvmLendingProvider::with_provider(Address::repeat_byte(1), provider),
+            asserter,
+        )
+    }
+
+    fn push_call_result<T: SolValue>(asserter: &Asserter, value: T) {
+        asserter.push_success(&Bytes::from(value.abi_encode_params()));
+    }
+
+    #[tokio::test]
+    async fn get_stable_loan_test() {
+        let (provider, asserter) = setup();
+        push_call_result(
+            &asserter,
+            (
+                U256::from(100_000),
+                U256::from(500_000),
+                42u128,
+                Address::repeat_byte(4),
+                false,
+                U256::from(10),
+                U256::from(1_000),
+                U256::from(25),
+            ),
+        );
+
+        let stable_loan = provider
+            .get_stable_loan(&[5; 32])
+            .await
+            .expect("to be able to get the stable loan");
+        assert_eq!(Amount::from_sat(100_000), stable_loan.collateral_amount);
+        assert_eq!(500_000, stable_loan.stable_loan_amount);
+        assert_eq!(42, stable_loan.borrower_preimage);
+        assert!(!stable_loan.repaid);
+        assert_eq!(10, stable_loan.interest_rate);
+        assert_eq!(1_000, stable_loan.start_time);
+        assert_eq!(25, stable_loan.accrued_interest);
+    }
+
+    #[tokio::test]
+    async fn get_stable_loan_out_of_range_test() {
+        let (provider, asserter) = setup();
+        push_call_result(
+            &asserter,
+            (
+                U256::MAX,
+                U256::from(500_000),
+                42u128,
+                Address::repeat_byte(4),
+                false,
+                U256::from(10),
+                U256::from(1_000),
+                U256::from(25),
+            ),
+        );
+
+        assert!(matches!(
+            provider.get_stable_loan(&[5; 32]).await,
+            Err(ManagerError::OutOfRange)
+        ));
+    }
+
+    #[tokio::test]
+    async fn call_failure_is_lending_error_test() {
+        let (provider, asserter) = setup();
+        asserter.push_failure_msg("execution reverted");
+
+        assert!(matches!(
+            provider.get_stable_loan(&[5; 32]).await,
+            Err(ManagerError::LendingError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn reclaim_stable_failure_is_lending_error_test() {
+        let (provider, asserter) = setup();
+        asserter.push_failure_msg("execution reverted: Stable loan claim period has not ended");
+
+        assert!(matches!(
+            provider.reclaim_stable(&[5; 32]).await,
+            Err(ManagerError::LendingError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn get_collateral_value_test() {
+        let (provider, asserter) = setup();
+        push_call_result(&asserter, U256::from(750_000));
+
+        let collateral_value = provider
+            .get_collateral_value(Amount::from_sat(100_000))
+            .await
+            .expect("to be able to get the collateral value");
+        assert_eq!(750_000, collateral_value);
+    }
+
+    #[tokio::test]
+    async fn realistic_principal_is_covered_test() {
+        let (provider, asserter) = setup();
+        // One bitcoin at $60,000 is worth 60,000 tokens of 18 decimals.
+        push_call_result(&asserter, U256::from(60_000u128 * 10u128.pow(18)));
+        push_call_result(&asserter, Address::repeat_byte(2));
+        push_call_result(&asserter, 18u8);
+
+        let collateral_value = provider
+            .get_collateral_value(Amount::ONE_BTC)
+            .await
+            .expect("to be able to get the collateral value");
+        let stable_unit = provider.get_stable_unit().await.unwrap();
+        let stable_loan_amount = 40_000 * stable_unit;
+        assert_eq!(40_000 * 10u128.pow(18), stable_loan_amount);
+        // A principal of 40,000 is exactly covered at a 150% collateral ratio.
+        assert_eq!(collateral_value * 100, stable_loan_amount * 150);
+    }
+
+    #[tokio::test]
+    async fn get_stable_unit_test() {
+        let (provider, asserter) = setup();
+        push_call_result(&asserter, Address::repeat_byte(2));
+        push_call_result(&asserter, 6u8);
+
+        assert_eq!(1_000_000, provider.get_stable_unit().await.unwrap());
+    }
+
+    fn push_price_feed_results(asserter: &Asserter, answer: I256) {
+        push_call_result(asserter, Address::repeat_byte(3));
+        push_call_result(
+            asserter,
+            (
+                U256::from(1),
+                answer,
+                U256::from(1_000),
+                U256::from(1_000),
+                U256::from(1),
+            ),
+        );
+        push_call_result(asserter, 8u8);
+    }
+
+    #[tokio::test]
+    async fn get_btc_price_test() {
+        let (provider, asserter) = setup();
+        push_price_feed_results(&asserter, I256::try_from(6_000_000_000_000i64).unwrap());
+
+        assert_eq!(60_000, provider.get_btc_price().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn get_btc_price_invalid_answer_test() {
+        let (provider, asserter) = setup();
+        push_price_feed_results(&asserter, I256::try_from(-1i64).unwrap());
+
+        assert!(matches!(
+            provider.get_btc_price().await,
+            Err(ManagerError::LendingError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn get_deployment_test() {
+        let (provider, asserter) = setup();
+        asserter.push_success(&U64::from(31337));
+        push_call_result(&asserter, Address::repeat_byte(2));
+
+        let deployment = provider.get_deployment().await.unwrap();
+        assert_eq!(31337, deployment.chain_id);
+        assert_eq!([1; 20], deployment.lending_contract_address);
+        assert_eq!([2; 20], deployment.stable_token_address);
+    }
+}
@@ -0,0 +1,18 @@
+//! Mock implementations of the interfaces required by the DLC manager, used
+//! to run tests without external services.
+
+extern crate async_trait;
+extern crate bitcoin;
+extern crate dlc;
+pub extern crate dlc_manager;
+extern crate dlc_messages;
+extern crate lightning;
+extern crate secp256k1_zkp;
+pub extern crate simple_wallet;
+
+pub mod memory_storage_provider;
+pub mod mock_blockchain;
+pub mod mock_lending;
+pub mod mock_oracle_provider;
+pub mod mock_time;
+pub mod mock_wallet;
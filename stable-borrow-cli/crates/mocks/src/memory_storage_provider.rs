@@ -0,0 +1,565 @@
+//! In memory implementation of the [`Storage`] and [`WalletStorage`]
+//! interfaces.
+
+use bitcoin::{Address, OutPoint, Txid};
+use dlc_manager::chain_monitor::ChainMonitor;
+use dlc_manager::channel::{
+    offered_channel::OfferedChannel,
+    signed_channel::{SignedChannel, SignedChannelStateType},
+    Channel,
+};
+use dlc_manager::contract::{
+    loan_negotiation::LoanNegotiation, loan_renewal::LoanRenewal, loan_request::LoanRequest,
+    loan_state::LoanLifecycle, offered_contract::OfferedContract,
+    offered_contract::OfferedLoanContract, ser::Serializable, signed_contract::SignedContract,
+    Contract, FailedLoanContract, LoanTerms, PreClosedContract, RepaidContract,
+};
+use dlc_manager::{error::Error as DaemonError, ChannelId, ContractId, Storage, Utxo};
+use secp256k1_zkp::SecretKey;
+use simple_wallet::WalletStorage;
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+
+/// Storage keeping all its data in memory. Contracts and channels can be
+/// snapshotted with [`MemoryStorage::save`] and restored with
+/// [`MemoryStorage::rollback`].
+pub struct MemoryStorage {
+    contracts: RwLock<HashMap<ContractId, Contract>>,
+    contracts_saved: Mutex<Option<HashMap<ContractId, Contract>>>,
+    channels: RwLock<HashMap<ChannelId, Channel>>,
+    channels_saved: Mutex<Option<HashMap<ChannelId, Channel>>>,
+    loan_preimages: RwLock<HashMap<ContractId, u128>>,
+    escrow_outpoints: RwLock<HashMap<ContractId, OutPoint>>,
+    borrower_hashes: RwLock<HashMap<ContractId, [u8; 32]>>,
+    loan_terms: RwLock<HashMap<ContractId, LoanTerms>>,
+    loan_lifecycles: RwLock<HashMap<ContractId, LoanLifecycle>>,
+    loan_requests: RwLock<HashMap<[u8; 32], LoanRequest>>,
+    loan_negotiations: RwLock<HashMap<[u8; 32], LoanNegotiation>>,
+    loan_renewals: RwLock<HashMap<ContractId, LoanRenewal>>,
+    chain_monitor: RwLock<Option<Vec<u8>>>,
+    addresses: RwLock<HashMap<Address, SecretKey>>,
+    keys: RwLock<HashMap<Vec<u8>, SecretKey>>,
+    utxos: RwLock<HashMap<(Txid, u32), Utxo>>,
+}
+
+impl MemoryStorage {
+    /// Creates a new empty instance.
+    pub fn new() -> Self {
+        MemoryStorage {
+            contracts: RwLock::new(HashMap::new()),
+            contracts_saved: Mutex::new(None),
+            channels: RwLock::new(HashMap::new()),
+            channels_saved: Mutex::new(None),
+            loan_preimages: RwLock::new(HashMap::new()),
+            escrow_outpoints: RwLock::new(HashMap::new()),
+            borrower_hashes: RwLock::new(HashMap::new()),
+            loan_terms: RwLock::new(HashMap::new()),
+            loan_lifecycles: RwLock::new(HashMap::new()),
+            loan_requests: RwLock::new(HashMap::new()),
+            loan_negotiations: RwLock::new(HashMap::new()),
+            loan_renewals: RwLock::new(HashMap::new()),
+            chain_monitor: RwLock::new(None),
+            addresses: RwLock::new(HashMap::new()),
+            keys: RwLock::new(HashMap::new()),
+            utxos: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Takes a snapshot of the current contracts and channels.
+    pub fn save(&self) {
+        let contracts = self.contracts.read().expect("Could not get read lock");
+        let channels = self.channels.read().expect("Could not get read lock");
+        *self.contracts_saved.lock().unwrap() = Some(contracts.clone());
+        *self.channels_saved.lock().unwrap() = Some(channels.clone());
+    }
+
+    /// Restores the contracts and channels to the last snapshot taken with
+    /// [`MemoryStorage::save`], if any.
+    pub fn rollback(&self) {
+        if let Some(contracts) = self.contracts_saved.lock().unwrap().take() {
+            *self.contracts.write().expect("Could not get write lock") = contracts;
+        }
+        if let Some(channels) = self.channels_saved.lock().unwrap().take() {
+            *self.channels.write().expect("Could not get write lock") = channels;
+        }
+    }
+
+    fn filter_contracts<T, F>(&self, filter: F) -> Vec<T>
+    where
+        F: Fn(&Contract) -> Option<T>,
+    {
+        self.contracts
+            .read()
+            .expect("Could not get read lock")
+            .values()
+            .filter_map(filter)
+            .collect()
+    }
+
+    fn filter_channels<T, F>(&self, filter: F) -> Vec<T>
+    where
+        F: Fn(&Channel) -> Option<T>,
+    {
+        self.channels
+            .read()
+            .expect("Could not get read lock")
+            .values()
+            .filter_map(filter)
+            .collect()
+    }
+}
+
+impl Default for MemoryStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn insert_contract(map: &mut HashMap<ContractId, Contract>, contract: Contract) {
+    match &contract {
+        a @ Contract::Accepted(_) | a @ Contract::Signed(_) => {
+            map.remove(&a.get_temporary_id());
+        }
+        _ => {}
+    };
+
+    map.insert(contract.get_id(), contract);
+}
+
+impl Storage for MemoryStorage {
+    fn get_contract(&self, id: &ContractId) -> Result<Option<Contract>, DaemonError> {
+        let map = self.contracts.read().expect("Could not get read lock");
+        Ok(map.get(id).cloned())
+    }
+
+    fn get_contracts(&self) -> Result<Vec<Contract>, DaemonError> {
+        Ok(self.filter_contracts(|c| Some(c.clone())))
+    }
+
+    fn create_contract(&self, contract: &OfferedContract) -> Result<(), DaemonError> {
+        let mut map = self.contracts.write().expect("Could not get write lock");
+        let res = map.insert(contract.id, Contract::Offered(contract.clone()));
+        match res {
+            None => Ok(()),
+            Some(_) => Err(DaemonError::StorageError(
+                "Contract already exists".to_string(),
+            )),
+        }
+    }
+
+    fn create_loan_contract(&self, contract: &OfferedLoanContract) -> Result<(), DaemonError> {
+        let mut map = self.contracts.write().expect("Could not get write lock");
+        let res = map.insert(
+            contract.offered_contract.id,
+            Contract::OfferedLoan(contract.clone()),
+        );
+        match res {
+            None => Ok(()),
+            Some(_) => Err(DaemonError::StorageError(
+                "Contract already exists".to_string(),
+            )),
+        }
+    }
+
+    fn set_loan_preimage(
+        &self,
+        contract_id: &ContractId,
+        preimage: u128,
+    ) -> Result<(), DaemonError> {
+        let mut map = self
+            .loan_preimages
+            .write()
+            .expect("Could not get write lock");
+        map.insert(*contract_id, preimage);
+        Ok(())
+    }
+
+    fn get_loan_preimage(&self, contract_id: &ContractId) -> Result<Option<u128>, DaemonError> {
+        let map = self.loan_preimages.read().expect("Could not get read lock");
+        Ok(map.get(contract_id).cloned())
+    }
+
+    fn set_escrow_outpoint(
+        &self,
+        contract_id: &ContractId,
+        outpoint: OutPoint,
+    ) -> Result<(), DaemonError> {
+        let mut map = self.escrow_outpoints.write().expect("Could not get write lock");
+        map.insert(*contract_id, outpoint);
+        Ok(())
+    }
+
+    fn get_escrow_outpoint(
+        &self,
+        contract_id: &ContractId,
+    ) -> Result<Option<OutPoint>, DaemonError> {
+        let map = self.escrow_outpoints.read().expect("Could not get read lock");
+        Ok(map.get(contract_id).cloned())
+    }
+
+    fn set_loan_borrower_hash(
+        &self,
+        contract_id: &ContractId,
+        borrower_hash: &[u8; 32],
+    ) -> Result<(), DaemonError> {
+        let mut map = self
+            .borrower_hashes
+            .write()
+            .expect("Could not get write lock");
+        map.insert(*contract_id, *borrower_hash);
+        Ok(())
+    }
+
+    fn get_loan_borrower_hash(
+        &self,
+        contract_id: &ContractId,
+    ) -> Result<Option<[u8; 32]>, DaemonError> {
+        let map = self
+            .borrower_hashes
+            .read()
+            .expect("Could not get read lock");
+        Ok(map.get(contract_id).cloned())
+    }
+
+    fn set_loan_terms(
+        &self,
+        contract_id: &ContractId,
+        loan_terms: &LoanTerms,
+    ) -> Result<(), DaemonError> {
+        let mut map = self.loan_terms.write().expect("Could not get write lock");
+        map.insert(*contract_id, loan_terms.clone());
+        Ok(())
+    }
+
+    fn get_loan_terms(&self, contract_id: &ContractId) -> Result<Option<LoanTerms>, DaemonError> {
+        let map = self.loan_terms.read().expect("Could not get read lock");
+        Ok(map.get(contract_id).cloned())
+    }
+
+    fn update_loan_lifecycle(
+        &self,
+        contract_id: &ContractId,
+        lifecycle: &LoanLifecycle,
+    ) -> Result<(), DaemonError> {
+        let mut map = self
+            .loan_lifecycles
+            .write()
+            .expect("Could not get write lock");
+        map.insert(*contract_id, lifecycle.clone());
+        Ok(())
+    }
+
+    fn get_loan_lifecycle(
+        &self,
+        contract_id: &ContractId,
+    ) -> Result<Option<LoanLifecycle>, DaemonError> {
+        let map = self
+            .loan_lifecycles
+            .read()
+            .expect("Could not get read lock");
+        Ok(map.get(contract_id).cloned())
+    }
+
+    fn get_loan_lifecycles(&self) -> Result<Vec<(ContractId, LoanLifecycle)>, DaemonError> {
+        let map = self
+            .loan_lifecycles
+            .read()
+            .expect("Could not get read lock");
+        Ok(map.iter().map(|(k, v)| (*k, v.clone())).collect())
+    }
+
+    fn upsert_loan_request(&self, loan_request: &LoanRequest) -> Result<(), DaemonError> {
+        let mut map = self
+            .loan_requests
+            .write()
+            .expect("Could not get write lock");
+        map.insert(loan_request.id, loan_request.clone());
+        Ok(())
+    }
+
+    fn get_loan_request(&self, request_id: &[u8; 32]) -> Result<Option<LoanRequest>, DaemonError> {
+        let map = self.loan_requests.read().expect("Could not get read lock");
+        Ok(map.get(request_id).cloned())
+    }
+
+    fn get_loan_requests(&self) -> Result<Vec<LoanRequest>, DaemonError> {
+        let map = self.loan_requests.read().expect("Could not get read lock");
+        Ok(map.values().cloned().collect())
+    }
+
+    fn upsert_loan_negotiation(&self, negotiation: &LoanNegotiation) -> Result<(), DaemonError> {
+        let mut map = self
+            .loan_negotiations
+            .write()
+            .expect("Could not get write lock");
+        map.insert(negotiation.id, negotiation.clone());
+        Ok(())
+    }
+
+    fn get_loan_negotiation(
+        &self,
+        negotiation_id: &[u8; 32],
+    ) -> Result<Option<LoanNegotiation>, DaemonError> {
+        let map = self
+            .loan_negotiations
+            .read()
+            .expect("Could not get read lock");
+        Ok(map.get(negotiation_id).cloned())
+    }
+
+    fn get_loan_negotiations(&self) -> Result<Vec<LoanNegotiation>, DaemonError> {
+        let map = self
+            .loan_negotiations
+            .read()
+            .expect("Could not get read lock");
+        Ok(map.values().cloned().collect())
+    }
+
+    fn upsert_loan_renewal(&self, renewal: &LoanRenewal) -> Result<(), DaemonError> {
+        let mut map = self
+            .loan_renewals
+            .write()
+            .expect("Could not get write lock");
+        map.insert(renewal.contract_id, renewal.clone());
+        Ok(())
+    }
+
+    fn get_loan_renewal(
+        &self,
+        contract_id: &ContractId,
+    ) -> Result<Option<LoanRenewal>, DaemonError> {
+        let map = self.loan_renewals.read().expect("Could not get read lock");
+        Ok(map.get(contract_id).cloned())
+    }
+
+    fn get_loan_renewals(&self) -> Result<Vec<LoanRenewal>, DaemonError> {
+        let map = self.loan_renewals.read().expect("Could not get read lock");
+        Ok(map.values().cloned().collect())
+    }
+
+    fn delete_contract(&self, id: &ContractId) -> Result<(), DaemonError> {
+        let mut map = self.contracts.write().expect("Could not get write lock");
+        map.remove(id);
+        Ok(())
+    }
+
+    fn update_contract(&self, contract: &Contract) -> Result<(), DaemonError> {
+        let mut map = self.contracts.write().expect("Could not get write lock");
+        insert_contract(&mut map, contract.clone());
+        Ok(())
+    }
+
+    fn get_contract_offers(&self) -> Result<Vec<OfferedContract>, DaemonError> {
+        Ok(self.filter_contracts(|c| match c {
+            Contract::Offered(o) => Some(o.clone()),
+            _ => None,
+        }))
+    }
+
+    fn get_loan_contract_offers(&self) -> Result<Vec<OfferedLoanContract>, DaemonError> {
+        Ok(self.filter_contracts(|c| match c {
+            Contract::OfferedLoan(o) => Some(o.clone()),
+            _ => None,
+        }))
+    }
+
+    fn get_escrow_confirmed_loan_contracts(&self) -> Result<Vec<OfferedLoanContract>, DaemonError> {
+        Ok(self.filter_contracts(|c| match c {
+            Contract::OfferedLoanEscrowConfirmed(o) => Some(o.clone()),
+            _ => None,
+        }))
+    }
+
+    fn get_failed_loan_contracts(&self) -> Result<Vec<FailedLoanContract>, DaemonError> {
+        Ok(self.filter_contracts(|c| match c {
+            Contract::FailedLoan(f) => Some(f.clone()),
+            _ => None,
+        }))
+    }
+
+    fn get_cancelled_loan_contracts(&self) -> Result<Vec<OfferedLoanContract>, DaemonError> {
+        Ok(self.filter_contracts(|c| match c {
+            Contract::CancelledLoan(o) => Some(o.clone()),
+            _ => None,
+        }))
+    }
+
+    fn get_signed_contracts(&self) -> Result<Vec<SignedContract>, DaemonError> {
+        Ok(self.filter_contracts(|c| match c {
+            Contract::Signed(s) => Some(s.clone()),
+            _ => None,
+        }))
+    }
+
+    fn get_confirmed_contracts(&self) -> Result<Vec<SignedContract>, DaemonError> {
+        Ok(self.filter_contracts(|c| match c {
+            Contract::Confirmed(s) => Some(s.clone()),
+            _ => None,
+        }))
+    }
+
+    fn get_preclosed_contracts(&self) -> Result<Vec<PreClosedContract>, DaemonError> {
+        Ok(self.filter_contracts(|c| match c {
+            Contract::PreClosed(p) => Some(p.clone()),
+            _ => None,
+        }))
+    }
+
+    fn get_prerepaid_contracts(&self) -> Result<Vec<RepaidContract>, DaemonError> {
+        Ok(self.filter_contracts(|c| match c {
+            Contract::PreRepaid(r) => Some(r.clone()),
+            _ => None,
+        }))
+    }
+
+    fn upsert_channel(
+        &self,
+        channel: Channel,
+        contract: Option<Contract>,
+    ) -> Result<(), DaemonError> {
+        let mut map = self.channels.write().expect("Could not get write lock");
+        match &channel {
+            a @ Channel::Accepted(_) | a @ Channel::Signed(_) => {
+                map.remove(&a.get_temporary_id());
+            }
+            _ => {}
+        };
+        map.insert(channel.get_id(), channel);
+
+        if let Some(c) = contract {
+            let mut map = self.contracts.write().expect("Could not get write lock");
+            insert_contract(&mut map, c);
+        }
+
+        Ok(())
+    }
+
+    fn delete_channel(&self, channel_id: &ChannelId) -> Result<(), DaemonError> {
+        let mut map = self.channels.write().expect("Could not get write lock");
+        map.remove(channel_id);
+        Ok(())
+    }
+
+    fn get_channel(&self, channel_id: &ChannelId) -> Result<Option<Channel>, DaemonError> {
+        let map = self.channels.read().expect("Could not get read lock");
+        Ok(map.get(channel_id).cloned())
+    }
+
+    fn get_signed_channels(
+        &self,
+        channel_state: Option<SignedChannelStateType>,
+    ) -> Result<Vec<SignedChannel>, DaemonError> {
+        Ok(self.filter_channels(|c| match c {
+            Channel::Signed(s)
+                if channel_state
+                    .as_ref()
+                    .map_or(true, |state| s.state.get_type() == *state) =>
+            {
+                Some(s.clone())
+            }
+            _ => None,
+        }))
+    }
+
+    fn get_offered_channels(&self) -> Result<Vec<OfferedChannel>, DaemonError> {
+        Ok(self.filter_channels(|c| match c {
+            Channel::Offered(o) => Some(o.clone()),
+            _ => None,
+        }))
+    }
+
+    fn persist_chain_monitor(&self, monitor: &ChainMonitor) -> Result<(), DaemonError> {
+        let serialized = monitor.serialize()?;
+        *self
+            .chain_monitor
+            .write()
+            .expect("Could not get write lock") = Some(serialized);
+        Ok(())
+    }
+
+    fn get_chain_monitor(&self) -> Result<Option<ChainMonitor>, DaemonError> {
+        let serialized = self.chain_monitor.read().expect("Could not get read lock");
+        match serialized.as_ref() {
+            Some(s) => {
+                let monitor = ChainMonitor::deserialize(&mut lightning::io::Cursor::new(s))
+                    .map_err(|e| DaemonError::StorageError(format!("{}", e)))?;
+                Ok(Some(monitor))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+impl WalletStorage for MemoryStorage {
+    fn upsert_address(&self, address: &Address, privkey: &SecretKey) -> Result<(), DaemonError> {
+        let mut map = self.addresses.write().expect("Could not get write lock");
+        map.insert(address.clone(), *privkey);
+        Ok(())
+    }
+
+    fn delete_address(&self, address: &Address) -> Result<(), DaemonError> {
+        let mut map = self.addresses.write().expect("Could not get write lock");
+        map.remove(address);
+        Ok(())
+    }
+
+    fn get_addresses(&self) -> Result<Vec<Address>, DaemonError> {
+        let map = self.addresses.read().expect("Could not get read lock");
+        Ok(map.keys().cloned().collect())
+    }
+
+    fn get_priv_key_for_address(
+        &self,
+        address: &Address,
+    ) -> Result<Option<SecretKey>, DaemonError> {
+        let map = self.addresses.read().expect("Could not get read lock");
+        Ok(map.get(address).cloned())
+    }
+
+    fn upsert_key(&self, identifier: &[u8], privkey: &SecretKey) -> Result<(), DaemonError> {
+        let mut map = self.keys.write().expect("Could not get write lock");
+        map.insert(identifier.to_vec(), *privkey);
+        Ok(())
+    }
+
+    fn get_priv_key(&self, identifier: &[u8]) -> Result<Option<SecretKey>, DaemonError> {
+        let map = self.keys.read().expect("Could not get read lock");
+        Ok(map.get(identifier).cloned())
+    }
+
+    fn upsert_utxo(&self, utxo: &Utxo) -> Result<(), DaemonError> {
+        let mut map = self.utxos.write().expect("Could not get write lock");
+        map.insert((utxo.outpoint.txid, utxo.outpoint.vout), utxo.clone());
+        Ok(())
+    }
+
+    fn has_utxo(&self, utxo: &Utxo) -> Result<bool, DaemonError> {
+        let map = self.utxos.read().expect("Could not get read lock");
+        Ok(map.contains_key(&(utxo.outpoint.txid, utxo.outpoint.vout)))
+    }
+
+    fn delete_utxo(&self, utxo: &Utxo) -> Result<(), DaemonError> {
+        let mut map = self.utxos.write().expect("Could not get write lock");
+        map.remove(&(utxo.outpoint.txid, utxo.outpoint.vout));
+        Ok(())
+    }
+
+    fn get_utxos(&self) -> Result<Vec<Utxo>, DaemonError> {
+        let map = self.utxos.read().expect("Could not get read lock");
+        Ok(map.values().cloned().collect())
+    }
+
+    fn unreserve_utxo(&self, txid: &Txid, vout: u32) -> Result<(), DaemonError> {
+        let mut map = self.utxos.write().expect("Could not get write lock");
+        match map.get_mut(&(*txid, vout)) {
+            Some(utxo) => {
+                utxo.reserved = false;
+                Ok(())
+            }
+            None => Err(DaemonError::InvalidState(format!(
+                "No utxo for {} {}",
+                txid, vout
+            ))),
+        }
+    }
+}
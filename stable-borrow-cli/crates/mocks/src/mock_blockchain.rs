@@ -0,0 +1,179 @@
+//! In memory implementation of the [`Blockchain`] interface.
+
+use crate::mock_time::MockTime;
+use bitcoin::block::{Header, Version};
+use bitcoin::hashes::Hash;
+use bitcoin::{Address, Block, BlockHash, CompactTarget, Network, Transaction, TxMerkleNode, Txid};
+use dlc_manager::{error::Error, Blockchain, Time, Utxo};
+use lightning::chain::chaininterface::{ConfirmationTarget, FeeEstimator};
+use simple_wallet::WalletBlockchainProvider;
+use std::sync::Mutex;
+
+/// Fee rate, in satoshis per 1000 weight units, returned by the mock fee
+/// estimator.
+const MOCK_FEE_RATE: u32 = 253;
+
+struct BlockchainState {
+    mempool: Vec<Transaction>,
+    blocks: Vec<Block>,
+    nb_failed_broadcasts: usize,
+}
+
+/// Blockchain keeping its transactions in memory. Broadcast transactions stay
+/// in the mempool until blocks are generated with
+/// [`MockBlockchain::generate_blocks`]. Blocks are timestamped with the
+/// current [`MockTime`].
+pub struct MockBlockchain {
+    state: Mutex<BlockchainState>,
+}
+
+impl MockBlockchain {
+    /// Creates a new instance containing only a genesis block.
+    pub fn new() -> Self {
+        let genesis = make_block(BlockHash::all_zeros(), Vec::new());
+        MockBlockchain {
+            state: Mutex::new(BlockchainState {
+                mempool: Vec::new(),
+                blocks: vec![genesis],
+                nb_failed_broadcasts: 0,
+            }),
+        }
+    }
+
+    /// Mines `nb_blocks` blocks, the first of which includes all the
+    /// transactions currently in the mempool.
+    pub fn generate_blocks(&self, nb_blocks: u64) {
+        let mut state = self.state.lock().unwrap();
+        for _ in 0..nb_blocks {
+            let txdata = std::mem::take(&mut state.mempool);
+            let prev_blockhash = state
+                .blocks
+                .last()
+                .expect("to have a genesis block")
+                .block_hash();
+            let block = make_block(prev_blockhash, txdata);
+            state.blocks.push(block);
+        }
+    }
+
+    /// Drops the unconfirmed transactions, as a node restarting without its
+    /// mempool would.
+    pub fn clear_mempool(&self) {
+        self.state.lock().unwrap().mempool.clear();
+    }
+
+    /// Makes the next `nb_broadcasts` transaction broadcasts fail, as they
+    /// would with an unreachable node.
+    pub fn fail_next_broadcasts(&self, nb_broadcasts: usize) {
+        self.state.lock().unwrap().nb_failed_broadcasts = nb_broadcasts;
+    }
+
+    /// Returns whether the transaction with the given id was broadcast,
+    /// confirmed or not.
+    pub fn has_transaction(&self, txid: &Txid) -> bool {
+        self.find_transaction(txid).is_some()
+    }
+
+    fn find_transaction(&self, txid: &Txid) -> Option<(Transaction, Option<usize>)> {
+        let state = self.state.lock().unwrap();
+        if let Some(tx) = state.mempool.iter().find(|tx| tx.compute_txid() == *txid) {
+            return Some((tx.clone(), None));
+        }
+        state.blocks.iter().enumerate().find_map(|(height, block)| {
+            block
+                .txdata
+                .iter()
+                .find(|tx| tx.compute_txid() == *txid)
+                .map(|tx| (tx.clone(), Some(height)))
+        })
+    }
+}
+
+impl Default for MockBlockchain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn make_block(prev_blockhash: BlockHash, txdata: Vec<Transaction>) -> Block {
+    let header = Header {
+        version: Version::TWO,
+        prev_blockhash,
+        merkle_root: TxMerkleNode::all_zeros(),
+        time: MockTime {}.unix_time_now() as u32,
+        bits: CompactTarget::from_consensus(0x207fffff),
+        nonce: 0,
+    };
+    Block { header, txdata }
+}
+
+impl Blockchain for MockBlockchain {
+    fn send_transaction(&self, transaction: &Transaction) -> Result<(), Error> {
+        let txid = transaction.compute_txid();
+        if self.has_transaction(&txid) {
+            return Ok(());
+        }
+        let mut state = self.state.lock().unwrap();
+        if state.nb_failed_broadcasts > 0 {
+            state.nb_failed_broadcasts -= 1;
+            return Err(Error::BlockchainError(
+                "Failed to broadcast transaction".to_string(),
+            ));
+        }
+        state.mempool.push(transaction.clone());
+        Ok(())
+    }
+
+    fn get_network(&self) -> Result<Network, Error> {
+        Ok(Network::Regtest)
+    }
+
+    fn get_blockchain_height(&self) -> Result<u64, Error> {
+        Ok(self.state.lock().unwrap().blocks.len() as u64 - 1)
+    }
+
+    fn get_block_at_height(&self, height: u64) -> Result<Block, Error> {
+        self.state
+            .lock()
+            .unwrap()
+            .blocks
+            .get(height as usize)
+            .cloned()
+            .ok_or_else(|| Error::BlockchainError(format!("No block at height {}", height)))
+    }
+
+    fn get_transaction(&self, tx_id: &Txid) -> Result<Transaction, Error> {
+        self.find_transaction(tx_id)
+            .map(|(tx, _)| tx)
+            .ok_or_else(|| Error::BlockchainError(format!("Transaction {} not found", tx_id)))
+    }
+
+    fn get_raw_transaction(&self, tx_id: &Txid) -> Result<Transaction, Error> {
+        self.get_transaction(tx_id)
+    }
+
+    fn get_transaction_confirmations(&self, tx_id: &Txid) -> Result<u32, Error> {
+        let height = match self.find_transaction(tx_id) {
+            Some((_, Some(height))) => height as u64,
+            _ => return Ok(0),
+        };
+        let tip = self.get_blockchain_height()?;
+        Ok((tip - height + 1) as u32)
+    }
+}
+
+impl FeeEstimator for MockBlockchain {
+    fn get_est_sat_per_1000_weight(&self, _confirmation_target: ConfirmationTarget) -> u32 {
+        MOCK_FEE_RATE
+    }
+}
+
+impl WalletBlockchainProvider for MockBlockchain {
+    fn get_utxos_for_address(&self, _address: &Address) -> Result<Vec<Utxo>, Error> {
+        Ok(Vec::new())
+    }
+
+    fn is_output_spent(&self, _txid: &Txid, _vout: u32) -> Result<bool, Error> {
+        Ok(false)
+    }
+}
@@ -0,0 +1,889 @@
+//! In-memory implementation of the P2PBTCLending contract.
+
+use crate::mock_time::MockTime;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::Amount;
+use dlc_manager::error::Error;
+use dlc_manager::loan_math::{calculate_stable_interest, get_stable_loan_amount_due};
+use dlc_manager::{LendingBackend, LendingDeployment, StableLoan, Time};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// The account holding the stable tokens locked in the lending contract.
+const CONTRACT_ACCOUNT: &str = "P2PBTCLending";
+/// The time the borrower has to claim lent stables before the lender can
+/// reclaim them, in seconds.
+pub const STABLE_CLAIM_PERIOD: u64 = 86400;
+/// The deployment reported by the backends of the mock lending contract.
+pub const MOCK_LENDING_DEPLOYMENT: LendingDeployment = LendingDeployment {
+    chain_id: 31337,
+    lending_contract_address: [1; 20],
+    stable_token_address: [2; 20],
+};
+
+struct StableLoanEntry {
+    stable_loan: StableLoan,
+    lender: String,
+}
+
+#[derive(Default)]
+struct ContractState {
+    stable_loans: HashMap<[u8; 32], StableLoanEntry>,
+    balances: HashMap<String, u128>,
+    btc_price: u64,
+}
+
+impl ContractState {
+    fn transfer(&mut self, from: &str, to: &str, amount: u128) -> Result<(), Error> {
+        let from_balance = self.balances.get(from).copied().unwrap_or(0);
+        if from_balance < amount {
+            return Err(revert("Stable token transfer failed"));
+        }
+        self.balances
+            .insert(from.to_string(), from_balance - amount);
+        *self.balances.entry(to.to_string()).or_insert(0) += amount;
+        Ok(())
+    }
+}
+
+fn revert(reason: &str) -> Error {
+    Error::LendingError(format!("execution reverted: {}", reason))
+}
+
+/// In-memory implementation of the P2PBTCLending contract semantics, shared
+/// by the [`MockLending`] backends of the parties to a loan.
+pub struct MockLendingContract {
+    stable_per_sat: u128,
+    stable_unit: u128,
+    state: Mutex<ContractState>,
+}
+
+impl MockLendingContract {
+    /// Creates a new contract valuing each satoshi of collateral at
+    /// `stable_per_sat` stable token units, for a stable token with
+    /// `stable_unit` units per unit of loan principal.
+    pub fn new(stable_per_sat: u128, stable_unit: u128) -> Self {
+        MockLendingContract {
+            stable_per_sat,
+            stable_unit,
+            state: Mutex::new(ContractState::default()),
+        }
+    }
+
+    /// Credits the given account with stable tokens.
+    pub fn mint(&self, account: &str, amount: u128) {
+        *self
+            .state
+            .lock()
+            .unwrap()
+            .balances
+            .entry(account.to_string())
+            .or_insert(0) += amount;
+    }
+
+    /// Sets the answer of the BTC price feed, in whole stable token units per
+    /// bitcoin as returned by [`LendingBackend::get_btc_price`].
+    pub fn set_btc_price(&self, price: u64) {
+        self.state.lock().unwrap().btc_price = price;
+    }
+
+    /// Returns the stable token balance of the given account.
+    pub fn balance_of(&self, account: &str) -> u128 {
+        self.state
+            .lock()
+            .unwrap()
+            .balances
+            .get(account)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn get_stable_loan(&self, borrower_hash: &[u8; 32]) -> StableLoan {
+        match self.state.lock().unwrap().stable_loans.get(borrower_hash) {
+            Some(entry) => entry.stable_loan.clone(),
+            None => StableLoan {
+                collateral_amount: Amount::ZERO,
+                stable_loan_amount: 0,
+                borrower_preimage: 0,
+                repaid: false,
+                interest_rate: 0,
+                start_time: 0,
+                accrued_interest: 0,
+            },
+        }
+    }
+
+    fn get_collateral_value(&self, collateral: Amount) -> Result<u128, Error> {
+        (collateral.to_sat() as u128)
+            .checked_mul(self.stable_per_sat)
+            .ok_or(Error::OutOfRange)
+    }
+
+    fn get_btc_price(&self) -> Result<u64, Error> {
+        match self.state.lock().unwrap().btc_price {
+            0 => Err(Error::LendingError("Invalid BTC price data".to_string())),
+            price => Ok(price),
+        }
+    }
+
+    fn lend_stable(
+        &self,
+        sender: &str,
+        borrower_hash: &[u8; 32],
+        collateral: Amount,
+        stable_loan_amount: u128,
+        interest_rate: u64,
+        collateral_ratio: u64,
+    ) -> Result<(), Error> {
+        if collateral == Amount::ZERO {
+            return Err(revert("BTC Collateral amount must be greater than 0"));
+        }
+        if interest_rate == 0 || interest_rate > 100 {
+            return Err(revert("Interest rate must be between 1 and 100"));
+        }
+        if collateral_ratio <= 100 {
+            return Err(revert("Collateral ratio must be greater than 100"));
+        }
+        let max_stable_loan_amount = self
+            .get_collateral_value(collateral)?
+            .checked_mul(100)
+            .ok_or(Error::OutOfRange)?
+            / collateral_ratio as u128;
+        if stable_loan_amount == 0 || stable_loan_amount > max_stable_loan_amount {
+            return Err(revert(
+                "Stable loan amount must be between 0 and the collateral allowance",
+            ));
+        }
+        let mut state = self.state.lock().unwrap();
+        if state.stable_loans.contains_key(borrower_hash) {
+            return Err(revert("Loan already exists for this borrower"));
+        }
+        state.transfer(sender, CONTRACT_ACCOUNT, stable_loan_amount)?;
+        state.stable_loans.insert(
+            *borrower_hash,
+            StableLoanEntry {
+                stable_loan: StableLoan {
+                    collateral_amount: collateral,
+                    stable_loan_amount,
+                    borrower_preimage: 0,
+                    repaid: false,
+                    interest_rate,
+                    start_time: MockTime {}.unix_time_now(),
+                    accrued_interest: 0,
+                },
+                lender: sender.to_string(),
+            },
+        );
+        Ok(())
+    }
+
+    fn claim_stable(&self, sender: &str, borrower_preimage: u128) -> Result<(), Error> {
+        if borrower_preimage == 0 {
+            return Err(revert("Invalid borrower preimage"));
+        }
+        let borrower_hash = sha256::Hash::hash(&borrower_preimage.to_be_bytes()).to_byte_array();
+        let mut state = self.state.lock().unwrap();
+        let stable_loan_amount = match state.stable_loans.get(&borrower_hash) {
+            Some(entry) if entry.stable_loan.borrower_preimage != 0 => {
+                return Err(revert("Stable loan already claimed"))
+            }
+            Some(entry) => entry.stable_loan.stable_loan_amount,
+            None => return Err(revert("No stable loan exists for this borrower")),
+        };
+        state.transfer(CONTRACT_ACCOUNT, sender, stable_loan_amount)?;
+        if let Some(entry) = state.stable_loans.get_mut(&borrower_hash) {
+            entry.stable_loan.borrower_preimage = borrower_preimage;
+            entry.stable_loan.start_time = MockTime {}.unix_time_now();
+        }
+        Ok(())
+    }
+
+    fn reclaim_stable(&self, sender: &str, borrower_hash: &[u8; 32]) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let (stable_loan, lender) = match state.stable_loans.get(borrower_hash) {
+            Some(entry) => (entry.stable_loan.clone(), entry.lender.clone()),
+            None => return Err(revert("No stable loan exists for this borrower")),
+        };
+        if lender != sender {
+            return Err(revert("Only the lender can reclaim the stable loan"));
+        }
+        if stable_loan.borrower_preimage != 0 {
+            return Err(revert("Stable loan already claimed"));
+        }
+        let now = MockTime {}.unix_time_now();
+        if now < stable_loan.start_time + STABLE_CLAIM_PERIOD {
+            return Err(revert("Stable loan claim period has not ended"));
+        }
+        state.stable_loans.remove(borrower_hash);
+        state.transfer(CONTRACT_ACCOUNT, sender, stable_loan.stable_loan_amount)
+    }
+
+    fn repay_stable(&self, sender: &str, borrower_hash: &[u8; 32]) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let (stable_loan, lender) = match state.stable_loans.get(borrower_hash) {
+            Some(entry) => (entry.stable_loan.clone(), entry.lender.clone()),
+            None => return Err(revert("Stable loan has not been claimed")),
+        };
+        if stable_loan.borrower_preimage == 0 {
+            return Err(revert("Stable loan has not been claimed"));
+        }
+        if stable_loan.repaid {
+            return Err(revert("Stable loan already repaid"));
+        }
+        let amount_due =
+            get_stable_loan_amount_due(&stable_loan, MockTime {}.unix_time_now())?.total_due;
+        state.transfer(sender, &lender, amount_due)?;
+        if let Some(entry) = state.stable_loans.get_mut(borrower_hash) {
+            entry.stable_loan.repaid = true;
+        }
+        Ok(())
+    }
+
+    fn repay_stable_partially(
+        &self,
+        sender: &str,
+        borrower_hash: &[u8; 32],
+        amount: u128,
+    ) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let (stable_loan, lender) = match state.stable_loans.get(borrower_hash) {
+            Some(entry) => (entry.stable_loan.clone(), entry.lender.clone()),
+            None => return Err(revert("Stable loan has not been claimed")),
+        };
+        if stable_loan.borrower_preimage == 0 {
+            return Err(revert("Stable loan has not been claimed"));
+        }
+        if stable_loan.repaid {
+            return Err(revert("Stable loan already repaid"));
+        }
+        if amount == 0 || amount >= stable_loan.stable_loan_amount {
+            return Err(revert(
+                "Partial repayment must be between 0 and the loan amount",
+            ));
+        }
+        // Close the interest period on the amount owed so far, the next one
+        // accrues on the remaining amount.
+        let accrued_interest = stable_loan
+            .accrued_interest
+            .checked_add(calculate_stable_interest(
+                &stable_loan,
+                MockTime {}.unix_time_now(),
+            )?)
+            .ok_or(Error::OutOfRange)?;
+        state.transfer(sender, &lender, amount)?;
+        if let Some(entry) = state.stable_loans.get_mut(borrower_hash) {
+            entry.stable_loan.stable_loan_amount -= amount;
+            entry.stable_loan.accrued_interest = accrued_interest;
+            entry.stable_loan.start_time = MockTime {}.unix_time_now();
+        }
+        Ok(())
+    }
+}
+
+/// A [`LendingBackend`] sending its calls to a [`MockLendingContract`] on
+/// behalf of a given account.
+pub struct MockLending {
+    contract: Arc<MockLendingContract>,
+    account: String,
+}
+
+impl MockLending {
+    /// Creates a new backend calling the given contract from `account`.
+    pub fn new(contract: Arc<MockLendingContract>, account: &str) -> Self {
+        MockLending {
+            contract,
+            account: account.to_string(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl LendingBackend for MockLending {
+    async fn get_stable_loan(&self, borrower_hash: &[u8; 32]) -> Result<StableLoan, Error> {
+        Ok(self.contract.get_stable_loan(borrower_hash))
+    }
+
+    async fn lend_stable(
+        &self,
+        borrower_hash: &[u8; 32],
+        collateral: Amount,
+        stable_loan_amount: u128,
+        interest_rate: u64,
+        collateral_ratio: u64,
+    ) -> Result<(), Error> {
+        self.contract.lend_stable(
+            &self.account,
+            borrower_hash,
+            collateral,
+            stable_loan_amount,
+            interest_rate,
+            collateral_ratio,
+        )
+    }
+
+    async fn claim_stable(&self, borrower_preimage: u128) -> Result<(), Error> {
+        self.contract.claim_stable(&self.account, borrower_preimage)
+    }
+
+    async fn reclaim_stable(&self, borrower_hash: &[u8; 32]) -> Result<(), Error> {
+        self.contract.reclaim_stable(&self.account, borrower_hash)
+    }
+
+    async fn repay_stable(&self, borrower_hash: &[u8; 32]) -> Result<(), Error> {
+        self.contract.repay_stable(&self.account, borrower_hash)
+    }
+
+    async fn repay_stable_partially(
+        &self,
+        borrower_hash: &[u8; 32],
+        amount: u128,
+    ) -> Result<(), Error> {
+        self.contract
+            .repay_stable_partially(&self.account, borrower_hash, amount)
+    }
+
+    async fn get_collateral_value(&self, collateral: Amount) -> Result<u128, Error> {
+        self.contract.get_collateral_value(collateral)
+    }
+
+    async fn get_stable_unit(&self) -> Result<u128, Error> {
+        Ok(self.contract.stable_unit)
+    }
+
+    async fn get_btc_price(&self) -> Result<u64, Error> {
+        self.contract.get_btc_price()
+    }
+
+    async fn get_deployment(&self) -> Result<LendingDeployment, Error> {
+        Ok(MOCK_LENDING_DEPLOYMENT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_time::set_time;
+    use dlc_manager::loan_math::SECONDS_PER_YEAR;
+
+    const LENDER: &str = "lender";
+    const BORROWER: &str = "borrower";
+    const BORROWER_PREIMAGE: u128 = 42;
+    const COLLATERAL: Amount = Amount::from_sat(100_000);
+    const STABLE_LOAN_AMOUNT: u128 = 500_000;
+    const INTEREST_RATE: u64 = 10;
+    /// The collateral ratio at which the collateral covers exactly the stable
+    /// loan amount.
+    const COLLATERAL_RATIO: u64 = 160;
+
+    fn borrower_hash() -> [u8; 32] {
+        sha256::Hash::hash(&BORROWER_PREIMAGE.to_be_bytes()).to_byte_array()
+    }
+
+    fn setup() -> (Arc<MockLendingContract>, MockLending, MockLending) {
+        let contract = Arc::new(MockLendingContract::new(8, 100));
+        contract.mint(LENDER, 1_000_000);
+        let lender = MockLending::new(contract.clone(), LENDER);
+        let borrower = MockLending::new(contract.clone(), BORROWER);
+        (contract, lender, borrower)
+    }
+
+    #[tokio::test]
+    async fn lend_claim_repay_test() {
+        let (contract, lender, borrower) = setup();
+
+        lender
+            .lend_stable(
+                &borrower_hash(),
+                COLLATERAL,
+                STABLE_LOAN_AMOUNT,
+                INTEREST_RATE,
+                COLLATERAL_RATIO,
+            )
+            .await
+            .expect("to be able to lend");
+        let stable_loan = lender.get_stable_loan(&borrower_hash()).await.unwrap();
+        assert_eq!(COLLATERAL, stable_loan.collateral_amount);
+        assert_eq!(500_000, stable_loan.stable_loan_amount);
+        assert_eq!(500_000, contract.balance_of(LENDER));
+
+        borrower
+            .claim_stable(BORROWER_PREIMAGE)
+            .await
+            .expect("to be able to claim");
+        let stable_loan = lender.get_stable_loan(&borrower_hash()).await.unwrap();
+        assert_eq!(BORROWER_PREIMAGE, stable_loan.borrower_preimage);
+        assert_eq!(500_000, contract.balance_of(BORROWER));
+
+        borrower
+            .repay_stable(&borrower_hash())
+            .await
+            .expect("to be able to repay");
+        let stable_loan = lender.get_stable_loan(&borrower_hash()).await.unwrap();
+        assert!(stable_loan.repaid);
+        assert_eq!(0, contract.balance_of(BORROWER));
+        assert_eq!(1_000_000, contract.balance_of(LENDER));
+    }
+
+    #[tokio::test]
+    async fn repay_with_interest_test() {
+        let (contract, lender, borrower) = setup();
+        contract.mint(BORROWER, 25_000);
+
+        set_time(1_000);
+        lender
+            .lend_stable(
+                &borrower_hash(),
+                COLLATERAL,
+                STABLE_LOAN_AMOUNT,
+                INTEREST_RATE,
+                COLLATERAL_RATIO,
+            )
+            .await
+            .unwrap();
+        borrower.claim_stable(BORROWER_PREIMAGE).await.unwrap();
+        let stable_loan = lender.get_stable_loan(&borrower_hash()).await.unwrap();
+        assert_eq!(INTEREST_RATE, stable_loan.interest_rate);
+        assert_eq!(1_000, stable_loan.start_time);
+
+        set_time(1_000 + SECONDS_PER_YEAR / 2);
+        borrower
+            .repay_stable(&borrower_hash())
+            .await
+            .expect("to be able to repay with interest");
+        assert_eq!(0, contract.balance_of(BORROWER));
+        assert_eq!(1_025_000, contract.balance_of(LENDER));
+    }
+
+    #[tokio::test]
+    async fn repay_without_interest_funds_fails_test() {
+        let (contract, lender, borrower) = setup();
+
+        set_time(1_000);
+        lender
+            .lend_stable(
+                &borrower_hash(),
+                COLLATERAL,
+                STABLE_LOAN_AMOUNT,
+                INTEREST_RATE,
+                COLLATERAL_RATIO,
+            )
+            .await
+            .unwrap();
+        borrower.claim_stable(BORROWER_PREIMAGE).await.unwrap();
+        set_time(1_000 + SECONDS_PER_YEAR);
+        borrower
+            .repay_stable(&borrower_hash())
+            .await
+            .expect_err("should not repay without the interest");
+        let stable_loan = lender.get_stable_loan(&borrower_hash()).await.unwrap();
+        assert!(!stable_loan.repaid);
+        assert_eq!(500_000, contract.balance_of(BORROWER));
+    }
+
+    #[tokio::test]
+    async fn lend_with_invalid_interest_rate_fails_test() {
+        let (_, lender, _) = setup();
+
+        lender
+            .lend_stable(
+                &borrower_hash(),
+                COLLATERAL,
+                STABLE_LOAN_AMOUNT,
+                0,
+                COLLATERAL_RATIO,
+            )
+            .await
+            .expect_err("should not lend without interest");
+        lender
+            .lend_stable(
+                &borrower_hash(),
+                COLLATERAL,
+                STABLE_LOAN_AMOUNT,
+                101,
+                COLLATERAL_RATIO,
+            )
+            .await
+            .expect_err("should not lend above 100 percent");
+    }
+
+    #[tokio::test]
+    async fn lend_above_collateral_allowance_fails_test() {
+        let (contract, lender, _) = setup();
+
+        lender
+            .lend_stable(
+                &borrower_hash(),
+                COLLATERAL,
+                STABLE_LOAN_AMOUNT + 1,
+                INTEREST_RATE,
+                COLLATERAL_RATIO,
+            )
+            .await
+            .expect_err("should not lend more than the collateral allows");
+        lender
+            .lend_stable(
+                &borrower_hash(),
+                COLLATERAL,
+                0,
+                INTEREST_RATE,
+                COLLATERAL_RATIO,
+            )
+            .await
+            .expect_err("should not lend nothing");
+        lender
+            .lend_stable(
+                &borrower_hash(),
+                COLLATERAL,
+                200_000,
+                INTEREST_RATE,
+                COLLATERAL_RATIO,
+            )
+            .await
+            .expect("to be able to lend less than the collateral allows");
+        let stable_loan = lender.get_stable_loan(&borrower_hash()).await.unwrap();
+        assert_eq!(200_000, stable_loan.stable_loan_amount);
+        assert_eq!(800_000, contract.balance_of(LENDER));
+    }
+
+    #[tokio::test]
+    async fn lend_above_collateral_ratio_fails_test() {
+        let (contract, lender, _) = setup();
+
+        lender
+            .lend_stable(
+                &borrower_hash(),
+                COLLATERAL,
+                STABLE_LOAN_AMOUNT,
+                INTEREST_RATE,
+                COLLATERAL_RATIO + 1,
+            )
+            .await
+            .expect_err("should not lend more than the collateral ratio allows");
+        lender
+            .lend_stable(&borrower_hash(), COLLATERAL, 1, INTEREST_RATE, 100)
+            .await
+            .expect_err("should not lend without overcollateralization");
+        assert_eq!(1_000_000, contract.balance_of(LENDER));
+    }
+
+    #[tokio::test]
+    async fn partial_repay_test() {
+        let (contract, lender, borrower) = setup();
+
+        lender
+            .lend_stable(
+                &borrower_hash(),
+                COLLATERAL,
+                STABLE_LOAN_AMOUNT,
+                INTEREST_RATE,
+                COLLATERAL_RATIO,
+            )
+            .await
+            .unwrap();
+        borrower.claim_stable(BORROWER_PREIMAGE).await.unwrap();
+        borrower
+            .repay_stable_partially(&borrower_hash(), 500_000)
+            .await
+            .expect_err("should not partially repay the whole loan");
+        borrower
+            .repay_stable_partially(&borrower_hash(), 200_000)
+            .await
+            .expect("to be able to partially repay");
+        let stable_loan = lender.get_stable_loan(&borrower_hash()).await.unwrap();
+        assert!(!stable_loan.repaid);
+        assert_eq!(300_000, stable_loan.stable_loan_amount);
+        assert_eq!(300_000, contract.balance_of(BORROWER));
+        assert_eq!(700_000, contract.balance_of(LENDER));
+
+        borrower
+            .repay_stable(&borrower_hash())
+            .await
+            .expect("to be able to repay the rest");
+        assert_eq!(0, contract.balance_of(BORROWER));
+        assert_eq!(1_000_000, contract.balance_of(LENDER));
+    }
+
+    #[tokio::test]
+    async fn partial_repay_accrues_interest_per_period_test() {
+        let (contract, lender, borrower) = setup();
+        contract.mint(BORROWER, 40_000);
+
+        set_time(1_000);
+        lender
+            .lend_stable(
+                &borrower_hash(),
+                COLLATERAL,
+                STABLE_LOAN_AMOUNT,
+                INTEREST_RATE,
+                COLLATERAL_RATIO,
+            )
+            .await
+            .unwrap();
+        borrower.claim_stable(BORROWER_PREIMAGE).await.unwrap();
+
+        // The first half year accrues on the whole amount lent.
+        set_time(1_000 + SECONDS_PER_YEAR / 2);
+        borrower
+            .repay_stable_partially(&borrower_hash(), 200_000)
+            .await
+            .unwrap();
+        let stable_loan = lender.get_stable_loan(&borrower_hash()).await.unwrap();
+        assert_eq!(300_000, stable_loan.stable_loan_amount);
+        assert_eq!(25_000, stable_loan.accrued_interest);
+        assert_eq!(1_000 + SECONDS_PER_YEAR / 2, stable_loan.start_time);
+
+        // The second one only on the amount left.
+        set_time(1_000 + SECONDS_PER_YEAR);
+        borrower
+            .repay_stable(&borrower_hash())
+            .await
+            .expect("to be able to repay with the interest of both periods");
+        assert_eq!(0, contract.balance_of(BORROWER));
+        assert_eq!(1_040_000, contract.balance_of(LENDER));
+    }
+
+    #[tokio::test]
+    async fn collateral_value_test() {
+        let (_, lender, _) = setup();
+
+        let collateral_value = lender.get_collateral_value(COLLATERAL).await.unwrap();
+        assert_eq!(800_000, collateral_value);
+        assert_eq!(
+            STABLE_LOAN_AMOUNT,
+            collateral_value * 100 / COLLATERAL_RATIO as u128
+        );
+    }
+
+    #[tokio::test]
+    async fn btc_price_test() {
+        let (contract, lender, _) = setup();
+
+        lender
+            .get_btc_price()
+            .await
+            .expect_err("should not return a price before the feed answers");
+        contract.set_btc_price(60_000);
+        assert_eq!(60_000, lender.get_btc_price().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn deployment_test() {
+        let (_, lender, _) = setup();
+
+        let deployment = lender.get_deployment().await.unwrap();
+        MOCK_LENDING_DEPLOYMENT
+            .validate(&deployment)
+            .expect("same deployment to be valid");
+        LendingDeployment {
+            chain_id: 1,
+            ..deployment
+        }
+        .validate(&deployment)
+        .expect_err("should not accept another chain");
+        LendingDeployment {
+            stable_token_address: [3; 20],
+            ..deployment
+        }
+        .validate(&deployment)
+        .expect_err("should not accept another stable token");
+    }
+
+    #[tokio::test]
+    async fn double_lend_fails_test() {
+        let (contract, lender, _) = setup();
+
+        lender
+            .lend_stable(
+                &borrower_hash(),
+                COLLATERAL,
+                STABLE_LOAN_AMOUNT,
+                INTEREST_RATE,
+                COLLATERAL_RATIO,
+            )
+            .await
+            .unwrap();
+        lender
+            .lend_stable(
+                &borrower_hash(),
+                COLLATERAL,
+                STABLE_LOAN_AMOUNT,
+                INTEREST_RATE,
+                COLLATERAL_RATIO,
+            )
+            .await
+            .expect_err("should not lend twice to the same borrower hash");
+        assert_eq!(500_000, contract.balance_of(LENDER));
+    }
+
+    #[tokio::test]
+    async fn lend_without_balance_fails_test() {
+        let (_, _, borrower) = setup();
+
+        borrower
+            .lend_stable(
+                &borrower_hash(),
+                COLLATERAL,
+                STABLE_LOAN_AMOUNT,
+                INTEREST_RATE,
+                COLLATERAL_RATIO,
+            )
+            .await
+            .expect_err("should not lend without stable tokens");
+        let stable_loan = borrower.get_stable_loan(&borrower_hash()).await.unwrap();
+        assert_eq!(Amount::ZERO, stable_loan.collateral_amount);
+    }
+
+    #[tokio::test]
+    async fn claim_with_wrong_preimage_fails_test() {
+        let (contract, lender, borrower) = setup();
+
+        lender
+            .lend_stable(
+                &borrower_hash(),
+                COLLATERAL,
+                STABLE_LOAN_AMOUNT,
+                INTEREST_RATE,
+                COLLATERAL_RATIO,
+            )
+            .await
+            .unwrap();
+        borrower
+            .claim_stable(BORROWER_PREIMAGE + 1)
+            .await
+            .expect_err("should not claim with a wrong preimage");
+        assert_eq!(0, contract.balance_of(BORROWER));
+    }
+
+    #[tokio::test]
+    async fn double_claim_fails_test() {
+        let (contract, lender, borrower) = setup();
+
+        lender
+            .lend_stable(
+                &borrower_hash(),
+                COLLATERAL,
+                STABLE_LOAN_AMOUNT,
+                INTEREST_RATE,
+                COLLATERAL_RATIO,
+            )
+            .await
+            .unwrap();
+        contract.mint(CONTRACT_ACCOUNT, 500_000);
+        borrower.claim_stable(BORROWER_PREIMAGE).await.unwrap();
+        borrower
+            .claim_stable(BORROWER_PREIMAGE)
+            .await
+            .expect_err("should not claim twice");
+        assert_eq!(500_000, contract.balance_of(BORROWER));
+    }
+
+    #[tokio::test]
+    async fn reclaim_unclaimed_loan_test() {
+        let (contract, lender, borrower) = setup();
+
+        set_time(1_000);
+        lender
+            .lend_stable(
+                &borrower_hash(),
+                COLLATERAL,
+                STABLE_LOAN_AMOUNT,
+                INTEREST_RATE,
+                COLLATERAL_RATIO,
+            )
+            .await
+            .unwrap();
+        set_time(1_000 + STABLE_CLAIM_PERIOD - 1);
+        lender
+            .reclaim_stable(&borrower_hash())
+            .await
+            .expect_err("should not reclaim during the claim period");
+        set_time(1_000 + STABLE_CLAIM_PERIOD);
+        borrower
+            .reclaim_stable(&borrower_hash())
+            .await
+            .expect_err("only the lender should reclaim");
+        lender
+            .reclaim_stable(&borrower_hash())
+            .await
+            .expect("to be able to reclaim after the claim period");
+        assert_eq!(1_000_000, contract.balance_of(LENDER));
+        let stable_loan = lender.get_stable_loan(&borrower_hash()).await.unwrap();
+        assert_eq!(Amount::ZERO, stable_loan.collateral_amount);
+
+        borrower
+            .claim_stable(BORROWER_PREIMAGE)
+            .await
+            .expect_err("should not claim a reclaimed loan");
+        assert_eq!(0, contract.balance_of(BORROWER));
+    }
+
+    #[tokio::test]
+    async fn reclaim_claimed_loan_fails_test() {
+        let (contract, lender, borrower) = setup();
+
+        set_time(1_000);
+        lender
+            .lend_stable(
+                &borrower_hash(),
+                COLLATERAL,
+                STABLE_LOAN_AMOUNT,
+                INTEREST_RATE,
+                COLLATERAL_RATIO,
+            )
+            .await
+            .unwrap();
+        borrower.claim_stable(BORROWER_PREIMAGE).await.unwrap();
+        set_time(1_000 + STABLE_CLAIM_PERIOD);
+        lender
+            .reclaim_stable(&borrower_hash())
+            .await
+            .expect_err("should not reclaim a claimed loan");
+        assert_eq!(500_000, contract.balance_of(LENDER));
+        assert_eq!(500_000, contract.balance_of(BORROWER));
+    }
+
+    #[tokio::test]
+    async fn repay_before_claim_fails_test() {
+        let (contract, lender, borrower) = setup();
+        contract.mint(BORROWER, 500_000);
+
+        lender
+            .lend_stable(
+                &borrower_hash(),
+                COLLATERAL,
+                STABLE_LOAN_AMOUNT,
+                INTEREST_RATE,
+                COLLATERAL_RATIO,
+            )
+            .await
+            .unwrap();
+        borrower
+            .repay_stable(&borrower_hash())
+            .await
+            .expect_err("should not repay an unclaimed loan");
+    }
+
+    #[tokio::test]
+    async fn double_repay_fails_test() {
+        let (contract, lender, borrower) = setup();
+        contract.mint(BORROWER, 500_000);
+
+        lender
+            .lend_stable(
+                &borrower_hash(),
+                COLLATERAL,
+                STABLE_LOAN_AMOUNT,
+                INTEREST_RATE,
+                COLLATERAL_RATIO,
+            )
+            .await
+            .unwrap();
+        borrower.claim_stable(BORROWER_PREIMAGE).await.unwrap();
+        borrower.repay_stable(&borrower_hash()).await.unwrap();
+        borrower
+            .repay_stable(&borrower_hash())
+            .await
+            .expect_err("should not repay twice");
+        assert_eq!(500_000, contract.balance_of(BORROWER));
+    }
+}
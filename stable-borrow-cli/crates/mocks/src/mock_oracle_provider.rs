@@ -0,0 +1,164 @@
+//! In memory implementation of the [`Oracle`] interface.
+
+use bitcoin::hashes::{sha256, Hash};
+use dlc_manager::error::Error as DaemonError;
+use dlc_manager::Oracle;
+use dlc_messages::oracle_msgs::{
+    EventDescriptor, OracleAnnouncement, OracleAttestation, OracleEvent,
+};
+use lightning::util::ser::Writeable;
+use secp256k1_zkp::rand::thread_rng;
+use secp256k1_zkp::{Keypair, Message, SecretKey, XOnlyPublicKey, SECP256K1};
+
+use std::collections::HashMap;
+
+/// Oracle whose announcements and attestations are created on demand with
+/// [`MockOracle::add_event`] and [`MockOracle::add_attestation`].
+#[derive(Clone)]
+pub struct MockOracle {
+    key_pair: Keypair,
+    announcements: HashMap<String, OracleAnnouncement>,
+    attestations: HashMap<String, OracleAttestation>,
+    nonces: HashMap<String, Vec<SecretKey>>,
+}
+
+impl MockOracle {
+    /// Creates a new oracle with a random key.
+    pub fn new() -> Self {
+        let key_pair = Keypair::new(SECP256K1, &mut thread_rng());
+
+        MockOracle {
+            key_pair,
+            announcements: HashMap::new(),
+            attestations: HashMap::new(),
+            nonces: HashMap::new(),
+        }
+    }
+
+    /// Creates a new oracle using the given secret key.
+    pub fn from_secret_key(sk: &SecretKey) -> Self {
+        let key_pair = Keypair::from_secret_key(SECP256K1, sk);
+
+        MockOracle {
+            key_pair,
+            announcements: HashMap::new(),
+            attestations: HashMap::new(),
+            nonces: HashMap::new(),
+        }
+    }
+}
+
+impl Default for MockOracle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Oracle for MockOracle {
+    fn get_public_key(&self) -> XOnlyPublicKey {
+        XOnlyPublicKey::from_keypair(&self.key_pair).0
+    }
+
+    fn get_announcement(&self, event_id: &str) -> Result<OracleAnnouncement, DaemonError> {
+        let res = self
+            .announcements
+            .get(event_id)
+            .ok_or_else(|| DaemonError::OracleError("Announcement not found".to_string()))?;
+        Ok(res.clone())
+    }
+
+    fn get_attestation(&self, event_id: &str) -> Result<OracleAttestation, DaemonError> {
+        let res = self
+            .attestations
+            .get(event_id)
+            .ok_or_else(|| DaemonError::OracleError("Attestation not found".to_string()))?;
+        Ok(res.clone())
+    }
+}
+
+impl MockOracle {
+    fn generate_nonces_for_event(
+        &mut self,
+        event_id: &str,
+        event_descriptor: &EventDescriptor,
+    ) -> Vec<XOnlyPublicKey> {
+        let nb_nonces = match event_descriptor {
+            EventDescriptor::EnumEvent(_) => 1,
+            EventDescriptor::DigitDecompositionEvent(d) => {
+                d.nb_digits as usize + usize::from(d.is_signed)
+            }
+        };
+
+        let priv_nonces: Vec<_> = (0..nb_nonces)
+            .map(|_| SecretKey::new(&mut thread_rng()))
+            .collect();
+        let key_pairs: Vec<_> = priv_nonces
+            .iter()
+            .map(|x| Keypair::from_seckey_slice(SECP256K1, x.as_ref()).unwrap())
+            .collect();
+
+        let nonces = key_pairs
+            .iter()
+            .map(|k| XOnlyPublicKey::from_keypair(k).0)
+            .collect();
+
+        self.nonces.insert(event_id.to_string(), priv_nonces);
+
+        nonces
+    }
+
+    /// Creates and signs the announcement of an event with the given
+    /// descriptor and maturity.
+    pub fn add_event(&mut self, event_id: &str, event_descriptor: &EventDescriptor, maturity: u32) {
+        let oracle_nonces = self.generate_nonces_for_event(event_id, event_descriptor);
+        let oracle_event = OracleEvent {
+            oracle_nonces,
+            event_maturity_epoch: maturity,
+            event_descriptor: event_descriptor.clone(),
+            event_id: event_id.to_string(),
+        };
+        let mut event_hex = Vec::new();
+        oracle_event
+            .write(&mut event_hex)
+            .expect("Error writing oracle event");
+        let hash = sha256::Hash::hash(&event_hex);
+        let msg = Message::from_digest(hash.to_byte_array());
+        let sig = SECP256K1.sign_schnorr(&msg, &self.key_pair);
+        let announcement = OracleAnnouncement {
+            oracle_event,
+            oracle_public_key: self.get_public_key(),
+            announcement_signature: sig,
+        };
+        self.announcements
+            .insert(event_id.to_string(), announcement);
+    }
+
+    /// Signs the given outcomes of a previously added event.
+    pub fn add_attestation(&mut self, event_id: &str, outcomes: &[String]) {
+        let nonces = self
+            .nonces
+            .get(event_id)
+            .expect("to have generated nonces for the event");
+        let signatures = outcomes
+            .iter()
+            .zip(nonces.iter())
+            .map(|(x, nonce)| {
+                let hash = sha256::Hash::hash(x.as_bytes());
+                let msg = Message::from_digest(hash.to_byte_array());
+                dlc::secp_utils::schnorrsig_sign_with_nonce(
+                    SECP256K1,
+                    &msg,
+                    &self.key_pair,
+                    &nonce.secret_bytes(),
+                )
+            })
+            .collect();
+        let attestation = OracleAttestation {
+            event_id: event_id.to_string(),
+            oracle_public_key: self.get_public_key(),
+            signatures,
+            outcomes: outcomes.to_vec(),
+        };
+        self.attestations.insert(event_id.to_string(), attestation);
+    }
+}
@@ -0,0 +1,25 @@
+//! Controllable implementation of the [`Time`] interface.
+
+use dlc_manager::Time;
+use std::cell::RefCell;
+
+thread_local! {
+    static MOCK_TIME: RefCell<u64> = RefCell::new(0);
+}
+
+/// Time provider returning the value last set with [`set_time`] on the
+/// current thread.
+pub struct MockTime {}
+
+impl Time for MockTime {
+    fn unix_time_now(&self) -> u64 {
+        MOCK_TIME.with(|f| *f.borrow())
+    }
+}
+
+/// Sets the time returned by [`MockTime`] on the current thread.
+pub fn set_time(time: u64) {
+    MOCK_TIME.with(|f| {
+        *f.borrow_mut() = time;
+    });
+}
@@ -0,0 +1,212 @@
+//! In memory implementation of the [`Wallet`] and [`ContractSignerProvider`]
+//! interfaces.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Mutex;
+
+use bitcoin::absolute::LockTime;
+use bitcoin::hashes::Hash;
+use bitcoin::psbt::Psbt;
+use bitcoin::transaction::Version;
+use bitcoin::{
+    Address, Amount, CompressedPublicKey, Network, OutPoint, ScriptBuf, Sequence, Transaction,
+    TxIn, TxOut, Txid, Witness,
+};
+use dlc_manager::{
+    error::Error, Blockchain, ContractSignerProvider, KeysId, SimpleSigner, Utxo, Wallet,
+};
+use secp256k1_zkp::{rand::thread_rng, PublicKey, SecretKey, SECP256K1};
+
+use crate::mock_blockchain::MockBlockchain;
+
+/// Wallet holding a fixed set of UTXOs, whose funding transactions are
+/// broadcast to the given [`MockBlockchain`]. Keys are generated randomly so
+/// that two instances never share a key.
+pub struct MockWallet {
+    address: Address,
+    utxos: Mutex<Vec<Utxo>>,
+    unreserved: Mutex<Vec<OutPoint>>,
+    signer_keys: Mutex<HashMap<KeysId, SecretKey>>,
+    secret_keys: Mutex<HashMap<PublicKey, SecretKey>>,
+}
+
+impl MockWallet {
+    /// Creates a new wallet with one UTXO for each of the given values.
+    pub fn new(blockchain: &Rc<MockBlockchain>, utxo_values: &[Amount]) -> Self {
+        let secret_key = SecretKey::new(&mut thread_rng());
+        let pubkey = CompressedPublicKey(PublicKey::from_secret_key(SECP256K1, &secret_key));
+        let address = Address::p2wpkh(&pubkey, Network::Regtest);
+
+        let mut utxos = Vec::with_capacity(utxo_values.len());
+        for (i, utxo_value) in utxo_values.iter().enumerate() {
+            let tx_out = TxOut {
+                value: *utxo_value,
+                script_pubkey: address.script_pubkey(),
+            };
+            // A transaction without inputs would be ambiguous to deserialize,
+            // so each funding transaction spends a dummy outpoint.
+            let tx = Transaction {
+                version: Version::TWO,
+                lock_time: LockTime::ZERO,
+                input: vec![TxIn {
+                    previous_output: OutPoint {
+                        txid: Txid::all_zeros(),
+                        vout: i as u32,
+                    },
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::MAX,
+                    witness: Witness::new(),
+                }],
+                output: vec![tx_out.clone()],
+            };
+            blockchain
+                .send_transaction(&tx)
+                .expect("the mock blockchain to accept transactions");
+            utxos.push(Utxo {
+                tx_out,
+                outpoint: OutPoint {
+                    txid: tx.compute_txid(),
+                    vout: 0,
+                },
+                address: address.clone(),
+                redeem_script: ScriptBuf::new(),
+                reserved: false,
+            });
+        }
+
+        let mut secret_keys = HashMap::new();
+        secret_keys.insert(pubkey.0, secret_key);
+
+        MockWallet {
+            address,
+            utxos: Mutex::new(utxos),
+            unreserved: Mutex::new(Vec::new()),
+            signer_keys: Mutex::new(HashMap::new()),
+            secret_keys: Mutex::new(secret_keys),
+        }
+    }
+
+    /// Returns the outpoints passed to [`Wallet::unreserve_utxos`], in call
+    /// order.
+    pub fn get_unreserved_utxos(&self) -> Vec<OutPoint> {
+        self.unreserved.lock().unwrap().clone()
+    }
+
+    /// Returns the outpoints of the UTXOs currently reserved.
+    pub fn get_reserved_utxos(&self) -> Vec<OutPoint> {
+        self.utxos
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|u| u.reserved)
+            .map(|u| u.outpoint)
+            .collect()
+    }
+
+    fn new_secret_key(&self) -> SecretKey {
+        let secret_key = SecretKey::new(&mut thread_rng());
+        self.secret_keys.lock().unwrap().insert(
+            PublicKey::from_secret_key(SECP256K1, &secret_key),
+            secret_key,
+        );
+        secret_key
+    }
+}
+
+impl ContractSignerProvider for MockWallet {
+    type Signer = SimpleSigner;
+
+    fn derive_signer_key_id(&self, _is_offer_party: bool, temp_id: [u8; 32]) -> [u8; 32] {
+        temp_id
+    }
+
+    fn derive_contract_signer(&self, key_id: KeysId) -> Result<Self::Signer, Error> {
+        if let Some(secret_key) = self.signer_keys.lock().unwrap().get(&key_id) {
+            return Ok(SimpleSigner::new(*secret_key));
+        }
+        let secret_key = self.new_secret_key();
+        self.signer_keys.lock().unwrap().insert(key_id, secret_key);
+        Ok(SimpleSigner::new(secret_key))
+    }
+
+    fn get_secret_key_for_pubkey(&self, pubkey: &PublicKey) -> Result<SecretKey, Error> {
+        self.secret_keys
+            .lock()
+            .unwrap()
+            .get(pubkey)
+            .cloned()
+            .ok_or_else(|| Error::InvalidParameters("Unknown public key".to_string()))
+    }
+
+    fn get_new_secret_key(&self) -> Result<SecretKey, Error> {
+        Ok(self.new_secret_key())
+    }
+}
+
+impl Wallet for MockWallet {
+    fn get_new_address(&self) -> Result<Address, Error> {
+        Ok(self.address.clone())
+    }
+
+    fn get_new_change_address(&self) -> Result<Address, Error> {
+        Ok(self.address.clone())
+    }
+
+    fn get_utxos_for_amount(
+        &self,
+        amount: Amount,
+        _fee_rate: u64,
+        lock_utxos: bool,
+    ) -> Result<Vec<Utxo>, Error> {
+        let mut utxos = self.utxos.lock().unwrap();
+        let mut selected = Vec::new();
+        let mut total = Amount::ZERO;
+        for utxo in utxos.iter_mut().filter(|u| !u.reserved) {
+            if total >= amount {
+                break;
+            }
+            total += utxo.tx_out.value;
+            if lock_utxos {
+                utxo.reserved = true;
+            }
+            selected.push(utxo.clone());
+        }
+
+        if total < amount {
+            for utxo in utxos.iter_mut() {
+                if selected.iter().any(|s| s.outpoint == utxo.outpoint) {
+                    utxo.reserved = false;
+                }
+            }
+            return Err(Error::InvalidState(
+                "Not enough UTXOs for amount".to_string(),
+            ));
+        }
+
+        Ok(selected)
+    }
+
+    fn import_address(&self, _address: &Address) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn sign_psbt_input(&self, _psbt: &mut Psbt, _input_index: usize) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn sign_raw_transaction(&self, transaction: &Transaction) -> Result<Transaction, Error> {
+        Ok(transaction.clone())
+    }
+
+    fn unreserve_utxos(&self, outpoints: &[OutPoint]) -> Result<(), Error> {
+        let mut utxos = self.utxos.lock().unwrap();
+        for utxo in utxos.iter_mut() {
+            if outpoints.contains(&utxo.outpoint) {
+                utxo.reserved = false;
+            }
+        }
+        self.unreserved.lock().unwrap().extend_from_slice(outpoints);
+        Ok(())
+    }
+}
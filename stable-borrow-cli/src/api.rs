@@ -0,0 +1,1042 @@
+use crate::cli::{connect_peer_if_necessary, parse_peer_info, ApiConfig};
+use crate::daemon::{relay_loan_listings, run_periodic_checks};
+use crate::hex_utils::{hex_str, to_slice};
+use crate::DlcManager;
+use crate::DlcMessageHandler;
+use crate::PeerManager;
+use bitcoin::secp256k1::{PublicKey, SecretKey};
+use bitcoin::Amount;
+use dlc_manager::contract::contract_input::{ContractInput, LoanContractInput};
+use dlc_manager::contract::offered_contract::OfferedContract;
+use dlc_manager::contract::{
+    Contract, FailedAcceptContract, FailedLoanContract, FailedSignContract,
+};
+use dlc_manager::loan_marketplace::{LoanListingFilter, LoanListingInput};
+use dlc_manager::Storage;
+use dlc_messages::LoanOfferReason;
+use dlc_messages::Message as DlcMessage;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::Read;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio::sync::Mutex;
+
+/// The maximum size in bytes of the body of a request.
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// An error answered to a JSON-RPC request.
+#[derive(Debug)]
+pub(crate) struct ApiError {
+    code: i64,
+    message: String,
+}
+
+impl ApiError {
+    fn invalid_request(message: &str) -> Self {
+        ApiError {
+            code: -32600,
+            message: message.to_string(),
+        }
+    }
+
+    fn method_not_found(method: &str) -> Self {
+        ApiError {
+            code: -32601,
+            message: format!("Unknown method {}", method),
+        }
+    }
+
+    fn invalid_params(message: String) -> Self {
+        ApiError {
+            code: -32602,
+            message,
+        }
+    }
+
+    fn node_error(message: String) -> Self {
+        ApiError {
+            code: -32000,
+            message,
+        }
+    }
+}
+
+impl From<dlc_manager::error::Error> for ApiError {
+    fn from(e: dlc_manager::error::Error) -> Self {
+        ApiError::node_error(e.to_string())
+    }
+}
+
+#[derive(Deserialize)]
+struct RpcRequest {
+    method: String,
+    #[serde(default)]
+    params: Value,
+    #[serde(default)]
+    id: Value,
+}
+
+/// A request received by the server, to be executed by the daemon which
+/// sends the result back.
+pub(crate) struct ApiRequest {
+    method: String,
+    params: Value,
+    result_sender: mpsc::Sender<Result<Value, ApiError>>,
+}
+
+/// Executes the requests received by the API server on the node.
+pub(crate) struct ApiHandler {
+    peer_manager: Arc<PeerManager>,
+    dlc_message_handler: Arc<DlcMessageHandler>,
+    dlc_manager: Arc<Mutex<DlcManager>>,
+    node_secret_key: SecretKey,
+    requests: UnboundedReceiver<ApiRequest>,
+}
+
+/// Starts a JSON-RPC server on the local interface and returns the handler of
+/// the requests it receives. Requests must carry the configured token as
+/// bearer token. As the manager is not shared across tasks, the requests are
+/// executed by the daemon in between its other tasks.
+pub(crate) fn start_api_server(
+    config: &ApiConfig,
+    peer_manager: Arc<PeerManager>,
+    dlc_message_handler: Arc<DlcMessageHandler>,
+    dlc_manager: Arc<Mutex<DlcManager>>,
+    node_secret_key: SecretKey,
+) -> ApiHandler {
+    let server = tiny_http::Server::http(("127.0.0.1", config.port))
+        .expect("Failed to bind to API port - is something else already listening on it?");
+    let (sender, requests) = tokio::sync::mpsc::unbounded_channel();
+    let token = config.token.clone();
+    std::thread::spawn(move || serve(server, &token, sender));
+    println!("API listening on 127.0.0.1:{}", config.port);
+
+    ApiHandler {
+        peer_manager,
+        dlc_message_handler,
+        dlc_manager,
+        node_secret_key,
+        requests,
+    }
+}
+
+fn serve(server: tiny_http::Server, token: &str, sender: UnboundedSender<ApiRequest>) {
+    let content_type =
+        tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).ok();
+    for mut request in server.incoming_requests() {
+        let authorized = request
+            .headers()
+            .iter()
+            .find(|h| h.field.equiv("Authorization"))
+            .and_then(|h| h.value.as_str().strip_prefix("Bearer "))
+            .is_some_and(|t| is_token_valid(t, token));
+        if !authorized {
+            let _ = request.respond(tiny_http::Response::empty(401));
+            continue;
+        }
+        if *request.method() != tiny_http::Method::Post {
+            let _ = request.respond(tiny_http::Response::empty(405));
+            continue;
+        }
+        if request.body_length().is_some_and(|x| x > MAX_BODY_SIZE) {
+            let _ = request.respond(tiny_http::Response::empty(413));
+            continue;
+        }
+
+        // The body length is not known for chunked requests, so at most one
+        // byte more than allowed is read to detect larger bodies.
+        let mut body = String::new();
+        let read = request
+            .as_reader()
+            .take(MAX_BODY_SIZE as u64 + 1)
+            .read_to_string(&mut body);
+        if body.len() > MAX_BODY_SIZE {
+            let _ = request.respond(tiny_http::Response::empty(413));
+            continue;
+        }
+        let response = match read {
+            Ok(_) => handle_body(&body, &sender),
+            Err(_) => rpc_error(Value::Null, ApiError::invalid_request("Invalid body")),
+        };
+        let mut response = tiny_http::Response::from_string(response.to_string());
+        if let Some(content_type) = &content_type {
+            response.add_header(content_type.clone());
+        }
+        let _ = request.respond(response);
+    }
+}
+
+fn handle_body(body: &str, sender: &UnboundedSender<ApiRequest>) -> Value {
+    let rpc_request: RpcRequest = match serde_json::from_str(body) {
+        Ok(r) => r,
+        Err(e) => {
+            return rpc_error(
+                Value::Null,
+                ApiError {
+                    code: -32700,
+                    message: e.to_string(),
+                },
+            )
+        }
+    };
+    let (result_sender, result_receiver) = mpsc::channel();
+    let request = ApiRequest {
+        method: rpc_request.method,
+        params: rpc_request.params,
+        result_sender,
+    };
+    let result = match sender.send(request) {
+        Ok(()) => result_receiver
+            .recv()
+            .unwrap_or_else(|_| Err(ApiError::node_error("Request was dropped".to_string()))),
+        Err(_) => Err(ApiError::node_error("Node is shutting down".to_string())),
+    };
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": rpc_request.id }),
+        Err(e) => rpc_error(rpc_request.id, e),
+    }
+}
+
+fn rpc_error(id: Value, error: ApiError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "error": { "code": error.code, "message": error.message },
+        "id": id,
+    })
+}
+
+/// Compares the tokens in constant time.
+fn is_token_valid(token: &str, expected: &str) -> bool {
+    token.len() == expected.len()
+        && token
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+impl ApiHandler {
+    /// Waits for the next request received by the server.
+    pub(crate) async fn next_request(&mut self) -> Option<ApiRequest> {
+        self.requests.recv().await
+    }
+
+    /// Executes the given request and sends its result to the server.
+    pub(crate) async fn handle(&self, request: ApiRequest) {
+        let result = self.execute(&request.method, &request.params).await;
+        let _ = request.result_sender.send(result);
+    }
+
+    fn send_message(&self, node_id: PublicKey, msg: DlcMessage) {
+        self.dlc_message_handler.send_message(node_id, msg);
+        self.peer_manager.process_events();
+    }
+
+    async fn connect_peer(&self, params: &Value) -> Result<PublicKey, ApiError> {
+        let peer: String = param(params, "peer")?;
+        let (pubkey, peer_addr) =
+            parse_peer_info(peer).map_err(|e| ApiError::invalid_params(e.to_string()))?;
+        connect_peer_if_necessary(pubkey, peer_addr, self.peer_manager.clone())
+            .await
+            .map_err(|_| ApiError::node_error(format!("Could not connect to {}", pubkey)))?;
+        Ok(pubkey)
+    }
+
+    async fn execute(&self, method: &str, params: &Value) -> Result<Value, ApiError> {
+        match method {
+            "connectpeer" => {
+                let pubkey = self.connect_peer(params).await?;
+                Ok(json!({ "nodeId": pubkey.to_string() }))
+            }
+            "listpeers" => Ok(Value::Array(
+                self.peer_manager
+                    .list_peers()
+                    .iter()
+                    .map(|x| json!({ "nodeId": x.counterparty_node_id.to_string() }))
+                    .collect(),
+            )),
+            "offercontract" | "offerchannel" => {
+                let pubkey = self.connect_peer(params).await?;
+                let contract_input: ContractInput = param(params, "contractInput")?;
+                let manager_clone = self.dlc_manager.clone();
+                let is_contract = method == "offercontract";
+                // Because the oracle client is currently blocking we need to use `spawn_blocking` here.
+                let (id, msg) = tokio::task::spawn_blocking(move || {
+                    let manager = manager_clone.blocking_lock();
+                    if is_contract {
+                        manager
+                            .send_offer(&contract_input, pubkey)
+                            .map(|x| (x.temporary_contract_id, DlcMessage::Offer(x)))
+                    } else {
+                        manager
+                            .offer_channel(&contract_input, pubkey)
+                            .map(|x| (x.temporary_channel_id, DlcMessage::OfferChannel(x)))
+                    }
+                })
+                .await
+                .unwrap()?;
+                self.send_message(pubkey, msg);
+                Ok(json!({ "id": hex_str(&id) }))
+            }
+            "offerloan" => {
+                let pubkey = self.connect_peer(params).await?;
+                let contract_input: LoanContractInput = param(params, "contractInput")?;
+                let manager_clone = self.dlc_manager.clone();
+                let msg = tokio::task::spawn_blocking(move || {
+                    manager_clone
+                        .blocking_lock()
+                        .send_offer_loan(&contract_input, pubkey)
+                })
+                .await
+                .unwrap()?;
+                let contract_id = msg.offer_dlc.temporary_contract_id;
+                self.send_message(pubkey, DlcMessage::OfferLoan(msg));
+                Ok(json!({ "contractId": hex_str(&contract_id) }))
+            }
+            "requestloan" => {
+                let pubkey = self.connect_peer(params).await?;
+                let msg = self.dlc_manager.lock().await.send_loan_request(
+                    pubkey,
+                    Amount::from_sat(param(params, "collateral")?),
+                    param(params, "principal")?,
+                    param(params, "maxInterestRate")?,
+                    param(params, "duration")?,
+                )?;
+                let request_id = msg.request_id;
+                self.send_message(pubkey, DlcMessage::RequestLoan(msg));
+                Ok(json!({ "requestId": hex_str(&request_id) }))
+            }
+            "publishloanlisting" => {
+                let listing_input: LoanListingInput = param(params, "listing")?;
+                let listing = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .publish_loan_listing(&listing_input, &self.node_secret_key)?;
+                relay_loan_listings(
+                    &self.peer_manager,
+                    &self.dlc_manager,
+                    &self.dlc_message_handler,
+                )
+                .await;
+                self.peer_manager.process_events();
+                Ok(json!({ "listingId": hex_str(&listing.terms.listing_id) }))
+            }
+            "listloanlistings" => {
+                let filter = LoanListingFilter {
+                    principal: param(params, "principal")?,
+                    max_interest_rate: param(params, "maxInterestRate")?,
+                    max_collateral_ratio: param(params, "maxCollateralRatio")?,
+                    max_duration: param(params, "maxDuration")?,
+                    oracle_public_key: param(params, "oraclePublicKey")?,
+                    lender: param(params, "lender")?,
+                };
+                let listings = self.dlc_manager.lock().await.get_loan_listings(&filter);
+                Ok(Value::Array(
+                    listings
+                        .iter()
+                        .map(|x| {
+                            let terms = &x.terms;
+                            json!({
+                                "listingId": hex_str(&terms.listing_id),
+                                "lender": terms.lender.to_string(),
+                                "lenderAddress": terms.lender_address,
+                                "minPrincipal": terms.min_principal,
+                                "maxPrincipal": terms.max_principal,
+                                "interestRate": terms.interest_rate,
+                                "collateralRatio": terms.collateral_ratio,
+                                "duration": terms.duration,
+                                "oraclePublicKey": terms.oracle_public_key.to_string(),
+                                "expiry": terms.expiry,
+                            })
+                        })
+                        .collect(),
+                ))
+            }
+            "requestlistedloan" => {
+                let lender: PublicKey = param(params, "lender")?;
+                let listing_id = id_param(params, "listingId")?;
+                let listing = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .get_loan_listing(&lender, &listing_id)
+                    .ok_or_else(|| {
+                        ApiError::invalid_params("Unknown or expired loan listing".to_string())
+                    })?;
+                let peer = json!({
+                    "peer": format!("{}@{}", listing.terms.lender, listing.terms.lender_address)
+                });
+                self.connect_peer(&peer).await?;
+                let (node_id, msg) = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .send_loan_request_for_listing(
+                        &lender,
+                        &listing_id,
+                        Amount::from_sat(param(params, "collateral")?),
+                        param(params, "principal")?,
+                    )?;
+                let request_id = msg.request_id;
+                self.send_message(node_id, DlcMessage::RequestLoan(msg));
+                Ok(json!({ "requestId": hex_str(&request_id) }))
+            }
+            "listloanrequests" => {
+                let requests = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .get_store()
+                    .get_loan_requests()?;
+                Ok(Value::Array(
+                    requests
+                        .iter()
+                        .map(|x| {
+                            json!({
+                                "requestId": hex_str(&x.id),
+                                "counterParty": x.counter_party.to_string(),
+                                "isBorrower": x.is_borrower,
+                                "collateral": x.collateral.to_sat(),
+                                "principal": x.principal,
+                                "maxInterestRate": x.max_interest_rate,
+                                "duration": x.duration,
+                                "state": x.state.to_string(),
+                                "contractId": x.contract_id.as_ref().map(|id| hex_str(id)),
+                                "listingId": x.listing_id.as_ref().map(|id| hex_str(id)),
+                            })
+                        })
+                        .collect(),
+                ))
+            }
+            "offerloanforrequest" => {
+                let request_id = id_param(params, "requestId")?;
+                let contract_input: LoanContractInput = param(params, "contractInput")?;
+                let manager_clone = self.dlc_manager.clone();
+                let (node_id, msg) = tokio::task::spawn_blocking(move || {
+                    manager_clone
+                        .blocking_lock()
+                        .send_offer_loan_for_request(&request_id, &contract_input)
+                })
+                .await
+                .unwrap()?;
+                let contract_id = msg.offer_dlc.temporary_contract_id;
+                self.send_message(node_id, DlcMessage::OfferLoan(msg));
+                Ok(json!({ "contractId": hex_str(&contract_id) }))
+            }
+            "rejectloanrequest" => {
+                let (node_id, msg) = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .reject_loan_request(&id_param(params, "requestId")?, reason_param(params)?)?;
+                self.send_message(node_id, DlcMessage::RejectLoanRequest(msg));
+                Ok(Value::Null)
+            }
+            "counterloanoffer" => {
+                let (node_id, msg) = self.dlc_manager.lock().await.counter_loan_offer(
+                    &id_param(params, "contractId")?,
+                    param(params, "collateralRatio")?,
+                    param(params, "interestRate")?,
+                    param(params, "duration")?,
+                )?;
+                let result = json!({
+                    "negotiationId": hex_str(&msg.negotiation_id),
+                    "round": msg.round,
+                });
+                self.send_message(node_id, DlcMessage::CounterOfferLoan(msg));
+                Ok(result)
+            }
+            "listloannegotiations" => {
+                let negotiations = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .get_store()
+                    .get_loan_negotiations()?;
+                Ok(Value::Array(
+                    negotiations
+                        .iter()
+                        .map(|x| {
+                            json!({
+                                "negotiationId": hex_str(&x.id),
+                                "counterParty": x.counter_party.to_string(),
+                                "isLender": x.is_lender,
+                                "state": x.state.to_string(),
+                                "round": x.get_round(),
+                                "contractId": hex_str(&x.contract_id),
+                                "revisions": x.revisions.iter().map(|r| json!({
+                                    "counteredContractId": hex_str(&r.countered_contract_id),
+                                    "collateralRatio": r.collateral_ratio,
+                                    "interestRate": r.interest_rate,
+                                    "duration": r.duration,
+                                })).collect::<Vec<_>>(),
+                            })
+                        })
+                        .collect(),
+                ))
+            }
+            "offerloanforcounteroffer" => {
+                let negotiation_id = id_param(params, "negotiationId")?;
+                let contract_input: LoanContractInput = param(params, "contractInput")?;
+                let manager_clone = self.dlc_manager.clone();
+                let (node_id, msg) = tokio::task::spawn_blocking(move || {
+                    manager_clone
+                        .blocking_lock()
+                        .send_offer_loan_for_counter_offer(&negotiation_id, &contract_input)
+                })
+                .await
+                .unwrap()?;
+                let contract_id = msg.offer_dlc.temporary_contract_id;
+                self.send_message(node_id, DlcMessage::OfferLoan(msg));
+                Ok(json!({ "contractId": hex_str(&contract_id) }))
+            }
+            "rejectloancounteroffer" => {
+                let (node_id, msg) = self.dlc_manager.lock().await.reject_loan_counter_offer(
+                    &id_param(params, "negotiationId")?,
+                    reason_param(params)?,
+                )?;
+                self.send_message(node_id, DlcMessage::RejectLoanCounterOffer(msg));
+                Ok(Value::Null)
+            }
+            "listoffers" => {
+                let offers = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .get_store()
+                    .get_contract_offers()?;
+                Ok(Value::Array(
+                    offers
+                        .iter()
+                        .filter(|x| !x.is_offer_party)
+                        .map(to_json)
+                        .collect::<Result<_, _>>()?,
+                ))
+            }
+            "listloanoffers" => {
+                let offers = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .get_store()
+                    .get_loan_contract_offers()?;
+                Ok(Value::Array(
+                    offers
+                        .iter()
+                        .filter(|x| !x.offered_contract.is_offer_party)
+                        .map(to_json)
+                        .collect::<Result<_, _>>()?,
+                ))
+            }
+            "acceptoffer" => {
+                let (_, node_id, msg) = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .accept_contract_offer(&id_param(params, "contractId")?)?;
+                self.send_message(node_id, DlcMessage::Accept(msg));
+                Ok(Value::Null)
+            }
+            "acceptloanoffer" => {
+                let (node_id, msg) = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .initiate_accept_loan_contract_offer(&id_param(params, "contractId")?)?;
+                self.send_message(node_id, DlcMessage::InitiateAcceptLoan(msg));
+                Ok(Value::Null)
+            }
+            "finishacceptloanoffer" => {
+                let (_, node_id, msg) = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .accept_loan_contract_offer(&id_param(params, "contractId")?)?;
+                self.send_message(node_id, DlcMessage::AcceptLoan(msg));
+                Ok(Value::Null)
+            }
+            "rejectloanoffer" => {
+                let (node_id, msg) = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .reject_loan_offer(&id_param(params, "contractId")?, reason_param(params)?)?;
+                self.send_message(node_id, DlcMessage::RejectLoan(msg));
+                Ok(Value::Null)
+            }
+            "cancelloanoffer" => {
+                let contract_id = id_param(params, "contractId")?;
+                let reason = reason_param(params)?;
+                let (node_id, msg) = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .cancel_loan_offer(&contract_id, reason)
+                    .await?;
+                self.send_message(node_id, DlcMessage::CancelLoan(msg));
+                Ok(Value::Null)
+            }
+            "repayloan" => {
+                let contract_id = id_param(params, "contractId")?;
+                let (node_id, msg) = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .repay_loan(&contract_id)
+                    .await?;
+                self.send_message(node_id, DlcMessage::RepayLoan(msg));
+                Ok(Value::Null)
+            }
+            "renewloan" => {
+                let contract_id = id_param(params, "contractId")?;
+                let additional_collateral =
+                    Amount::from_sat(param(params, "additionalCollateral")?);
+                let repaid_principal = param(params, "repaidPrincipal")?;
+                let (node_id, msg) = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .renew_loan(&contract_id, additional_collateral, repaid_principal)
+                    .await?;
+                self.send_message(node_id, DlcMessage::RenewLoanOffer(msg));
+                Ok(Value::Null)
+            }
+            "listloanrenewals" => {
+                let renewals = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .get_store()
+                    .get_loan_renewals()?;
+                Ok(Value::Array(
+                    renewals
+                        .iter()
+                        .map(|x| {
+                            json!({
+                                "contractId": hex_str(&x.contract_id),
+                                "counterParty": x.counter_party.to_string(),
+                                "isBorrower": x.is_borrower,
+                                "state": x.state.to_string(),
+                                "additionalCollateral": x.additional_collateral.to_sat(),
+                                "repaidPrincipal": x.repaid_principal,
+                            })
+                        })
+                        .collect(),
+                ))
+            }
+            "loanamountdue" => {
+                let contract_id = id_param(params, "contractId")?;
+                let now = SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs();
+                let amount_due = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .get_loan_amount_due(&contract_id, now)
+                    .await?;
+                // Stable amounts may not fit in a JSON number.
+                Ok(json!({
+                    "principal": amount_due.principal.to_string(),
+                    "accruedInterest": amount_due.accrued_interest.to_string(),
+                    "totalDue": amount_due.total_due.to_string(),
+                }))
+            }
+            "listloanrisks" => Ok(Value::Array(
+                self.dlc_manager
+                    .lock()
+                    .await
+                    .get_loan_risks()
+                    .iter()
+                    .map(|x| {
+                        json!({
+                            "contractId": hex_str(&x.contract_id),
+                            "isLender": x.is_lender,
+                            "level": x.level.to_string(),
+                            "price": x.price,
+                            "collateralRatio": x.collateral_ratio,
+                            "liquidationRatio": x.liquidation_ratio,
+                            "timestamp": x.timestamp,
+                        })
+                    })
+                    .collect(),
+            )),
+            "listcontracts" => {
+                run_periodic_checks(&self.dlc_manager).await?;
+                let manager = self.dlc_manager.lock().await;
+                let contracts = manager.get_store().get_contracts()?;
+                Ok(Value::Array(
+                    contracts
+                        .iter()
+                        .map(|x| contract_to_json(&manager, x))
+                        .collect::<Result<_, _>>()?,
+                ))
+            }
+            "listchanneloffers" => {
+                let offers = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .get_store()
+                    .get_offered_channels()?;
+                Ok(Value::Array(
+                    offers
+                        .iter()
+                        .filter(|x| !x.is_offer_party)
+                        .map(to_json)
+                        .collect::<Result<_, _>>()?,
+                ))
+            }
+            "acceptchannel" => {
+                let (msg, _, _, node_id) = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .accept_channel(&id_param(params, "channelId")?)?;
+                self.send_message(node_id, DlcMessage::AcceptChannel(msg));
+                Ok(Value::Null)
+            }
+            "offersettlechannel" => {
+                let (msg, node_id) = self.dlc_manager.lock().await.settle_offer(
+                    &id_param(params, "channelId")?,
+                    Amount::from_sat(param(params, "counterPayout")?),
+                )?;
+                self.send_message(node_id, DlcMessage::SettleOffer(msg));
+                Ok(Value::Null)
+            }
+            "acceptsettlechanneloffer" => {
+                let (msg, node_id) = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .accept_settle_offer(&id_param(params, "channelId")?)?;
+                self.send_message(node_id, DlcMessage::SettleAccept(msg));
+                Ok(Value::Null)
+            }
+            "rejectsettlechanneloffer" => {
+                let (msg, node_id) = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .reject_settle_offer(&id_param(params, "channelId")?)?;
+                self.send_message(node_id, DlcMessage::Reject(msg));
+                Ok(Value::Null)
+            }
+            "offerchannelrenew" => {
+                let channel_id = id_param(params, "channelId")?;
+                let counter_payout = Amount::from_sat(param(params, "counterPayout")?);
+                let contract_input: ContractInput = param(params, "contractInput")?;
+                let manager_clone = self.dlc_manager.clone();
+                let (msg, node_id) = tokio::task::spawn_blocking(move || {
+                    manager_clone.blocking_lock().renew_offer(
+                        &channel_id,
+                        counter_payout,
+                        &contract_input,
+                    )
+                })
+                .await
+                .unwrap()?;
+                self.send_message(node_id, DlcMessage::RenewOffer(msg));
+                Ok(Value::Null)
+            }
+            "acceptrenewchannel" => {
+                let (msg, node_id) = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .accept_renew_offer(&id_param(params, "channelId")?)?;
+                self.send_message(node_id, DlcMessage::RenewAccept(msg));
+                Ok(Value::Null)
+            }
+            "rejectrenewchanneloffer" => {
+                let (msg, node_id) = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .reject_renew_offer(&id_param(params, "channelId")?)?;
+                self.send_message(node_id, DlcMessage::Reject(msg));
+                Ok(Value::Null)
+            }
+            "listsignedchannels" => {
+                let state: Option<String> = param(params, "state")?;
+                let channels = self
+                    .dlc_manager
+                    .lock()
+                    .await
+                    .get_store()
+                    .get_signed_channels(None)?;
+                Ok(Value::Array(
+                    channels
+                        .iter()
+                        .filter(|x| state.as_ref().is_none_or(|s| *s == x.state.to_string()))
+                        .map(|x| {
+                            json!({
+                                "channelId": hex_str(&x.channel_id),
+                                "temporaryChannelId": hex_str(&x.temporary_channel_id),
+                                "counterParty": x.counter_party.to_string(),
+                                "state": x.state.to_string(),
+                                "contractId": x.get_contract_id().as_ref().map(|id| hex_str(id)),
+                                "ownCollateral": x.own_params.collateral.to_sat(),
+                                "counterCollateral": x.counter_params.collateral.to_sat(),
+                                "fundTxid": x.fund_tx.compute_txid().to_string(),
+                                "updateIdx": x.update_idx,
+                                "feeRatePerVb": x.fee_rate_per_vb,
+                            })
+                        })
+                        .collect(),
+                ))
+            }
+            _ => Err(ApiError::method_not_found(method)),
+        }
+    }
+}
+
+fn param<T: DeserializeOwned>(params: &Value, name: &str) -> Result<T, ApiError> {
+    serde_json::from_value(params.get(name).cloned().unwrap_or(Value::Null))
+        .map_err(|e| ApiError::invalid_params(format!("Invalid {}: {}", name, e)))
+}
+
+fn id_param(params: &Value, name: &str) -> Result<[u8; 32], ApiError> {
+    let id: String = param(params, name)?;
+    let mut res = [0u8; 32];
+    to_slice(&id, &mut res).map_err(|_| ApiError::invalid_params(format!("Invalid {}", name)))?;
+    Ok(res)
+}
+
+fn reason_param(params: &Value) -> Result<LoanOfferReason, ApiError> {
+    let reason: Option<String> = param(params, "reason")?;
+    match reason.as_deref() {
+        None => Ok(LoanOfferReason::Unspecified),
+        Some("terms") => Ok(LoanOfferReason::UnacceptableTerms),
+        Some("funds") => Ok(LoanOfferReason::InsufficientFunds),
+        Some("expired") => Ok(LoanOfferReason::Expired),
+        Some(_) => Err(ApiError::invalid_params(
+            "Invalid reason, expected one of terms, funds or expired".to_string(),
+        )),
+    }
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<Value, ApiError> {
+    serde_json::to_value(value).map_err(|e| ApiError::node_error(e.to_string()))
+}
+
+fn get_offered_contract(contract: &Contract) -> Option<&OfferedContract> {
+    match contract {
+        Contract::Offered(o) | Contract::Rejected(o) => Some(o),
+        Contract::OfferedLoan(o)
+        | Contract::OfferedLoanEscrowConfirmed(o)
+        | Contract::RejectedLoan(o)
+        | Contract::CancelledLoan(o) => Some(&o.offered_contract),
+        Contract::Accepted(a) => Some(&a.offered_contract),
+        Contract::Signed(s) | Contract::Confirmed(s) | Contract::Refunded(s) => {
+            Some(&s.accepted_contract.offered_contract)
+        }
+        Contract::FailedAccept(f) => Some(&f.offered_contract),
+        Contract::FailedSign(f) => Some(&f.accepted_contract.offered_contract),
+        Contract::PreClosed(c) => Some(&c.signed_contract.accepted_contract.offered_contract),
+        Contract::PreRepaid(c) | Contract::Repaid(c) => {
+            Some(&c.signed_contract.accepted_contract.offered_contract)
+        }
+        Contract::EscrowReclaimed(c) => Some(&c.offered_loan_contract.offered_contract),
+        Contract::FailedLoan(c) => Some(&c.offered_loan_contract.offered_contract),
+        Contract::Closed(_) => None,
+    }
+}
+
+fn get_contract_state(contract: &Contract) -> &'static str {
+    match contract {
+        Contract::Offered(_) => "offered",
+        Contract::OfferedLoan(_) => "offeredLoan",
+        Contract::OfferedLoanEscrowConfirmed(_) => "offeredLoanEscrowConfirmed",
+        Contract::Accepted(_) => "accepted",
+        Contract::Signed(_) => "signed",
+        Contract::Confirmed(_) => "confirmed",
+        Contract::PreClosed(_) => "preClosed",
+        Contract::Closed(_) => "closed",
+        Contract::Refunded(_) => "refunded",
+        Contract::FailedAccept(_) => "failedAccept",
+        Contract::FailedSign(_) => "failedSign",
+        Contract::Rejected(_) => "rejected",
+        Contract::PreRepaid(_) => "preRepaid",
+        Contract::Repaid(_) => "repaid",
+        Contract::EscrowReclaimed(_) => "escrowReclaimed",
+        Contract::FailedLoan(_) => "failedLoan",
+        Contract::RejectedLoan(_) => "rejectedLoan",
+        Contract::CancelledLoan(_) => "cancelledLoan",
+    }
+}
+
+fn contract_to_json(manager: &DlcManager, contract: &Contract) -> Result<Value, ApiError> {
+    let mut value = json!({
+        "contractId": hex_str(&contract.get_id()),
+        "temporaryContractId": hex_str(&contract.get_temporary_id()),
+        "state": get_contract_state(contract),
+        "counterParty": contract.get_counter_party_id().to_string(),
+    });
+    if let Some(offered_contract) = get_offered_contract(contract) {
+        value["isOfferParty"] = json!(offered_contract.is_offer_party);
+        value["totalCollateral"] = json!(offered_contract.total_collateral.to_sat());
+        value["offerCollateral"] = json!(offered_contract.offer_params.collateral.to_sat());
+        value["feeRatePerVb"] = json!(offered_contract.fee_rate_per_vb);
+        value["cetLocktime"] = json!(offered_contract.cet_locktime);
+        value["refundLocktime"] = json!(offered_contract.refund_locktime);
+    }
+    match contract {
+        Contract::Closed(c) => {
+            value["pnl"] = json!(c.pnl.to_sat());
+            if let Some(attestations) = &c.attestations {
+                value["outcomes"] = json!(attestations
+                    .iter()
+                    .map(|x| x.outcomes.clone())
+                    .collect::<Vec<_>>());
+            }
+        }
+        Contract::FailedAccept(FailedAcceptContract { error_message, .. })
+        | Contract::FailedSign(FailedSignContract { error_message, .. })
+        | Contract::FailedLoan(FailedLoanContract { error_message, .. }) => {
+            value["error"] = json!(error_message);
+        }
+        _ => {}
+    }
+    if let Some(loan_terms) = manager.get_loan_terms(contract)? {
+        value["loanTerms"] = json!({
+            "principal": loan_terms.principal,
+            "collateralRatio": loan_terms.collateral_ratio,
+            "liquidationRatio": loan_terms.liquidation_ratio,
+            "interestRate": loan_terms.interest_rate,
+            "duration": loan_terms.duration,
+            "chainId": loan_terms.lending_deployment.chain_id,
+            "lendingContractAddress": hex_str(&loan_terms.lending_deployment.lending_contract_address),
+            "stableTokenAddress": hex_str(&loan_terms.lending_deployment.stable_token_address),
+            "escrowRelativeLocktime": loan_terms.escrow_relative_locktime,
+            "escrowConfirmations": loan_terms.escrow_confirmations,
+            "offerExpiry": loan_terms.offer_expiry,
+            "escrowTxid": loan_terms.escrow_txid.map(|x| x.to_string()),
+        });
+    }
+    if let Some(loan_lifecycle) = manager.get_loan_lifecycle(contract)? {
+        value["loanState"] = json!({
+            "state": loan_lifecycle.get_state().to_string(),
+            "since": loan_lifecycle.get_state_timestamp(),
+            "nextAction": loan_lifecycle.get_next_action(),
+        });
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::blocking::Client;
+    use reqwest::StatusCode;
+
+    const TOKEN: &str = "test-token";
+
+    /// Serves the API on a free port and answers its requests with the method
+    /// name, as the daemon would with their result.
+    fn start_test_server() -> String {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let port = server.server_addr().to_ip().unwrap().port();
+        let (sender, mut requests) = tokio::sync::mpsc::unbounded_channel::<ApiRequest>();
+        std::thread::spawn(move || serve(server, TOKEN, sender));
+        std::thread::spawn(move || {
+            while let Some(request) = requests.blocking_recv() {
+                let _ = request.result_sender.send(Ok(json!(request.method)));
+            }
+        });
+        format!("http://127.0.0.1:{}", port)
+    }
+
+    fn post(
+        url: &str,
+        token: Option<&str>,
+        body: impl Into<reqwest::blocking::Body>,
+    ) -> reqwest::blocking::Response {
+        let mut request = Client::new().post(url).body(body);
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        request.send().unwrap()
+    }
+
+    #[test]
+    fn missing_or_wrong_token_is_unauthorized_test() {
+        let url = start_test_server();
+        let body = r#"{"method":"listpeers","id":1}"#;
+
+        assert_eq!(StatusCode::UNAUTHORIZED, post(&url, None, body).status());
+        assert_eq!(
+            StatusCode::UNAUTHORIZED,
+            post(&url, Some("wrong-token"), body).status()
+        );
+        assert_eq!(
+            StatusCode::UNAUTHORIZED,
+            post(&url, Some(""), body).status()
+        );
+    }
+
+    #[test]
+    fn get_request_is_not_allowed_test() {
+        let url = start_test_server();
+
+        let response = Client::new().get(&url).bearer_auth(TOKEN).send().unwrap();
+
+        assert_eq!(StatusCode::METHOD_NOT_ALLOWED, response.status());
+    }
+
+    #[test]
+    fn malformed_body_is_parse_error_test() {
+        let url = start_test_server();
+
+        let response = post(&url, Some(TOKEN), "{not json");
+
+        assert_eq!(StatusCode::OK, response.status());
+        let response: Value = response.json().unwrap();
+        assert_eq!(-32700, response["error"]["code"]);
+        assert_eq!(Value::Null, response["id"]);
+    }
+
+    #[test]
+    fn too_large_body_is_rejected_test() {
+        let url = start_test_server();
+
+        let response = post(&url, Some(TOKEN), vec![b' '; MAX_BODY_SIZE + 1]);
+
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, response.status());
+    }
+
+    #[test]
+    fn authorized_request_returns_result_test() {
+        let url = start_test_server();
+
+        let response = post(&url, Some(TOKEN), r#"{"method":"listpeers","id":7}"#);
+
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(
+            Some("application/json"),
+            response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|x| x.to_str().ok())
+        );
+        let response: Value = response.json().unwrap();
+        assert_eq!(
+            json!({ "jsonrpc": "2.0", "result": "listpeers", "id": 7 }),
+            response
+        );
+    }
+
+    #[test]
+    fn token_is_compared_in_full_test() {
+        assert!(is_token_valid(TOKEN, TOKEN));
+        assert!(!is_token_valid("test-toke", TOKEN));
+        assert!(!is_token_valid("test-tokem", TOKEN));
+        assert!(!is_token_valid("", TOKEN));
+    }
+}
@@ -1,17 +1,23 @@
 // This code is mainly copied and adapted from the LdkSample (https://github.com/lightningdevkit/ldk-sample)
+use crate::daemon::{
+    process_incoming_messages, relay_loan_listings, run_periodic_checks, Daemon, NodeTasks,
+};
 use crate::hex_utils;
 use crate::DlcManager;
 use crate::DlcMessageHandler;
 use crate::PeerManager;
-use bitcoin::secp256k1::PublicKey;
+use bitcoin::secp256k1::{PublicKey, SecretKey};
 use bitcoin::Amount;
 use dlc_manager::channel::signed_channel::SignedChannelState;
 use dlc_manager::channel::signed_channel::SignedChannelStateType;
 use dlc_manager::contract::contract_input::ContractInput;
 use dlc_manager::contract::contract_input::LoanContractInput;
 use dlc_manager::contract::Contract;
+use dlc_manager::loan_marketplace::{LoanListingFilter, LoanListingInput};
+use dlc_manager::loan_monitor::{LoanRiskEvent, LoanRiskLevel};
 use dlc_manager::Storage;
 use dlc_messages::AcceptLoanDlc;
+use dlc_messages::LoanOfferReason;
 use dlc_messages::Message as DlcMessage;
 use hex_utils::{hex_str, to_slice};
 use serde::Deserialize;
@@ -22,8 +28,11 @@ use std::io;
 use std::io::{BufRead, Write};
 use std::net::{SocketAddr, ToSocketAddrs};
 use std::str::SplitWhitespace;
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::Mutex;
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -41,6 +50,46 @@ pub struct OracleConfig {
     pub host: String,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvmConfig {
+    pub rpc_url: String,
+    pub lending_contract_address: String,
+    pub private_key: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PriceSourceConfig {
+    Oracle,
+    LendingContract,
+    Feed,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoanMonitorConfig {
+    pub price_source: PriceSourceConfig,
+    pub feed_url: Option<String>,
+    pub warning_margin: u64,
+    pub margin_call_margin: u64,
+    pub check_interval: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DaemonConfig {
+    pub check_interval: u64,
+    pub interactive: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiConfig {
+    pub port: u16,
+    pub token: String,
+}
+
 #[derive(Debug)]
 pub struct NetworkConfig {
     pub peer_listening_port: u16,
@@ -51,10 +100,13 @@ pub struct NetworkConfig {
 pub struct Configuration {
     pub bitcoin_info: BitcoindInfo,
     pub storage_dir_path: String,
-    pub evm_private_key: String,
+    pub evm_config: EvmConfig,
     #[serde(deserialize_with = "deserialize_network_configuration")]
     pub network_configuration: NetworkConfig,
     pub oracle_config: OracleConfig,
+    pub loan_monitor_config: Option<LoanMonitorConfig>,
+    pub daemon_config: Option<DaemonConfig>,
+    pub api_config: Option<ApiConfig>,
 }
 
 fn deserialize_network_configuration<'de, D>(deserializer: D) -> Result<NetworkConfig, D::Error>
@@ -77,7 +129,13 @@ where
 pub(crate) fn parse_config(config_path: &str) -> Result<Configuration, String> {
     let config_file = fs::read_to_string(config_path).map_err(|e| e.to_string())?;
 
-    serde_yaml::from_str(&config_file).map_err(|e| e.to_string())
+    let config: Configuration = serde_yaml::from_str(&config_file).map_err(|e| e.to_string())?;
+    let api_token = config.api_config.as_ref().map(|x| x.token.as_str());
+    if api_token.is_some_and(str::is_empty) {
+        return Err("The API token must not be empty".to_string());
+    }
+
+    Ok(config)
 }
 
 macro_rules! read_id_or_continue {
@@ -89,21 +147,31 @@ macro_rules! read_id_or_continue {
     };
 }
 
+macro_rules! read_u64_or_continue {
+    ($words: ident, $err_cmd: expr, $err_arg: expr) => {
+        match read_u64(&mut $words, $err_cmd, $err_arg) {
+            Ok(res) => res,
+            Err(()) => continue,
+        }
+    };
+}
+
 pub(crate) async fn poll_for_user_input(
     peer_manager: Arc<PeerManager>,
     dlc_message_handler: Arc<DlcMessageHandler>,
     dlc_manager: Arc<Mutex<DlcManager>>,
     offers_path: &str,
-    evm_pk: &str,
+    node_secret_key: SecretKey,
+    mut daemon: Daemon<NodeTasks>,
 ) {
     println!("DLC node startup successful. To view available commands: \"help\".");
     println!("DLC logs are available at <your-supplied-ldk-data-dir-path>/.dlc/logs");
-    let stdin = io::stdin();
+    // Messages and contracts keep being processed while waiting for input.
+    let mut lines = read_lines();
     print!("> ");
     io::stdout().flush().unwrap(); // Without flushing, the `>` doesn't print
-    for line in stdin.lock().lines() {
-        process_incoming_messages(&peer_manager, &dlc_manager, &dlc_message_handler, evm_pk).await;
-        let line = line.unwrap();
+    while let Some(line) = daemon.run_until(lines.recv()).await {
+        process_incoming_messages(&peer_manager, &dlc_manager, &dlc_message_handler).await;
         let mut words = line.split_whitespace();
         if let Some(word) = words.next() {
             match word {
@@ -173,16 +241,14 @@ pub(crate) async fn poll_for_user_input(
                         if is_contract {
                             DlcMessage::Offer(
                                 manager_clone
-                                    .lock()
-                                    .unwrap()
+                                    .blocking_lock()
                                     .send_offer(&contract_input, pubkey)
                                     .expect("Error sending offer"),
                             )
                         } else {
                             DlcMessage::OfferChannel(
                                 manager_clone
-                                    .lock()
-                                    .unwrap()
+                                    .blocking_lock()
                                     .offer_channel(&contract_input, pubkey)
                                     .expect("Error sending offer channel"),
                             )
@@ -230,8 +296,7 @@ pub(crate) async fn poll_for_user_input(
                     let offer = tokio::task::spawn_blocking(move || {
                         DlcMessage::OfferLoan(
                             manager_clone
-                                .lock()
-                                .unwrap()
+                                .blocking_lock()
                                 .send_offer_loan(&contract_input, pubkey)
                                 .expect("Error sending offer"),
                             )
@@ -241,8 +306,302 @@ pub(crate) async fn poll_for_user_input(
                     dlc_message_handler.send_message(pubkey, offer);
                     peer_manager.process_events();
                 }
+                "requestloan" => {
+                    let peer_pubkey_and_ip_addr = match words.next() {
+                        Some(pp) => pp,
+                        None => {
+                            println!("ERROR: requestloan requires peer connection info and loan terms: `requestloan pubkey@host:port collateral_sats principal max_interest_rate duration`");
+                            print!("> ");
+                            io::stdout().flush().unwrap();
+                            continue;
+                        }
+                    };
+                    let (pubkey, peer_addr) =
+                        match parse_peer_info(peer_pubkey_and_ip_addr.to_string()) {
+                            Ok(info) => info,
+                            Err(e) => {
+                                println!("{:?}", e);
+                                print!("> ");
+                                io::stdout().flush().unwrap();
+                                continue;
+                            }
+                        };
+                    let collateral = read_u64_or_continue!(words, "requestloan", "collateral");
+                    let principal = read_u64_or_continue!(words, "requestloan", "principal");
+                    let max_interest_rate =
+                        read_u64_or_continue!(words, "requestloan", "max interest rate");
+                    let duration = read_u64_or_continue!(words, "requestloan", "duration");
+                    if connect_peer_if_necessary(pubkey, peer_addr, peer_manager.clone())
+                        .await
+                        .is_ok()
+                    {
+                        println!("SUCCESS: connected to peer {}", pubkey);
+                    }
+                    let msg = dlc_manager
+                        .lock()
+                        .await
+                        .send_loan_request(
+                            pubkey,
+                            Amount::from_sat(collateral),
+                            principal,
+                            max_interest_rate,
+                            duration,
+                        )
+                        .expect("Error sending loan request.");
+                    println!("Loan request {} sent", hex_str(&msg.request_id));
+                    dlc_message_handler.send_message(pubkey, DlcMessage::RequestLoan(msg));
+                    peer_manager.process_events();
+                }
+                "publishloanlisting" => {
+                    let listing_path = match words.next() {
+                        Some(lp) => lp,
+                        None => {
+                            println!("ERROR: publishloanlisting requires the listing path: `publishloanlisting listing_path`");
+                            print!("> ");
+                            io::stdout().flush().unwrap();
+                            continue;
+                        }
+                    };
+                    let listing_input_str =
+                        fs::read_to_string(listing_path).expect("Error reading loan listing file.");
+                    let listing_input: LoanListingInput = serde_json::from_str(&listing_input_str)
+                        .expect("Error deserializing loan listing.");
+                    let listing = dlc_manager
+                        .lock()
+                        .await
+                        .publish_loan_listing(&listing_input, &node_secret_key)
+                        .expect("Error publishing loan listing.");
+                    println!(
+                        "Loan listing {} published",
+                        hex_str(&listing.terms.listing_id)
+                    );
+                    relay_loan_listings(&peer_manager, &dlc_manager, &dlc_message_handler).await;
+                    peer_manager.process_events();
+                }
+                "listloanlistings" => {
+                    let filter = match read_loan_listing_filter(&mut words) {
+                        Ok(filter) => filter,
+                        Err(()) => continue,
+                    };
+                    for listing in dlc_manager.lock().await.get_loan_listings(&filter) {
+                        let terms = &listing.terms;
+                        println!(
+                            "Loan listing {} from {}@{}: principal {} to {}, interest rate {}%, collateral ratio {}%, duration {}s, oracle {}, expires at {}",
+                            hex_str(&terms.listing_id),
+                            terms.lender,
+                            terms.lender_address,
+                            terms.min_principal,
+                            terms.max_principal,
+                            terms.interest_rate,
+                            terms.collateral_ratio,
+                            terms.duration,
+                            terms.oracle_public_key,
+                            terms.expiry
+                        );
+                    }
+                }
+                r @ "requestlistedloan" => {
+                    let lender: PublicKey = match words.next().map(|x| x.parse()) {
+                        Some(Ok(lender)) => lender,
+                        _ => {
+                            println!("ERROR: {} expects the lender public key as parameter.", r);
+                            print!("> ");
+                            io::stdout().flush().unwrap();
+                            continue;
+                        }
+                    };
+                    let listing_id = read_id_or_continue!(words, r, "listing id");
+                    let collateral = read_u64_or_continue!(words, r, "collateral");
+                    let principal = read_u64_or_continue!(words, r, "principal");
+                    let listing = match dlc_manager
+                        .lock()
+                        .await
+                        .get_loan_listing(&lender, &listing_id)
+                    {
+                        Some(listing) => listing,
+                        None => {
+                            println!("ERROR: unknown or expired loan listing.");
+                            print!("> ");
+                            io::stdout().flush().unwrap();
+                            continue;
+                        }
+                    };
+                    let (pubkey, peer_addr) = match parse_peer_info(format!(
+                        "{}@{}",
+                        listing.terms.lender, listing.terms.lender_address
+                    )) {
+                        Ok(info) => info,
+                        Err(e) => {
+                            println!("{:?}", e);
+                            print!("> ");
+                            io::stdout().flush().unwrap();
+                            continue;
+                        }
+                    };
+                    if connect_peer_if_necessary(pubkey, peer_addr, peer_manager.clone())
+                        .await
+                        .is_ok()
+                    {
+                        println!("SUCCESS: connected to peer {}", pubkey);
+                    }
+                    let (node_id, msg) = dlc_manager
+                        .lock()
+                        .await
+                        .send_loan_request_for_listing(
+                            &lender,
+                            &listing_id,
+                            Amount::from_sat(collateral),
+                            principal,
+                        )
+                        .expect("Error sending loan request.");
+                    println!("Loan request {} sent", hex_str(&msg.request_id));
+                    dlc_message_handler.send_message(node_id, DlcMessage::RequestLoan(msg));
+                    peer_manager.process_events();
+                }
+                "listloanrequests" => {
+                    let locked_manager = dlc_manager.lock().await;
+                    for request in locked_manager.get_store().get_loan_requests().unwrap() {
+                        let direction = if request.is_borrower { "to" } else { "from" };
+                        println!(
+                            "Loan request {} {} {}: collateral {} sats, principal {}, max interest rate {}%, duration {}s, {}",
+                            hex_str(&request.id),
+                            direction,
+                            request.counter_party,
+                            request.collateral.to_sat(),
+                            request.principal,
+                            request.max_interest_rate,
+                            request.duration,
+                            request.state
+                        );
+                        if let Some(contract_id) = request.contract_id {
+                            println!("  Offered loan contract: {}", hex_str(&contract_id));
+                        }
+                    }
+                }
+                o @ "offerloanforrequest" => {
+                    let request_id = read_id_or_continue!(words, o, "request id");
+                    let contract_path = match words.next() {
+                        Some(cp) => cp,
+                        None => {
+                            println!("ERROR: offerloanforrequest requires the request id and contract path: `offerloanforrequest request_id contract_path`");
+                            print!("> ");
+                            io::stdout().flush().unwrap();
+                            continue;
+                        }
+                    };
+                    let contract_input_str = fs::read_to_string(contract_path)
+                        .expect("Error reading contract input file.");
+                    let contract_input: LoanContractInput = serde_json::from_str(&contract_input_str)
+                        .expect("Error deserializing contract input.");
+                    let manager_clone = dlc_manager.clone();
+                    let (node_id, msg) = tokio::task::spawn_blocking(move || {
+                        manager_clone
+                            .blocking_lock()
+                            .send_offer_loan_for_request(&request_id, &contract_input)
+                            .expect("Error sending offer")
+                    })
+                    .await
+                    .unwrap();
+                    dlc_message_handler.send_message(node_id, DlcMessage::OfferLoan(msg));
+                    peer_manager.process_events();
+                }
+                r @ "rejectloanrequest" => {
+                    let request_id = read_id_or_continue!(words, r, "request id");
+                    let reason = match read_loan_offer_reason(&mut words) {
+                        Ok(reason) => reason,
+                        Err(()) => continue,
+                    };
+                    let (node_id, msg) = dlc_manager
+                        .lock()
+                        .await
+                        .reject_loan_request(&request_id, reason)
+                        .expect("Error rejecting loan request.");
+                    dlc_message_handler.send_message(node_id, DlcMessage::RejectLoanRequest(msg));
+                    peer_manager.process_events();
+                }
+                c @ "counterloanoffer" => {
+                    let contract_id = read_id_or_continue!(words, c, "contract id");
+                    let collateral_ratio = read_u64_or_continue!(words, c, "collateral ratio");
+                    let interest_rate = read_u64_or_continue!(words, c, "interest rate");
+                    let duration = read_u64_or_continue!(words, c, "duration");
+                    let (node_id, msg) = dlc_manager
+                        .lock()
+                        .await
+                        .counter_loan_offer(&contract_id, collateral_ratio, interest_rate, duration)
+                        .expect("Error countering loan offer.");
+                    println!(
+                        "Counter offer {} of negotiation {} sent",
+                        msg.round,
+                        hex_str(&msg.negotiation_id)
+                    );
+                    dlc_message_handler.send_message(node_id, DlcMessage::CounterOfferLoan(msg));
+                    peer_manager.process_events();
+                }
+                "listloannegotiations" => {
+                    let locked_manager = dlc_manager.lock().await;
+                    for negotiation in locked_manager.get_store().get_loan_negotiations().unwrap() {
+                        println!(
+                            "Loan negotiation {} with {}: {} after {} counter offers, last offer {}",
+                            hex_str(&negotiation.id),
+                            negotiation.counter_party,
+                            negotiation.state,
+                            negotiation.get_round(),
+                            hex_str(&negotiation.contract_id)
+                        );
+                        if let Some(revision) = negotiation.revisions.last() {
+                            println!(
+                                "  Last counter offer: collateral ratio {}%, interest rate {}%, duration {}s",
+                                revision.collateral_ratio,
+                                revision.interest_rate,
+                                revision.duration
+                            );
+                        }
+                    }
+                }
+                o @ "offerloanforcounteroffer" => {
+                    let negotiation_id = read_id_or_continue!(words, o, "negotiation id");
+                    let contract_path = match words.next() {
+                        Some(cp) => cp,
+                        None => {
+                            println!("ERROR: offerloanforcounteroffer requires the negotiation id and contract path: `offerloanforcounteroffer negotiation_id contract_path`");
+                            print!("> ");
+                            io::stdout().flush().unwrap();
+                            continue;
+                        }
+                    };
+                    let contract_input_str = fs::read_to_string(contract_path)
+                        .expect("Error reading contract input file.");
+                    let contract_input: LoanContractInput = serde_json::from_str(&contract_input_str)
+                        .expect("Error deserializing contract input.");
+                    let manager_clone = dlc_manager.clone();
+                    let (node_id, msg) = tokio::task::spawn_blocking(move || {
+                        manager_clone
+                            .blocking_lock()
+                            .send_offer_loan_for_counter_offer(&negotiation_id, &contract_input)
+                            .expect("Error sending offer")
+                    })
+                    .await
+                    .unwrap();
+                    dlc_message_handler.send_message(node_id, DlcMessage::OfferLoan(msg));
+                    peer_manager.process_events();
+                }
+                r @ "rejectloancounteroffer" => {
+                    let negotiation_id = read_id_or_continue!(words, r, "negotiation id");
+                    let reason = match read_loan_offer_reason(&mut words) {
+                        Ok(reason) => reason,
+                        Err(()) => continue,
+                    };
+                    let (node_id, msg) = dlc_manager
+                        .lock()
+                        .await
+                        .reject_loan_counter_offer(&negotiation_id, reason)
+                        .expect("Error rejecting loan counter offer.");
+                    dlc_message_handler
+                        .send_message(node_id, DlcMessage::RejectLoanCounterOffer(msg));
+                    peer_manager.process_events();
+                }
                 "listoffers" => {
-                    let locked_manager = dlc_manager.lock().unwrap();
+                    let locked_manager = dlc_manager.lock().await;
                     for offer in locked_manager
                         .get_store()
                         .get_contract_offers()
@@ -262,7 +621,7 @@ pub(crate) async fn poll_for_user_input(
                     }
                 }
                 "listloanoffers" => {
-                    let locked_manager = dlc_manager.lock().unwrap();
+                    let locked_manager = dlc_manager.lock().await;
                     for offer in locked_manager
                         .get_store()
                         .get_loan_contract_offers()
@@ -286,7 +645,7 @@ pub(crate) async fn poll_for_user_input(
 
                     let (_, node_id, msg) = dlc_manager
                         .lock()
-                        .unwrap()
+                        .await
                         .accept_contract_offer(&contract_id)
                         .expect("Error accepting contract.");
                     dlc_message_handler.send_message(node_id, DlcMessage::Accept(msg));
@@ -297,7 +656,7 @@ pub(crate) async fn poll_for_user_input(
 
                     let (node_id, msg) = dlc_manager
                         .lock()
-                        .unwrap()
+                        .await
                         .initiate_accept_loan_contract_offer(&contract_id)
                         .expect("Error accepting contract.");
                     println!("Wait for escrow confirmation then run listcontracts");
@@ -308,31 +667,140 @@ pub(crate) async fn poll_for_user_input(
                     let contract_id = read_id_or_continue!(words, a, "contract id");
                     let (_, node_id, msg) = dlc_manager
                         .lock()
-                        .unwrap()
+                        .await
                         .accept_loan_contract_offer(&contract_id)
                         .expect("Error accepting contract.");
                     println!("Sending accept loan message to node {}", node_id);
                     dlc_message_handler.send_message(node_id, DlcMessage::AcceptLoan(msg));
                     peer_manager.process_events();
                 }
+                a @ "repayloan" => {
+                    let contract_id = read_id_or_continue!(words, a, "contract id");
+                    let (node_id, msg) = dlc_manager
+                        .lock()
+                        .await
+                        .repay_loan(&contract_id)
+                        .await
+                        .expect("Error repaying loan.");
+                    println!("Stable loan repaid, requesting collateral release from node {}", node_id);
+                    dlc_message_handler.send_message(node_id, DlcMessage::RepayLoan(msg));
+                    peer_manager.process_events();
+                }
+                r @ "renewloan" => {
+                    let contract_id = read_id_or_continue!(words, r, "contract id");
+                    let additional_collateral = read_u64_or_continue!(words, r, "additional collateral");
+                    let repaid_principal = read_u64_or_continue!(words, r, "repaid principal");
+                    let (node_id, msg) = dlc_manager
+                        .lock()
+                        .await
+                        .renew_loan(
+                            &contract_id,
+                            Amount::from_sat(additional_collateral),
+                            repaid_principal,
+                        )
+                        .await
+                        .expect("Error renewing loan.");
+                    println!("Sending loan renewal to node {}", node_id);
+                    dlc_message_handler.send_message(node_id, DlcMessage::RenewLoanOffer(msg));
+                    peer_manager.process_events();
+                }
+                "listloanrenewals" => {
+                    let locked_manager = dlc_manager.lock().await;
+                    for renewal in locked_manager.get_store().get_loan_renewals().unwrap() {
+                        println!(
+                            "Loan renewal of {} with {}: {}, additional collateral {}, repaid principal {}",
+                            hex_str(&renewal.contract_id),
+                            renewal.counter_party,
+                            renewal.state,
+                            renewal.additional_collateral,
+                            renewal.repaid_principal
+                        );
+                    }
+                }
+                "listloanrisks" => {
+                    for risk in dlc_manager.lock().await.get_loan_risks() {
+                        println!(
+                            "Loan {} ({}): {}, collateral ratio {}% at price {}, liquidation at {}%",
+                            hex_str(&risk.contract_id),
+                            if risk.is_lender { "lender" } else { "borrower" },
+                            risk.level,
+                            risk.collateral_ratio,
+                            risk.price,
+                            risk.liquidation_ratio
+                        );
+                    }
+                }
+                a @ "loanamountdue" => {
+                    let contract_id = read_id_or_continue!(words, a, "contract id");
+                    let now = SystemTime::now()
+                        .duration_since(SystemTime::UNIX_EPOCH)
+                        .unwrap()
+                        .as_secs();
+                    match dlc_manager
+                        .lock()
+                        .await
+                        .get_loan_amount_due(&contract_id, now)
+                        .await
+                    {
+                        Ok(amount_due) => println!(
+                            "Principal {}, accrued interest {}, total due {}",
+                            amount_due.principal,
+                            amount_due.accrued_interest,
+                            amount_due.total_due
+                        ),
+                        Err(e) => println!("ERROR: could not compute the amount due: {}", e),
+                    }
+                }
+                r @ "rejectloanoffer" => {
+                    let contract_id = read_id_or_continue!(words, r, "contract id");
+                    let reason = match read_loan_offer_reason(&mut words) {
+                        Ok(reason) => reason,
+                        Err(()) => continue,
+                    };
+                    let (node_id, msg) = dlc_manager
+                        .lock()
+                        .await
+                        .reject_loan_offer(&contract_id, reason)
+                        .expect("Error rejecting loan offer.");
+                    dlc_message_handler.send_message(node_id, DlcMessage::RejectLoan(msg));
+                    peer_manager.process_events();
+                }
+                c @ "cancelloanoffer" => {
+                    let contract_id = read_id_or_continue!(words, c, "contract id");
+                    let reason = match read_loan_offer_reason(&mut words) {
+                        Ok(reason) => reason,
+                        Err(()) => continue,
+                    };
+                    let (node_id, msg) = dlc_manager
+                        .lock()
+                        .await
+                        .cancel_loan_offer(&contract_id, reason)
+                        .await
+                        .expect("Error cancelling loan offer.");
+                    dlc_message_handler.send_message(node_id, DlcMessage::CancelLoan(msg));
+                    peer_manager.process_events();
+                }
                 "listcontracts" => {
+                    run_periodic_checks(&dlc_manager)
+                        .await
+                        .expect("Error doing periodic check.");
                     let manager_clone = dlc_manager.clone();
-                    let evm_pk_owned = evm_pk.to_string();
-                    // Because the oracle client is currently blocking we need to use `spawn_blocking` here.
                     tokio::task::spawn_blocking(move || {
-                        manager_clone
-                            .lock()
-                            .unwrap()
-                            .periodic_check(true, &evm_pk_owned)
-                            .expect("Error doing periodic check.");
                         let contracts = manager_clone
-                            .lock()
-                            .unwrap()
+                            .blocking_lock()
                             .get_store()
                             .get_contracts()
                             .expect("Error retrieving contract list.");
                         for contract in contracts {
                             let id = hex_str(&contract.get_id());
+                            let loan_terms = manager_clone
+                                .blocking_lock()
+                                .get_loan_terms(&contract)
+                                .expect("Error retrieving loan terms.");
+                            let loan_lifecycle = manager_clone
+                                .blocking_lock()
+                                .get_loan_lifecycle(&contract)
+                                .expect("Error retrieving loan lifecycle.");
                             match contract {
                                 Contract::Offered(_) => {
                                     println!("Offered contract: {}", id);
@@ -373,6 +841,40 @@ pub(crate) async fn poll_for_user_input(
                                 }
                                 Contract::Rejected(_) => println!("Rejected contract: {}", id),
                                 Contract::PreClosed(_) => println!("Pre-closed contract: {}", id),
+                                Contract::PreRepaid(_) => println!("Pre-repaid contract: {}", id),
+                                Contract::Repaid(_) => println!("Repaid contract: {}", id),
+                                Contract::EscrowReclaimed(_) => {
+                                    println!("Escrow reclaimed contract: {}", id)
+                                }
+                                Contract::FailedLoan(failed) => {
+                                    println!(
+                                        "Failed loan contract: {} ({})",
+                                        id, failed.error_message
+                                    )
+                                }
+                                Contract::RejectedLoan(_) => {
+                                    println!("Rejected loan contract: {}", id)
+                                }
+                                Contract::CancelledLoan(_) => {
+                                    println!("Cancelled loan contract: {}", id)
+                                }
+                            }
+                            if let Some(loan_terms) = loan_terms {
+                                println!(
+                                    "  Loan terms: collateral ratio {}%, liquidation ratio {}%, interest rate {}%, duration {}s",
+                                    loan_terms.collateral_ratio,
+                                    loan_terms.liquidation_ratio,
+                                    loan_terms.interest_rate,
+                                    loan_terms.duration
+                                );
+                            }
+                            if let Some(loan_lifecycle) = loan_lifecycle {
+                                println!(
+                                    "  Loan state: {} since {}, next action: {}",
+                                    loan_lifecycle.get_state(),
+                                    loan_lifecycle.get_state_timestamp(),
+                                    loan_lifecycle.get_next_action()
+                                );
                             }
                         }
                     })
@@ -380,7 +882,7 @@ pub(crate) async fn poll_for_user_input(
                     .expect("Error listing contract info");
                 }
                 "listchanneloffers" => {
-                    let locked_manager = dlc_manager.lock().unwrap();
+                    let locked_manager = dlc_manager.lock().await;
                     for offer in locked_manager
                         .get_store()
                         .get_offered_channels()
@@ -408,7 +910,7 @@ pub(crate) async fn poll_for_user_input(
 
                     let (msg, _, _, node_id) = dlc_manager
                         .lock()
-                        .unwrap()
+                        .await
                         .accept_channel(&channel_id)
                         .expect("Error accepting channel.");
                     dlc_message_handler.send_message(node_id, DlcMessage::AcceptChannel(msg));
@@ -426,7 +928,7 @@ pub(crate) async fn poll_for_user_input(
 
                     let (msg, node_id) = dlc_manager
                         .lock()
-                        .unwrap()
+                        .await
                         .settle_offer(&channel_id, counter_payout)
                         .expect("Error getting settle offer message.");
                     dlc_message_handler.send_message(node_id, DlcMessage::SettleOffer(msg));
@@ -436,7 +938,7 @@ pub(crate) async fn poll_for_user_input(
                     let channel_id = read_id_or_continue!(words, l, "channel id");
                     let (msg, node_id) = dlc_manager
                         .lock()
-                        .unwrap()
+                        .await
                         .accept_settle_offer(&channel_id)
                         .expect("Error accepting settle channel offer.");
                     dlc_message_handler.send_message(node_id, DlcMessage::SettleAccept(msg));
@@ -446,14 +948,14 @@ pub(crate) async fn poll_for_user_input(
                     let channel_id = read_id_or_continue!(words, l, "channel id");
                     let (msg, node_id) = dlc_manager
                         .lock()
-                        .unwrap()
+                        .await
                         .reject_settle_offer(&channel_id)
                         .expect("Error rejecting settle channel offer.");
                     dlc_message_handler.send_message(node_id, DlcMessage::Reject(msg));
                     peer_manager.process_events();
                 }
                 "listsettlechanneloffers" => {
-                    let locked_manager = dlc_manager.lock().unwrap();
+                    let locked_manager = dlc_manager.lock().await;
                     for channel in locked_manager
                         .get_store()
                         .get_signed_channels(Some(SignedChannelStateType::SettledReceived))
@@ -485,8 +987,7 @@ pub(crate) async fn poll_for_user_input(
                     let manager_clone = dlc_manager.clone();
                     let (renew_offer, node_id) = tokio::task::spawn_blocking(move || {
                         manager_clone
-                            .lock()
-                            .unwrap()
+                            .blocking_lock()
                             .renew_offer(&channel_id, counter_payout, &contract_input)
                             .expect("Error sending offer")
                     })
@@ -496,7 +997,7 @@ pub(crate) async fn poll_for_user_input(
                     peer_manager.process_events();
                 }
                 "listrenewchanneloffers" => {
-                    let locked_manager = dlc_manager.lock().unwrap();
+                    let locked_manager = dlc_manager.lock().await;
                     for channel in locked_manager
                         .get_store()
                         .get_signed_channels(Some(SignedChannelStateType::RenewOffered))
@@ -529,7 +1030,7 @@ pub(crate) async fn poll_for_user_input(
                     let channel_id = read_id_or_continue!(words, l, "channel id");
                     let (msg, node_id) = dlc_manager
                         .lock()
-                        .unwrap()
+                        .await
                         .accept_renew_offer(&channel_id)
                         .expect("Error accepting channel.");
                     dlc_message_handler.send_message(node_id, DlcMessage::RenewAccept(msg));
@@ -539,14 +1040,14 @@ pub(crate) async fn poll_for_user_input(
                     let channel_id = read_id_or_continue!(words, l, "channel id");
                     let (msg, node_id) = dlc_manager
                         .lock()
-                        .unwrap()
+                        .await
                         .reject_renew_offer(&channel_id)
                         .expect("Error rejecting settle channel offer.");
                     dlc_message_handler.send_message(node_id, DlcMessage::Reject(msg));
                     peer_manager.process_events();
                 }
                 "listsignedchannels" => {
-                    let locked_manager = dlc_manager.lock().unwrap();
+                    let locked_manager = dlc_manager.lock().await;
                     for channel in locked_manager
                         .get_store()
                         .get_signed_channels(None)
@@ -568,6 +1069,20 @@ pub(crate) async fn poll_for_user_input(
     }
 }
 
+/// Reads the lines of user input from their own thread, as reading stdin
+/// blocks.
+fn read_lines() -> UnboundedReceiver<String> {
+    let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            if sender.send(line.unwrap()).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
 fn read_id(words: &mut SplitWhitespace, err_cmd: &str, err_arg: &str) -> Result<[u8; 32], ()> {
     match words.next() {
         None => {
@@ -587,12 +1102,93 @@ fn read_id(words: &mut SplitWhitespace, err_cmd: &str, err_arg: &str) -> Result<
     }
 }
 
+fn read_u64(words: &mut SplitWhitespace, err_cmd: &str, err_arg: &str) -> Result<u64, ()> {
+    match words.next() {
+        None => {
+            println!("ERROR: {} expects the {} as parameter.", err_cmd, err_arg);
+            Err(())
+        }
+        Some(s) => s.parse().map_err(|_| {
+            println!("ERROR: invalid {}.", err_arg);
+        }),
+    }
+}
+
+fn read_loan_listing_filter(words: &mut SplitWhitespace) -> Result<LoanListingFilter, ()> {
+    let mut filter = LoanListingFilter::default();
+    for word in words {
+        let (key, value) = match word.split_once('=') {
+            Some(kv) => kv,
+            None => {
+                println!("ERROR: invalid filter {}, expected key=value.", word);
+                return Err(());
+            }
+        };
+        let parse_u64 = |v: &str| -> Result<Option<u64>, ()> {
+            v.parse().map(Some).map_err(|_| {
+                println!("ERROR: invalid {}.", key);
+            })
+        };
+        match key {
+            "principal" => filter.principal = parse_u64(value)?,
+            "maxinterestrate" => filter.max_interest_rate = parse_u64(value)?,
+            "maxcollateralratio" => filter.max_collateral_ratio = parse_u64(value)?,
+            "maxduration" => filter.max_duration = parse_u64(value)?,
+            "oracle" => {
+                filter.oracle_public_key = Some(value.parse().map_err(|_| {
+                    println!("ERROR: invalid oracle public key.");
+                })?)
+            }
+            _ => {
+                println!("ERROR: unknown filter {}, expected one of principal, maxinterestrate, maxcollateralratio, maxduration or oracle.", key);
+                return Err(());
+            }
+        }
+    }
+    Ok(filter)
+}
+
+fn read_loan_offer_reason(words: &mut SplitWhitespace) -> Result<LoanOfferReason, ()> {
+    match words.next() {
+        None => Ok(LoanOfferReason::Unspecified),
+        Some("terms") => Ok(LoanOfferReason::UnacceptableTerms),
+        Some("funds") => Ok(LoanOfferReason::InsufficientFunds),
+        Some("expired") => Ok(LoanOfferReason::Expired),
+        Some(_) => {
+            println!("ERROR: invalid reason, expected one of terms, funds or expired.");
+            Err(())
+        }
+    }
+}
+
 fn help() {
     println!("connectpeer <pubkey@host:port>");
     println!("listpeers");
     println!("offercontract <pubkey@host:port> <path_to_contract_input_json>");
     println!("listoffers");
     println!("acceptoffer <contract_id>");
+    println!("offerloan <pubkey@host:port> <path_to_loan_contract_input_json>");
+    println!("listloanoffers");
+    println!("requestloan <pubkey@host:port> <collateral_sats> <principal> <max_interest_rate> <duration>");
+    println!("listloanrequests");
+    println!("publishloanlisting <path_to_loan_listing_json>");
+    println!("listloanlistings [principal=<principal>] [maxinterestrate=<rate>] [maxcollateralratio=<ratio>] [maxduration=<duration>] [oracle=<oracle_public_key>]");
+    println!("requestlistedloan <lender_pubkey> <listing_id> <collateral_sats> <principal>");
+    println!("offerloanforrequest <request_id> <path_to_loan_contract_input_json>");
+    println!("rejectloanrequest <request_id> [terms|funds|expired]");
+    println!("counterloanoffer <contract_id> <collateral_ratio> <interest_rate> <duration>");
+    println!("listloannegotiations");
+    println!("offerloanforcounteroffer <negotiation_id> <path_to_loan_contract_input_json>");
+    println!("rejectloancounteroffer <negotiation_id> [terms|funds|expired]");
+    println!("acceptloanoffer <contract_id>");
+    println!("finishacceptloanoffer <contract_id>");
+    println!("repayloan <contract_id>");
+    println!("renewloan <contract_id> <additional_collateral_sats> <repaid_principal>");
+    println!("listloanrenewals");
+    println!("loanamountdue <contract_id>");
+    println!("listloanrisks");
+    println!("rejectloanoffer <contract_id> [terms|funds|expired]");
+    println!("cancelloanoffer <contract_id> [terms|funds|expired]");
     println!("listcontracts");
     println!("offerchannel <pubkey@host:port> <path_to_contract_input_json>");
     println!("listchanneloffers");
@@ -608,6 +1204,29 @@ fn help() {
     println!("listsignedchannels");
 }
 
+/// Prints the risk level changes of loans as they are emitted by the manager.
+pub(crate) fn print_loan_risk_events(receiver: Receiver<LoanRiskEvent>) {
+    for event in receiver {
+        let risk = &event.risk;
+        let advice = match (risk.level, risk.is_lender) {
+            (LoanRiskLevel::MarginCall, false) => ", add collateral or repay part of the loan",
+            _ => "",
+        };
+        println!(
+            "\nLoan {} went from {} to {}: collateral ratio {}% at price {}, liquidation at {}%{}",
+            hex_str(&risk.contract_id),
+            event.previous_level,
+            risk.level,
+            risk.collateral_ratio,
+            risk.price,
+            risk.liquidation_ratio,
+            advice
+        );
+        print!("> ");
+        io::stdout().flush().unwrap();
+    }
+}
+
 fn list_peers(peer_manager: Arc<PeerManager>) {
     println!("\t{{");
     for peer in peer_manager.list_peers() {
@@ -691,31 +1310,3 @@ pub(crate) fn parse_peer_info(
 
     Ok((pubkey.unwrap(), peer_addr.unwrap().unwrap()))
 }
-
-async fn process_incoming_messages(
-    peer_manager: &Arc<PeerManager>,
-    dlc_manager: &Arc<Mutex<DlcManager>>,
-    dlc_message_handler: &Arc<DlcMessageHandler>,
-    evm_pk: &str,
-) {
-    println!("Checking for messages");
-    let messages = dlc_message_handler.get_and_clear_received_messages();
-    println!("Found {} messages", messages.len());
-
-    for (node_id, message) in messages {
-        println!("Processing message from {}", node_id);
-        let resp = dlc_manager
-            .lock()
-            .unwrap()
-            .on_dlc_message(&message, node_id, evm_pk)
-            .await.expect("Error processing message");
-        if let Some(msg) = resp {
-            println!("Sending message to {}", node_id);
-            dlc_message_handler.send_message(node_id, msg);
-        }
-    }
-
-    if dlc_message_handler.has_pending_messages() {
-        peer_manager.process_events();
-    }
-}
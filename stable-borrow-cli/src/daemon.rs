@@ -0,0 +1,272 @@
+use crate::api::{ApiHandler, ApiRequest};
+use crate::disk::FilesystemLogger;
+use crate::DlcManager;
+use crate::DlcMessageHandler;
+use crate::PeerManager;
+use dlc_manager::error::Error;
+use dlc_messages::Message as DlcMessage;
+use lightning::log_error;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::{Interval, MissedTickBehavior};
+
+/// How often the queue of received messages is drained.
+const MESSAGE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The default number of seconds between two runs of the periodic checks.
+pub(crate) const DEFAULT_CHECK_INTERVAL: u64 = 30;
+
+enum DaemonTask {
+    ProcessMessages,
+    PeriodicChecks,
+    ApiRequest(ApiRequest),
+}
+
+/// The tasks the daemon runs, on the node or in tests.
+pub(crate) trait DaemonTasks {
+    /// Handles the messages received since the last call.
+    async fn process_messages(&self);
+    /// Runs the periodic checks, reporting their errors.
+    async fn run_periodic_checks(&self);
+    /// Waits for the next request of the API server, forever if there is no
+    /// API server.
+    async fn next_api_request(&mut self) -> Option<ApiRequest>;
+    /// Executes the given request of the API server.
+    async fn handle_api_request(&self, request: ApiRequest);
+}
+
+/// The tasks of the node, run on its manager.
+pub(crate) struct NodeTasks {
+    pub(crate) peer_manager: Arc<PeerManager>,
+    pub(crate) dlc_message_handler: Arc<DlcMessageHandler>,
+    pub(crate) dlc_manager: Arc<Mutex<DlcManager>>,
+    pub(crate) api: Option<ApiHandler>,
+    pub(crate) logger: Arc<FilesystemLogger>,
+}
+
+impl DaemonTasks for NodeTasks {
+    async fn process_messages(&self) {
+        process_incoming_messages(
+            &self.peer_manager,
+            &self.dlc_manager,
+            &self.dlc_message_handler,
+        )
+        .await
+    }
+
+    async fn run_periodic_checks(&self) {
+        if let Err(e) = run_periodic_checks(&self.dlc_manager).await {
+            log_error!(self.logger, "Periodic check failed: {}", e);
+        }
+    }
+
+    async fn next_api_request(&mut self) -> Option<ApiRequest> {
+        match &mut self.api {
+            Some(api) => api.next_request().await,
+            None => std::future::pending().await,
+        }
+    }
+
+    async fn handle_api_request(&self, request: ApiRequest) {
+        if let Some(api) = &self.api {
+            api.handle(request).await;
+        }
+    }
+}
+
+/// Processes the incoming messages as they arrive and runs the periodic checks
+/// of the manager at a fixed interval, so that contracts progress without any
+/// user input.
+pub(crate) struct Daemon<T: DaemonTasks> {
+    tasks: T,
+    message_interval: Interval,
+    check_interval: Interval,
+}
+
+impl<T: DaemonTasks> Daemon<T> {
+    pub(crate) fn new(tasks: T, check_interval: Duration) -> Self {
+        let mut message_interval = tokio::time::interval(MESSAGE_POLL_INTERVAL);
+        message_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut check_interval = tokio::time::interval(check_interval);
+        check_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Daemon {
+            tasks,
+            message_interval,
+            check_interval,
+        }
+    }
+
+    /// Runs the daemon until the given future completes and returns its
+    /// output. A task that started is always run to completion, the future is
+    /// only polled in between tasks.
+    pub(crate) async fn run_until<F: Future>(&mut self, future: F) -> F::Output {
+        tokio::pin!(future);
+        loop {
+            let task = tokio::select! {
+                output = &mut future => return output,
+                _ = self.message_interval.tick() => DaemonTask::ProcessMessages,
+                _ = self.check_interval.tick() => DaemonTask::PeriodicChecks,
+                Some(request) = self.tasks.next_api_request() => DaemonTask::ApiRequest(request),
+            };
+            match task {
+                DaemonTask::ProcessMessages => self.tasks.process_messages().await,
+                DaemonTask::PeriodicChecks => self.tasks.run_periodic_checks().await,
+                DaemonTask::ApiRequest(request) => self.tasks.handle_api_request(request).await,
+            }
+        }
+    }
+
+    /// Runs the daemon forever.
+    pub(crate) async fn run(mut self) {
+        self.run_until(std::future::pending::<()>()).await
+    }
+}
+
+/// Updates the chain monitor and checks the contracts, channels and loans
+/// of the manager.
+pub(crate) async fn run_periodic_checks(dlc_manager: &Arc<Mutex<DlcManager>>) -> Result<(), Error> {
+    dlc_manager.lock().await.periodic_loan_check().await?;
+    let manager_clone = dlc_manager.clone();
+    // Because the oracle client is currently blocking we need to use `spawn_blocking` here.
+    tokio::task::spawn_blocking(move || {
+        let manager = manager_clone.blocking_lock();
+        manager.periodic_chain_monitor()?;
+        manager.periodic_check(true)
+    })
+    .await
+    .map_err(|e| Error::InvalidState(format!("Periodic check task failed: {}", e)))?
+}
+
+/// Handles the messages received since the last call and sends the answers
+/// and the loan listings to relay.
+pub(crate) async fn process_incoming_messages(
+    peer_manager: &Arc<PeerManager>,
+    dlc_manager: &Arc<Mutex<DlcManager>>,
+    dlc_message_handler: &Arc<DlcMessageHandler>,
+) {
+    let messages = dlc_message_handler.get_and_clear_received_messages();
+
+    for (node_id, message) in messages {
+        println!("Processing message from {}", node_id);
+        let resp = dlc_manager
+            .lock()
+            .await
+            .on_dlc_message(&message, node_id)
+            .await;
+        match resp {
+            Ok(Some(msg)) => {
+                println!("Sending message to {}", node_id);
+                dlc_message_handler.send_message(node_id, msg);
+            }
+            Ok(None) => {}
+            Err(e) => println!("ERROR: could not process message from {}: {}", node_id, e),
+        }
+    }
+
+    relay_loan_listings(peer_manager, dlc_manager, dlc_message_handler).await;
+
+    if dlc_message_handler.has_pending_messages() {
+        peer_manager.process_events();
+    }
+}
+
+/// Sends the loan listings published or received since the last call to the
+/// connected peers, except the one a listing came from and its lender.
+pub(crate) async fn relay_loan_listings(
+    peer_manager: &Arc<PeerManager>,
+    dlc_manager: &Arc<Mutex<DlcManager>>,
+    dlc_message_handler: &Arc<DlcMessageHandler>,
+) {
+    let listings = dlc_manager
+        .lock()
+        .await
+        .get_and_clear_loan_listings_to_relay();
+    if listings.is_empty() {
+        return;
+    }
+    let peers = peer_manager.list_peers();
+    for (from, listing) in listings {
+        for peer in &peers {
+            let node_id = peer.counterparty_node_id;
+            if Some(node_id) == from || node_id == listing.terms.lender {
+                continue;
+            }
+            dlc_message_handler.send_message(node_id, DlcMessage::LoanListing(listing.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+    /// Counts the tasks run by the daemon, each periodic check taking the given
+    /// time.
+    #[derive(Default)]
+    struct CountingTasks {
+        check_duration: Duration,
+        nb_message_polls: AtomicUsize,
+        nb_checks_started: AtomicUsize,
+        nb_checks_done: AtomicUsize,
+    }
+
+    impl DaemonTasks for CountingTasks {
+        async fn process_messages(&self) {
+            self.nb_message_polls.fetch_add(1, Ordering::SeqCst);
+        }
+
+        async fn run_periodic_checks(&self) {
+            self.nb_checks_started.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(self.check_duration).await;
+            self.nb_checks_done.fetch_add(1, Ordering::SeqCst);
+        }
+
+        async fn next_api_request(&mut self) -> Option<ApiRequest> {
+            std::future::pending().await
+        }
+
+        async fn handle_api_request(&self, _: ApiRequest) {}
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn periodic_checks_run_at_check_interval_test() {
+        let mut daemon = Daemon::new(CountingTasks::default(), CHECK_INTERVAL);
+
+        // The first check runs right away, then one per interval.
+        let run_time = CHECK_INTERVAL * 3 - Duration::from_secs(1);
+        daemon.run_until(tokio::time::sleep(run_time)).await;
+        assert_eq!(3, daemon.tasks.nb_checks_done.load(Ordering::SeqCst));
+        assert!(daemon.tasks.nb_message_polls.load(Ordering::SeqCst) > 0);
+
+        // Checks resume when the daemon runs again.
+        daemon.run_until(tokio::time::sleep(CHECK_INTERVAL)).await;
+        assert_eq!(4, daemon.tasks.nb_checks_done.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn shutdown_waits_for_the_running_check_test() {
+        let tasks = CountingTasks {
+            check_duration: Duration::from_secs(10),
+            ..Default::default()
+        };
+        let mut daemon = Daemon::new(tasks, CHECK_INTERVAL);
+        let start = tokio::time::Instant::now();
+
+        // The shutdown is requested while the first check runs.
+        let output = daemon
+            .run_until(async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                42
+            })
+            .await;
+        assert_eq!(42, output);
+        assert!(start.elapsed() >= Duration::from_secs(10));
+        assert_eq!(1, daemon.tasks.nb_checks_started.load(Ordering::SeqCst));
+        assert_eq!(1, daemon.tasks.nb_checks_done.load(Ordering::SeqCst));
+    }
+}
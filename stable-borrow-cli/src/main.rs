@@ -1,25 +1,32 @@
+mod api;
 mod cli;
+mod daemon;
 mod disk;
 mod hex_utils;
+mod price_feed;
 
 use disk::FilesystemLogger;
 
 use bitcoin::secp256k1::rand::{thread_rng, RngCore};
 use bitcoin::secp256k1::SecretKey;
 use bitcoin_rpc_provider::BitcoinCoreProvider;
+use dlc_manager::loan_monitor::{LoanMonitorConfig, LoanPriceSource};
 use dlc_manager::{CachedContractSignerProvider, Oracle, SimpleSigner, SystemTimeProvider};
 use dlc_messages::message_handler::MessageHandler as DlcMessageHandler;
+use evm_lending_provider::EvmLendingProvider;
 use lightning::ln::peer_handler::{
     ErroringMessageHandler, IgnoringMessageHandler, MessageHandler, PeerManager as LdkPeerManager,
 };
 use lightning::sign::{KeysManager, NodeSigner};
 use lightning_net_tokio::SocketDescriptor;
 use p2pd_oracle_client::P2PDOracleClient;
+use price_feed::HttpPriceFeed;
 use std::collections::hash_map::HashMap;
 use std::env;
 use std::fs;
-use std::sync::{Arc, Mutex};
-use std::time::SystemTime;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::Mutex;
 
 pub(crate) type PeerManager = LdkPeerManager<
     SocketDescriptor,
@@ -39,6 +46,7 @@ pub(crate) type DlcManager = dlc_manager::manager::Manager<
     Box<P2PDOracleClient>,
     Arc<SystemTimeProvider>,
     Arc<BitcoinCoreProvider>,
+    Arc<EvmLendingProvider>,
     SimpleSigner,
 >;
 
@@ -80,22 +88,56 @@ async fn main() {
     let mut oracles = HashMap::new();
     oracles.insert(oracle.get_public_key(), Box::new(oracle));
 
-    // Instantiate a DlcManager.
-    let dlc_manager = Arc::new(Mutex::new(
-        dlc_manager::manager::Manager::new(
-            bitcoind_provider.clone(),
-            bitcoind_provider.clone(),
-            bitcoind_provider.clone(),
-            Box::new(
-                dlc_sled_storage_provider::SledStorageProvider::new(&config.storage_dir_path)
-                    .expect("Error creating storage."),
-            ),
-            oracles,
-            Arc::new(dlc_manager::SystemTimeProvider {}),
-            bitcoind_provider.clone(),
+    // Instantiate a lending backend for the EVM side of loans.
+    let lending_provider = Arc::new(
+        EvmLendingProvider::new(
+            &config.evm_config.rpc_url,
+            &config.evm_config.lending_contract_address,
+            &config.evm_config.private_key,
         )
-        .expect("Could not create manager."),
-    ));
+        .expect("Error creating EvmLendingProvider"),
+    );
+
+    // Instantiate a DlcManager.
+    let mut dlc_manager = dlc_manager::manager::Manager::new(
+        bitcoind_provider.clone(),
+        bitcoind_provider.clone(),
+        bitcoind_provider.clone(),
+        Box::new(
+            dlc_sled_storage_provider::SledStorageProvider::new(&config.storage_dir_path)
+                .expect("Error creating storage."),
+        ),
+        oracles,
+        Arc::new(dlc_manager::SystemTimeProvider {}),
+        bitcoind_provider.clone(),
+        lending_provider,
+    )
+    .expect("Could not create manager.");
+
+    // Monitor the liquidation risk of loans with the configured price source
+    // and print the risk level changes.
+    if let Some(monitor_config) = config.loan_monitor_config {
+        let price_source = match monitor_config.price_source {
+            cli::PriceSourceConfig::Oracle => LoanPriceSource::Oracle,
+            cli::PriceSourceConfig::LendingContract => LoanPriceSource::LendingContract,
+            cli::PriceSourceConfig::Feed => LoanPriceSource::Feed(Arc::new(HttpPriceFeed::new(
+                &monitor_config
+                    .feed_url
+                    .expect("The feed price source requires a feedUrl"),
+            ))),
+        };
+        dlc_manager
+            .set_loan_monitor_config(LoanMonitorConfig {
+                price_source,
+                warning_margin: monitor_config.warning_margin,
+                margin_call_margin: monitor_config.margin_call_margin,
+                check_interval: monitor_config.check_interval,
+            })
+            .expect("Invalid loan monitor configuration");
+    }
+    let loan_risk_events = dlc_manager.subscribe_loan_risk_events();
+    let dlc_manager = Arc::new(Mutex::new(dlc_manager));
+    std::thread::spawn(move || cli::print_loan_risk_events(loan_risk_events));
 
     let dlc_data_dir = format!("{}/.dlc", config.storage_dir_path);
     let logger = Arc::new(FilesystemLogger::new(dlc_data_dir.clone()));
@@ -116,8 +158,6 @@ async fn main() {
         sk
     };
 
-    let evm_pk = config.evm_private_key;
-
     // Setup a handler for the DLC messages that will be sent/received through LDK.
     let dlc_message_handler = Arc::new(DlcMessageHandler::new());
 
@@ -135,6 +175,9 @@ async fn main() {
         "Node public key: {}",
         km.get_node_id(lightning::sign::Recipient::Node).unwrap()
     );
+    // Loan listings are signed with the node key so that borrowers can
+    // authenticate the lender they connect to.
+    let node_secret_key = km.get_node_secret_key();
 
     // The peer manager helps us establish connections and communicate with our peers.
     let peer_manager: Arc<PeerManager> = Arc::new(PeerManager::new(
@@ -169,13 +212,48 @@ async fn main() {
         }
     });
 
+    // Process messages and check contracts in the background, either on their
+    // own or in between the commands of the CLI.
+    let (check_interval, interactive) = config
+        .daemon_config
+        .map_or((daemon::DEFAULT_CHECK_INTERVAL, true), |x| {
+            (x.check_interval, x.interactive)
+        });
+    // Serve the control API, if configured, from the daemon as well.
+    let api = config.api_config.map(|x| {
+        api::start_api_server(
+            &x,
+            peer_manager.clone(),
+            dlc_message_handler.clone(),
+            dlc_manager.clone(),
+            node_secret_key,
+        )
+    });
+    let daemon = daemon::Daemon::new(
+        daemon::NodeTasks {
+            peer_manager: peer_manager.clone(),
+            dlc_message_handler: dlc_message_handler.clone(),
+            dlc_manager: dlc_manager.clone(),
+            api,
+            logger: logger.clone(),
+        },
+        Duration::from_secs(check_interval),
+    );
+
+    if !interactive {
+        println!("DLC node running in daemon mode.");
+        daemon.run().await;
+        return;
+    }
+
     // Start the CLI.
     cli::poll_for_user_input(
         peer_manager.clone(),
         dlc_message_handler.clone(),
         dlc_manager.clone(),
         &offers_path,
-        &evm_pk,
+        node_secret_key,
+        daemon,
     )
     .await;
 }
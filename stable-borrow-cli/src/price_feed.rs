@@ -0,0 +1,37 @@
+use dlc_manager::error::Error;
+use dlc_manager::PriceFeed;
+use serde_json::Value;
+
+/// Reads the price of a bitcoin from an HTTP endpoint answering either with a
+/// JSON number or with an object holding the number in a `price` field. Like
+/// the oracle client, it uses reqwest in blocking mode.
+pub struct HttpPriceFeed {
+    url: String,
+}
+
+impl HttpPriceFeed {
+    pub fn new(url: &str) -> Self {
+        HttpPriceFeed {
+            url: url.to_string(),
+        }
+    }
+}
+
+impl PriceFeed for HttpPriceFeed {
+    fn get_price(&self) -> Result<u64, Error> {
+        let value = reqwest::blocking::get(&self.url)
+            .map_err(|x| Error::IOError(std::io::Error::new(std::io::ErrorKind::Other, x).into()))?
+            .json::<Value>()
+            .map_err(|e| Error::OracleError(e.to_string()))?;
+        let price = match &value {
+            Value::Object(o) => o.get("price"),
+            _ => Some(&value),
+        };
+
+        // Fractions of the price unit are dropped.
+        price
+            .and_then(|x| x.as_u64().or_else(|| x.as_f64().map(|f| f as u64)))
+            .filter(|x| *x > 0)
+            .ok_or_else(|| Error::OracleError(format!("Invalid price feed answer {}", value)))
+    }
+}
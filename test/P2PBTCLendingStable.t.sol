@@ -0,0 +1,280 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.13;
+
+import {Test} from "forge-std/Test.sol";
+import {P2PBTCLending} from "../src/P2PBTCLending.sol";
+import {MockERC20} from "./mocks/MockERC20.sol";
+import {MockPriceFeed} from "./mocks/MockPriceFeed.sol";
+
+contract P2PBTCLendingStableTest is Test {
+    P2PBTCLending public lending;
+    MockERC20 public stableToken;
+    MockPriceFeed public btcPriceFeed;
+    MockPriceFeed public stablePriceFeed;
+
+    address public lender = address(0x1);
+    address public borrower = address(0x2);
+    uint128 public constant BORROWER_PREIMAGE = 42;
+    uint256 public constant INTEREST_RATE = 10; // 10% per year
+    uint256 public constant COLLATERAL_RATIO = 150; // 150% collateral required
+    uint256 public constant BTC_COLLATERAL = 1e8; // 1 BTC
+    int256 public constant BTC_PRICE = 60000e8; // $60,000 per BTC
+    int256 public constant STABLE_PRICE = 1e8; // $1 per stable token
+
+    function setUp() public {
+        stableToken = new MockERC20("Stable", "STB", 18);
+        btcPriceFeed = new MockPriceFeed();
+        stablePriceFeed = new MockPriceFeed();
+        btcPriceFeed.setPrice(BTC_PRICE);
+        stablePriceFeed.setPrice(STABLE_PRICE);
+
+        lending = new P2PBTCLending(address(stableToken), address(stablePriceFeed), address(btcPriceFeed));
+
+        stableToken.mint(lender, 1e24);
+        vm.prank(lender);
+        stableToken.approve(address(lending), type(uint256).max);
+    }
+
+    function borrowerHash() internal pure returns (bytes32) {
+        return sha256(abi.encodePacked(BORROWER_PREIMAGE));
+    }
+
+    function lend() internal returns (uint256) {
+        uint256 stableLoanAmount = lending.getStableLoanAmount(BTC_COLLATERAL, COLLATERAL_RATIO);
+        vm.prank(lender);
+        lending.lendStable(borrowerHash(), BTC_COLLATERAL, stableLoanAmount, INTEREST_RATE, COLLATERAL_RATIO);
+        return stableLoanAmount;
+    }
+
+    function test_StableCollateralValueIsInStableTokenUnits() public {
+        // One bitcoin at $60,000 is worth 60,000 tokens of 18 decimals.
+        assertEq(lending.getStableCollateralValue(BTC_COLLATERAL), 60_000e18);
+        assertEq(lending.getStableLoanAmount(BTC_COLLATERAL, COLLATERAL_RATIO), 40_000e18);
+        assertEq(lending.getStableLoanAmount(BTC_COLLATERAL, 200), 30_000e18);
+    }
+
+    function test_LendStableRealisticPrincipal() public {
+        uint256 lenderBalance = stableToken.balanceOf(lender);
+
+        vm.prank(lender);
+        lending.lendStable(borrowerHash(), BTC_COLLATERAL, 40_000e18, INTEREST_RATE, COLLATERAL_RATIO);
+
+        (uint256 collateralAmount, uint256 lentAmount,,,,,,) = lending.stableLoans(borrowerHash());
+        assertEq(collateralAmount, BTC_COLLATERAL);
+        assertEq(lentAmount, 40_000e18);
+        assertEq(stableToken.balanceOf(lender), lenderBalance - 40_000e18);
+    }
+
+    function test_LendStableAboveOfferedCollateralRatioReverts() public {
+        // 40,000 tokens against one bitcoin is only a 150% collateral ratio.
+        vm.prank(lender);
+        vm.expectRevert("Stable loan amount must be between 0 and the collateral allowance");
+        lending.lendStable(borrowerHash(), BTC_COLLATERAL, 40_000e18, INTEREST_RATE, 160);
+    }
+
+    function test_LendStableWithInvalidCollateralRatioReverts() public {
+        vm.prank(lender);
+        vm.expectRevert("Collateral ratio must be greater than 100");
+        lending.lendStable(borrowerHash(), BTC_COLLATERAL, 1, INTEREST_RATE, 100);
+    }
+
+    function test_LendStableLendsTheGivenAmount() public {
+        uint256 stableLoanAmount = lending.getStableLoanAmount(BTC_COLLATERAL, COLLATERAL_RATIO) / 2;
+        uint256 lenderBalance = stableToken.balanceOf(lender);
+
+        vm.prank(lender);
+        lending.lendStable(borrowerHash(), BTC_COLLATERAL, stableLoanAmount, INTEREST_RATE, COLLATERAL_RATIO);
+
+        (uint256 collateralAmount, uint256 lentAmount,,,,,,) = lending.stableLoans(borrowerHash());
+        assertEq(collateralAmount, BTC_COLLATERAL);
+        assertEq(lentAmount, stableLoanAmount);
+        assertEq(stableToken.balanceOf(lender), lenderBalance - stableLoanAmount);
+    }
+
+    function test_LendStableAboveCollateralAllowanceReverts() public {
+        uint256 stableLoanAmount = lending.getStableLoanAmount(BTC_COLLATERAL, COLLATERAL_RATIO) + 1;
+
+        vm.prank(lender);
+        vm.expectRevert("Stable loan amount must be between 0 and the collateral allowance");
+        lending.lendStable(borrowerHash(), BTC_COLLATERAL, stableLoanAmount, INTEREST_RATE, COLLATERAL_RATIO);
+    }
+
+    function test_ClaimStable() public {
+        uint256 stableLoanAmount = lend();
+
+        vm.prank(borrower);
+        lending.claimStable(BORROWER_PREIMAGE);
+
+        (,, uint128 borrowerPreimage,,,, uint256 startTime,) = lending.stableLoans(borrowerHash());
+        assertEq(borrowerPreimage, BORROWER_PREIMAGE);
+        assertEq(startTime, block.timestamp);
+        assertEq(stableToken.balanceOf(borrower), stableLoanAmount);
+    }
+
+    function test_ClaimStableTwiceReverts() public {
+        uint256 stableLoanAmount = lend();
+        // Funds that a second claim could drain.
+        stableToken.mint(address(lending), stableLoanAmount);
+
+        vm.startPrank(borrower);
+        lending.claimStable(BORROWER_PREIMAGE);
+        vm.expectRevert("Stable loan already claimed");
+        lending.claimStable(BORROWER_PREIMAGE);
+        vm.stopPrank();
+
+        assertEq(stableToken.balanceOf(borrower), stableLoanAmount);
+    }
+
+    function test_ClaimStableWithZeroPreimageReverts() public {
+        vm.prank(borrower);
+        vm.expectRevert("Invalid borrower preimage");
+        lending.claimStable(0);
+    }
+
+    function test_ReclaimStableAfterClaimPeriod() public {
+        uint256 lenderBalance = stableToken.balanceOf(lender);
+        lend();
+
+        vm.warp(block.timestamp + lending.STABLE_CLAIM_PERIOD());
+        vm.prank(lender);
+        lending.reclaimStable(borrowerHash());
+
+        assertEq(stableToken.balanceOf(lender), lenderBalance);
+        (uint256 collateralAmount,,,,,,,) = lending.stableLoans(borrowerHash());
+        assertEq(collateralAmount, 0);
+
+        vm.prank(borrower);
+        vm.expectRevert("No stable loan exists for this borrower");
+        lending.claimStable(BORROWER_PREIMAGE);
+    }
+
+    function test_ReclaimStableBeforeClaimPeriodEndsReverts() public {
+        lend();
+
+        vm.warp(block.timestamp + lending.STABLE_CLAIM_PERIOD() - 1);
+        vm.prank(lender);
+        vm.expectRevert("Stable loan claim period has not ended");
+        lending.reclaimStable(borrowerHash());
+    }
+
+    function test_ReclaimClaimedStableReverts() public {
+        lend();
+        vm.prank(borrower);
+        lending.claimStable(BORROWER_PREIMAGE);
+
+        vm.warp(block.timestamp + lending.STABLE_CLAIM_PERIOD());
+        vm.prank(lender);
+        vm.expectRevert("Stable loan already claimed");
+        lending.reclaimStable(borrowerHash());
+    }
+
+    function test_ReclaimStableByAnotherAccountReverts() public {
+        lend();
+
+        vm.warp(block.timestamp + lending.STABLE_CLAIM_PERIOD());
+        vm.prank(borrower);
+        vm.expectRevert("Only the lender can reclaim the stable loan");
+        lending.reclaimStable(borrowerHash());
+    }
+
+    function claimAndApprove() internal returns (uint256) {
+        uint256 stableLoanAmount = lend();
+        vm.startPrank(borrower);
+        lending.claimStable(BORROWER_PREIMAGE);
+        stableToken.approve(address(lending), type(uint256).max);
+        vm.stopPrank();
+        // Enough for the borrower to pay the interest on top of the principal.
+        stableToken.mint(borrower, stableLoanAmount);
+        return stableLoanAmount;
+    }
+
+    function test_RepayStablePaysInterest() public {
+        uint256 stableLoanAmount = claimAndApprove();
+        uint256 lenderBalance = stableToken.balanceOf(lender);
+
+        vm.warp(block.timestamp + lending.SECONDS_PER_YEAR() / 2);
+        uint256 amountDue = lending.getStableAmountDue(borrowerHash(), block.timestamp);
+        assertEq(amountDue, stableLoanAmount + stableLoanAmount * INTEREST_RATE / 200);
+
+        vm.prank(borrower);
+        lending.repayStable(borrowerHash());
+
+        (,,,, bool repaid,,,) = lending.stableLoans(borrowerHash());
+        assertTrue(repaid);
+        assertEq(stableToken.balanceOf(lender), lenderBalance + amountDue);
+        assertEq(stableToken.balanceOf(borrower), 2 * stableLoanAmount - amountDue);
+    }
+
+    function test_RepayStablePartiallyAccruesInterestPerPeriod() public {
+        uint256 stableLoanAmount = claimAndApprove();
+        uint256 halfYear = lending.SECONDS_PER_YEAR() / 2;
+
+        // The first half year accrues on the whole amount lent.
+        vm.warp(block.timestamp + halfYear);
+        vm.prank(borrower);
+        lending.repayStablePartially(borrowerHash(), stableLoanAmount / 2);
+        uint256 firstPeriodInterest = stableLoanAmount * INTEREST_RATE / 200;
+        (, uint256 remainingAmount,,,,, uint256 startTime, uint256 accruedInterest) = lending.stableLoans(borrowerHash());
+        assertEq(remainingAmount, stableLoanAmount - stableLoanAmount / 2);
+        assertEq(startTime, block.timestamp);
+        assertEq(accruedInterest, firstPeriodInterest);
+
+        // The second one only on the amount left.
+        vm.warp(block.timestamp + halfYear);
+        uint256 amountDue = lending.getStableAmountDue(borrowerHash(), block.timestamp);
+        assertEq(amountDue, remainingAmount + firstPeriodInterest + remainingAmount * INTEREST_RATE / 200);
+
+        uint256 lenderBalance = stableToken.balanceOf(lender);
+        vm.prank(borrower);
+        lending.repayStable(borrowerHash());
+        assertEq(stableToken.balanceOf(lender), lenderBalance + amountDue);
+    }
+
+    function test_StableLoanAmountDueIsNotCappedAtMaturity() public {
+        P2PBTCLending.StableLoan memory stableLoan = P2PBTCLending.StableLoan({
+            collateralAmount: BTC_COLLATERAL,
+            stableLoanAmount: 1234567,
+            borrowerPreimage: BORROWER_PREIMAGE,
+            lender: lender,
+            repaid: false,
+            interestRate: 10,
+            startTime: 1000,
+            accruedInterest: 25
+        });
+
+        // Pinned to the value of stable_loan_amount_due_matches_lending_contract_test
+        // in the loan_math module of dlc-manager, two years past the start of the loan.
+        uint256 timestamp = 1000 + 2 * lending.SECONDS_PER_YEAR() + 12345;
+        assertEq(lending.calculateStableInterest(stableLoan, timestamp), 246961);
+        assertEq(lending.getStableLoanAmountDue(stableLoan, timestamp), 1481553);
+    }
+
+    function test_RepayStableWithoutInterestFundsReverts() public {
+        uint256 stableLoanAmount = lend();
+        vm.startPrank(borrower);
+        lending.claimStable(BORROWER_PREIMAGE);
+        stableToken.approve(address(lending), type(uint256).max);
+        vm.warp(block.timestamp + 30 days);
+        // The borrower only holds the principal.
+        assertEq(stableToken.balanceOf(borrower), stableLoanAmount);
+        vm.expectRevert();
+        lending.repayStable(borrowerHash());
+        vm.stopPrank();
+    }
+
+    function test_RepayStableTwiceReverts() public {
+        claimAndApprove();
+
+        vm.startPrank(borrower);
+        lending.repayStable(borrowerHash());
+        vm.expectRevert("Stable loan already repaid");
+        lending.repayStable(borrowerHash());
+        vm.stopPrank();
+    }
+
+    function test_LendStableWithInvalidInterestRateReverts() public {
+        vm.prank(lender);
+        vm.expectRevert("Interest rate must be between 1 and 100");
+        lending.lendStable(borrowerHash(), BTC_COLLATERAL, 1, 0, COLLATERAL_RATIO);
+    }
+}